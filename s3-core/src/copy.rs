@@ -0,0 +1,87 @@
+// https://docs.aws.amazon.com/AmazonS3/latest/API/API_CopyObject.html
+
+use chrono::{DateTime, Utc};
+
+use crate::{object_lock::format_date, url_decode, S3Error};
+
+/// Object named by the x-amz-copy-source header of a CopyObject,
+/// `[/]<bucket>/<key>[?versionId=<version>]` with the key URL encoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopySource {
+    pub bucket: String,
+    pub key: String,
+    pub version_id: Option<String>,
+}
+
+impl CopySource {
+    pub fn parse(value: &str) -> Result<Self, S3Error> {
+        let invalid = || S3Error::InvalidArgument(format!("Invalid copy source {}", value));
+        // A `?` of the key is encoded, so the first one starts the query
+        let (path, query) = match value.trim().split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (value.trim(), None),
+        };
+        let version_id = match query {
+            Some(query) => match query.strip_prefix("versionId=") {
+                Some(version_id) if !version_id.is_empty() => {
+                    Some(url_decode(version_id).ok_or_else(invalid)?)
+                }
+                _ => return Err(invalid()),
+            },
+            None => None,
+        };
+        let path = url_decode(path.strip_prefix('/').unwrap_or(path)).ok_or_else(invalid)?;
+        match path.split_once('/') {
+            Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() => Ok(Self {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                version_id,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename = "CopyObjectResult", rename_all = "PascalCase")]
+pub struct CopyObjectResult {
+    #[serde(rename = "ETag")]
+    pub etag: String,
+    pub last_modified: String,
+}
+
+impl CopyObjectResult {
+    pub fn new(etag: &str, last_modified: DateTime<Utc>) -> Self {
+        Self {
+            etag: etag.to_string(),
+            last_modified: format_date(last_modified),
+        }
+    }
+
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let source = CopySource::parse("/photos/2024/a%20b%3F.jpg").unwrap();
+        assert_eq!(source.bucket, "photos");
+        assert_eq!(source.key, "2024/a b?.jpg");
+        assert_eq!(source.version_id, None);
+
+        let source = CopySource::parse("photos/a.jpg?versionId=0192d4b8").unwrap();
+        assert_eq!(source.bucket, "photos");
+        assert_eq!(source.key, "a.jpg");
+        assert_eq!(source.version_id.as_deref(), Some("0192d4b8"));
+
+        assert!(CopySource::parse("photos").is_err());
+        assert!(CopySource::parse("/photos/").is_err());
+        assert!(CopySource::parse("photos/a.jpg?acl").is_err());
+        assert!(CopySource::parse("photos/a%zz").is_err());
+    }
+}
@@ -0,0 +1,193 @@
+// https://docs.aws.amazon.com/AmazonS3/latest/API/API_PutBucketCors.html
+
+use crate::S3Error;
+
+// Maximum number of rules in a CORS configuration
+pub static MAX_CORS_RULES: usize = 100;
+
+static ALLOWED_METHODS: [&str; 5] = ["GET", "PUT", "POST", "DELETE", "HEAD"];
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "CORSConfiguration")]
+pub struct CorsConfiguration {
+    #[serde(rename = "CORSRule", default)]
+    pub rules: Vec<CorsRule>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct CorsRule {
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "AllowedHeader", default)]
+    pub allowed_headers: Vec<String>,
+    #[serde(rename = "AllowedMethod", default)]
+    pub allowed_methods: Vec<String>,
+    #[serde(rename = "AllowedOrigin", default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(rename = "ExposeHeader", default)]
+    pub expose_headers: Vec<String>,
+    #[serde(
+        rename = "MaxAgeSeconds",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_age_seconds: Option<u64>,
+}
+
+impl CorsConfiguration {
+    pub fn from_xml(body: &[u8]) -> Result<Self, S3Error> {
+        let body = std::str::from_utf8(body).map_err(|_| S3Error::MalformedXML)?;
+        let config: Self = quick_xml::de::from_str(body).map_err(|_| S3Error::MalformedXML)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), S3Error> {
+        if self.rules.is_empty() || self.rules.len() > MAX_CORS_RULES {
+            return Err(S3Error::MalformedXML);
+        }
+
+        for rule in &self.rules {
+            if rule.allowed_methods.is_empty() || rule.allowed_origins.is_empty() {
+                return Err(S3Error::MalformedXML);
+            }
+            if let Some(method) = rule
+                .allowed_methods
+                .iter()
+                .find(|method| !ALLOWED_METHODS.contains(&method.as_str()))
+            {
+                return Err(S3Error::InvalidArgument(format!(
+                    "Found unsupported HTTP method in CORS config. Unsupported method is {}",
+                    method
+                )));
+            }
+            for origin in &rule.allowed_origins {
+                if origin.matches('*').count() > 1 {
+                    return Err(S3Error::InvalidArgument(format!(
+                        "AllowedOrigin \"{}\" can not have more than one wildcard.",
+                        origin
+                    )));
+                }
+            }
+            for header in &rule.allowed_headers {
+                if header.matches('*').count() > 1 {
+                    return Err(S3Error::InvalidArgument(format!(
+                        "AllowedHeader \"{}\" can not have more than one wildcard.",
+                        header
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Find the first rule allowing a request from `origin` using `method`
+    /// and sending `headers`. Rules are evaluated in order, as S3 does.
+    pub fn find_rule(&self, origin: &str, method: &str, headers: &[String]) -> Option<&CorsRule> {
+        self.rules.iter().find(|rule| {
+            rule.allowed_origins
+                .iter()
+                .any(|allowed| wildcard_match(allowed, origin, false))
+                && rule.allowed_methods.iter().any(|allowed| allowed == method)
+                && headers.iter().all(|header| {
+                    rule.allowed_headers
+                        .iter()
+                        .any(|allowed| wildcard_match(allowed, header, true))
+                })
+        })
+    }
+}
+
+impl CorsRule {
+    pub fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+}
+
+// Matches a pattern holding at most one '*' wildcard
+fn wildcard_match(pattern: &str, value: &str, ignore_case: bool) -> bool {
+    let (pattern, value) = if ignore_case {
+        (pattern.to_ascii_lowercase(), value.to_ascii_lowercase())
+    } else {
+        (pattern.to_string(), value.to_string())
+    };
+
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            value.len() >= prefix.len() + suffix.len()
+                && value.starts_with(prefix)
+                && value.ends_with(suffix)
+        }
+        None => pattern == value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static CONFIG: &str = r#"<CORSConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+        <CORSRule>
+            <AllowedOrigin>https://*.example.com</AllowedOrigin>
+            <AllowedMethod>PUT</AllowedMethod>
+            <AllowedMethod>GET</AllowedMethod>
+            <AllowedHeader>x-amz-*</AllowedHeader>
+            <AllowedHeader>Content-Type</AllowedHeader>
+            <ExposeHeader>ETag</ExposeHeader>
+            <MaxAgeSeconds>3000</MaxAgeSeconds>
+        </CORSRule>
+        <CORSRule>
+            <AllowedOrigin>*</AllowedOrigin>
+            <AllowedMethod>GET</AllowedMethod>
+        </CORSRule>
+    </CORSConfiguration>"#;
+
+    #[test]
+    fn test_parse() {
+        let config = CorsConfiguration::from_xml(CONFIG.as_bytes()).unwrap();
+        assert_eq!(config.rules.len(), 2);
+        assert_eq!(config.rules[0].allowed_methods, vec!["PUT", "GET"]);
+        assert_eq!(config.rules[0].expose_headers, vec!["ETag"]);
+        assert_eq!(config.rules[0].max_age_seconds, Some(3000));
+        assert_eq!(config.rules[1].max_age_seconds, None);
+
+        let roundtrip = CorsConfiguration::from_xml(config.to_xml().as_bytes()).unwrap();
+        assert_eq!(roundtrip, config);
+    }
+
+    #[test]
+    fn test_find_rule() {
+        let config: CorsConfiguration = quick_xml::de::from_str(CONFIG).unwrap();
+        let headers = vec!["X-Amz-Date".to_string(), "content-type".to_string()];
+
+        let rule = config.find_rule("https://app.example.com", "PUT", &headers);
+        assert_eq!(rule, Some(&config.rules[0]));
+
+        let rule = config.find_rule("https://evil.com", "GET", &[]);
+        assert_eq!(rule, Some(&config.rules[1]));
+        assert!(rule.unwrap().allows_any_origin());
+
+        assert!(config.find_rule("https://evil.com", "PUT", &[]).is_none());
+        assert!(config
+            .find_rule(
+                "https://app.example.com",
+                "PUT",
+                &["authorization".to_string()]
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config: CorsConfiguration = quick_xml::de::from_str(CONFIG).unwrap();
+        config.rules[1].allowed_methods = vec!["PATCH".to_string()];
+        assert!(config.validate().is_err());
+
+        config.rules.clear();
+        assert!(config.validate().is_err());
+    }
+}
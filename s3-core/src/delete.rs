@@ -0,0 +1,134 @@
+// https://docs.aws.amazon.com/AmazonS3/latest/API/API_DeleteObjects.html
+
+use crate::S3Error;
+
+// Keys a single DeleteObjects may name
+pub static MAX_DELETE_KEYS: usize = 1000;
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "Delete")]
+pub struct Delete {
+    #[serde(rename = "Object", default)]
+    pub objects: Vec<ObjectIdentifier>,
+    #[serde(rename = "Quiet", default)]
+    pub quiet: bool,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct ObjectIdentifier {
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "VersionId")]
+    pub version_id: Option<String>,
+}
+
+#[derive(Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "DeleteResult")]
+pub struct DeleteResult {
+    #[serde(rename = "Deleted", skip_serializing_if = "Vec::is_empty")]
+    pub deleted: Vec<DeletedObject>,
+    #[serde(rename = "Error", skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<DeleteError>,
+}
+
+/// A deleted version, or the delete marker a delete added.
+#[derive(Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeletedObject {
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub delete_marker: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete_marker_version_id: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeleteError {
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+    pub code: String,
+    pub message: String,
+}
+
+impl Delete {
+    pub fn from_xml(body: &[u8]) -> Result<Self, S3Error> {
+        let body = std::str::from_utf8(body).map_err(|_| S3Error::MalformedXML)?;
+        let delete: Self = quick_xml::de::from_str(body).map_err(|_| S3Error::MalformedXML)?;
+        if delete.objects.is_empty() || delete.objects.len() > MAX_DELETE_KEYS {
+            return Err(S3Error::MalformedXML);
+        }
+        Ok(delete)
+    }
+}
+
+impl DeleteError {
+    pub fn new(object: &ObjectIdentifier, error: &S3Error) -> Self {
+        Self {
+            key: object.key.clone(),
+            version_id: object.version_id.clone(),
+            code: error.code(),
+            message: error.message(),
+        }
+    }
+}
+
+impl DeleteResult {
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let delete = Delete::from_xml(
+            br#"<Delete xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+                <Object><Key>a.txt</Key></Object>
+                <Object><Key>b.txt</Key><VersionId>0192d4b8</VersionId></Object>
+                <Quiet>true</Quiet>
+            </Delete>"#,
+        )
+        .unwrap();
+        assert!(delete.quiet);
+        assert_eq!(delete.objects.len(), 2);
+        assert_eq!(delete.objects[0].version_id, None);
+        assert_eq!(delete.objects[1].key, "b.txt");
+        assert_eq!(delete.objects[1].version_id.as_deref(), Some("0192d4b8"));
+
+        assert!(matches!(
+            Delete::from_xml(b"<Delete></Delete>"),
+            Err(S3Error::MalformedXML)
+        ));
+        let many = "<Object><Key>a</Key></Object>".repeat(MAX_DELETE_KEYS + 1);
+        assert!(Delete::from_xml(format!("<Delete>{}</Delete>", many).as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_to_xml() {
+        let result = DeleteResult {
+            deleted: vec![DeletedObject {
+                key: "a.txt".to_string(),
+                delete_marker: true,
+                delete_marker_version_id: Some("0192d4b8".to_string()),
+                ..Default::default()
+            }],
+            errors: vec![DeleteError::new(
+                &ObjectIdentifier {
+                    key: "b.txt".to_string(),
+                    version_id: None,
+                },
+                &S3Error::AccessDenied,
+            )],
+        };
+        let xml = result.to_xml();
+        assert!(xml.starts_with("<DeleteResult><Deleted><Key>a.txt</Key><DeleteMarker>true</DeleteMarker><DeleteMarkerVersionId>0192d4b8</DeleteMarkerVersionId></Deleted>"));
+        assert!(xml.contains("<Error><Key>b.txt</Key><Code>AccessDenied</Code>"));
+    }
+}
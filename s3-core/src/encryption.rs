@@ -0,0 +1,149 @@
+// https://docs.aws.amazon.com/AmazonS3/latest/API/API_PutBucketEncryption.html
+
+use crate::S3Error;
+
+// Values of SSEAlgorithm and the x-amz-server-side-encryption header
+pub static AES256: &str = "AES256";
+pub static AWS_KMS: &str = "aws:kms";
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "ServerSideEncryptionConfiguration")]
+pub struct ServerSideEncryptionConfiguration {
+    #[serde(rename = "Rule", default)]
+    pub rules: Vec<ServerSideEncryptionRule>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct ServerSideEncryptionRule {
+    #[serde(
+        rename = "ApplyServerSideEncryptionByDefault",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub default_encryption: Option<DefaultEncryption>,
+    #[serde(
+        rename = "BucketKeyEnabled",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bucket_key_enabled: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct DefaultEncryption {
+    #[serde(rename = "SSEAlgorithm")]
+    pub algorithm: String,
+    #[serde(
+        rename = "KMSMasterKeyID",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub kms_master_key_id: Option<String>,
+}
+
+impl ServerSideEncryptionConfiguration {
+    pub fn from_xml(body: &[u8]) -> Result<Self, S3Error> {
+        let body = std::str::from_utf8(body).map_err(|_| S3Error::MalformedXML)?;
+        let config: Self = quick_xml::de::from_str(body).map_err(|_| S3Error::MalformedXML)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), S3Error> {
+        // S3 takes exactly one rule
+        let rule = match self.rules.as_slice() {
+            [rule] => rule,
+            _ => return Err(S3Error::MalformedXML),
+        };
+        let default = rule
+            .default_encryption
+            .as_ref()
+            .ok_or(S3Error::MalformedXML)?;
+        validate_algorithm(&default.algorithm, default.kms_master_key_id.as_deref())
+    }
+
+    /// Encryption applied to objects uploaded without
+    /// x-amz-server-side-encryption.
+    pub fn default_encryption(&self) -> Option<&DefaultEncryption> {
+        self.rules
+            .first()
+            .and_then(|rule| rule.default_encryption.as_ref())
+    }
+}
+
+/// Check an algorithm and KMS key as given in a bucket default or in the
+/// x-amz-server-side-encryption headers of a request.
+pub fn validate_algorithm(algorithm: &str, kms_key_id: Option<&str>) -> Result<(), S3Error> {
+    if algorithm != AES256 && algorithm != AWS_KMS {
+        return Err(S3Error::InvalidArgument(format!(
+            "Unsupported server side encryption algorithm {}",
+            algorithm
+        )));
+    }
+    match kms_key_id {
+        Some(_) if algorithm != AWS_KMS => Err(S3Error::InvalidArgument(
+            "A KMS key can only be given for aws:kms encryption".to_string(),
+        )),
+        Some("") => Err(S3Error::InvalidArgument(
+            "The KMS key id must not be empty".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static CONFIG: &str = r#"<ServerSideEncryptionConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+        <Rule>
+            <ApplyServerSideEncryptionByDefault>
+                <SSEAlgorithm>aws:kms</SSEAlgorithm>
+                <KMSMasterKeyID>backups</KMSMasterKeyID>
+            </ApplyServerSideEncryptionByDefault>
+            <BucketKeyEnabled>true</BucketKeyEnabled>
+        </Rule>
+    </ServerSideEncryptionConfiguration>"#;
+
+    #[test]
+    fn test_parse() {
+        let config = ServerSideEncryptionConfiguration::from_xml(CONFIG.as_bytes()).unwrap();
+        let default = config.default_encryption().unwrap();
+        assert_eq!(default.algorithm, AWS_KMS);
+        assert_eq!(default.kms_master_key_id.as_deref(), Some("backups"));
+        assert_eq!(config.rules[0].bucket_key_enabled, Some(true));
+
+        let roundtrip =
+            ServerSideEncryptionConfiguration::from_xml(config.to_xml().as_bytes()).unwrap();
+        assert_eq!(roundtrip, config);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config: ServerSideEncryptionConfiguration =
+            quick_xml::de::from_str(CONFIG).unwrap();
+        config.rules[0]
+            .default_encryption
+            .as_mut()
+            .unwrap()
+            .algorithm = AES256.to_string();
+        assert!(config.validate().is_err());
+
+        config.rules[0]
+            .default_encryption
+            .as_mut()
+            .unwrap()
+            .kms_master_key_id = None;
+        assert!(config.validate().is_ok());
+
+        config.rules.push(config.rules[0].clone());
+        assert!(config.validate().is_err());
+
+        assert!(validate_algorithm("aws:kms:dsse", None).is_err());
+        assert!(validate_algorithm(AWS_KMS, Some("")).is_err());
+    }
+}
@@ -3,29 +3,56 @@ use axum::response::IntoResponse;
 #[derive(Debug, Serialize)]
 pub enum S3Error {
     AccessDenied,
+    AccessForbidden,
     AuthorizationHeaderMalformed,
     BucketAlreadyOwnedByYou(String),
     BucketAlreadyExists(String),
     BucketNotEmpty,
     EntityTooLarge,
+    EntityTooSmall,
     KeyTooLong(String),
     InvalidArgument(String),
     InvalidBucketName(String),
+    InvalidBucketState(String),
     InvalidAccessKeyId,
+    InvalidPart(String),
+    InvalidPartOrder,
+    InvalidTag(String),
+    InvalidTargetBucketForLogging(String),
     MissingDateHeader,
     MissingContentLength,
+    MalformedXML,
     MaxMessageLengthExceeded,
+    MethodNotAllowed,
     NoSuchBucket(String),
+    NoSuchCORSConfiguration(String),
     NoSuchKey(String),
+    NoSuchLifecycleConfiguration(String),
+    NoSuchObjectLockConfiguration,
+    NoSuchTagSet(String),
+    NoSuchUpload(String),
+    NoSuchVersion(String),
+    NoSuchWebsiteConfiguration(String),
+    ObjectLockConfigurationNotFoundError(String),
+    OperationAborted,
+    InvalidRange,
     InvalidRequest,
     InternalError,
     NotImplemented,
+    ReplicationConfigurationNotFoundError(String),
     RequestTimeTooSkewed,
+    ServerSideEncryptionConfigurationNotFoundError(String),
     SignatureDoesNotMatch,
     TooManyBuckets,
-    SlowDown,
+    // Seconds the client should wait before retrying
+    SlowDown(u64),
 }
 
+/// Code of the error a response was made from, kept in the extensions of
+/// the response for access logs.
+#[derive(Debug, Clone)]
+pub struct ErrorCode(pub String);
+
 #[derive(Serialize, Debug)]
 struct Error {
     #[serde(skip)]
@@ -49,6 +76,13 @@ fn s3error_to_error(error: &S3Error) -> Error {
             resource: "".to_string(),
             request_id: "".to_string(),
         },
+        S3Error::AccessForbidden => Error {
+            status: http::StatusCode::FORBIDDEN.into(),
+            code: "AccessForbidden".to_string(),
+            message: "CORSResponse: This CORS request is not allowed. This is usually because the evalution of Origin, request method / Access-Control-Request-Method or Access-Control-Request-Headers are not whitelisted by the resource's CORS spec.".to_string(),
+            resource: "".to_string(),
+            request_id: "".to_string(),
+        },
         S3Error::BucketAlreadyOwnedByYou(bucket) => Error {
             status: http::StatusCode::CONFLICT.into(),
             code: "AlreadyOwnedByYou".to_string(),
@@ -70,6 +104,13 @@ fn s3error_to_error(error: &S3Error) -> Error {
             resource: "".to_string(),
             request_id: "".to_string(),
         },
+        S3Error::EntityTooSmall => Error {
+            status: http::StatusCode::BAD_REQUEST.into(),
+            code: "EntityTooSmall".to_string(),
+            message: "Your proposed upload is smaller than the minimum allowed object size.".to_string(),
+            resource: "".to_string(),
+            request_id: "".to_string(),
+        },
         S3Error::MissingContentLength => Error {
             status: http::StatusCode::LENGTH_REQUIRED.into(),
             code: "MissingContentLength".to_string(),
@@ -98,6 +139,34 @@ fn s3error_to_error(error: &S3Error) -> Error {
             resource: "".to_string(),
             request_id: "".to_string(),
         },
+        S3Error::InvalidPart(message) => Error {
+            status: http::StatusCode::BAD_REQUEST.into(),
+            code: "InvalidPart".to_string(),
+            message: message.to_string(),
+            resource: "".to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::InvalidPartOrder => Error {
+            status: http::StatusCode::BAD_REQUEST.into(),
+            code: "InvalidPartOrder".to_string(),
+            message: "The list of parts was not in ascending order. The parts list must be specified in order by part number.".to_string(),
+            resource: "".to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::InvalidTag(message) => Error {
+            status: http::StatusCode::BAD_REQUEST.into(),
+            code: "InvalidTag".to_string(),
+            message: message.to_string(),
+            resource: "".to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::InvalidTargetBucketForLogging(message) => Error {
+            status: http::StatusCode::BAD_REQUEST.into(),
+            code: "InvalidTargetBucketForLogging".to_string(),
+            message: message.to_string(),
+            resource: "".to_string(),
+            request_id: "".to_string(),
+        },
         S3Error::InvalidAccessKeyId => Error {
             status: http::StatusCode::FORBIDDEN.into(),
             code: "InvalidAccessKeyId".to_string(),
@@ -119,6 +188,104 @@ fn s3error_to_error(error: &S3Error) -> Error {
             resource: bucket.to_string(),
             request_id: "".to_string(),
         },
+        S3Error::NoSuchLifecycleConfiguration(bucket) => Error {
+            status: http::StatusCode::NOT_FOUND.into(),
+            code: "NoSuchLifecycleConfiguration".to_string(),
+            message: "The lifecycle configuration does not exist".to_string(),
+            resource: bucket.to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::NoSuchObjectLockConfiguration => Error {
+            status: http::StatusCode::NOT_FOUND.into(),
+            code: "NoSuchObjectLockConfiguration".to_string(),
+            message: "The specified object does not have a ObjectLock configuration".to_string(),
+            resource: "".to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::ObjectLockConfigurationNotFoundError(bucket) => Error {
+            status: http::StatusCode::NOT_FOUND.into(),
+            code: "ObjectLockConfigurationNotFoundError".to_string(),
+            message: "Object Lock configuration does not exist for this bucket".to_string(),
+            resource: bucket.to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::ReplicationConfigurationNotFoundError(bucket) => Error {
+            status: http::StatusCode::NOT_FOUND.into(),
+            code: "ReplicationConfigurationNotFoundError".to_string(),
+            message: "The replication configuration was not found".to_string(),
+            resource: bucket.to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::InvalidBucketState(message) => Error {
+            status: http::StatusCode::CONFLICT.into(),
+            code: "InvalidBucketState".to_string(),
+            message: message.to_string(),
+            resource: "".to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::OperationAborted => Error {
+            status: http::StatusCode::CONFLICT.into(),
+            code: "OperationAborted".to_string(),
+            message: "A conflicting conditional operation is currently in progress against this resource. Please try again.".to_string(),
+            resource: "".to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::NoSuchTagSet(bucket) => Error {
+            status: http::StatusCode::NOT_FOUND.into(),
+            code: "NoSuchTagSet".to_string(),
+            message: "The TagSet does not exist".to_string(),
+            resource: bucket.to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::NoSuchUpload(upload_id) => Error {
+            status: http::StatusCode::NOT_FOUND.into(),
+            code: "NoSuchUpload".to_string(),
+            message: "The specified multipart upload does not exist. The upload ID might be invalid, or the multipart upload might have been aborted or completed.".to_string(),
+            resource: upload_id.to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::NoSuchVersion(version_id) => Error {
+            status: http::StatusCode::NOT_FOUND.into(),
+            code: "NoSuchVersion".to_string(),
+            message: "The specified version does not exist.".to_string(),
+            resource: version_id.to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::NoSuchWebsiteConfiguration(bucket) => Error {
+            status: http::StatusCode::NOT_FOUND.into(),
+            code: "NoSuchWebsiteConfiguration".to_string(),
+            message: "The specified bucket does not have a website configuration".to_string(),
+            resource: bucket.to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::NoSuchCORSConfiguration(bucket) => Error {
+            status: http::StatusCode::NOT_FOUND.into(),
+            code: "NoSuchCORSConfiguration".to_string(),
+            message: "The CORS configuration does not exist".to_string(),
+            resource: bucket.to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::ServerSideEncryptionConfigurationNotFoundError(bucket) => Error {
+            status: http::StatusCode::NOT_FOUND.into(),
+            code: "ServerSideEncryptionConfigurationNotFoundError".to_string(),
+            message: "The server side encryption configuration was not found".to_string(),
+            resource: bucket.to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::InvalidRange => Error {
+            status: http::StatusCode::RANGE_NOT_SATISFIABLE.into(),
+            code: "InvalidRange".to_string(),
+            message: "The requested range is not satisfiable".to_string(),
+            resource: "".to_string(),
+            request_id: "".to_string(),
+        },
+        S3Error::MalformedXML => Error {
+            status: http::StatusCode::BAD_REQUEST.into(),
+            code: "MalformedXML".to_string(),
+            message: "The XML you provided was not well-formed or did not validate against our published schema.".to_string(),
+            resource: "".to_string(),
+            request_id: "".to_string(),
+        },
         S3Error::NoSuchKey(key) => Error {
             status: http::StatusCode::NOT_FOUND.into(),
             code: "NoSuchKey".to_string(),
@@ -168,6 +335,13 @@ fn s3error_to_error(error: &S3Error) -> Error {
             resource: "".to_string(),
             request_id: "".to_string(),
         },
+        S3Error::MethodNotAllowed => Error {
+            status: http::StatusCode::METHOD_NOT_ALLOWED.into(),
+            code: "MethodNotAllowed".to_string(),
+            message: "The specified method is not allowed against this resource.".to_string(),
+            resource: "".to_string(),
+            request_id: "".to_string(),
+        },
         S3Error::KeyTooLong(key) => Error {
             status: http::StatusCode::BAD_REQUEST.into(),
             code: "KeyTooLong".to_string(),
@@ -182,7 +356,7 @@ fn s3error_to_error(error: &S3Error) -> Error {
             resource: "".to_string(),
             request_id: "".to_string(),
         },
-        S3Error::SlowDown => Error {
+        S3Error::SlowDown(_) => Error {
             status: http::StatusCode::SERVICE_UNAVAILABLE.into(),
             code: "SlowDown".to_string(),
             message: "Please reduce your request rate.".to_string(),
@@ -199,6 +373,68 @@ fn s3error_to_error(error: &S3Error) -> Error {
     }
 }
 
+impl S3Error {
+    pub fn status_code(&self) -> u16 {
+        s3error_to_error(self).status
+    }
+
+    pub fn code(&self) -> String {
+        s3error_to_error(self).code
+    }
+
+    pub fn message(&self) -> String {
+        s3error_to_error(self).message
+    }
+
+    /// The error as an HTML page, as the website endpoint returns errors.
+    pub fn into_html_response(self) -> axum::response::Response<axum::body::Body> {
+        let error = s3error_to_error(&self);
+        let status = http::StatusCode::from_u16(error.status)
+            .unwrap_or(http::StatusCode::INTERNAL_SERVER_ERROR);
+        let title = format!(
+            "{} {}",
+            status.as_u16(),
+            status.canonical_reason().unwrap_or_default()
+        );
+        let mut items = vec![
+            format!("<li>Code: {}</li>", escape_html(&error.code)),
+            format!("<li>Message: {}</li>", escape_html(&error.message)),
+        ];
+        if !error.resource.is_empty() {
+            items.push(format!(
+                "<li>Resource: {}</li>",
+                escape_html(&error.resource)
+            ));
+        }
+        let body = format!(
+            "<html>\n<head><title>{title}</title></head>\n<body>\n<h1>{title}</h1>\n<ul>\n{}\n</ul>\n<hr/>\n</body>\n</html>\n",
+            items.join("\n")
+        );
+
+        axum::http::Response::builder()
+            .status(status)
+            .extension(ErrorCode(error.code))
+            .header("Content-Type", "text/html; charset=utf-8")
+            .body(axum::body::Body::from(body))
+            .unwrap_or_default()
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 impl IntoResponse for S3Error {
     fn into_response(self) -> axum::response::Response<axum::body::Body> {
         let error = s3error_to_error(&self);
@@ -216,9 +452,13 @@ impl IntoResponse for S3Error {
         request_id_tag.finish().data(&error.request_id);
         inner_error_tag.finish();
 
-        axum::http::Response::builder()
+        let mut builder = axum::http::Response::builder()
             .status(error.status)
-            .body(axum::body::Body::from(s))
-            .unwrap()
+            .extension(ErrorCode(error.code));
+        if let S3Error::SlowDown(retry_after) = &self {
+            builder = builder.header("Retry-After", retry_after.to_string());
+        }
+
+        builder.body(axum::body::Body::from(s)).unwrap()
     }
 }
@@ -1,14 +1,26 @@
 #[macro_use]
 extern crate serde_derive;
 
+pub mod copy;
+pub mod cors;
+pub mod delete;
+pub mod encryption;
 pub mod error;
+pub mod lifecycle;
+pub mod logging;
+pub mod multipart;
+pub mod notification;
+pub mod object_lock;
+pub mod replication;
 pub mod request;
 pub mod response;
+pub mod tagging;
 pub mod types;
 pub mod util;
 pub mod versioning;
+pub mod website;
 
 pub use error::S3Error;
-pub use request::S3Action;
+pub use request::{S3Action, S3ActionClass};
 pub use types::{Bucket, Object, ObjectMetadata, StorageClass};
 pub use util::*;
@@ -0,0 +1,577 @@
+// https://docs.aws.amazon.com/AmazonS3/latest/API/API_PutBucketLifecycleConfiguration.html
+
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+
+use crate::{S3Error, StorageClass};
+
+// Maximum number of rules in a lifecycle configuration
+pub static MAX_LIFECYCLE_RULES: usize = 1000;
+
+static MAX_RULE_ID_LENGTH: usize = 255;
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "LifecycleConfiguration")]
+pub struct LifecycleConfiguration {
+    #[serde(rename = "Rule", default)]
+    pub rules: Vec<LifecycleRule>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct LifecycleRule {
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "Status")]
+    pub status: String,
+    #[serde(rename = "Filter", default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<LifecycleFilter>,
+    // Deprecated rule level prefix, still sent by older clients
+    #[serde(rename = "Prefix", default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(
+        rename = "Expiration",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expiration: Option<Expiration>,
+    #[serde(rename = "Transition", default, skip_serializing_if = "Vec::is_empty")]
+    pub transitions: Vec<Transition>,
+    #[serde(
+        rename = "NoncurrentVersionExpiration",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub noncurrent_version_expiration: Option<NoncurrentVersionExpiration>,
+    #[serde(
+        rename = "AbortIncompleteMultipartUpload",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub abort_incomplete_multipart_upload: Option<AbortIncompleteMultipartUpload>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct LifecycleFilter {
+    #[serde(rename = "Prefix", default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(rename = "Tag", default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<Tag>,
+    #[serde(
+        rename = "ObjectSizeGreaterThan",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub object_size_greater_than: Option<i64>,
+    #[serde(
+        rename = "ObjectSizeLessThan",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub object_size_less_than: Option<i64>,
+    #[serde(rename = "And", default, skip_serializing_if = "Option::is_none")]
+    pub and: Option<LifecycleAnd>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct LifecycleAnd {
+    #[serde(rename = "Prefix", default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(rename = "Tag", default)]
+    pub tags: Vec<Tag>,
+    #[serde(
+        rename = "ObjectSizeGreaterThan",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub object_size_greater_than: Option<i64>,
+    #[serde(
+        rename = "ObjectSizeLessThan",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub object_size_less_than: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct Tag {
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "Value")]
+    pub value: String,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct Expiration {
+    #[serde(rename = "Days", default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<u32>,
+    #[serde(rename = "Date", default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(
+        rename = "ExpiredObjectDeleteMarker",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expired_object_delete_marker: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct Transition {
+    #[serde(rename = "Days", default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<u32>,
+    #[serde(rename = "Date", default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(rename = "StorageClass")]
+    pub storage_class: String,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct NoncurrentVersionExpiration {
+    #[serde(rename = "NoncurrentDays")]
+    pub noncurrent_days: u32,
+    #[serde(
+        rename = "NewerNoncurrentVersions",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub newer_noncurrent_versions: Option<u32>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct AbortIncompleteMultipartUpload {
+    #[serde(rename = "DaysAfterInitiation")]
+    pub days_after_initiation: u32,
+}
+
+/// Object selection of a rule with the `Filter`/`And`/`Prefix` variants
+/// flattened.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct RuleCriteria {
+    pub prefix: String,
+    pub tags: Vec<Tag>,
+    pub size_greater_than: Option<i64>,
+    pub size_less_than: Option<i64>,
+}
+
+impl LifecycleConfiguration {
+    pub fn from_xml(body: &[u8]) -> Result<Self, S3Error> {
+        let body = std::str::from_utf8(body).map_err(|_| S3Error::MalformedXML)?;
+        let config: Self = quick_xml::de::from_str(body).map_err(|_| S3Error::MalformedXML)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), S3Error> {
+        if self.rules.is_empty() || self.rules.len() > MAX_LIFECYCLE_RULES {
+            return Err(S3Error::MalformedXML);
+        }
+
+        let mut ids = HashSet::new();
+        for rule in &self.rules {
+            if let Some(id) = &rule.id {
+                if id.len() > MAX_RULE_ID_LENGTH {
+                    return Err(S3Error::InvalidArgument(
+                        "ID length should not exceed allowed limit of 255".to_string(),
+                    ));
+                }
+                if !ids.insert(id.as_str()) {
+                    return Err(S3Error::InvalidArgument(
+                        "Rule ID must be unique. Found same ID for more than one rule".to_string(),
+                    ));
+                }
+            }
+            rule.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Fill in missing rule IDs, as S3 does when storing a configuration.
+    pub fn assign_ids(&mut self) {
+        let mut next = 0;
+        let used = self
+            .rules
+            .iter()
+            .filter_map(|rule| rule.id.clone())
+            .collect::<HashSet<_>>();
+        for rule in self.rules.iter_mut().filter(|rule| rule.id.is_none()) {
+            let id = loop {
+                next += 1;
+                let id = format!("rule-{}", next);
+                if !used.contains(&id) {
+                    break id;
+                }
+            };
+            rule.id = Some(id);
+        }
+    }
+}
+
+impl LifecycleRule {
+    pub fn is_enabled(&self) -> bool {
+        self.status == "Enabled"
+    }
+
+    fn validate(&self) -> Result<(), S3Error> {
+        if self.status != "Enabled" && self.status != "Disabled" {
+            return Err(S3Error::MalformedXML);
+        }
+        if self.filter.is_some() && self.prefix.is_some() {
+            return Err(S3Error::MalformedXML);
+        }
+        if let Some(filter) = &self.filter {
+            let conditions = [
+                filter.prefix.is_some(),
+                filter.tag.is_some(),
+                filter.object_size_greater_than.is_some(),
+                filter.object_size_less_than.is_some(),
+                filter.and.is_some(),
+            ];
+            if conditions.iter().filter(|set| **set).count() > 1 {
+                return Err(S3Error::MalformedXML);
+            }
+        }
+        if self.expiration.is_none()
+            && self.transitions.is_empty()
+            && self.noncurrent_version_expiration.is_none()
+            && self.abort_incomplete_multipart_upload.is_none()
+        {
+            return Err(S3Error::InvalidRequest);
+        }
+
+        let criteria = self.criteria();
+        if let (Some(greater), Some(less)) = (criteria.size_greater_than, criteria.size_less_than) {
+            if greater >= less {
+                return Err(S3Error::InvalidArgument(
+                    "ObjectSizeGreaterThan must be less than ObjectSizeLessThan".to_string(),
+                ));
+            }
+        }
+
+        if let Some(expiration) = &self.expiration {
+            let set = [
+                expiration.days.is_some(),
+                expiration.date.is_some(),
+                expiration.expired_object_delete_marker.is_some(),
+            ];
+            if set.iter().filter(|set| **set).count() != 1 {
+                return Err(S3Error::MalformedXML);
+            }
+            if expiration.days == Some(0) {
+                return Err(S3Error::InvalidArgument(
+                    "'Days' for Expiration action must be a positive integer".to_string(),
+                ));
+            }
+            if let Some(date) = &expiration.date {
+                parse_date(date)?;
+            }
+            if expiration.expired_object_delete_marker.is_some() && !criteria.tags.is_empty() {
+                return Err(S3Error::InvalidArgument(
+                    "ExpiredObjectDeleteMarker cannot be specified with tags".to_string(),
+                ));
+            }
+        }
+        let mut classes = HashSet::new();
+        for transition in &self.transitions {
+            let class = transition.class()?;
+            if class == StorageClass::Standard || !classes.insert(class) {
+                return Err(S3Error::InvalidArgument(format!(
+                    "Invalid StorageClass for Transition: {}",
+                    transition.storage_class
+                )));
+            }
+            if transition.days.is_some() == transition.date.is_some() {
+                return Err(S3Error::MalformedXML);
+            }
+            if let Some(date) = &transition.date {
+                parse_date(date)?;
+            }
+            let expiration_days = self.expiration.as_ref().and_then(|e| e.days);
+            if let (Some(days), Some(expiration_days)) = (transition.days, expiration_days) {
+                if days >= expiration_days {
+                    return Err(S3Error::InvalidArgument(
+                        "'Days' in the Expiration action must be greater than 'Days' in the Transition action".to_string(),
+                    ));
+                }
+            }
+        }
+        if let Some(noncurrent) = &self.noncurrent_version_expiration {
+            if noncurrent.noncurrent_days == 0 {
+                return Err(S3Error::InvalidArgument(
+                    "'NoncurrentDays' for NoncurrentVersionExpiration action must be a positive integer".to_string(),
+                ));
+            }
+        }
+        if let Some(abort) = &self.abort_incomplete_multipart_upload {
+            if abort.days_after_initiation == 0 {
+                return Err(S3Error::InvalidArgument(
+                    "'DaysAfterInitiation' for AbortIncompleteMultipartUpload action must be a positive integer".to_string(),
+                ));
+            }
+            if !criteria.tags.is_empty() {
+                return Err(S3Error::InvalidArgument(
+                    "AbortIncompleteMultipartUpload cannot be specified with tags".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn criteria(&self) -> RuleCriteria {
+        let filter = match &self.filter {
+            Some(filter) => filter,
+            None => {
+                return RuleCriteria {
+                    prefix: self.prefix.clone().unwrap_or_default(),
+                    ..Default::default()
+                }
+            }
+        };
+        match &filter.and {
+            Some(and) => RuleCriteria {
+                prefix: and.prefix.clone().unwrap_or_default(),
+                tags: and.tags.clone(),
+                size_greater_than: and.object_size_greater_than,
+                size_less_than: and.object_size_less_than,
+            },
+            None => RuleCriteria {
+                prefix: filter.prefix.clone().unwrap_or_default(),
+                tags: filter.tag.iter().cloned().collect(),
+                size_greater_than: filter.object_size_greater_than,
+                size_less_than: filter.object_size_less_than,
+            },
+        }
+    }
+}
+
+impl RuleCriteria {
+    pub fn matches(&self, key: &str, size: i64, tags: &HashMap<String, String>) -> bool {
+        key.starts_with(&self.prefix)
+            && self.size_greater_than.is_none_or(|min| size > min)
+            && self.size_less_than.is_none_or(|max| size < max)
+            && self
+                .tags
+                .iter()
+                .all(|tag| tags.get(&tag.key) == Some(&tag.value))
+    }
+}
+
+impl Expiration {
+    /// Objects created before the returned time have expired at `now`.
+    pub fn cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        action_cutoff(self.days, self.date.as_deref(), now)
+    }
+}
+
+impl Transition {
+    pub fn class(&self) -> Result<StorageClass, S3Error> {
+        StorageClass::from_str(&self.storage_class).map_err(S3Error::InvalidArgument)
+    }
+
+    /// Objects created before the returned time are due to move at `now`.
+    pub fn cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        action_cutoff(self.days, self.date.as_deref(), now)
+    }
+}
+
+fn action_cutoff(
+    days: Option<u32>,
+    date: Option<&str>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    if let Some(days) = days {
+        return Some(days_cutoff(days, now));
+    }
+    match date.map(parse_date) {
+        // Every matching object is due once the date has passed
+        Some(Ok(date)) if date <= now => Some(now),
+        _ => None,
+    }
+}
+
+/// Actions based on a number of days fire at the first midnight UTC after
+/// that many days have passed, so an object created at `t` expires at
+/// `ceil_midnight(t + days)`. That is before `now` exactly when `t` is before
+/// the returned time.
+pub fn days_cutoff(days: u32, now: DateTime<Utc>) -> DateTime<Utc> {
+    let midnight = now.date_naive().and_time(NaiveTime::MIN).and_utc();
+    midnight - Duration::days(days as i64) + Duration::nanoseconds(1)
+}
+
+fn parse_date(date: &str) -> Result<DateTime<Utc>, S3Error> {
+    let invalid = || {
+        S3Error::InvalidArgument("'Date' must be at midnight GMT in ISO 8601 format".to_string())
+    };
+    let date = DateTime::parse_from_rfc3339(date)
+        .map_err(|_| invalid())?
+        .with_timezone(&Utc);
+    if date.time() != NaiveTime::MIN {
+        return Err(invalid());
+    }
+    Ok(date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static CONFIG: &str = r#"<LifecycleConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+        <Rule>
+            <ID>logs</ID>
+            <Filter>
+                <And>
+                    <Prefix>logs/</Prefix>
+                    <Tag><Key>class</Key><Value>debug</Value></Tag>
+                    <ObjectSizeGreaterThan>1024</ObjectSizeGreaterThan>
+                </And>
+            </Filter>
+            <Status>Enabled</Status>
+            <Expiration><Days>30</Days></Expiration>
+            <Transition><Days>7</Days><StorageClass>STANDARD_IA</StorageClass></Transition>
+            <Transition><Days>14</Days><StorageClass>GLACIER</StorageClass></Transition>
+            <NoncurrentVersionExpiration>
+                <NoncurrentDays>7</NoncurrentDays>
+                <NewerNoncurrentVersions>2</NewerNoncurrentVersions>
+            </NoncurrentVersionExpiration>
+        </Rule>
+        <Rule>
+            <Filter><Prefix>tmp/</Prefix></Filter>
+            <Status>Disabled</Status>
+            <Expiration><ExpiredObjectDeleteMarker>true</ExpiredObjectDeleteMarker></Expiration>
+            <AbortIncompleteMultipartUpload>
+                <DaysAfterInitiation>1</DaysAfterInitiation>
+            </AbortIncompleteMultipartUpload>
+        </Rule>
+    </LifecycleConfiguration>"#;
+
+    #[test]
+    fn test_parse() {
+        let mut config = LifecycleConfiguration::from_xml(CONFIG.as_bytes()).unwrap();
+        assert_eq!(config.rules.len(), 2);
+        assert!(config.rules[0].is_enabled());
+        assert!(!config.rules[1].is_enabled());
+
+        let criteria = config.rules[0].criteria();
+        assert_eq!(criteria.prefix, "logs/");
+        assert_eq!(criteria.tags.len(), 1);
+        assert_eq!(criteria.size_greater_than, Some(1024));
+        assert_eq!(config.rules[0].transitions.len(), 2);
+        assert_eq!(
+            config.rules[0].transitions[1].class().unwrap(),
+            StorageClass::Glacier
+        );
+        assert_eq!(
+            config.rules[0]
+                .noncurrent_version_expiration
+                .as_ref()
+                .unwrap()
+                .newer_noncurrent_versions,
+            Some(2)
+        );
+        assert_eq!(
+            config.rules[1]
+                .expiration
+                .as_ref()
+                .unwrap()
+                .expired_object_delete_marker,
+            Some(true)
+        );
+
+        config.assign_ids();
+        assert_eq!(config.rules[1].id.as_deref(), Some("rule-1"));
+
+        let roundtrip = LifecycleConfiguration::from_xml(config.to_xml().as_bytes()).unwrap();
+        assert_eq!(roundtrip, config);
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = LifecycleConfiguration::from_xml(CONFIG.as_bytes()).unwrap();
+
+        let mut invalid = config.clone();
+        invalid.rules[1].id = Some("logs".to_string());
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = config.clone();
+        invalid.rules[0].expiration = Some(Expiration {
+            days: Some(1),
+            date: Some("2030-01-01T00:00:00Z".to_string()),
+            ..Default::default()
+        });
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = config.clone();
+        invalid.rules[0].expiration = Some(Expiration {
+            date: Some("2030-01-01T12:00:00Z".to_string()),
+            ..Default::default()
+        });
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = config.clone();
+        invalid.rules[0].abort_incomplete_multipart_upload = Some(AbortIncompleteMultipartUpload {
+            days_after_initiation: 3,
+        });
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = config.clone();
+        invalid.rules[0].transitions[1].days = Some(30);
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = config.clone();
+        invalid.rules[0].transitions[1].storage_class = "STANDARD_IA".to_string();
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = config;
+        invalid.rules[1].expiration = None;
+        invalid.rules[1].abort_incomplete_multipart_upload = None;
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let config = LifecycleConfiguration::from_xml(CONFIG.as_bytes()).unwrap();
+        let criteria = config.rules[0].criteria();
+        let tags = HashMap::from([("class".to_string(), "debug".to_string())]);
+
+        assert!(criteria.matches("logs/a", 2048, &tags));
+        assert!(!criteria.matches("logs/a", 512, &tags));
+        assert!(!criteria.matches("data/a", 2048, &tags));
+        assert!(!criteria.matches("logs/a", 2048, &HashMap::new()));
+    }
+
+    #[test]
+    fn test_cutoff() {
+        let now = DateTime::parse_from_rfc3339("2024-05-10T15:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let cutoff = days_cutoff(1, now);
+
+        // Created the day before yesterday, expired at midnight this morning
+        let created = DateTime::parse_from_rfc3339("2024-05-08T23:59:59Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert!(created < cutoff);
+        // Created exactly at midnight yesterday, also expired this morning
+        let created = DateTime::parse_from_rfc3339("2024-05-09T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert!(created < cutoff);
+        // Created yesterday morning, expires at midnight tonight
+        let created = DateTime::parse_from_rfc3339("2024-05-09T08:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert!(created >= cutoff);
+
+        let expiration = Expiration {
+            date: Some("2024-05-11T00:00:00Z".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(expiration.cutoff(now), None);
+    }
+}
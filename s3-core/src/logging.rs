@@ -0,0 +1,84 @@
+// https://docs.aws.amazon.com/AmazonS3/latest/API/API_PutBucketLogging.html
+
+use crate::S3Error;
+
+/// Logging status of a bucket, logging is disabled without LoggingEnabled.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "BucketLoggingStatus")]
+pub struct BucketLoggingStatus {
+    #[serde(
+        rename = "LoggingEnabled",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub logging_enabled: Option<LoggingEnabled>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct LoggingEnabled {
+    #[serde(rename = "TargetBucket")]
+    pub target_bucket: String,
+    #[serde(rename = "TargetPrefix", default)]
+    pub target_prefix: String,
+}
+
+impl BucketLoggingStatus {
+    pub fn from_xml(body: &[u8]) -> Result<Self, S3Error> {
+        let body = std::str::from_utf8(body).map_err(|_| S3Error::MalformedXML)?;
+        let status: Self = quick_xml::de::from_str(body).map_err(|_| S3Error::MalformedXML)?;
+        status.validate()?;
+        Ok(status)
+    }
+
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), S3Error> {
+        if let Some(logging) = &self.logging_enabled {
+            if !crate::is_valid_bucket_name(&logging.target_bucket) {
+                return Err(S3Error::InvalidTargetBucketForLogging(format!(
+                    "The target bucket for logging does not exist: {}",
+                    logging.target_bucket
+                )));
+            }
+            if logging.target_prefix.len() > 1024 {
+                return Err(S3Error::InvalidArgument(
+                    "The TargetPrefix is too long".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let status = BucketLoggingStatus::from_xml(
+            br#"<BucketLoggingStatus xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+                <LoggingEnabled>
+                    <TargetBucket>logs</TargetBucket>
+                    <TargetPrefix>access/</TargetPrefix>
+                </LoggingEnabled>
+            </BucketLoggingStatus>"#,
+        )
+        .unwrap();
+        let logging = status.logging_enabled.clone().unwrap();
+        assert_eq!(logging.target_bucket, "logs");
+        assert_eq!(logging.target_prefix, "access/");
+        let roundtrip = BucketLoggingStatus::from_xml(status.to_xml().as_bytes()).unwrap();
+        assert_eq!(roundtrip, status);
+
+        let disabled = BucketLoggingStatus::from_xml(b"<BucketLoggingStatus/>").unwrap();
+        assert_eq!(disabled.logging_enabled, None);
+        assert!(BucketLoggingStatus::from_xml(
+            b"<BucketLoggingStatus><LoggingEnabled><TargetBucket>Not_Valid</TargetBucket>\
+            </LoggingEnabled></BucketLoggingStatus>"
+        )
+        .is_err());
+    }
+}
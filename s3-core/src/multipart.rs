@@ -0,0 +1,150 @@
+// https://docs.aws.amazon.com/AmazonS3/latest/API/API_CompleteMultipartUpload.html
+
+use crate::S3Error;
+
+// Part numbers run from 1 to MAX_PART_NUMBER
+pub static MAX_PART_NUMBER: u32 = 10000;
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "CompleteMultipartUpload")]
+pub struct CompleteMultipartUpload {
+    #[serde(rename = "Part", default)]
+    pub parts: Vec<CompletedPart>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct CompletedPart {
+    #[serde(rename = "PartNumber")]
+    pub part_number: u32,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename = "InitiateMultipartUploadResult", rename_all = "PascalCase")]
+pub struct InitiateMultipartUploadResult {
+    pub bucket: String,
+    pub key: String,
+    pub upload_id: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename = "CompleteMultipartUploadResult", rename_all = "PascalCase")]
+pub struct CompleteMultipartUploadResult {
+    pub location: String,
+    pub bucket: String,
+    pub key: String,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+}
+
+impl CompleteMultipartUpload {
+    pub fn from_xml(body: &[u8]) -> Result<Self, S3Error> {
+        let body = std::str::from_utf8(body).map_err(|_| S3Error::MalformedXML)?;
+        let mut upload: Self = quick_xml::de::from_str(body).map_err(|_| S3Error::MalformedXML)?;
+        upload.validate()?;
+        // Clients send the ETags of UploadPart back as they got them, quoted
+        for part in &mut upload.parts {
+            part.etag = part.etag.trim().trim_matches('"').to_string();
+        }
+        Ok(upload)
+    }
+
+    pub fn validate(&self) -> Result<(), S3Error> {
+        if self.parts.is_empty() {
+            return Err(S3Error::MalformedXML);
+        }
+        for part in &self.parts {
+            check_part_number(part.part_number)?;
+        }
+        if self
+            .parts
+            .windows(2)
+            .any(|pair| pair[0].part_number >= pair[1].part_number)
+        {
+            return Err(S3Error::InvalidPartOrder);
+        }
+        Ok(())
+    }
+}
+
+impl InitiateMultipartUploadResult {
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+}
+
+impl CompleteMultipartUploadResult {
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Parse the partNumber of an UploadPart.
+pub fn parse_part_number(value: &str) -> Result<u32, S3Error> {
+    let part_number = value.parse().map_err(|_| {
+        S3Error::InvalidArgument(format!("Part number must be an integer, got {}", value))
+    })?;
+    check_part_number(part_number)?;
+    Ok(part_number)
+}
+
+fn check_part_number(part_number: u32) -> Result<(), S3Error> {
+    if part_number == 0 || part_number > MAX_PART_NUMBER {
+        return Err(S3Error::InvalidArgument(format!(
+            "Part number must be an integer between 1 and {}, inclusive",
+            MAX_PART_NUMBER
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let upload = CompleteMultipartUpload::from_xml(
+            br#"<CompleteMultipartUpload xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+                <Part><PartNumber>1</PartNumber><ETag>"a54357aff0632cce46d942af68356b38"</ETag></Part>
+                <Part><PartNumber>3</PartNumber><ETag>0c78aef83f66abc1fa1e8477f296d394</ETag></Part>
+            </CompleteMultipartUpload>"#,
+        )
+        .unwrap();
+        assert_eq!(upload.parts.len(), 2);
+        assert_eq!(upload.parts[0].etag, "a54357aff0632cce46d942af68356b38");
+        assert_eq!(upload.parts[1].part_number, 3);
+
+        assert!(matches!(
+            CompleteMultipartUpload::from_xml(
+                b"<CompleteMultipartUpload></CompleteMultipartUpload>"
+            ),
+            Err(S3Error::MalformedXML)
+        ));
+        assert!(matches!(
+            CompleteMultipartUpload::from_xml(
+                b"<CompleteMultipartUpload>\
+                <Part><PartNumber>2</PartNumber><ETag>a</ETag></Part>\
+                <Part><PartNumber>1</PartNumber><ETag>b</ETag></Part>\
+                </CompleteMultipartUpload>"
+            ),
+            Err(S3Error::InvalidPartOrder)
+        ));
+        assert!(CompleteMultipartUpload::from_xml(
+            b"<CompleteMultipartUpload>\
+            <Part><PartNumber>0</PartNumber><ETag>a</ETag></Part>\
+            </CompleteMultipartUpload>"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_part_number() {
+        assert_eq!(parse_part_number("1").unwrap(), 1);
+        assert_eq!(parse_part_number("10000").unwrap(), 10000);
+        assert!(parse_part_number("0").is_err());
+        assert!(parse_part_number("10001").is_err());
+        assert!(parse_part_number("one").is_err());
+    }
+}
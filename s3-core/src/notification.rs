@@ -0,0 +1,375 @@
+// https://docs.aws.amazon.com/AmazonS3/latest/API/API_PutBucketNotificationConfiguration.html
+
+use crate::S3Error;
+
+/// Events a configuration may subscribe to, the categories ending in * match
+/// every event of the category.
+static EVENTS: [&str; 18] = [
+    "s3:TestEvent",
+    "s3:ObjectCreated:*",
+    "s3:ObjectCreated:Put",
+    "s3:ObjectCreated:Post",
+    "s3:ObjectCreated:Copy",
+    "s3:ObjectCreated:CompleteMultipartUpload",
+    "s3:ObjectRemoved:*",
+    "s3:ObjectRemoved:Delete",
+    "s3:ObjectRemoved:DeleteMarkerCreated",
+    "s3:ObjectTagging:*",
+    "s3:ObjectTagging:Put",
+    "s3:ObjectTagging:Delete",
+    "s3:LifecycleExpiration:*",
+    "s3:LifecycleExpiration:Delete",
+    "s3:LifecycleExpiration:DeleteMarkerCreated",
+    "s3:LifecycleTransition",
+    "s3:Replication:*",
+    "s3:Replication:OperationFailedReplication",
+];
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "NotificationConfiguration")]
+pub struct NotificationConfiguration {
+    #[serde(rename = "TopicConfiguration", default)]
+    pub topic_configurations: Vec<TopicConfiguration>,
+    #[serde(rename = "QueueConfiguration", default)]
+    pub queue_configurations: Vec<QueueConfiguration>,
+    #[serde(rename = "CloudFunctionConfiguration", default)]
+    pub cloud_function_configurations: Vec<CloudFunctionConfiguration>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct TopicConfiguration {
+    #[serde(rename = "Id", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "Topic")]
+    pub arn: String,
+    #[serde(rename = "Event", default)]
+    pub events: Vec<String>,
+    #[serde(rename = "Filter", default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<NotificationFilter>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct QueueConfiguration {
+    #[serde(rename = "Id", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "Queue")]
+    pub arn: String,
+    #[serde(rename = "Event", default)]
+    pub events: Vec<String>,
+    #[serde(rename = "Filter", default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<NotificationFilter>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct CloudFunctionConfiguration {
+    #[serde(rename = "Id", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "CloudFunction")]
+    pub arn: String,
+    #[serde(rename = "Event", default)]
+    pub events: Vec<String>,
+    #[serde(rename = "Filter", default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<NotificationFilter>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct NotificationFilter {
+    #[serde(rename = "S3Key", default)]
+    pub key: KeyFilter,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct KeyFilter {
+    #[serde(rename = "FilterRule", default)]
+    pub rules: Vec<FilterRule>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct FilterRule {
+    // prefix or suffix
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Value")]
+    pub value: String,
+}
+
+/// A topic, queue or function configuration, whichever kind of destination
+/// it names.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationRule<'a> {
+    pub id: Option<&'a str>,
+    pub arn: &'a str,
+    pub events: &'a [String],
+    pub filter: Option<&'a NotificationFilter>,
+}
+
+impl NotificationConfiguration {
+    pub fn from_xml(body: &[u8]) -> Result<Self, S3Error> {
+        let body = std::str::from_utf8(body).map_err(|_| S3Error::MalformedXML)?;
+        let config: Self = quick_xml::de::from_str(body).map_err(|_| S3Error::MalformedXML)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), S3Error> {
+        for rule in self.rules() {
+            if rule.events.is_empty() {
+                return Err(S3Error::MalformedXML);
+            }
+            if let Some(event) = rule
+                .events
+                .iter()
+                .find(|event| !EVENTS.contains(&event.as_str()))
+            {
+                return Err(S3Error::InvalidArgument(format!(
+                    "The event is not supported for notifications: {}",
+                    event
+                )));
+            }
+            if target_name(rule.arn).is_none() {
+                return Err(S3Error::InvalidArgument(format!(
+                    "The destination ARN is not well-formed: {}",
+                    rule.arn
+                )));
+            }
+            if let Some(filter) = rule.filter {
+                filter.validate()?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules().is_empty()
+    }
+
+    pub fn rules(&self) -> Vec<NotificationRule<'_>> {
+        let topics = self.topic_configurations.iter().map(|c| NotificationRule {
+            id: c.id.as_deref(),
+            arn: &c.arn,
+            events: &c.events,
+            filter: c.filter.as_ref(),
+        });
+        let queues = self.queue_configurations.iter().map(|c| NotificationRule {
+            id: c.id.as_deref(),
+            arn: &c.arn,
+            events: &c.events,
+            filter: c.filter.as_ref(),
+        });
+        let functions = self
+            .cloud_function_configurations
+            .iter()
+            .map(|c| NotificationRule {
+                id: c.id.as_deref(),
+                arn: &c.arn,
+                events: &c.events,
+                filter: c.filter.as_ref(),
+            });
+        topics.chain(queues).chain(functions).collect()
+    }
+
+    /// The rules an event on the key is delivered by.
+    pub fn matching_rules(&self, event: &str, key: &str) -> Vec<NotificationRule<'_>> {
+        self.rules()
+            .into_iter()
+            .filter(|rule| rule.matches(event, key))
+            .collect()
+    }
+}
+
+impl<'a> NotificationRule<'a> {
+    pub fn matches(&self, event: &str, key: &str) -> bool {
+        self.events
+            .iter()
+            .any(|pattern| event_matches(pattern, event))
+            && self.filter.is_none_or(|filter| filter.matches(key))
+    }
+
+    /// Name of the gateway target the rule delivers to, the last field of
+    /// its ARN.
+    pub fn target(&self) -> &'a str {
+        target_name(self.arn).unwrap_or_default()
+    }
+}
+
+impl NotificationFilter {
+    fn validate(&self) -> Result<(), S3Error> {
+        let mut names = Vec::new();
+        for rule in &self.key.rules {
+            let name = rule.name.to_lowercase();
+            if name != "prefix" && name != "suffix" {
+                return Err(S3Error::InvalidArgument(format!(
+                    "filter rule name must be either prefix or suffix: {}",
+                    rule.name
+                )));
+            }
+            if names.contains(&name) {
+                return Err(S3Error::InvalidArgument(
+                    "Cannot specify more than one prefix or suffix rule in a filter.".to_string(),
+                ));
+            }
+            names.push(name);
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, key: &str) -> bool {
+        self.key.rules.iter().all(|rule| {
+            if rule.name.eq_ignore_ascii_case("prefix") {
+                key.starts_with(&rule.value)
+            } else {
+                key.ends_with(&rule.value)
+            }
+        })
+    }
+}
+
+/// Whether an event name such as s3:ObjectCreated:Put is subscribed to by
+/// the pattern, which may name its whole category.
+pub fn event_matches(pattern: &str, event: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(category) => event.starts_with(category),
+        None => pattern == event,
+    }
+}
+
+// arn:partition:service:region:account:name
+fn target_name(arn: &str) -> Option<&str> {
+    let fields: Vec<&str> = arn.split(':').collect();
+    match fields.as_slice() {
+        ["arn", _, _, _, _, name] if !name.is_empty() => Some(name),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = NotificationConfiguration::from_xml(
+            br#"<NotificationConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+                <QueueConfiguration>
+                    <Id>images</Id>
+                    <Queue>arn:aws:sqs:us-east-1:123456789012:indexer</Queue>
+                    <Event>s3:ObjectCreated:*</Event>
+                    <Event>s3:ObjectRemoved:Delete</Event>
+                    <Filter>
+                        <S3Key>
+                            <FilterRule><Name>prefix</Name><Value>images/</Value></FilterRule>
+                            <FilterRule><Name>suffix</Name><Value>.jpg</Value></FilterRule>
+                        </S3Key>
+                    </Filter>
+                </QueueConfiguration>
+                <TopicConfiguration>
+                    <Topic>arn:aws:sns:us-east-1:123456789012:audit</Topic>
+                    <Event>s3:ObjectTagging:*</Event>
+                </TopicConfiguration>
+            </NotificationConfiguration>"#,
+        )
+        .unwrap();
+        let rules = config.rules();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].target(), "audit");
+        assert_eq!(rules[1].target(), "indexer");
+        assert_eq!(rules[1].id, Some("images"));
+
+        let roundtrip = NotificationConfiguration::from_xml(config.to_xml().as_bytes()).unwrap();
+        assert_eq!(roundtrip, config);
+        assert!(
+            NotificationConfiguration::from_xml(b"<NotificationConfiguration/>")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let invalid = [
+            // Unknown event
+            "<QueueConfiguration><Queue>arn:aws:sqs:us-east-1:1:q</Queue>\
+            <Event>s3:ObjectCreated:Rename</Event></QueueConfiguration>",
+            // Malformed ARN
+            "<QueueConfiguration><Queue>indexer</Queue>\
+            <Event>s3:ObjectCreated:*</Event></QueueConfiguration>",
+            // Unknown filter rule
+            "<QueueConfiguration><Queue>arn:aws:sqs:us-east-1:1:q</Queue>\
+            <Event>s3:ObjectCreated:*</Event><Filter><S3Key><FilterRule>\
+            <Name>contains</Name><Value>x</Value></FilterRule></S3Key></Filter>\
+            </QueueConfiguration>",
+            // No event
+            "<QueueConfiguration><Queue>arn:aws:sqs:us-east-1:1:q</Queue></QueueConfiguration>",
+        ];
+        for configuration in invalid {
+            let xml = format!(
+                "<NotificationConfiguration>{}</NotificationConfiguration>",
+                configuration
+            );
+            assert!(
+                NotificationConfiguration::from_xml(xml.as_bytes()).is_err(),
+                "{}",
+                configuration
+            );
+        }
+    }
+
+    #[test]
+    fn test_matching_rules() {
+        let filter = |prefix: &str, suffix: &str| NotificationFilter {
+            key: KeyFilter {
+                rules: vec![
+                    FilterRule {
+                        name: "Prefix".to_string(),
+                        value: prefix.to_string(),
+                    },
+                    FilterRule {
+                        name: "Suffix".to_string(),
+                        value: suffix.to_string(),
+                    },
+                ],
+            },
+        };
+        let config = NotificationConfiguration {
+            queue_configurations: vec![QueueConfiguration {
+                arn: "arn:aws:sqs:us-east-1:1:indexer".to_string(),
+                events: vec!["s3:ObjectCreated:*".to_string()],
+                filter: Some(filter("images/", ".jpg")),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            config
+                .matching_rules("s3:ObjectCreated:Put", "images/cat.jpg")
+                .len(),
+            1
+        );
+        assert!(config
+            .matching_rules("s3:ObjectCreated:Put", "images/cat.png")
+            .is_empty());
+        assert!(config
+            .matching_rules("s3:ObjectCreated:Put", "docs/cat.jpg")
+            .is_empty());
+        assert!(config
+            .matching_rules("s3:ObjectRemoved:Delete", "images/cat.jpg")
+            .is_empty());
+
+        assert!(event_matches(
+            "s3:ObjectRemoved:*",
+            "s3:ObjectRemoved:Delete"
+        ));
+        assert!(event_matches(
+            "s3:LifecycleTransition",
+            "s3:LifecycleTransition"
+        ));
+        assert!(!event_matches(
+            "s3:ObjectRemoved:Delete",
+            "s3:ObjectRemoved:DeleteMarkerCreated"
+        ));
+    }
+}
@@ -0,0 +1,298 @@
+// https://docs.aws.amazon.com/AmazonS3/latest/userguide/object-lock.html
+
+use chrono::{DateTime, Duration, Months, SecondsFormat, Utc};
+
+use crate::S3Error;
+
+// Retention modes. A GOVERNANCE version may be deleted and its retention
+// shortened with x-amz-bypass-governance-retention, a COMPLIANCE one never.
+pub static GOVERNANCE: &str = "GOVERNANCE";
+pub static COMPLIANCE: &str = "COMPLIANCE";
+
+// Values of ObjectLockEnabled and of the legal hold Status
+pub static ENABLED: &str = "Enabled";
+pub static LEGAL_HOLD_ON: &str = "ON";
+pub static LEGAL_HOLD_OFF: &str = "OFF";
+
+// Longest default retention S3 accepts
+static MAX_RETENTION_DAYS: u32 = 36500;
+static MAX_RETENTION_YEARS: u32 = 100;
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "ObjectLockConfiguration")]
+pub struct ObjectLockConfiguration {
+    #[serde(
+        rename = "ObjectLockEnabled",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub object_lock_enabled: Option<String>,
+    #[serde(rename = "Rule", default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<ObjectLockRule>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct ObjectLockRule {
+    #[serde(rename = "DefaultRetention")]
+    pub default_retention: DefaultRetention,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct DefaultRetention {
+    #[serde(rename = "Mode")]
+    pub mode: String,
+    #[serde(rename = "Days", default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<u32>,
+    #[serde(rename = "Years", default, skip_serializing_if = "Option::is_none")]
+    pub years: Option<u32>,
+}
+
+/// Retention of an object version, both fields are absent to remove it.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "Retention")]
+pub struct ObjectRetention {
+    #[serde(rename = "Mode", default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(
+        rename = "RetainUntilDate",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub retain_until_date: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "LegalHold")]
+pub struct LegalHold {
+    #[serde(rename = "Status")]
+    pub status: String,
+}
+
+impl ObjectLockConfiguration {
+    /// Configuration of a bucket created with
+    /// x-amz-bucket-object-lock-enabled, without a default retention.
+    pub fn enabled() -> Self {
+        Self {
+            object_lock_enabled: Some(ENABLED.to_string()),
+            rule: None,
+        }
+    }
+
+    pub fn from_xml(body: &[u8]) -> Result<Self, S3Error> {
+        let body = std::str::from_utf8(body).map_err(|_| S3Error::MalformedXML)?;
+        let config: Self = quick_xml::de::from_str(body).map_err(|_| S3Error::MalformedXML)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), S3Error> {
+        if self.object_lock_enabled.as_deref() != Some(ENABLED) {
+            return Err(S3Error::MalformedXML);
+        }
+        let retention = match &self.rule {
+            Some(rule) => &rule.default_retention,
+            None => return Ok(()),
+        };
+        validate_mode(&retention.mode)?;
+        match (retention.days, retention.years) {
+            (Some(days), None) if days > 0 && days <= MAX_RETENTION_DAYS => Ok(()),
+            (None, Some(years)) if years > 0 && years <= MAX_RETENTION_YEARS => Ok(()),
+            (Some(_), Some(_)) | (None, None) => Err(S3Error::MalformedXML),
+            _ => Err(S3Error::InvalidArgument(
+                "Default retention period must be a positive integer value".to_string(),
+            )),
+        }
+    }
+
+    pub fn default_retention(&self) -> Option<&DefaultRetention> {
+        self.rule.as_ref().map(|rule| &rule.default_retention)
+    }
+}
+
+impl DefaultRetention {
+    /// End of the retention of a version created at `created`.
+    pub fn retain_until(&self, created: DateTime<Utc>) -> DateTime<Utc> {
+        match (self.days, self.years) {
+            (Some(days), _) => created + Duration::days(days.into()),
+            (None, Some(years)) => created
+                .checked_add_months(Months::new(years * 12))
+                .unwrap_or(DateTime::<Utc>::MAX_UTC),
+            (None, None) => created,
+        }
+    }
+}
+
+impl ObjectRetention {
+    pub fn from_xml(body: &[u8]) -> Result<Self, S3Error> {
+        let body = std::str::from_utf8(body).map_err(|_| S3Error::MalformedXML)?;
+        let retention: Self = quick_xml::de::from_str(body).map_err(|_| S3Error::MalformedXML)?;
+        retention.parse()?;
+        Ok(retention)
+    }
+
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+
+    /// Mode and end of the retention, None when it is removed.
+    pub fn parse(&self) -> Result<Option<(String, DateTime<Utc>)>, S3Error> {
+        match (&self.mode, &self.retain_until_date) {
+            (Some(mode), Some(date)) => {
+                validate_mode(mode)?;
+                Ok(Some((mode.clone(), parse_date(date)?)))
+            }
+            (None, None) => Ok(None),
+            _ => Err(S3Error::MalformedXML),
+        }
+    }
+
+    pub fn new(mode: &str, retain_until: DateTime<Utc>) -> Self {
+        Self {
+            mode: Some(mode.to_string()),
+            retain_until_date: Some(format_date(retain_until)),
+        }
+    }
+}
+
+impl LegalHold {
+    pub fn from_xml(body: &[u8]) -> Result<Self, S3Error> {
+        let body = std::str::from_utf8(body).map_err(|_| S3Error::MalformedXML)?;
+        let hold: Self = quick_xml::de::from_str(body).map_err(|_| S3Error::MalformedXML)?;
+        parse_legal_hold(&hold.status)?;
+        Ok(hold)
+    }
+
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+
+    pub fn new(on: bool) -> Self {
+        Self {
+            status: if on { LEGAL_HOLD_ON } else { LEGAL_HOLD_OFF }.to_string(),
+        }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.status == LEGAL_HOLD_ON
+    }
+}
+
+pub fn validate_mode(mode: &str) -> Result<(), S3Error> {
+    if mode != GOVERNANCE && mode != COMPLIANCE {
+        return Err(S3Error::InvalidArgument(format!(
+            "Unknown object lock mode {}",
+            mode
+        )));
+    }
+    Ok(())
+}
+
+/// Parse ON or OFF, as in the x-amz-object-lock-legal-hold header.
+pub fn parse_legal_hold(status: &str) -> Result<bool, S3Error> {
+    match status {
+        s if s == LEGAL_HOLD_ON => Ok(true),
+        s if s == LEGAL_HOLD_OFF => Ok(false),
+        _ => Err(S3Error::InvalidArgument(format!(
+            "Unknown legal hold status {}",
+            status
+        ))),
+    }
+}
+
+/// Parse an ISO 8601 retain until date, as in RetainUntilDate and the
+/// x-amz-object-lock-retain-until-date header.
+pub fn parse_date(date: &str) -> Result<DateTime<Utc>, S3Error> {
+    DateTime::parse_from_rfc3339(date.trim())
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|_| S3Error::InvalidArgument(format!("Invalid retain until date {}", date)))
+}
+
+pub fn format_date(date: DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_configuration() {
+        let config = ObjectLockConfiguration::from_xml(
+            br#"<ObjectLockConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+                <ObjectLockEnabled>Enabled</ObjectLockEnabled>
+                <Rule><DefaultRetention><Mode>COMPLIANCE</Mode><Years>7</Years></DefaultRetention></Rule>
+            </ObjectLockConfiguration>"#,
+        )
+        .unwrap();
+        let retention = config.default_retention().unwrap();
+        assert_eq!(retention.mode, COMPLIANCE);
+        let created = parse_date("2024-02-29T12:00:00Z").unwrap();
+        assert_eq!(
+            format_date(retention.retain_until(created)),
+            "2031-02-28T12:00:00.000Z"
+        );
+        let roundtrip = ObjectLockConfiguration::from_xml(config.to_xml().as_bytes()).unwrap();
+        assert_eq!(roundtrip, config);
+
+        assert!(ObjectLockConfiguration::from_xml(
+            ObjectLockConfiguration::enabled().to_xml().as_bytes()
+        )
+        .is_ok());
+        for invalid in [
+            "<ObjectLockConfiguration></ObjectLockConfiguration>",
+            "<ObjectLockConfiguration><ObjectLockEnabled>Enabled</ObjectLockEnabled><Rule><DefaultRetention>\
+            <Mode>GOVERNANCE</Mode><Days>1</Days><Years>1</Years></DefaultRetention></Rule></ObjectLockConfiguration>",
+            "<ObjectLockConfiguration><ObjectLockEnabled>Enabled</ObjectLockEnabled><Rule><DefaultRetention>\
+            <Mode>GOVERNANCE</Mode><Days>0</Days></DefaultRetention></Rule></ObjectLockConfiguration>",
+            "<ObjectLockConfiguration><ObjectLockEnabled>Enabled</ObjectLockEnabled><Rule><DefaultRetention>\
+            <Mode>WORM</Mode><Days>1</Days></DefaultRetention></Rule></ObjectLockConfiguration>",
+        ] {
+            assert!(ObjectLockConfiguration::from_xml(invalid.as_bytes()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_retention() {
+        let retention = ObjectRetention::from_xml(
+            b"<Retention><Mode>GOVERNANCE</Mode>\
+            <RetainUntilDate>2030-01-01T00:00:00Z</RetainUntilDate></Retention>",
+        )
+        .unwrap();
+        let (mode, until) = retention.parse().unwrap().unwrap();
+        assert_eq!(mode, GOVERNANCE);
+        assert_eq!(
+            ObjectRetention::new(&mode, until).to_xml(),
+            "<Retention><Mode>GOVERNANCE</Mode>\
+            <RetainUntilDate>2030-01-01T00:00:00.000Z</RetainUntilDate></Retention>"
+        );
+
+        let removed = ObjectRetention::from_xml(b"<Retention></Retention>").unwrap();
+        assert_eq!(removed.parse().unwrap(), None);
+        assert!(
+            ObjectRetention::from_xml(b"<Retention><Mode>GOVERNANCE</Mode></Retention>").is_err()
+        );
+        assert!(ObjectRetention::from_xml(
+            b"<Retention><Mode>GOVERNANCE</Mode><RetainUntilDate>soon</RetainUntilDate></Retention>"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_legal_hold() {
+        assert!(
+            LegalHold::from_xml(b"<LegalHold><Status>ON</Status></LegalHold>")
+                .unwrap()
+                .is_on()
+        );
+        assert!(
+            !LegalHold::from_xml(LegalHold::new(false).to_xml().as_bytes())
+                .unwrap()
+                .is_on()
+        );
+        assert!(LegalHold::from_xml(b"<LegalHold><Status>on</Status></LegalHold>").is_err());
+    }
+}
@@ -0,0 +1,353 @@
+// https://docs.aws.amazon.com/AmazonS3/latest/API/API_PutBucketReplication.html
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{lifecycle::Tag, S3Error, StorageClass};
+
+// Maximum number of rules in a replication configuration
+pub static MAX_REPLICATION_RULES: usize = 1000;
+
+static MAX_RULE_ID_LENGTH: usize = 255;
+
+static BUCKET_ARN_PREFIX: &str = "arn:aws:s3:::";
+
+/// Replication status of a version waiting to be copied to its destination.
+pub static REPLICATION_PENDING: &str = "PENDING";
+/// Replication status of a version copied to its destination.
+pub static REPLICATION_COMPLETED: &str = "COMPLETED";
+/// Replication status of a version that could not be copied.
+pub static REPLICATION_FAILED: &str = "FAILED";
+/// Replication status of the copy of a version in the destination bucket.
+pub static REPLICATION_REPLICA: &str = "REPLICA";
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "ReplicationConfiguration")]
+pub struct ReplicationConfiguration {
+    #[serde(rename = "Role", default)]
+    pub role: String,
+    #[serde(rename = "Rule", default)]
+    pub rules: Vec<ReplicationRule>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct ReplicationRule {
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "Priority", default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+    #[serde(rename = "Status")]
+    pub status: String,
+    #[serde(rename = "Filter", default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<ReplicationFilter>,
+    // Deprecated rule level prefix, still sent by older clients
+    #[serde(rename = "Prefix", default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(
+        rename = "DeleteMarkerReplication",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub delete_marker_replication: Option<DeleteMarkerReplication>,
+    #[serde(rename = "Destination")]
+    pub destination: Destination,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct ReplicationFilter {
+    #[serde(rename = "Prefix", default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(rename = "Tag", default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<Tag>,
+    #[serde(rename = "And", default, skip_serializing_if = "Option::is_none")]
+    pub and: Option<ReplicationAnd>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct ReplicationAnd {
+    #[serde(rename = "Prefix", default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(rename = "Tag", default)]
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct DeleteMarkerReplication {
+    #[serde(rename = "Status")]
+    pub status: String,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct Destination {
+    #[serde(rename = "Bucket")]
+    pub bucket: String,
+    #[serde(
+        rename = "StorageClass",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub storage_class: Option<String>,
+}
+
+impl ReplicationConfiguration {
+    pub fn from_xml(body: &[u8]) -> Result<Self, S3Error> {
+        let body = std::str::from_utf8(body).map_err(|_| S3Error::MalformedXML)?;
+        let config: Self = quick_xml::de::from_str(body).map_err(|_| S3Error::MalformedXML)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), S3Error> {
+        if self.rules.is_empty() || self.rules.len() > MAX_REPLICATION_RULES {
+            return Err(S3Error::MalformedXML);
+        }
+
+        let mut ids = HashSet::new();
+        let mut priorities = HashSet::new();
+        for rule in &self.rules {
+            if let Some(id) = &rule.id {
+                if id.len() > MAX_RULE_ID_LENGTH {
+                    return Err(S3Error::InvalidArgument(
+                        "ID length should not exceed allowed limit of 255".to_string(),
+                    ));
+                }
+                if !ids.insert(id.as_str()) {
+                    return Err(S3Error::InvalidArgument(
+                        "Rule ID must be unique. Found same ID for more than one rule".to_string(),
+                    ));
+                }
+            }
+            if let Some(priority) = rule.priority {
+                if !priorities.insert(priority) {
+                    return Err(S3Error::InvalidArgument(
+                        "Found duplicate priority. Priority must be unique among rules".to_string(),
+                    ));
+                }
+            }
+            rule.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Rule that replicates a version of `key` with `tags`: the enabled rule
+    /// of highest priority whose filter matches, None when no rule does.
+    pub fn rule_for(&self, key: &str, tags: &HashMap<String, String>) -> Option<&ReplicationRule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.is_enabled() && rule.matches(key, tags))
+            .max_by_key(|rule| rule.priority.unwrap_or_default())
+    }
+
+    /// Names of the buckets that versions are replicated to.
+    pub fn destination_buckets(&self) -> HashSet<&str> {
+        self.rules
+            .iter()
+            .filter_map(|rule| rule.destination.bucket_name())
+            .collect()
+    }
+}
+
+impl ReplicationRule {
+    pub fn is_enabled(&self) -> bool {
+        self.status == "Enabled"
+    }
+
+    /// Delete markers are replicated only when the rule asks for it.
+    pub fn replicates_delete_markers(&self) -> bool {
+        self.delete_marker_replication
+            .as_ref()
+            .is_some_and(|replication| replication.status == "Enabled")
+    }
+
+    fn validate(&self) -> Result<(), S3Error> {
+        if self.status != "Enabled" && self.status != "Disabled" {
+            return Err(S3Error::MalformedXML);
+        }
+        if self.filter.is_some() && self.prefix.is_some() {
+            return Err(S3Error::MalformedXML);
+        }
+        if let Some(filter) = &self.filter {
+            let conditions = [
+                filter.prefix.is_some(),
+                filter.tag.is_some(),
+                filter.and.is_some(),
+            ];
+            if conditions.iter().filter(|set| **set).count() > 1 {
+                return Err(S3Error::MalformedXML);
+            }
+        }
+        if let Some(replication) = &self.delete_marker_replication {
+            if replication.status != "Enabled" && replication.status != "Disabled" {
+                return Err(S3Error::MalformedXML);
+            }
+            // S3 does not replicate delete markers by tag
+            if replication.status == "Enabled" && !self.tags().is_empty() {
+                return Err(S3Error::InvalidArgument(
+                    "Delete marker replication is not supported if any Tag filter is specified"
+                        .to_string(),
+                ));
+            }
+        }
+        if self.destination.bucket_name().is_none() {
+            return Err(S3Error::InvalidArgument(format!(
+                "Invalid destination bucket ARN: {}",
+                self.destination.bucket
+            )));
+        }
+        if let Some(class) = &self.destination.storage_class {
+            class
+                .parse::<StorageClass>()
+                .map_err(S3Error::InvalidArgument)?;
+        }
+        Ok(())
+    }
+
+    fn prefix(&self) -> &str {
+        let prefix = match &self.filter {
+            Some(filter) => match &filter.and {
+                Some(and) => &and.prefix,
+                None => &filter.prefix,
+            },
+            None => &self.prefix,
+        };
+        prefix.as_deref().unwrap_or_default()
+    }
+
+    fn tags(&self) -> Vec<&Tag> {
+        match &self.filter {
+            Some(filter) => match &filter.and {
+                Some(and) => and.tags.iter().collect(),
+                None => filter.tag.iter().collect(),
+            },
+            None => vec![],
+        }
+    }
+
+    pub fn matches(&self, key: &str, tags: &HashMap<String, String>) -> bool {
+        key.starts_with(self.prefix())
+            && self
+                .tags()
+                .iter()
+                .all(|tag| tags.get(&tag.key) == Some(&tag.value))
+    }
+}
+
+impl Destination {
+    /// Name of the destination bucket, from its `arn:aws:s3:::<name>` ARN.
+    pub fn bucket_name(&self) -> Option<&str> {
+        self.bucket
+            .strip_prefix(BUCKET_ARN_PREFIX)
+            .filter(|name| !name.is_empty() && !name.contains(['/', ':']))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static CONFIG: &str = r#"<ReplicationConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+        <Role>arn:aws:iam::123456789012:role/replication</Role>
+        <Rule>
+            <ID>logs</ID>
+            <Priority>1</Priority>
+            <Status>Enabled</Status>
+            <Filter><Prefix>logs/</Prefix></Filter>
+            <DeleteMarkerReplication><Status>Enabled</Status></DeleteMarkerReplication>
+            <Destination><Bucket>arn:aws:s3:::archive</Bucket></Destination>
+        </Rule>
+        <Rule>
+            <ID>reports</ID>
+            <Priority>2</Priority>
+            <Status>Enabled</Status>
+            <Filter>
+                <And>
+                    <Prefix>logs/reports/</Prefix>
+                    <Tag><Key>class</Key><Value>report</Value></Tag>
+                </And>
+            </Filter>
+            <Destination>
+                <Bucket>arn:aws:s3:::reports</Bucket>
+                <StorageClass>STANDARD_IA</StorageClass>
+            </Destination>
+        </Rule>
+        <Rule>
+            <ID>disabled</ID>
+            <Priority>3</Priority>
+            <Status>Disabled</Status>
+            <Filter><Prefix></Prefix></Filter>
+            <Destination><Bucket>arn:aws:s3:::other</Bucket></Destination>
+        </Rule>
+    </ReplicationConfiguration>"#;
+
+    #[test]
+    fn test_parse() {
+        let config = ReplicationConfiguration::from_xml(CONFIG.as_bytes()).unwrap();
+        assert_eq!(config.rules.len(), 3);
+        assert!(config.rules[0].replicates_delete_markers());
+        assert!(!config.rules[1].replicates_delete_markers());
+        assert_eq!(
+            config.destination_buckets(),
+            HashSet::from(["archive", "reports", "other"])
+        );
+        assert_eq!(
+            config.rules[1].destination.storage_class.as_deref(),
+            Some("STANDARD_IA")
+        );
+
+        let config = ReplicationConfiguration::from_xml(config.to_xml().as_bytes()).unwrap();
+        assert_eq!(config.rules.len(), 3);
+    }
+
+    #[test]
+    fn test_rule_for() {
+        let config = ReplicationConfiguration::from_xml(CONFIG.as_bytes()).unwrap();
+        let tagged = HashMap::from([("class".to_string(), "report".to_string())]);
+        let rule = |key: &str, tags: &HashMap<String, String>| {
+            config.rule_for(key, tags).and_then(|rule| rule.id.clone())
+        };
+        assert_eq!(rule("logs/a", &HashMap::new()).as_deref(), Some("logs"));
+        assert_eq!(
+            rule("logs/reports/a", &HashMap::new()).as_deref(),
+            Some("logs")
+        );
+        assert_eq!(rule("logs/reports/a", &tagged).as_deref(), Some("reports"));
+        // Disabled rules replicate nothing
+        assert_eq!(rule("data/a", &tagged), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let invalid = [
+            // Invalid destination
+            CONFIG.replace("arn:aws:s3:::archive", "archive"),
+            CONFIG.replace("arn:aws:s3:::archive", "arn:aws:s3:::"),
+            // Duplicate priority
+            CONFIG.replace("<Priority>2</Priority>", "<Priority>1</Priority>"),
+            // Duplicate ID
+            CONFIG.replace("<ID>reports</ID>", "<ID>logs</ID>"),
+            // Unknown status
+            CONFIG.replace("<Status>Disabled</Status>", "<Status>Off</Status>"),
+            // Unknown storage class
+            CONFIG.replace("STANDARD_IA", "COLD"),
+            // Delete markers cannot be replicated by tag
+            CONFIG.replace(
+                "<Filter><Prefix>logs/</Prefix></Filter>",
+                "<Filter><Tag><Key>a</Key><Value>b</Value></Tag></Filter>",
+            ),
+        ];
+        for config in invalid {
+            assert!(
+                ReplicationConfiguration::from_xml(config.as_bytes()).is_err(),
+                "{}",
+                config
+            );
+        }
+        assert!(ReplicationConfiguration::from_xml(
+            b"<ReplicationConfiguration><Role></Role></ReplicationConfiguration>"
+        )
+        .is_err());
+    }
+}
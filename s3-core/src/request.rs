@@ -11,6 +11,7 @@
 // Added:
 // - OptionsPreflight
 // - PostObject
+// - WebsiteRequest
 #[derive(Default, Debug, Clone, PartialEq)]
 pub enum S3Action {
     #[default]
@@ -110,5 +111,86 @@ pub enum S3Action {
     SelectObjectContent,
     UploadPart,
     UploadPartCopy,
+    WebsiteRequest,
     WriteGetObjectResponse,
 }
+
+// Coarse grouping of actions used to apply policies (e.g. rate limits) to a
+// family of operations instead of every action individually.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum S3ActionClass {
+    Read,
+    List,
+    Write,
+    Delete,
+    Admin,
+}
+
+impl S3ActionClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            S3ActionClass::Read => "read",
+            S3ActionClass::List => "list",
+            S3ActionClass::Write => "write",
+            S3ActionClass::Delete => "delete",
+            S3ActionClass::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for S3ActionClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "read" => Ok(S3ActionClass::Read),
+            "list" => Ok(S3ActionClass::List),
+            "write" => Ok(S3ActionClass::Write),
+            "delete" => Ok(S3ActionClass::Delete),
+            "admin" => Ok(S3ActionClass::Admin),
+            _ => Err(format!("Unknown action class: {}", s)),
+        }
+    }
+}
+
+impl S3Action {
+    pub fn class(&self) -> S3ActionClass {
+        match self {
+            S3Action::GetObject
+            | S3Action::GetObjectAcl
+            | S3Action::GetObjectAttributes
+            | S3Action::GetObjectLegalHold
+            | S3Action::GetObjectRetention
+            | S3Action::GetObjectTagging
+            | S3Action::GetObjectTorrent
+            | S3Action::HeadBucket
+            | S3Action::HeadObject
+            | S3Action::SelectObjectContent
+            | S3Action::WebsiteRequest => S3ActionClass::Read,
+            S3Action::ListBuckets
+            | S3Action::ListMultipartUploads
+            | S3Action::ListObjects
+            | S3Action::ListObjectsV2
+            | S3Action::ListObjectVersions
+            | S3Action::ListParts => S3ActionClass::List,
+            S3Action::CompleteMultipartUpload
+            | S3Action::CopyObject
+            | S3Action::CreateMultipartUpload
+            | S3Action::PostObject
+            | S3Action::PutObject
+            | S3Action::PutObjectAcl
+            | S3Action::PutObjectLegalHold
+            | S3Action::PutObjectRetention
+            | S3Action::PutObjectTagging
+            | S3Action::RestoreObject
+            | S3Action::UploadPart
+            | S3Action::UploadPartCopy
+            | S3Action::WriteGetObjectResponse => S3ActionClass::Write,
+            S3Action::AbortMultipartUpload
+            | S3Action::DeleteObject
+            | S3Action::DeleteObjects
+            | S3Action::DeleteObjectTagging => S3ActionClass::Delete,
+            _ => S3ActionClass::Admin,
+        }
+    }
+}
@@ -0,0 +1,202 @@
+// https://docs.aws.amazon.com/AmazonS3/latest/API/API_PutObjectTagging.html
+
+use std::collections::HashMap;
+
+use crate::{lifecycle::Tag, url_decode, S3Error};
+
+// Maximum number of tags on an object and on a bucket
+pub static MAX_OBJECT_TAGS: usize = 10;
+pub static MAX_BUCKET_TAGS: usize = 50;
+
+static MAX_KEY_LENGTH: usize = 128;
+static MAX_VALUE_LENGTH: usize = 256;
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "Tagging")]
+pub struct Tagging {
+    #[serde(rename = "TagSet", default)]
+    pub tag_set: TagSet,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct TagSet {
+    #[serde(rename = "Tag", default)]
+    pub tags: Vec<Tag>,
+}
+
+impl Tagging {
+    pub fn from_xml(body: &[u8], max_tags: usize) -> Result<Self, S3Error> {
+        let body = std::str::from_utf8(body).map_err(|_| S3Error::MalformedXML)?;
+        let tagging: Self = quick_xml::de::from_str(body).map_err(|_| S3Error::MalformedXML)?;
+        tagging.validate(max_tags)?;
+        Ok(tagging)
+    }
+
+    /// Parse the URL encoded `key=value&...` form of the x-amz-tagging
+    /// header of an upload.
+    pub fn from_header(value: &str) -> Result<Self, S3Error> {
+        let mut tags = vec![];
+        for pair in value.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            tags.push(Tag {
+                key: url_decode(key).ok_or_else(invalid_header)?,
+                value: url_decode(value).ok_or_else(invalid_header)?,
+            });
+        }
+        let tagging = Self {
+            tag_set: TagSet { tags },
+        };
+        tagging.validate(MAX_OBJECT_TAGS)?;
+        Ok(tagging)
+    }
+
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+
+    pub fn validate(&self, max_tags: usize) -> Result<(), S3Error> {
+        let tags = &self.tag_set.tags;
+        if tags.len() > max_tags {
+            return Err(S3Error::InvalidTag(format!(
+                "Cannot have more than {} tags",
+                max_tags
+            )));
+        }
+        for (i, tag) in tags.iter().enumerate() {
+            let key_length = tag.key.chars().count();
+            if key_length == 0 || key_length > MAX_KEY_LENGTH {
+                return Err(S3Error::InvalidTag(
+                    "The TagKey you have provided is invalid".to_string(),
+                ));
+            }
+            if tag.value.chars().count() > MAX_VALUE_LENGTH {
+                return Err(S3Error::InvalidTag(
+                    "The TagValue you have provided is invalid".to_string(),
+                ));
+            }
+            if !tag.key.chars().chain(tag.value.chars()).all(allowed_char) {
+                return Err(S3Error::InvalidTag(format!(
+                    "The tag {} has characters that are not allowed",
+                    tag.key
+                )));
+            }
+            if tag.key.starts_with("aws:") {
+                return Err(S3Error::InvalidTag(
+                    "Your TagKey cannot be prefixed with aws:".to_string(),
+                ));
+            }
+            if tags[..i].iter().any(|other| other.key == tag.key) {
+                return Err(S3Error::InvalidTag(
+                    "Cannot provide multiple Tags with the same key".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn from_map(tags: &HashMap<String, String>) -> Self {
+        let mut tags: Vec<Tag> = tags
+            .iter()
+            .map(|(key, value)| Tag {
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .collect();
+        tags.sort_by(|a, b| a.key.cmp(&b.key));
+        Self {
+            tag_set: TagSet { tags },
+        }
+    }
+
+    pub fn into_map(self) -> HashMap<String, String> {
+        self.tag_set
+            .tags
+            .into_iter()
+            .map(|tag| (tag.key, tag.value))
+            .collect()
+    }
+}
+
+fn invalid_header() -> S3Error {
+    S3Error::InvalidArgument("The header x-amz-tagging is invalid".to_string())
+}
+
+// Letters, digits and whitespace of any script, and + - = . _ : / @
+fn allowed_char(c: char) -> bool {
+    c.is_alphanumeric() || c.is_whitespace() || "+-=._:/@".contains(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let tagging = Tagging::from_xml(
+            br#"<Tagging xmlns="http://s3.amazonaws.com/doc/2006-03-01/"><TagSet>
+                <Tag><Key>project</Key><Value>apollo</Value></Tag>
+                <Tag><Key>class</Key><Value></Value></Tag>
+            </TagSet></Tagging>"#,
+            MAX_OBJECT_TAGS,
+        )
+        .unwrap();
+        let tags = tagging.clone().into_map();
+        assert_eq!(tags.get("project").map(String::as_str), Some("apollo"));
+        assert_eq!(tags.get("class").map(String::as_str), Some(""));
+
+        let roundtrip = Tagging::from_xml(
+            Tagging::from_map(&tags).to_xml().as_bytes(),
+            MAX_OBJECT_TAGS,
+        )
+        .unwrap();
+        assert_eq!(roundtrip.into_map(), tags);
+
+        let empty = Tagging::from_xml(b"<Tagging><TagSet></TagSet></Tagging>", 10).unwrap();
+        assert!(empty.tag_set.tags.is_empty());
+    }
+
+    #[test]
+    fn test_from_header() {
+        let tags = Tagging::from_header("project=apollo&team=data%20eng&note=a+b&flag")
+            .unwrap()
+            .into_map();
+        assert_eq!(tags.len(), 4);
+        assert_eq!(tags["team"], "data eng");
+        assert_eq!(tags["note"], "a b");
+        assert_eq!(tags["flag"], "");
+        assert!(Tagging::from_header("").unwrap().tag_set.tags.is_empty());
+        assert!(Tagging::from_header("a=%zz").is_err());
+        assert!(Tagging::from_header("a=1&a=2").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let tagging = |tags: Vec<(&str, &str)>| Tagging {
+            tag_set: TagSet {
+                tags: tags
+                    .into_iter()
+                    .map(|(key, value)| Tag {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                    })
+                    .collect(),
+            },
+        };
+        assert!(tagging(vec![("a", "b")]).validate(1).is_ok());
+        assert!(tagging(vec![("a", "b"), ("c", "d")]).validate(1).is_err());
+        assert!(tagging(vec![("", "b")]).validate(10).is_err());
+        assert!(tagging(vec![("aws:created", "b")]).validate(10).is_err());
+        assert!(tagging(vec![("a", "b;c")]).validate(10).is_err());
+        assert!(tagging(vec![("größe", "groß")]).validate(10).is_ok());
+
+        let key = "k".repeat(MAX_KEY_LENGTH);
+        let value = "v".repeat(MAX_VALUE_LENGTH);
+        assert!(tagging(vec![(&key, &value)]).validate(10).is_ok());
+        assert!(tagging(vec![(&format!("{}k", key), "")])
+            .validate(10)
+            .is_err());
+        assert!(tagging(vec![("k", &format!("{}v", value))])
+            .validate(10)
+            .is_err());
+    }
+}
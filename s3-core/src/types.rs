@@ -52,11 +52,50 @@ impl Default for ObjectMetadata {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+// Ordered from the hottest to the coldest class
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum StorageClass {
     Standard,
     ReducedRedundancy,
+    IntelligentTiering,
+    StandardIa,
+    OnezoneIa,
+    GlacierIr,
     Glacier,
+    DeepArchive,
+}
+
+impl StorageClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StorageClass::Standard => "STANDARD",
+            StorageClass::ReducedRedundancy => "REDUCED_REDUNDANCY",
+            StorageClass::IntelligentTiering => "INTELLIGENT_TIERING",
+            StorageClass::StandardIa => "STANDARD_IA",
+            StorageClass::OnezoneIa => "ONEZONE_IA",
+            StorageClass::GlacierIr => "GLACIER_IR",
+            StorageClass::Glacier => "GLACIER",
+            StorageClass::DeepArchive => "DEEP_ARCHIVE",
+        }
+    }
+}
+
+impl std::str::FromStr for StorageClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "STANDARD" => Ok(StorageClass::Standard),
+            "REDUCED_REDUNDANCY" => Ok(StorageClass::ReducedRedundancy),
+            "INTELLIGENT_TIERING" => Ok(StorageClass::IntelligentTiering),
+            "STANDARD_IA" => Ok(StorageClass::StandardIa),
+            "ONEZONE_IA" => Ok(StorageClass::OnezoneIa),
+            "GLACIER_IR" => Ok(StorageClass::GlacierIr),
+            "GLACIER" => Ok(StorageClass::Glacier),
+            "DEEP_ARCHIVE" => Ok(StorageClass::DeepArchive),
+            _ => Err(format!("Unknown storage class: {}", s)),
+        }
+    }
 }
 
 impl Default for StorageClass {
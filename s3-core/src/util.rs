@@ -1,6 +1,9 @@
 // Maximum object part size for multipart upload
 pub static MAX_OBJECT_PART_SIZE: usize = 5 * 1024 * 1024 * 1024; // 5GB
 
+// Minimum size of every part of a multipart upload but the last
+pub static MIN_OBJECT_PART_SIZE: usize = 5 * 1024 * 1024; // 5MB
+
 // https://docs.aws.amazon.com/AmazonS3/latest/userguide/bucketnamingrules.html
 pub fn is_valid_bucket_name(b: &str) -> bool {
     let len = b.len();
@@ -34,3 +37,99 @@ pub fn is_valid_bucket_name(b: &str) -> bool {
 
     true
 }
+
+/// Resolve a `Range: bytes=<first>-<last>` header against an object of
+/// `size` bytes into an inclusive byte range. Like S3, headers that do not
+/// parse or ask for several ranges are ignored and yield None.
+pub fn parse_range(value: &str, size: u64) -> Result<Option<(u64, u64)>, crate::S3Error> {
+    let spec = match value.trim().strip_prefix("bytes=") {
+        Some(spec) if !spec.contains(',') => spec,
+        _ => return Ok(None),
+    };
+    let (first, last) = match spec.split_once('-') {
+        Some(range) => range,
+        None => return Ok(None),
+    };
+    let range = match (first.trim(), last.trim()) {
+        ("", "") => return Ok(None),
+        // The last `n` bytes
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return Err(crate::S3Error::InvalidRange),
+            Ok(n) => (size.saturating_sub(n), size.saturating_sub(1)),
+            Err(_) => return Ok(None),
+        },
+        (first, last) => {
+            let first = match first.parse::<u64>() {
+                Ok(first) => first,
+                Err(_) => return Ok(None),
+            };
+            let last = match last {
+                "" => u64::MAX,
+                last => match last.parse::<u64>() {
+                    Ok(last) if last >= first => last,
+                    _ => return Ok(None),
+                },
+            };
+            (first, last.min(size.saturating_sub(1)))
+        }
+    };
+    if size == 0 || range.0 >= size {
+        return Err(crate::S3Error::InvalidRange);
+    }
+    Ok(Some(range))
+}
+
+/// Percent decoding of a URL query or form component, `+` stands for a
+/// space. None when the escapes or the decoded UTF-8 are invalid.
+pub fn url_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = value.get(i + 1..i + 3)?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-9", 100).unwrap(), Some((0, 9)));
+        assert_eq!(parse_range("bytes=90-200", 100).unwrap(), Some((90, 99)));
+        assert_eq!(parse_range("bytes=10-", 100).unwrap(), Some((10, 99)));
+        assert_eq!(parse_range("bytes=-10", 100).unwrap(), Some((90, 99)));
+        assert_eq!(parse_range("bytes=-200", 100).unwrap(), Some((0, 99)));
+        assert_eq!(parse_range("bytes=0-1,5-6", 100).unwrap(), None);
+        assert_eq!(parse_range("bytes=9-0", 100).unwrap(), None);
+        assert_eq!(parse_range("items=0-9", 100).unwrap(), None);
+        assert!(parse_range("bytes=100-", 100).is_err());
+        assert!(parse_range("bytes=-0", 100).is_err());
+        assert!(parse_range("bytes=0-", 0).is_err());
+    }
+
+    #[test]
+    fn test_url_decode() {
+        assert_eq!(url_decode("a%20b+c").as_deref(), Some("a b c"));
+        assert_eq!(url_decode("%E2%9C%93").as_deref(), Some("\u{2713}"));
+        assert_eq!(url_decode("%zz"), None);
+        assert_eq!(url_decode("%2"), None);
+        assert_eq!(url_decode("%ff"), None);
+    }
+}
@@ -1,5 +1,125 @@
+// https://docs.aws.amazon.com/AmazonS3/latest/API/API_PutBucketVersioning.html
+
+use crate::S3Error;
+
 pub type VersioningStatus = u8;
 
 pub const VERSIONING_DISABLED: VersioningStatus = 0;
 pub const VERSIONING_ENABLED: VersioningStatus = 1;
 pub const VERSIONING_SUSPENDED: VersioningStatus = 2;
+
+// Values of Status and MfaDelete
+static ENABLED: &str = "Enabled";
+static SUSPENDED: &str = "Suspended";
+static DISABLED: &str = "Disabled";
+
+/// Versioning state of a bucket. A bucket that was never versioned has
+/// neither field.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "VersioningConfiguration")]
+pub struct VersioningConfiguration {
+    #[serde(rename = "Status", default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(rename = "MfaDelete", default, skip_serializing_if = "Option::is_none")]
+    pub mfa_delete: Option<String>,
+}
+
+impl VersioningConfiguration {
+    pub fn new(status: VersioningStatus) -> Self {
+        let status = match status {
+            VERSIONING_ENABLED => Some(ENABLED.to_string()),
+            VERSIONING_SUSPENDED => Some(SUSPENDED.to_string()),
+            _ => None,
+        };
+        Self {
+            status,
+            mfa_delete: None,
+        }
+    }
+
+    pub fn from_xml(body: &[u8]) -> Result<Self, S3Error> {
+        let body = std::str::from_utf8(body).map_err(|_| S3Error::MalformedXML)?;
+        let config: Self = quick_xml::de::from_str(body).map_err(|_| S3Error::MalformedXML)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), S3Error> {
+        self.versioning_status()?;
+        match self.mfa_delete.as_deref() {
+            None => Ok(()),
+            Some(mfa_delete) if mfa_delete == ENABLED || mfa_delete == DISABLED => Ok(()),
+            Some(_) => Err(S3Error::MalformedXML),
+        }
+    }
+
+    /// Status the configuration sets. A request without Status leaves the
+    /// bucket as it is, which only a never versioned bucket reports.
+    pub fn versioning_status(&self) -> Result<VersioningStatus, S3Error> {
+        match self.status.as_deref() {
+            None => Ok(VERSIONING_DISABLED),
+            Some(status) if status == ENABLED => Ok(VERSIONING_ENABLED),
+            Some(status) if status == SUSPENDED => Ok(VERSIONING_SUSPENDED),
+            Some(_) => Err(S3Error::MalformedXML),
+        }
+    }
+
+    pub fn mfa_delete_enabled(&self) -> bool {
+        self.mfa_delete.as_deref() == Some(ENABLED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_configuration() {
+        let config = VersioningConfiguration::from_xml(
+            br#"<VersioningConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+                <Status>Enabled</Status>
+                <MfaDelete>Disabled</MfaDelete>
+            </VersioningConfiguration>"#,
+        )
+        .unwrap();
+        assert_eq!(config.versioning_status().unwrap(), VERSIONING_ENABLED);
+        assert!(!config.mfa_delete_enabled());
+        let roundtrip = VersioningConfiguration::from_xml(config.to_xml().as_bytes()).unwrap();
+        assert_eq!(roundtrip, config);
+
+        let suspended = VersioningConfiguration::from_xml(
+            b"<VersioningConfiguration><Status>Suspended</Status></VersioningConfiguration>",
+        )
+        .unwrap();
+        assert_eq!(suspended.versioning_status().unwrap(), VERSIONING_SUSPENDED);
+
+        for invalid in [
+            "<VersioningConfiguration><Status>On</Status></VersioningConfiguration>",
+            "<VersioningConfiguration><MfaDelete>Yes</MfaDelete></VersioningConfiguration>",
+        ] {
+            assert!(VersioningConfiguration::from_xml(invalid.as_bytes()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        assert_eq!(
+            VersioningConfiguration::new(VERSIONING_DISABLED).to_xml(),
+            "<VersioningConfiguration/>"
+        );
+        assert_eq!(
+            VersioningConfiguration::new(VERSIONING_ENABLED).to_xml(),
+            "<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>"
+        );
+        assert_eq!(
+            VersioningConfiguration::new(VERSIONING_SUSPENDED)
+                .versioning_status()
+                .unwrap(),
+            VERSIONING_SUSPENDED
+        );
+    }
+}
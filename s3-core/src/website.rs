@@ -0,0 +1,418 @@
+// https://docs.aws.amazon.com/AmazonS3/latest/API/API_PutBucketWebsite.html
+
+use crate::S3Error;
+
+// Maximum number of routing rules in a website configuration
+pub static MAX_ROUTING_RULES: usize = 50;
+
+// Status of redirects that do not set HttpRedirectCode
+pub static DEFAULT_REDIRECT_CODE: u16 = 301;
+
+static PROTOCOLS: [&str; 2] = ["http", "https"];
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename = "WebsiteConfiguration")]
+pub struct WebsiteConfiguration {
+    #[serde(
+        rename = "ErrorDocument",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub error_document: Option<ErrorDocument>,
+    #[serde(
+        rename = "IndexDocument",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub index_document: Option<IndexDocument>,
+    #[serde(
+        rename = "RedirectAllRequestsTo",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub redirect_all_requests_to: Option<RedirectAllRequestsTo>,
+    #[serde(
+        rename = "RoutingRules",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub routing_rules: Option<RoutingRules>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct ErrorDocument {
+    #[serde(rename = "Key")]
+    pub key: String,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct IndexDocument {
+    #[serde(rename = "Suffix")]
+    pub suffix: String,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct RedirectAllRequestsTo {
+    #[serde(rename = "HostName")]
+    pub host_name: String,
+    #[serde(rename = "Protocol", default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct RoutingRules {
+    #[serde(rename = "RoutingRule", default)]
+    pub rules: Vec<RoutingRule>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct RoutingRule {
+    #[serde(rename = "Condition", default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<Condition>,
+    #[serde(rename = "Redirect")]
+    pub redirect: Redirect,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct Condition {
+    #[serde(
+        rename = "HttpErrorCodeReturnedEquals",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub http_error_code_returned_equals: Option<u16>,
+    #[serde(
+        rename = "KeyPrefixEquals",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub key_prefix_equals: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct Redirect {
+    #[serde(rename = "HostName", default, skip_serializing_if = "Option::is_none")]
+    pub host_name: Option<String>,
+    #[serde(
+        rename = "HttpRedirectCode",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub http_redirect_code: Option<u16>,
+    #[serde(rename = "Protocol", default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+    #[serde(
+        rename = "ReplaceKeyPrefixWith",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub replace_key_prefix_with: Option<String>,
+    #[serde(
+        rename = "ReplaceKeyWith",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub replace_key_with: Option<String>,
+}
+
+impl WebsiteConfiguration {
+    pub fn from_xml(body: &[u8]) -> Result<Self, S3Error> {
+        let body = std::str::from_utf8(body).map_err(|_| S3Error::MalformedXML)?;
+        let config: Self = quick_xml::de::from_str(body).map_err(|_| S3Error::MalformedXML)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), S3Error> {
+        // RedirectAllRequestsTo stands alone
+        if let Some(redirect) = &self.redirect_all_requests_to {
+            if self.index_document.is_some()
+                || self.error_document.is_some()
+                || self.routing_rules.is_some()
+            {
+                return Err(S3Error::InvalidArgument(
+                    "RedirectAllRequestsTo cannot be provided in conjunction with other Routing/Redirect configurations.".to_string(),
+                ));
+            }
+            if redirect.host_name.is_empty() {
+                return Err(S3Error::MalformedXML);
+            }
+            return validate_protocol(redirect.protocol.as_deref());
+        }
+
+        match &self.index_document {
+            Some(index) if !index.suffix.is_empty() && !index.suffix.contains('/') => {}
+            Some(_) => {
+                return Err(S3Error::InvalidArgument(
+                    "The IndexDocument Suffix is not well formed".to_string(),
+                ))
+            }
+            None => {
+                return Err(S3Error::InvalidArgument(
+                    "A value for IndexDocument Suffix must be provided if RedirectAllRequestsTo is empty".to_string(),
+                ))
+            }
+        }
+        if let Some(error) = &self.error_document {
+            if error.key.is_empty() {
+                return Err(S3Error::InvalidArgument(
+                    "The ErrorDocument Key is not well formed".to_string(),
+                ));
+            }
+        }
+
+        let rules = self.rules();
+        if self.routing_rules.is_some() && (rules.is_empty() || rules.len() > MAX_ROUTING_RULES) {
+            return Err(S3Error::MalformedXML);
+        }
+        for rule in rules {
+            if let Some(condition) = &rule.condition {
+                if condition.http_error_code_returned_equals.is_none()
+                    && condition.key_prefix_equals.is_none()
+                {
+                    return Err(S3Error::MalformedXML);
+                }
+                if let Some(code) = condition.http_error_code_returned_equals {
+                    if !(400..600).contains(&code) {
+                        return Err(S3Error::InvalidArgument(format!(
+                            "The provided HTTP error code ({}) is not valid. Valid codes are 4XX or 5XX.",
+                            code
+                        )));
+                    }
+                }
+            }
+            let redirect = &rule.redirect;
+            if redirect.replace_key_prefix_with.is_some() && redirect.replace_key_with.is_some() {
+                return Err(S3Error::InvalidArgument(
+                    "You can only define ReplaceKeyPrefix or ReplaceKey but not both.".to_string(),
+                ));
+            }
+            if let Some(code) = redirect.http_redirect_code {
+                if !(300..400).contains(&code) {
+                    return Err(S3Error::InvalidArgument(format!(
+                        "The provided HTTP redirect code ({}) is not valid. Valid codes are 3XX.",
+                        code
+                    )));
+                }
+            }
+            validate_protocol(redirect.protocol.as_deref())?;
+        }
+        Ok(())
+    }
+
+    pub fn rules(&self) -> &[RoutingRule] {
+        self.routing_rules
+            .as_ref()
+            .map(|rules| rules.rules.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// First routing rule applying to `key`. Rules with an error code
+    /// condition only apply once a lookup failed with `error_code`, the
+    /// others only before the lookup.
+    pub fn routing_rule(&self, key: &str, error_code: Option<u16>) -> Option<&RoutingRule> {
+        self.rules()
+            .iter()
+            .find(|rule| rule.matches(key, error_code))
+    }
+
+    /// Key of the index document of a "directory" key, which is empty or
+    /// ends with a slash.
+    pub fn index_key(&self, key: &str) -> Option<String> {
+        let index = self.index_document.as_ref()?;
+        (key.is_empty() || key.ends_with('/')).then(|| format!("{}{}", key, index.suffix))
+    }
+}
+
+impl RedirectAllRequestsTo {
+    /// Location every request for `key` is sent to, using the protocol of
+    /// the request unless one is configured.
+    pub fn location(&self, key: &str, protocol: &str) -> String {
+        let protocol = self.protocol.as_deref().unwrap_or(protocol);
+        format!("{}://{}/{}", protocol, self.host_name, key)
+    }
+}
+
+impl RoutingRule {
+    pub fn matches(&self, key: &str, error_code: Option<u16>) -> bool {
+        let (code, prefix) = match &self.condition {
+            Some(condition) => (
+                condition.http_error_code_returned_equals,
+                condition.key_prefix_equals.as_deref().unwrap_or_default(),
+            ),
+            None => (None, ""),
+        };
+        code == error_code && key.starts_with(prefix)
+    }
+
+    /// Location of the redirect for `key`, the host and protocol of the
+    /// request are kept unless the rule replaces them.
+    pub fn location(&self, key: &str, host: &str, protocol: &str) -> String {
+        let redirect = &self.redirect;
+        let key = match (
+            &redirect.replace_key_with,
+            &redirect.replace_key_prefix_with,
+        ) {
+            (Some(replacement), _) => replacement.clone(),
+            (None, Some(replacement)) => {
+                let prefix = self
+                    .condition
+                    .as_ref()
+                    .and_then(|condition| condition.key_prefix_equals.as_deref())
+                    .unwrap_or_default();
+                format!("{}{}", replacement, &key[prefix.len()..])
+            }
+            (None, None) => key.to_string(),
+        };
+        format!(
+            "{}://{}/{}",
+            redirect.protocol.as_deref().unwrap_or(protocol),
+            redirect.host_name.as_deref().unwrap_or(host),
+            key
+        )
+    }
+
+    pub fn status_code(&self) -> u16 {
+        self.redirect
+            .http_redirect_code
+            .unwrap_or(DEFAULT_REDIRECT_CODE)
+    }
+}
+
+fn validate_protocol(protocol: Option<&str>) -> Result<(), S3Error> {
+    match protocol {
+        Some(protocol) if !PROTOCOLS.contains(&protocol) => Err(S3Error::InvalidArgument(format!(
+            "Invalid protocol {}, must be http or https",
+            protocol
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = WebsiteConfiguration::from_xml(
+            br#"<WebsiteConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+                <IndexDocument><Suffix>index.html</Suffix></IndexDocument>
+                <ErrorDocument><Key>error.html</Key></ErrorDocument>
+                <RoutingRules>
+                    <RoutingRule>
+                        <Condition><KeyPrefixEquals>docs/</KeyPrefixEquals></Condition>
+                        <Redirect><ReplaceKeyPrefixWith>documents/</ReplaceKeyPrefixWith></Redirect>
+                    </RoutingRule>
+                    <RoutingRule>
+                        <Condition><HttpErrorCodeReturnedEquals>404</HttpErrorCodeReturnedEquals></Condition>
+                        <Redirect>
+                            <HostName>fallback.example.com</HostName>
+                            <Protocol>https</Protocol>
+                            <HttpRedirectCode>302</HttpRedirectCode>
+                        </Redirect>
+                    </RoutingRule>
+                </RoutingRules>
+            </WebsiteConfiguration>"#,
+        )
+        .unwrap();
+        assert_eq!(config.rules().len(), 2);
+        let roundtrip = WebsiteConfiguration::from_xml(config.to_xml().as_bytes()).unwrap();
+        assert_eq!(roundtrip, config);
+
+        let redirect = WebsiteConfiguration::from_xml(
+            b"<WebsiteConfiguration><RedirectAllRequestsTo><HostName>example.com</HostName>\
+            </RedirectAllRequestsTo></WebsiteConfiguration>",
+        )
+        .unwrap();
+        assert_eq!(
+            redirect
+                .redirect_all_requests_to
+                .unwrap()
+                .location("a/b.html", "http"),
+            "http://example.com/a/b.html"
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        for invalid in [
+            "<WebsiteConfiguration></WebsiteConfiguration>",
+            "<WebsiteConfiguration><IndexDocument><Suffix>a/index.html</Suffix></IndexDocument>\
+            </WebsiteConfiguration>",
+            "<WebsiteConfiguration><IndexDocument><Suffix>index.html</Suffix></IndexDocument>\
+            <RedirectAllRequestsTo><HostName>example.com</HostName></RedirectAllRequestsTo>\
+            </WebsiteConfiguration>",
+            "<WebsiteConfiguration><RedirectAllRequestsTo><HostName>example.com</HostName>\
+            <Protocol>ftp</Protocol></RedirectAllRequestsTo></WebsiteConfiguration>",
+            "<WebsiteConfiguration><IndexDocument><Suffix>index.html</Suffix></IndexDocument>\
+            <RoutingRules><RoutingRule><Redirect><ReplaceKeyWith>a</ReplaceKeyWith>\
+            <ReplaceKeyPrefixWith>b</ReplaceKeyPrefixWith></Redirect></RoutingRule></RoutingRules>\
+            </WebsiteConfiguration>",
+            "<WebsiteConfiguration><IndexDocument><Suffix>index.html</Suffix></IndexDocument>\
+            <RoutingRules><RoutingRule><Condition><HttpErrorCodeReturnedEquals>200\
+            </HttpErrorCodeReturnedEquals></Condition><Redirect></Redirect></RoutingRule>\
+            </RoutingRules></WebsiteConfiguration>",
+        ] {
+            assert!(WebsiteConfiguration::from_xml(invalid.as_bytes()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_routing() {
+        let config = WebsiteConfiguration {
+            index_document: Some(IndexDocument {
+                suffix: "index.html".to_string(),
+            }),
+            routing_rules: Some(RoutingRules {
+                rules: vec![
+                    RoutingRule {
+                        condition: Some(Condition {
+                            key_prefix_equals: Some("docs/".to_string()),
+                            ..Default::default()
+                        }),
+                        redirect: Redirect {
+                            replace_key_prefix_with: Some("documents/".to_string()),
+                            ..Default::default()
+                        },
+                    },
+                    RoutingRule {
+                        condition: Some(Condition {
+                            http_error_code_returned_equals: Some(404),
+                            ..Default::default()
+                        }),
+                        redirect: Redirect {
+                            replace_key_with: Some("missing.html".to_string()),
+                            http_redirect_code: Some(302),
+                            ..Default::default()
+                        },
+                    },
+                ],
+            }),
+            ..Default::default()
+        };
+        assert_eq!(config.index_key(""), Some("index.html".to_string()));
+        assert_eq!(config.index_key("a/"), Some("a/index.html".to_string()));
+        assert_eq!(config.index_key("a"), None);
+
+        let rule = config.routing_rule("docs/guide.html", None).unwrap();
+        assert_eq!(rule.status_code(), 301);
+        assert_eq!(
+            rule.location("docs/guide.html", "site.example.com", "http"),
+            "http://site.example.com/documents/guide.html"
+        );
+        assert!(config.routing_rule("img/logo.png", None).is_none());
+        assert!(config.routing_rule("img/logo.png", Some(403)).is_none());
+
+        let rule = config.routing_rule("img/logo.png", Some(404)).unwrap();
+        assert_eq!(rule.status_code(), 302);
+        assert_eq!(
+            rule.location("img/logo.png", "site.example.com", "https"),
+            "https://site.example.com/missing.html"
+        );
+    }
+}
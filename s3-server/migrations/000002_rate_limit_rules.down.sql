@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS idx_rate_limits_rule;
+DELETE FROM rate_limits WHERE user_id IS NULL OR value !~ '^[0-9]+$';
+ALTER TABLE rate_limits ALTER COLUMN value TYPE INT USING value::INT;
+ALTER TABLE rate_limits ALTER COLUMN user_id SET NOT NULL;
+ALTER TABLE rate_limits DROP CONSTRAINT rate_limits_pkey;
+ALTER TABLE rate_limits DROP COLUMN id;
+ALTER TABLE rate_limits ADD PRIMARY KEY (user_id);
+ALTER TABLE rate_limits ADD CONSTRAINT rate_limits_user_id_dimension_value_key UNIQUE (user_id, dimension, value);
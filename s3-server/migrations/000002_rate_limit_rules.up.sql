@@ -0,0 +1,10 @@
+-- Rate limit rules are no longer keyed by user. A rule applies to one
+-- dimension (user_id, access_key, bucket, ip or action) and value, optionally
+-- scoped to a single user. A value of '*' applies the limit to each distinct
+-- value of the dimension.
+ALTER TABLE rate_limits DROP CONSTRAINT rate_limits_pkey;
+ALTER TABLE rate_limits DROP CONSTRAINT rate_limits_user_id_dimension_value_key;
+ALTER TABLE rate_limits ADD COLUMN id UUID NOT NULL DEFAULT gen_random_uuid() PRIMARY KEY;
+ALTER TABLE rate_limits ALTER COLUMN user_id DROP NOT NULL;
+ALTER TABLE rate_limits ALTER COLUMN value TYPE TEXT USING value::TEXT;
+CREATE UNIQUE INDEX idx_rate_limits_rule ON rate_limits(COALESCE(user_id, 0), dimension, value);
@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS idx_multipart_uploads_created_at;
+DROP INDEX IF EXISTS idx_lifecycle_rules_due;
+ALTER TABLE lifecycle_rules DROP CONSTRAINT lifecycle_rules_bucket_id_fkey;
+ALTER TABLE lifecycle_rules ADD CONSTRAINT lifecycle_rules_bucket_id_fkey
+    FOREIGN KEY (bucket_id) REFERENCES buckets(id);
+DELETE FROM objects WHERE is_delete_marker;
+ALTER TABLE objects DROP COLUMN is_delete_marker;
+ALTER TABLE objects DROP COLUMN tags;
@@ -0,0 +1,12 @@
+-- Lifecycle rules filter on object tags and expire into delete markers
+ALTER TABLE objects ADD COLUMN tags JSONB DEFAULT NULL;
+ALTER TABLE objects ADD COLUMN is_delete_marker BOOLEAN NOT NULL DEFAULT false;
+
+-- Rules go away with their bucket
+ALTER TABLE lifecycle_rules DROP CONSTRAINT lifecycle_rules_bucket_id_fkey;
+ALTER TABLE lifecycle_rules ADD CONSTRAINT lifecycle_rules_bucket_id_fkey
+    FOREIGN KEY (bucket_id) REFERENCES buckets(id) ON DELETE CASCADE;
+
+-- Workers poll for rules that are due
+CREATE INDEX idx_lifecycle_rules_due ON lifecycle_rules(next_execution_at) WHERE status = 'active';
+CREATE INDEX idx_multipart_uploads_created_at ON multipart_uploads(bucket_id, created_at);
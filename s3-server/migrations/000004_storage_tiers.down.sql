@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_objects_storage_class;
+ALTER TABLE objects DROP COLUMN backend_id;
+DROP INDEX IF EXISTS idx_storage_backends_class;
+ALTER TABLE storage_backends DROP COLUMN storage_class;
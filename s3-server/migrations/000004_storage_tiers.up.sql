@@ -0,0 +1,9 @@
+-- Storage class held by a backend, e.g. 'GLACIER' for a cold tier. Objects of
+-- other classes stay on the default backend.
+ALTER TABLE storage_backends ADD COLUMN storage_class TEXT;
+CREATE UNIQUE INDEX idx_storage_backends_class ON storage_backends(storage_class)
+    WHERE is_active AND storage_class IS NOT NULL;
+
+-- Backend holding the object data, NULL for the default backend
+ALTER TABLE objects ADD COLUMN backend_id UUID REFERENCES storage_backends(id);
+CREATE INDEX idx_objects_storage_class ON objects(bucket_id, storage_class) WHERE is_latest;
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_buckets_backend;
+ALTER TABLE buckets DROP COLUMN backend_id;
@@ -0,0 +1,4 @@
+-- Every bucket lives on one storage backend. Buckets created before backends
+-- were tracked keep a NULL backend and are served by the default backend.
+ALTER TABLE buckets ADD COLUMN backend_id UUID REFERENCES storage_backends(id);
+CREATE INDEX idx_buckets_backend ON buckets(backend_id);
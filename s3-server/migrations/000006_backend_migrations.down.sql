@@ -0,0 +1,7 @@
+ALTER TABLE backend_migrations DROP CONSTRAINT backend_migrations_bucket_id_fkey;
+ALTER TABLE backend_migrations ADD CONSTRAINT backend_migrations_bucket_id_fkey
+    FOREIGN KEY (bucket_id) REFERENCES buckets(id);
+
+DROP INDEX IF EXISTS idx_backend_migrations_running;
+ALTER TABLE backend_migrations DROP COLUMN updated_at;
+ALTER TABLE backend_migrations DROP COLUMN lease_until;
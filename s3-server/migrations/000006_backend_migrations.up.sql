@@ -0,0 +1,13 @@
+-- Gateways lease a running migration, a crashed run is resumed from its
+-- checkpoint in progress once the lease expires
+ALTER TABLE backend_migrations ADD COLUMN lease_until TIMESTAMP WITH TIME ZONE;
+ALTER TABLE backend_migrations ADD COLUMN updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW();
+
+-- A bucket moves to one backend at a time
+CREATE UNIQUE INDEX idx_backend_migrations_running ON backend_migrations(bucket_id)
+    WHERE status = 'in_progress';
+
+-- Migrations go away with their bucket
+ALTER TABLE backend_migrations DROP CONSTRAINT backend_migrations_bucket_id_fkey;
+ALTER TABLE backend_migrations ADD CONSTRAINT backend_migrations_bucket_id_fkey
+    FOREIGN KEY (bucket_id) REFERENCES buckets(id) ON DELETE CASCADE;
@@ -0,0 +1,7 @@
+-- Enum values cannot be dropped, the type is rebuilt without LOCAL. This
+-- fails while a LOCAL backend is still registered.
+ALTER TYPE storage_provider RENAME TO storage_provider_old;
+CREATE TYPE storage_provider AS ENUM ('AWS', 'GCP', 'AZURE', 'DO', 'CEPH');
+ALTER TABLE storage_backends
+    ALTER COLUMN provider TYPE storage_provider USING provider::TEXT::storage_provider;
+DROP TYPE storage_provider_old;
@@ -0,0 +1,2 @@
+-- Blobs on the gateway's own disk, the root directory is set in config
+ALTER TYPE storage_provider ADD VALUE IF NOT EXISTS 'LOCAL';
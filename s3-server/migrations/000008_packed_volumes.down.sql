@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_objects_volume;
+DROP TABLE IF EXISTS volumes;
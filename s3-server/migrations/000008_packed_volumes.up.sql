@@ -0,0 +1,17 @@
+-- Small objects are packed into shared volume blobs. An object in a volume
+-- has backend_specific_name set to the volume id and backend_specific_id set
+-- to `<volume id>:<offset>:<length>`.
+CREATE TABLE volumes (
+    id UUID PRIMARY KEY,
+    bucket_id UUID NOT NULL REFERENCES buckets(id) ON DELETE CASCADE,
+    backend_id UUID NOT NULL REFERENCES storage_backends(id),
+    size BIGINT NOT NULL,
+    -- Bytes still referenced by objects, the rest is reclaimed by compaction
+    live_bytes BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    lease_until TIMESTAMP WITH TIME ZONE
+);
+CREATE INDEX idx_volumes_created ON volumes(created_at);
+
+CREATE INDEX idx_objects_volume ON objects(bucket_id, backend_specific_name)
+    WHERE backend_specific_id IS NOT NULL;
@@ -0,0 +1,2 @@
+-- Payloads of tiny objects are kept in the index instead of a storage backend
+ALTER TABLE objects ADD COLUMN inline_data BYTEA;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS blobs;
+ALTER TABLE buckets DROP COLUMN IF EXISTS dedup;
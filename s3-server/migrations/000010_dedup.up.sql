@@ -0,0 +1,20 @@
+-- Buckets in dedup mode store every payload once under its SHA-256
+ALTER TABLE buckets ADD COLUMN dedup BOOLEAN NOT NULL DEFAULT false;
+
+-- Content addressed blobs of dedup buckets. Objects sharing a blob have
+-- backend_specific_id set to `sha256:<hex digest>`.
+CREATE TABLE blobs (
+    bucket_id UUID NOT NULL REFERENCES buckets(id) ON DELETE CASCADE,
+    backend_id UUID NOT NULL REFERENCES storage_backends(id),
+    sha256 TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    -- Objects referencing the blob, -1 while the collector deletes it
+    refcount BIGINT NOT NULL DEFAULT 0,
+    -- Set once the data is on the backend
+    stored BOOLEAN NOT NULL DEFAULT false,
+    -- When the last reference went away
+    released_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (bucket_id, backend_id, sha256)
+);
+CREATE INDEX idx_blobs_released ON blobs(released_at) WHERE refcount <= 0;
@@ -0,0 +1,2 @@
+ALTER TABLE objects DROP COLUMN IF EXISTS encryption;
+ALTER TABLE buckets DROP COLUMN IF EXISTS encryption;
@@ -0,0 +1,6 @@
+-- Default encryption of a bucket, as set by PutBucketEncryption
+ALTER TABLE buckets ADD COLUMN encryption JSONB;
+
+-- Algorithm, master key id, wrapped data key and chunk size of an object
+-- encrypted by the gateway, NULL for plaintext objects
+ALTER TABLE objects ADD COLUMN encryption JSONB;
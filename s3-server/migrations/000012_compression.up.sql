@@ -0,0 +1,4 @@
+-- Codec, frame size and compressed frame sizes of an object compressed by
+-- the gateway, NULL for objects stored as is. objects.size stays the size
+-- of the original data.
+ALTER TABLE objects ADD COLUMN compression JSONB;
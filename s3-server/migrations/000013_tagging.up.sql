@@ -0,0 +1,3 @@
+-- Tags of a bucket as a key to value object, as set by PutBucketTagging.
+-- Object tags live in objects.tags.
+ALTER TABLE buckets ADD COLUMN tags JSONB;
@@ -0,0 +1,9 @@
+-- Object Lock configuration of a bucket created with
+-- x-amz-bucket-object-lock-enabled, NULL for buckets without Object Lock
+ALTER TABLE buckets ADD COLUMN object_lock JSONB;
+
+-- Retention and legal hold of an object version. Versions are only deleted
+-- once neither protects them, see delete_object_version.
+ALTER TABLE objects ADD COLUMN retention_mode TEXT;
+ALTER TABLE objects ADD COLUMN retain_until TIMESTAMPTZ;
+ALTER TABLE objects ADD COLUMN legal_hold BOOLEAN NOT NULL DEFAULT false;
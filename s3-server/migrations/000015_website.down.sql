@@ -0,0 +1,2 @@
+ALTER TABLE objects DROP COLUMN IF EXISTS website_redirect_location;
+ALTER TABLE buckets DROP COLUMN IF EXISTS website;
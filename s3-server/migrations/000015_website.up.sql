@@ -0,0 +1,6 @@
+-- Static website configuration of a bucket, as set by PutBucketWebsite.
+ALTER TABLE buckets ADD COLUMN website JSONB;
+
+-- Redirect served by the website endpoint instead of the object, from the
+-- x-amz-website-redirect-location header of the upload.
+ALTER TABLE objects ADD COLUMN website_redirect_location TEXT;
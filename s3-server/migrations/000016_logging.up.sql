@@ -0,0 +1,3 @@
+-- Target bucket and prefix of the server access logs of a bucket, as set by
+-- PutBucketLogging. Logging is disabled when NULL.
+ALTER TABLE buckets ADD COLUMN logging JSONB;
@@ -0,0 +1,3 @@
+-- Event notification configuration of a bucket, as set by
+-- PutBucketNotificationConfiguration. No events are sent when NULL.
+ALTER TABLE buckets ADD COLUMN notification JSONB;
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS replication_queue;
+ALTER TABLE objects DROP COLUMN IF EXISTS replication_status;
+ALTER TABLE buckets DROP COLUMN IF EXISTS replication;
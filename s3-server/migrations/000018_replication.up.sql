@@ -0,0 +1,25 @@
+-- Replication configuration of a bucket, as set by PutBucketReplication
+ALTER TABLE buckets ADD COLUMN replication JSONB;
+
+-- PENDING, COMPLETED or FAILED for versions of a replicated bucket, REPLICA
+-- for the copies in the destination bucket, NULL for versions no rule covers
+ALTER TABLE objects ADD COLUMN replication_status TEXT;
+
+-- Versions waiting to be copied to their destination. A row is added in the
+-- transaction that indexes a PENDING version, and removed once the version
+-- is replicated. Gateways lease due rows by pushing next_attempt_at, failed
+-- copies are retried with backoff and kept with status 'failed' once they
+-- run out of attempts.
+CREATE TABLE replication_queue (
+    bucket_id UUID NOT NULL REFERENCES buckets(id) ON DELETE CASCADE,
+    key TEXT NOT NULL,
+    version_id UUID NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INT NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (bucket_id, key, version_id)
+);
+CREATE INDEX idx_replication_queue_due ON replication_queue(next_attempt_at)
+    WHERE status = 'pending';
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_objects_failover;
+ALTER TABLE objects DROP COLUMN IF EXISTS failover_from;
+ALTER TABLE buckets DROP COLUMN IF EXISTS fallback_backend_id;
@@ -0,0 +1,9 @@
+-- Writes for a bucket go to its fallback backend while its own backend is
+-- down, and are moved back once it recovers
+ALTER TABLE buckets ADD COLUMN fallback_backend_id UUID REFERENCES storage_backends(id);
+
+-- Backend a version was meant for when it was written to a fallback backend,
+-- cleared once the data has been moved back
+ALTER TABLE objects ADD COLUMN failover_from UUID REFERENCES storage_backends(id);
+CREATE INDEX idx_objects_failover ON objects(bucket_id, key, version_id)
+    WHERE failover_from IS NOT NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE multipart_uploads DROP COLUMN IF EXISTS backend_id;
+ALTER TABLE multipart_uploads DROP COLUMN IF EXISTS owner_id;
@@ -0,0 +1,5 @@
+-- Uploads remember who started them and the backend their parts are staged
+-- on, the object is assembled there. The settings of the object taken from
+-- CreateMultipartUpload are kept in metadata.
+ALTER TABLE multipart_uploads ADD COLUMN owner_id BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE multipart_uploads ADD COLUMN backend_id UUID REFERENCES storage_backends(id);
@@ -0,0 +1,3 @@
+-- Every part of an encrypted multipart upload is sealed with a data key of
+-- its own, kept here with the part until the upload is completed
+ALTER TABLE multipart_parts ADD COLUMN encryption JSONB;
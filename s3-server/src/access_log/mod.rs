@@ -0,0 +1,5 @@
+mod record;
+mod writer;
+
+pub use record::AccessLogRecord;
+pub use writer::{AccessLogWriter, AccessLogger};
@@ -0,0 +1,337 @@
+use std::time::Duration;
+
+use axum::http::{header, response::Parts, HeaderMap};
+use chrono::{DateTime, Utc};
+use s3_core::{error::ErrorCode, logging::LoggingEnabled, S3Action};
+use serde::Serialize;
+
+use crate::filter::S3Data;
+
+/// A request in the server access log.
+///
+/// https://docs.aws.amazon.com/AmazonS3/latest/userguide/LogFormat.html
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct AccessLogRecord {
+    pub bucket_owner: Option<i64>,
+    pub bucket: Option<String>,
+    pub time: DateTime<Utc>,
+    pub remote_ip: String,
+    pub requester: Option<i64>,
+    pub request_id: String,
+    pub operation: String,
+    pub key: Option<String>,
+    pub request_uri: String,
+    pub http_status: u16,
+    pub error_code: Option<String>,
+    pub bytes_sent: u64,
+    pub object_size: Option<u64>,
+    pub total_time_ms: u64,
+    pub turn_around_time_ms: u64,
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+    pub version_id: Option<String>,
+    pub signature_version: Option<String>,
+    pub auth_type: Option<String>,
+    pub host_header: Option<String>,
+    // Where the bucket's logs are delivered, not part of the record
+    #[serde(skip)]
+    pub target: Option<LoggingEnabled>,
+}
+
+impl AccessLogRecord {
+    /// Record of a request as far as it is known once its response is
+    /// ready. The bytes sent and the total time are known once the body has
+    /// been sent.
+    pub fn new(
+        data: &S3Data,
+        response: &Parts,
+        time: DateTime<Utc>,
+        turn_around: Duration,
+    ) -> Self {
+        let req = &data.req;
+        let header = |headers: &HeaderMap, name: header::HeaderName| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let (signature_version, auth_type) = if data.anonymous {
+            (None, None)
+        } else {
+            signature(req.headers(), req.uri().query().unwrap_or_default())
+        };
+        let object_size = match data.action {
+            S3Action::PutObject => Some(req.body().len() as u64),
+            _ => header(&response.headers, header::CONTENT_LENGTH)
+                .and_then(|length| length.parse().ok()),
+        };
+
+        Self {
+            bucket_owner: data.bucket.as_ref().map(|bucket| bucket.user_id),
+            bucket: (!data.bucket_name.is_empty()).then(|| data.bucket_name.clone()),
+            time,
+            remote_ip: data.client_ip.to_string(),
+            requester: (!data.anonymous && !data.auth_key.access_key.is_empty())
+                .then_some(data.auth_key.user_id),
+            request_id: data.request_id.clone(),
+            operation: operation(&data.action, req.method().as_str()),
+            key: (!data.key.is_empty()).then(|| data.key.clone()),
+            request_uri: format!(
+                "{} {} {:?}",
+                req.method(),
+                req.uri()
+                    .path_and_query()
+                    .map(|path| path.as_str())
+                    .unwrap_or("/"),
+                req.version()
+            ),
+            http_status: response.status.as_u16(),
+            error_code: response
+                .extensions
+                .get::<ErrorCode>()
+                .map(|code| code.0.clone()),
+            object_size,
+            turn_around_time_ms: turn_around.as_millis() as u64,
+            referer: header(req.headers(), header::REFERER),
+            user_agent: header(req.headers(), header::USER_AGENT),
+            version_id: response
+                .headers
+                .get("x-amz-version-id")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            signature_version,
+            auth_type,
+            host_header: header(req.headers(), header::HOST),
+            target: data
+                .bucket
+                .as_ref()
+                .and_then(|bucket| bucket.logging.clone()),
+            ..Default::default()
+        }
+    }
+
+    /// The record in the S3 server access log format, as delivered to
+    /// target buckets.
+    pub fn s3_line(&self) -> String {
+        let bytes_sent = (self.bytes_sent > 0).then_some(self.bytes_sent);
+        [
+            field(self.bucket_owner),
+            field(self.bucket.as_deref()),
+            format!("[{}]", self.time.format("%d/%b/%Y:%H:%M:%S %z")),
+            self.remote_ip.clone(),
+            field(self.requester),
+            self.request_id.clone(),
+            self.operation.clone(),
+            field(self.key.as_deref()),
+            quoted(Some(&self.request_uri)),
+            self.http_status.to_string(),
+            field(self.error_code.as_deref()),
+            field(bytes_sent),
+            field(self.object_size),
+            self.total_time_ms.to_string(),
+            self.turn_around_time_ms.to_string(),
+            quoted(self.referer.as_deref()),
+            quoted(self.user_agent.as_deref()),
+            field(self.version_id.as_deref()),
+            // Host ID and cipher suite
+            "-".to_string(),
+            field(self.signature_version.as_deref()),
+            "-".to_string(),
+            field(self.auth_type.as_deref()),
+            field(self.host_header.as_deref()),
+            // TLS version, access point ARN and ACL required
+            "-".to_string(),
+            "-".to_string(),
+            "-".to_string(),
+        ]
+        .join(" ")
+    }
+
+    /// The record in the combined log format of web servers.
+    pub fn combined_line(&self) -> String {
+        let bytes_sent = (self.bytes_sent > 0).then_some(self.bytes_sent);
+        format!(
+            "{} - {} [{}] {} {} {} {} {}",
+            self.remote_ip,
+            field(self.requester),
+            self.time.format("%d/%b/%Y:%H:%M:%S %z"),
+            quoted(Some(&self.request_uri)),
+            self.http_status,
+            field(bytes_sent),
+            quoted(self.referer.as_deref()),
+            quoted(self.user_agent.as_deref()),
+        )
+    }
+
+    pub fn json_line(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Operation of a request as S3 logs it, e.g. REST.GET.OBJECT or
+/// REST.PUT.CORS.
+pub fn operation(action: &S3Action, method: &str) -> String {
+    let resource = match action {
+        S3Action::GetObject
+        | S3Action::PutObject
+        | S3Action::HeadObject
+        | S3Action::DeleteObject
+        | S3Action::CopyObject
+        | S3Action::PostObject
+        | S3Action::WebsiteRequest => "OBJECT".to_string(),
+        S3Action::CreateBucket
+        | S3Action::DeleteBucket
+        | S3Action::HeadBucket
+        | S3Action::ListObjects
+        | S3Action::ListObjectsV2 => "BUCKET".to_string(),
+        S3Action::ListObjectVersions => "BUCKETVERSIONS".to_string(),
+        S3Action::ListBuckets => "SERVICE".to_string(),
+        S3Action::OptionsPreflight => "PREFLIGHT".to_string(),
+        S3Action::Unknown => "UNKNOWN".to_string(),
+        // The resource named by the action, e.g. GetBucketCors is CORS
+        _ => {
+            let name = format!("{:?}", action);
+            let name = ["Get", "Put", "Delete", "Head", "List"]
+                .iter()
+                .find_map(|verb| name.strip_prefix(verb))
+                .unwrap_or(&name);
+            let name = name.strip_prefix("Bucket").unwrap_or(name);
+            let name = name.strip_suffix("Configuration").unwrap_or(name);
+            upper_snake_case(name)
+        }
+    };
+    let kind = if *action == S3Action::WebsiteRequest {
+        "WEBSITE"
+    } else {
+        "REST"
+    };
+    format!("{}.{}.{}", kind, method, resource)
+}
+
+// Signature version and authentication type of a signed request
+fn signature(headers: &HeaderMap, query: &str) -> (Option<String>, Option<String>) {
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let (version, auth_type) = if authorization.starts_with("AWS4-HMAC-SHA256") {
+        ("SigV4", "AuthHeader")
+    } else if authorization.starts_with("AWS ") {
+        ("SigV2", "AuthHeader")
+    } else if query.contains("X-Amz-Algorithm=") {
+        ("SigV4", "QueryString")
+    } else if query.contains("Signature=") {
+        ("SigV2", "QueryString")
+    } else {
+        return (None, None);
+    };
+    (Some(version.to_string()), Some(auth_type.to_string()))
+}
+
+fn upper_snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if i > 0 && c.is_ascii_uppercase() {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_uppercase());
+    }
+    snake
+}
+
+// A field, - when absent
+fn field<T: ToString>(value: Option<T>) -> String {
+    value.map_or("-".to_string(), |value| value.to_string())
+}
+
+// A quoted field, quotes within it are escaped
+fn quoted(value: Option<&str>) -> String {
+    format!(
+        "\"{}\"",
+        value.map_or("-".to_string(), |value| value.replace('"', "\\\""))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn record() -> AccessLogRecord {
+        AccessLogRecord {
+            bucket_owner: Some(42),
+            bucket: Some("photos".to_string()),
+            time: Utc.with_ymd_and_hms(2024, 2, 6, 0, 0, 38).unwrap(),
+            remote_ip: "192.0.2.3".to_string(),
+            requester: Some(42),
+            request_id: "3E57427F3EXAMPLE".to_string(),
+            operation: "REST.GET.OBJECT".to_string(),
+            key: Some("cat.jpg".to_string()),
+            request_uri: "GET /photos/cat.jpg HTTP/1.1".to_string(),
+            http_status: 200,
+            bytes_sent: 113,
+            object_size: Some(113),
+            total_time_ms: 7,
+            turn_around_time_ms: 3,
+            user_agent: Some("aws-cli/2.15 \"test\"".to_string()),
+            signature_version: Some("SigV4".to_string()),
+            auth_type: Some("AuthHeader".to_string()),
+            host_header: Some("localhost:3000".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_s3_line() {
+        assert_eq!(
+            record().s3_line(),
+            "42 photos [06/Feb/2024:00:00:38 +0000] 192.0.2.3 42 3E57427F3EXAMPLE \
+            REST.GET.OBJECT cat.jpg \"GET /photos/cat.jpg HTTP/1.1\" 200 - 113 113 7 3 \"-\" \
+            \"aws-cli/2.15 \\\"test\\\"\" - - SigV4 - AuthHeader localhost:3000 - - -"
+        );
+        let anonymous = AccessLogRecord {
+            requester: None,
+            http_status: 404,
+            error_code: Some("NoSuchKey".to_string()),
+            bytes_sent: 0,
+            ..record()
+        };
+        assert!(anonymous.s3_line().contains("192.0.2.3 - 3E57427F3EXAMPLE"));
+        assert!(anonymous.s3_line().contains(" 404 NoSuchKey - 113 "));
+    }
+
+    #[test]
+    fn test_combined_line() {
+        assert_eq!(
+            record().combined_line(),
+            "192.0.2.3 - 42 [06/Feb/2024:00:00:38 +0000] \"GET /photos/cat.jpg HTTP/1.1\" 200 113 \
+            \"-\" \"aws-cli/2.15 \\\"test\\\"\""
+        );
+        let json: serde_json::Value = serde_json::from_str(&record().json_line()).unwrap();
+        assert_eq!(json["operation"], "REST.GET.OBJECT");
+        assert_eq!(json["bytes_sent"], 113);
+    }
+
+    #[test]
+    fn test_operation() {
+        assert_eq!(operation(&S3Action::GetObject, "GET"), "REST.GET.OBJECT");
+        assert_eq!(
+            operation(&S3Action::ListObjectsV2, "GET"),
+            "REST.GET.BUCKET"
+        );
+        assert_eq!(operation(&S3Action::PutBucketCors, "PUT"), "REST.PUT.CORS");
+        assert_eq!(
+            operation(&S3Action::GetBucketLifecycleConfiguration, "GET"),
+            "REST.GET.LIFECYCLE"
+        );
+        assert_eq!(
+            operation(&S3Action::PutObjectTagging, "PUT"),
+            "REST.PUT.OBJECT_TAGGING"
+        );
+        assert_eq!(
+            operation(&S3Action::WebsiteRequest, "GET"),
+            "WEBSITE.GET.OBJECT"
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use std::{collections::HashMap, sync::Arc, time::Duration, time::Instant};
+
+use axum::body::{Body, Bytes};
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+use crate::config::AccessLoggingConfig;
+use crate::filter::S3Data;
+
+use super::AccessLogRecord;
+
+/// Format of the local access log.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LocalFormat {
+    Combined,
+    Json,
+    S3,
+}
+
+impl LocalFormat {
+    fn parse(format: &str) -> Self {
+        match format {
+            "{json}" => Self::Json,
+            "{s3}" => Self::S3,
+            _ => Self::Combined,
+        }
+    }
+
+    fn line(&self, record: &AccessLogRecord) -> String {
+        match self {
+            Self::Combined => record.combined_line(),
+            Self::Json => record.json_line(),
+            Self::S3 => record.s3_line(),
+        }
+    }
+}
+
+/// Hands the records of requests to the AccessLogWriter.
+#[derive(Clone)]
+pub struct AccessLogger {
+    sender: mpsc::Sender<AccessLogRecord>,
+}
+
+impl AccessLogger {
+    /// Log the request once its response body has been sent, or the client
+    /// has gone away.
+    pub fn log_response(
+        &self,
+        data: &S3Data,
+        response: Response,
+        received: DateTime<Utc>,
+        started: Instant,
+    ) -> Response {
+        let (parts, body) = response.into_parts();
+        let record = AccessLogRecord::new(data, &parts, received, started.elapsed());
+        let mut pending = PendingRecord {
+            record: Some(record),
+            started,
+            sender: self.sender.clone(),
+        };
+        let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+            if let (Ok(bytes), Some(record)) = (&chunk, pending.record.as_mut()) {
+                record.bytes_sent += bytes.len() as u64;
+            }
+            chunk
+        }));
+        Response::from_parts(parts, body)
+    }
+}
+
+// Sends the record when the response body is dropped
+struct PendingRecord {
+    record: Option<AccessLogRecord>,
+    started: Instant,
+    sender: mpsc::Sender<AccessLogRecord>,
+}
+
+impl Drop for PendingRecord {
+    fn drop(&mut self) {
+        if let Some(mut record) = self.record.take() {
+            record.total_time_ms = self.started.elapsed().as_millis() as u64;
+            if let Err(mpsc::error::TrySendError::Full(record)) = self.sender.try_send(record) {
+                tracing::warn!(
+                    request_id = %record.request_id,
+                    "Access log queue is full, dropping record"
+                );
+            }
+        }
+    }
+}
+
+/// Writes server access logs in the background.
+///
+/// Every record is appended to the local access log. Records of buckets with
+/// logging enabled are batched per target bucket and prefix, and each batch
+/// is written as an object of the target bucket. Delivery is best effort, a
+/// batch that fails to be written is dropped.
+pub struct AccessLogWriter {
+    fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+    config: AccessLoggingConfig,
+    file: Option<tokio::fs::File>,
+    format: LocalFormat,
+    batches: HashMap<(String, String), Vec<String>>,
+}
+
+impl AccessLogWriter {
+    /// No local access log is written when the path is empty. The directory
+    /// of the log is created when missing.
+    pub fn new(
+        fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+        config: AccessLoggingConfig,
+        path: &str,
+        format: &str,
+    ) -> Result<Self, std::io::Error> {
+        let file = if path.is_empty() {
+            None
+        } else {
+            if let Some(dir) = std::path::Path::new(path).parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            Some(tokio::fs::File::from_std(file))
+        };
+        Ok(Self {
+            fullstack,
+            config,
+            file,
+            format: LocalFormat::parse(format),
+            batches: HashMap::new(),
+        })
+    }
+
+    pub fn start(self) -> AccessLogger {
+        let (sender, receiver) = mpsc::channel(self.config.queue_size.max(1));
+        tokio::spawn(self.run(receiver));
+        AccessLogger { sender }
+    }
+
+    async fn run(mut self, mut receiver: mpsc::Receiver<AccessLogRecord>) {
+        let mut flush = tokio::time::interval(Duration::from_secs(self.config.flush_secs.max(1)));
+        // The first tick completes immediately
+        flush.tick().await;
+        loop {
+            tokio::select! {
+                record = receiver.recv() => match record {
+                    Some(record) => self.append(record).await,
+                    None => break,
+                },
+                _ = flush.tick() => self.deliver_all(),
+            }
+        }
+        self.deliver_all();
+    }
+
+    async fn append(&mut self, record: AccessLogRecord) {
+        if let Some(file) = self.file.as_mut() {
+            let line = format!("{}\n", self.format.line(&record));
+            if let Err(e) = file
+                .write_all(line.as_bytes())
+                .await
+                .and(file.flush().await)
+            {
+                tracing::warn!("Failed to write access log: {:?}", e);
+            }
+        }
+
+        let target = match &record.target {
+            Some(target) => (target.target_bucket.clone(), target.target_prefix.clone()),
+            None => return,
+        };
+        let batch = self.batches.entry(target.clone()).or_default();
+        batch.push(record.s3_line());
+        if batch.len() >= self.config.max_batch_records {
+            let lines = self.batches.remove(&target).unwrap_or_default();
+            self.deliver(target, lines);
+        }
+    }
+
+    fn deliver_all(&mut self) {
+        for (target, lines) in std::mem::take(&mut self.batches) {
+            self.deliver(target, lines);
+        }
+    }
+
+    fn deliver(&self, (bucket, prefix): (String, String), lines: Vec<String>) {
+        if lines.is_empty() {
+            return;
+        }
+        let key = log_object_key(&prefix, Utc::now());
+        let body = Bytes::from(lines.join("\n") + "\n");
+        let fullstack = self.fullstack.clone();
+        tokio::spawn(async move {
+            if let Err(e) = fullstack.write_access_log(&bucket, key, body).await {
+                tracing::warn!(
+                    bucket = %bucket,
+                    "Failed to deliver {} access log records: {:?}",
+                    lines.len(),
+                    e
+                );
+            }
+        });
+    }
+}
+
+// Key of a log object as S3 names them, the prefix followed by the time of
+// delivery and a unique string
+fn log_object_key(prefix: &str, now: DateTime<Utc>) -> String {
+    let unique = uuid::Uuid::new_v4().simple().to_string()[..16].to_uppercase();
+    format!("{}{}-{}", prefix, now.format("%Y-%m-%d-%H-%M-%S"), unique)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_log_object_key() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 6, 0, 0, 38).unwrap();
+        let key = log_object_key("logs/", now);
+        assert!(key.starts_with("logs/2024-02-06-00-00-38-"));
+        assert_eq!(key.len(), "logs/2024-02-06-00-00-38-".len() + 16);
+        assert_ne!(key, log_object_key("logs/", now));
+    }
+
+    #[test]
+    fn test_local_format() {
+        assert_eq!(LocalFormat::parse("{combined}"), LocalFormat::Combined);
+        assert_eq!(LocalFormat::parse("{json}"), LocalFormat::Json);
+        assert_eq!(LocalFormat::parse("{s3}"), LocalFormat::S3);
+        assert_eq!(LocalFormat::parse(""), LocalFormat::Combined);
+    }
+}
@@ -0,0 +1,145 @@
+use std::{error::Error, sync::Arc};
+
+use axum::{
+    extract::{Path, Request, State},
+    http::StatusCode,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Json, Router,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::backend::FullstackBackend;
+
+/// HTTP API for operators, served on bind_admin_http_address.
+///
+/// Every request must carry `Authorization: Bearer <admin_key>`.
+pub struct AdminServer {
+    addr: String,
+    router: Router,
+}
+
+#[derive(Clone)]
+struct AdminState {
+    fullstack: Arc<Box<FullstackBackend>>,
+    admin_key: Arc<String>,
+}
+
+#[derive(Deserialize)]
+struct StartMigration {
+    bucket: String,
+    target_backend: String,
+}
+
+#[derive(Deserialize)]
+struct BucketDedup {
+    enabled: bool,
+}
+
+#[derive(Deserialize)]
+struct BucketFallback {
+    // Name of the fallback backend, null to stop failing over
+    backend: Option<String>,
+}
+
+impl AdminServer {
+    pub fn new(addr: String, admin_key: String, fullstack: Arc<Box<FullstackBackend>>) -> Self {
+        let state = AdminState {
+            fullstack,
+            admin_key: Arc::new(admin_key),
+        };
+        let router = Router::new()
+            .route("/admin/migrations", post(start_migration))
+            .route("/admin/migrations/:id", get(get_migration))
+            .route("/admin/buckets/:name/dedup", put(set_bucket_dedup))
+            .route("/admin/buckets/:name/fallback", put(set_bucket_fallback))
+            .route("/admin/backends", get(list_backends))
+            .route_layer(middleware::from_fn_with_state(state.clone(), authorize))
+            .with_state(state);
+        Self { addr, router }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(async move {
+            if let Err(e) = self.serve().await {
+                tracing::error!("Admin API stopped: {:?}", e);
+            }
+        });
+    }
+
+    async fn serve(self) -> Result<(), Box<dyn Error>> {
+        let listener = tokio::net::TcpListener::bind(&self.addr).await?;
+        tracing::info!("Starting admin API on {}", &self.addr);
+        axum::serve(listener, self.router).await.map_err(Into::into)
+    }
+}
+
+async fn authorize(State(state): State<AdminState>, req: Request, next: Next) -> Response {
+    let token = req
+        .headers()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !bool::from(subtle::ConstantTimeEq::ct_eq(
+        token.as_bytes(),
+        state.admin_key.as_bytes(),
+    )) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(req).await
+}
+
+async fn start_migration(
+    State(state): State<AdminState>,
+    Json(body): Json<StartMigration>,
+) -> Response {
+    match state
+        .fullstack
+        .start_backend_migration(&body.bucket, &body.target_backend)
+        .await
+    {
+        Ok(migration) => (StatusCode::CREATED, Json(migration)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn get_migration(State(state): State<AdminState>, Path(id): Path<Uuid>) -> Response {
+    match state.fullstack.get_backend_migration(id).await {
+        Ok(Some(migration)) => Json(migration).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn set_bucket_dedup(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+    Json(body): Json<BucketDedup>,
+) -> Response {
+    match state.fullstack.set_bucket_dedup(&name, body.enabled).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn set_bucket_fallback(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+    Json(body): Json<BucketFallback>,
+) -> Response {
+    match state
+        .fullstack
+        .set_bucket_fallback(&name, body.backend.as_deref())
+        .await
+    {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn list_backends(State(state): State<AdminState>) -> Response {
+    Json(state.fullstack.storage_backend_statuses()).into_response()
+}
@@ -1,4 +1,7 @@
+use std::collections::HashMap;
+
 use axum::async_trait;
+use chrono::{DateTime, Utc};
 
 use crate::backend::{types, IndexReader, Indexer};
 
@@ -38,9 +41,13 @@ impl IndexReader for Database {
     async fn get_bucket(&self, bucket_name: &str) -> Result<types::Bucket, sqlx::Error> {
         let result = sqlx::query!(
             r#"
-            SELECT id, name, user_id, created_at
-            FROM buckets
-            WHERE name = $1
+            SELECT b.id, b.name, b.user_id, b.created_at, b.backend_id, b.cors, b.lifecycle,
+                b.dedup, b.versioning, b.encryption, b.tags, b.object_lock,
+                b.website, b.logging, b.notification, b.replication, b.fallback_backend_id,
+                m.target_backend_id AS "migration_target?"
+            FROM buckets b
+            LEFT JOIN backend_migrations m ON m.bucket_id = b.id AND m.status = 'in_progress'
+            WHERE b.name = $1
             "#,
             bucket_name
         )
@@ -51,13 +58,43 @@ impl IndexReader for Database {
             name: result.name,
             user_id: result.user_id,
             created_at: result.created_at,
+            backend_id: result.backend_id,
+            migration_target: result.migration_target,
+            fallback_backend_id: result.fallback_backend_id,
+            dedup: result.dedup,
+            versioning: result.versioning,
+            cors: result
+                .cors
+                .and_then(|cors| serde_json::from_value(cors).ok()),
+            lifecycle: result
+                .lifecycle
+                .and_then(|lifecycle| serde_json::from_value(lifecycle).ok()),
+            encryption: result
+                .encryption
+                .and_then(|encryption| serde_json::from_value(encryption).ok()),
+            tags: object_tags(result.tags),
+            object_lock: result
+                .object_lock
+                .and_then(|object_lock| serde_json::from_value(object_lock).ok()),
+            website: result
+                .website
+                .and_then(|website| serde_json::from_value(website).ok()),
+            logging: result
+                .logging
+                .and_then(|logging| serde_json::from_value(logging).ok()),
+            notification: result
+                .notification
+                .and_then(|notification| serde_json::from_value(notification).ok()),
+            replication: result
+                .replication
+                .and_then(|replication| serde_json::from_value(replication).ok()),
         })
     }
 
     async fn list_buckets(&self, user_id: &i64) -> Result<Vec<types::Bucket>, sqlx::Error> {
         let results = sqlx::query!(
             r#"
-            SELECT id, name, user_id, created_at
+            SELECT id, name, user_id, created_at, backend_id
             FROM buckets
             WHERE user_id = $1
             "#,
@@ -72,6 +109,8 @@ impl IndexReader for Database {
                 name: result.name.clone(),
                 user_id: result.user_id,
                 created_at: result.created_at,
+                backend_id: result.backend_id,
+                ..Default::default()
             })
             .collect())
     }
@@ -83,9 +122,14 @@ impl IndexReader for Database {
     ) -> Result<types::Object, sqlx::Error> {
         let result = sqlx::query!(
             r#"
-            SELECT bucket_id, key, size
+            SELECT bucket_id, key, size, version_id, created_at, etag, tags, storage_class,
+                backend_id, backend_specific_name, backend_specific_id, is_delete_marker,
+                inline_data, encryption, compression, retention_mode, retain_until, legal_hold,
+                website_redirect_location, replication_status
             FROM objects
-            WHERE key = $1 and bucket_id = $2
+            WHERE key = $1 and bucket_id = $2 AND is_latest
+            ORDER BY created_at DESC
+            LIMIT 1
             "#,
             key,
             bucket_id
@@ -96,10 +140,79 @@ impl IndexReader for Database {
             bucket_id: result.bucket_id,
             key: result.key,
             size: result.size,
+            version_id: result.version_id,
+            is_latest: true,
+            is_delete_marker: result.is_delete_marker,
+            last_modified: result.created_at,
+            etag: result.etag,
+            tags: object_tags(result.tags),
+            storage_class: result.storage_class,
+            backend_id: result.backend_id,
+            backend_specific_name: result.backend_specific_name,
+            backend_specific_id: result.backend_specific_id,
+            inline_data: result.inline_data,
+            encryption: object_encryption(result.encryption)?,
+            compression: object_compression(result.compression)?,
+            retention_mode: result.retention_mode,
+            retain_until: result.retain_until,
+            legal_hold: result.legal_hold,
+            website_redirect_location: result.website_redirect_location,
+            replication_status: result.replication_status,
             ..Default::default()
         })
     }
 
+    async fn get_object_version(
+        &self,
+        bucket_id: uuid::Uuid,
+        key: &str,
+        version_id: uuid::Uuid,
+    ) -> Result<Option<types::Object>, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT bucket_id, key, size, version_id, created_at, etag, tags, storage_class,
+                backend_id, backend_specific_name, backend_specific_id, is_latest, is_delete_marker,
+                inline_data, encryption, compression, retention_mode, retain_until, legal_hold,
+                website_redirect_location, replication_status
+            FROM objects
+            WHERE bucket_id = $1 AND key = $2 AND version_id = $3
+            "#,
+            bucket_id,
+            key,
+            version_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        let result = match result {
+            Some(result) => result,
+            None => return Ok(None),
+        };
+        Ok(Some(types::Object {
+            bucket_id: result.bucket_id,
+            key: result.key,
+            size: result.size,
+            version_id: result.version_id,
+            is_latest: result.is_latest,
+            is_delete_marker: result.is_delete_marker,
+            last_modified: result.created_at,
+            etag: result.etag,
+            tags: object_tags(result.tags),
+            storage_class: result.storage_class,
+            backend_id: result.backend_id,
+            backend_specific_name: result.backend_specific_name,
+            backend_specific_id: result.backend_specific_id,
+            inline_data: result.inline_data,
+            encryption: object_encryption(result.encryption)?,
+            compression: object_compression(result.compression)?,
+            retention_mode: result.retention_mode,
+            retain_until: result.retain_until,
+            legal_hold: result.legal_hold,
+            website_redirect_location: result.website_redirect_location,
+            replication_status: result.replication_status,
+            ..Default::default()
+        }))
+    }
+
     async fn list_objects(&self, bucket_id: uuid::Uuid) -> Result<Vec<types::Object>, sqlx::Error> {
         let results = sqlx::query!(
             r#"
@@ -121,4 +234,584 @@ impl IndexReader for Database {
             })
             .collect())
     }
+
+    async fn list_rate_limits(&self) -> Result<Vec<types::RateLimit>, sqlx::Error> {
+        let results = sqlx::query!(
+            r#"
+            SELECT id, user_id, rps, dimension, value, weight, exclusive
+            FROM rate_limits
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(results
+            .into_iter()
+            .map(|result| types::RateLimit {
+                id: result.id,
+                user_id: result.user_id,
+                rps: result.rps,
+                dimension: result.dimension,
+                value: result.value,
+                weight: result.weight,
+                exclusive: result.exclusive,
+            })
+            .collect())
+    }
+
+    async fn list_expired_objects(
+        &self,
+        bucket_id: uuid::Uuid,
+        prefix: &str,
+        created_before: DateTime<Utc>,
+        after: &(String, uuid::Uuid),
+        limit: i64,
+    ) -> Result<Vec<types::Object>, sqlx::Error> {
+        let results = sqlx::query!(
+            r#"
+            SELECT bucket_id, key, size, version_id, created_at, etag, tags, backend_id,
+                backend_specific_name, backend_specific_id
+            FROM objects
+            WHERE bucket_id = $1 AND key LIKE $2 AND is_latest AND NOT is_delete_marker
+                AND created_at < $3 AND (key, version_id) > ($4, $5)
+            ORDER BY key, version_id
+            LIMIT $6
+            "#,
+            bucket_id,
+            like_prefix(prefix),
+            created_before,
+            after.0,
+            after.1,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(results
+            .into_iter()
+            .map(|result| types::Object {
+                bucket_id: result.bucket_id,
+                key: result.key,
+                size: result.size,
+                version_id: result.version_id,
+                is_latest: true,
+                last_modified: result.created_at,
+                etag: result.etag,
+                tags: object_tags(result.tags),
+                backend_id: result.backend_id,
+                backend_specific_name: result.backend_specific_name,
+                backend_specific_id: result.backend_specific_id,
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    async fn list_noncurrent_versions(
+        &self,
+        bucket_id: uuid::Uuid,
+        prefix: &str,
+        noncurrent_before: DateTime<Utc>,
+        newer_noncurrent_versions: i64,
+        after: &(String, uuid::Uuid),
+        limit: i64,
+    ) -> Result<Vec<types::Object>, sqlx::Error> {
+        // A version becomes noncurrent when the next newer version of the key
+        // is written, and versions are ranked newest first within each key.
+        let results = sqlx::query!(
+            r#"
+            SELECT bucket_id AS "bucket_id!", key AS "key!", size AS "size!",
+                version_id AS "version_id!", created_at AS "created_at!",
+                etag AS "etag!", tags, backend_id, backend_specific_name, backend_specific_id,
+                is_delete_marker AS "is_delete_marker!"
+            FROM (
+                SELECT bucket_id, key, size, version_id, created_at, etag, tags, backend_id,
+                    backend_specific_name, backend_specific_id, is_delete_marker, is_latest,
+                    LAG(created_at) OVER versions AS noncurrent_since,
+                    ROW_NUMBER() OVER versions AS version_rank
+                FROM objects
+                WHERE bucket_id = $1 AND key LIKE $2
+                WINDOW versions AS (PARTITION BY key ORDER BY created_at DESC, version_id DESC)
+            ) v
+            WHERE NOT is_latest AND noncurrent_since < $3 AND version_rank > $4::BIGINT + 1
+                AND (key, version_id) > ($5, $6)
+            ORDER BY key, version_id
+            LIMIT $7
+            "#,
+            bucket_id,
+            like_prefix(prefix),
+            noncurrent_before,
+            newer_noncurrent_versions,
+            after.0,
+            after.1,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(results
+            .into_iter()
+            .map(|result| types::Object {
+                bucket_id: result.bucket_id,
+                key: result.key,
+                size: result.size,
+                version_id: result.version_id,
+                is_delete_marker: result.is_delete_marker,
+                last_modified: result.created_at,
+                etag: result.etag,
+                tags: object_tags(result.tags),
+                backend_id: result.backend_id,
+                backend_specific_name: result.backend_specific_name,
+                backend_specific_id: result.backend_specific_id,
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    async fn list_expired_delete_markers(
+        &self,
+        bucket_id: uuid::Uuid,
+        prefix: &str,
+        after: &(String, uuid::Uuid),
+        limit: i64,
+    ) -> Result<Vec<types::Object>, sqlx::Error> {
+        // A delete marker has expired once no other version of its key is left
+        let results = sqlx::query!(
+            r#"
+            SELECT bucket_id, key, size, version_id, created_at
+            FROM objects o
+            WHERE bucket_id = $1 AND key LIKE $2 AND is_latest AND is_delete_marker
+                AND (key, version_id) > ($3, $4)
+                AND NOT EXISTS (
+                    SELECT 1 FROM objects other
+                    WHERE other.bucket_id = o.bucket_id AND other.key = o.key
+                        AND other.version_id <> o.version_id
+                )
+            ORDER BY key, version_id
+            LIMIT $5
+            "#,
+            bucket_id,
+            like_prefix(prefix),
+            after.0,
+            after.1,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(results
+            .into_iter()
+            .map(|result| types::Object {
+                bucket_id: result.bucket_id,
+                key: result.key,
+                size: result.size,
+                version_id: result.version_id,
+                is_latest: true,
+                is_delete_marker: true,
+                last_modified: result.created_at,
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    async fn list_stale_multipart_uploads(
+        &self,
+        bucket_id: uuid::Uuid,
+        prefix: &str,
+        created_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<types::Multipart>, sqlx::Error> {
+        let results = sqlx::query!(
+            r#"
+            SELECT id, bucket_id, object_name, upload_id, created_at, owner_id, backend_id
+            FROM multipart_uploads
+            WHERE bucket_id = $1 AND object_name LIKE $2 AND created_at < $3
+                AND status = 'in_progress'
+            ORDER BY created_at
+            LIMIT $4
+            "#,
+            bucket_id,
+            like_prefix(prefix),
+            created_before,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(results
+            .into_iter()
+            .map(|result| types::Multipart {
+                id: result.id,
+                bucket_id: result.bucket_id,
+                upload_id: result.upload_id,
+                key: result.object_name,
+                created_at: result.created_at,
+                owner_id: result.owner_id,
+                backend_id: result.backend_id,
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    async fn get_multipart_upload(
+        &self,
+        bucket_id: uuid::Uuid,
+        key: &str,
+        upload_id: &str,
+    ) -> Result<Option<types::Multipart>, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT id, bucket_id, object_name, upload_id, created_at, owner_id, backend_id,
+                metadata
+            FROM multipart_uploads
+            WHERE bucket_id = $1 AND object_name = $2 AND upload_id = $3
+                AND status = 'in_progress'
+            "#,
+            bucket_id,
+            key,
+            upload_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        let result = match result {
+            Some(result) => result,
+            None => return Ok(None),
+        };
+        Ok(Some(types::Multipart {
+            id: result.id,
+            bucket_id: result.bucket_id,
+            upload_id: result.upload_id,
+            key: result.object_name,
+            created_at: result.created_at,
+            owner_id: result.owner_id,
+            backend_id: result.backend_id,
+            metadata: upload_metadata(result.metadata)?,
+            ..Default::default()
+        }))
+    }
+
+    async fn list_multipart_parts(&self, id: uuid::Uuid) -> Result<Vec<types::Part>, sqlx::Error> {
+        let results = sqlx::query!(
+            r#"
+            SELECT part_number, size, etag, encryption
+            FROM multipart_parts
+            WHERE multipart_upload_id = $1
+            ORDER BY part_number
+            "#,
+            id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        results
+            .into_iter()
+            .map(|result| {
+                Ok(types::Part {
+                    part_number: result.part_number as u32,
+                    size: result.size,
+                    etag: result.etag,
+                    encryption: result
+                        .encryption
+                        .map(serde_json::from_value)
+                        .transpose()
+                        .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                })
+            })
+            .collect()
+    }
+
+    async fn list_transition_candidates(
+        &self,
+        bucket_id: uuid::Uuid,
+        prefix: &str,
+        created_before: DateTime<Utc>,
+        storage_class: &str,
+        after: &(String, uuid::Uuid),
+        limit: i64,
+    ) -> Result<Vec<types::Object>, sqlx::Error> {
+        let results = sqlx::query!(
+            r#"
+            SELECT bucket_id, key, size, version_id, created_at, etag, tags, storage_class,
+                backend_id, backend_specific_name, backend_specific_id
+            FROM objects
+            WHERE bucket_id = $1 AND key LIKE $2 AND is_latest AND NOT is_delete_marker
+                AND created_at < $3 AND storage_class <> $4 AND (key, version_id) > ($5, $6)
+                AND backend_specific_name IS NOT NULL
+            ORDER BY key, version_id
+            LIMIT $7
+            "#,
+            bucket_id,
+            like_prefix(prefix),
+            created_before,
+            storage_class,
+            after.0,
+            after.1,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(results
+            .into_iter()
+            .map(|result| types::Object {
+                bucket_id: result.bucket_id,
+                key: result.key,
+                size: result.size,
+                version_id: result.version_id,
+                is_latest: true,
+                last_modified: result.created_at,
+                etag: result.etag,
+                tags: object_tags(result.tags),
+                storage_class: result.storage_class,
+                backend_id: result.backend_id,
+                backend_specific_name: result.backend_specific_name,
+                backend_specific_id: result.backend_specific_id,
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    async fn list_migration_candidates(
+        &self,
+        bucket_id: uuid::Uuid,
+        source_backend_id: uuid::Uuid,
+        after: &(String, uuid::Uuid),
+        limit: i64,
+    ) -> Result<Vec<types::Object>, sqlx::Error> {
+        // Versions without a backend are on the bucket's backend, the source
+        let results = sqlx::query!(
+            r#"
+            SELECT bucket_id, key, size, version_id, is_latest, created_at, etag, storage_class,
+                backend_id, backend_specific_name, backend_specific_id
+            FROM objects
+            WHERE bucket_id = $1 AND (backend_id = $2 OR backend_id IS NULL)
+                AND backend_specific_name IS NOT NULL AND (key, version_id) > ($3, $4)
+            ORDER BY key, version_id
+            LIMIT $5
+            "#,
+            bucket_id,
+            source_backend_id,
+            after.0,
+            after.1,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(results
+            .into_iter()
+            .map(|result| types::Object {
+                bucket_id: result.bucket_id,
+                key: result.key,
+                size: result.size,
+                version_id: result.version_id,
+                is_latest: result.is_latest,
+                last_modified: result.created_at,
+                etag: result.etag,
+                storage_class: result.storage_class,
+                backend_id: result.backend_id,
+                backend_specific_name: result.backend_specific_name,
+                backend_specific_id: result.backend_specific_id,
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    async fn list_failover_writes(
+        &self,
+        after: &(uuid::Uuid, String, uuid::Uuid),
+        limit: i64,
+    ) -> Result<Vec<types::FailoverWrite>, sqlx::Error> {
+        let results = sqlx::query!(
+            r#"
+            SELECT b.name AS bucket_name, o.bucket_id, o.key, o.size, o.version_id, o.is_latest,
+                o.created_at, o.etag, o.storage_class, o.backend_id, o.backend_specific_name,
+                o.backend_specific_id, o.failover_from
+            FROM objects o
+            JOIN buckets b ON b.id = o.bucket_id
+            WHERE o.failover_from IS NOT NULL AND (o.bucket_id, o.key, o.version_id) > ($1, $2, $3)
+            ORDER BY o.bucket_id, o.key, o.version_id
+            LIMIT $4
+            "#,
+            after.0,
+            after.1,
+            after.2,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(results
+            .into_iter()
+            .map(|result| types::FailoverWrite {
+                bucket_name: result.bucket_name,
+                object: types::Object {
+                    bucket_id: result.bucket_id,
+                    key: result.key,
+                    size: result.size,
+                    version_id: result.version_id,
+                    is_latest: result.is_latest,
+                    last_modified: result.created_at,
+                    etag: result.etag,
+                    storage_class: result.storage_class,
+                    backend_id: result.backend_id,
+                    backend_specific_name: result.backend_specific_name,
+                    backend_specific_id: result.backend_specific_id,
+                    failover_from: result.failover_from,
+                    ..Default::default()
+                },
+            })
+            .collect())
+    }
+
+    async fn list_volume_objects(
+        &self,
+        volume: &types::Volume,
+    ) -> Result<Vec<types::Object>, sqlx::Error> {
+        let results = sqlx::query!(
+            r#"
+            SELECT bucket_id, key, size, version_id, is_latest, created_at, etag, storage_class,
+                backend_id, backend_specific_name, backend_specific_id
+            FROM objects
+            WHERE bucket_id = $1 AND backend_specific_name = $2
+                AND backend_specific_id IS NOT NULL
+            ORDER BY backend_specific_id
+            "#,
+            volume.bucket_id,
+            volume.id.to_string()
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(results
+            .into_iter()
+            .map(|result| types::Object {
+                bucket_id: result.bucket_id,
+                key: result.key,
+                size: result.size,
+                version_id: result.version_id,
+                is_latest: result.is_latest,
+                last_modified: result.created_at,
+                etag: result.etag,
+                storage_class: result.storage_class,
+                backend_id: result.backend_id,
+                backend_specific_name: result.backend_specific_name,
+                backend_specific_id: result.backend_specific_id,
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    async fn get_backend_migration(
+        &self,
+        id: uuid::Uuid,
+    ) -> Result<Option<types::BackendMigration>, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT m.id, m.bucket_id, b.name AS bucket_name, m.source_backend_id,
+                m.target_backend_id, m.status, m.progress, m.started_at, m.completed_at, m.error
+            FROM backend_migrations m
+            JOIN buckets b ON b.id = m.bucket_id
+            WHERE m.id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result.map(|result| types::BackendMigration {
+            id: result.id,
+            bucket_id: result.bucket_id,
+            bucket_name: result.bucket_name,
+            source_backend_id: result.source_backend_id,
+            target_backend_id: result.target_backend_id,
+            status: result.status,
+            progress: migration_progress(result.progress),
+            started_at: result.started_at,
+            completed_at: result.completed_at,
+            error: result.error,
+        }))
+    }
+
+    async fn list_storage_backends(&self) -> Result<Vec<types::StorageBackend>, sqlx::Error> {
+        let results = sqlx::query!(
+            r#"
+            SELECT id, name, provider::TEXT AS "provider!", credentials, endpoint, region,
+                storage_class, config
+            FROM storage_backends
+            WHERE is_active
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(results
+            .into_iter()
+            .map(|result| types::StorageBackend {
+                id: result.id,
+                name: result.name,
+                provider: result.provider,
+                credentials: result.credentials,
+                endpoint: result.endpoint,
+                region: result.region,
+                storage_class: result.storage_class,
+                config: result.config.unwrap_or_else(|| serde_json::json!({})),
+            })
+            .collect())
+    }
+}
+
+// Pattern matching every key that starts with `prefix`
+fn like_prefix(prefix: &str) -> String {
+    let mut pattern = String::with_capacity(prefix.len() + 1);
+    for c in prefix.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+fn object_tags(tags: Option<serde_json::Value>) -> HashMap<String, String> {
+    tags.and_then(|tags| serde_json::from_value(tags).ok())
+        .unwrap_or_default()
+}
+
+// Unlike tags, an envelope that does not parse must not pass for plaintext
+fn object_encryption(
+    encryption: Option<serde_json::Value>,
+) -> Result<Option<types::ObjectEncryption>, sqlx::Error> {
+    encryption
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+// Compressed data must not pass for the original either
+fn object_compression(
+    compression: Option<serde_json::Value>,
+) -> Result<Option<types::ObjectCompression>, sqlx::Error> {
+    compression
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+// The metadata of an upload decides how its object is stored, so it has to
+// parse as well
+fn upload_metadata(
+    metadata: Option<serde_json::Value>,
+) -> Result<types::UploadMetadata, sqlx::Error> {
+    metadata
+        .map(serde_json::from_value)
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+pub(super) fn migration_progress(progress: Option<serde_json::Value>) -> types::MigrationProgress {
+    progress
+        .and_then(|progress| serde_json::from_value(progress).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_like_prefix() {
+        assert_eq!(like_prefix(""), "%");
+        assert_eq!(like_prefix("logs/"), "logs/%");
+        assert_eq!(like_prefix("50%_off\\"), "50\\%\\_off\\\\%");
+    }
 }
@@ -1,42 +1,38 @@
 use axum::async_trait;
+use chrono::{DateTime, Utc};
+
+use s3_core::{
+    replication::{
+        REPLICATION_COMPLETED, REPLICATION_FAILED, REPLICATION_PENDING, REPLICATION_REPLICA,
+    },
+    versioning::VERSIONING_DISABLED,
+};
 
 use crate::backend::types;
 
-use super::{Database, IndexWriter};
+use super::{db_reader::migration_progress, Database, IndexWriter};
 
 #[async_trait]
 impl IndexWriter for Database {
-    async fn delete_object(&self, bucket: &str, key: &str) -> Result<(), sqlx::Error> {
-        sqlx::query!(
-            r#"
-            DELETE FROM objects
-            WHERE bucket_id = (SELECT id FROM buckets WHERE name = $1) AND key = $2
-            "#,
-            bucket,
-            key
-        )
-        .execute(&self.pool)
-        .await?;
-        Ok(())
-    }
-
-    async fn delete_objects(&self, bucket: &str, keys: Vec<String>) -> Result<(), sqlx::Error> {
-        for key in keys {
-            self.delete_object(bucket, &key).await?;
-        }
-        Ok(())
-    }
-
     async fn create_bucket(&self, bucket: &types::Bucket) -> Result<(), sqlx::Error> {
+        let object_lock = bucket
+            .object_lock
+            .as_ref()
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
         sqlx::query_as!(
             types::Bucket,
             r#"
-            INSERT INTO buckets (id, name, user_id)
-            VALUES ($1, $2, $3)
+            INSERT INTO buckets (id, name, user_id, backend_id, versioning, object_lock)
+            VALUES ($1, $2, $3, $4, $5, $6)
             "#,
             bucket.id,
             bucket.name,
-            bucket.user_id
+            bucket.user_id,
+            bucket.backend_id,
+            bucket.versioning,
+            object_lock
         )
         .execute(&self.pool)
         .await?;
@@ -61,25 +57,1484 @@ impl IndexWriter for Database {
         Ok(())
     }
 
+    async fn update_bucket_cors(
+        &self,
+        bucket_id: uuid::Uuid,
+        cors: Option<serde_json::Value>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE buckets
+            SET cors = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            bucket_id,
+            cors
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_bucket_website(
+        &self,
+        bucket_id: uuid::Uuid,
+        website: Option<serde_json::Value>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE buckets
+            SET website = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            bucket_id,
+            website
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_bucket_logging(
+        &self,
+        bucket_id: uuid::Uuid,
+        logging: Option<serde_json::Value>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE buckets
+            SET logging = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            bucket_id,
+            logging
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_bucket_notification(
+        &self,
+        bucket_id: uuid::Uuid,
+        notification: Option<serde_json::Value>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE buckets
+            SET notification = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            bucket_id,
+            notification
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_bucket_replication(
+        &self,
+        bucket_id: uuid::Uuid,
+        replication: Option<serde_json::Value>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE buckets
+            SET replication = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            bucket_id,
+            replication
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
     async fn put_object(
         &self,
         bucket: &types::Bucket,
         object: &types::Object,
-    ) -> Result<(), sqlx::Error> {
+    ) -> Result<Vec<types::Object>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let replaced = insert_object(&mut tx, bucket, object).await?;
+        tx.commit().await?;
+        Ok(replaced)
+    }
+
+    async fn put_replica(
+        &self,
+        bucket: &types::Bucket,
+        object: &types::Object,
+    ) -> Result<bool, sqlx::Error> {
+        let encryption = object
+            .encryption
+            .as_ref()
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+        let compression = object
+            .compression
+            .as_ref()
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+        let tags = (!object.tags.is_empty())
+            .then(|| serde_json::to_value(&object.tags))
+            .transpose()
+            .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+        let mut tx = self.pool.begin().await?;
+        // Copies of the versions of a key may land out of order, version ids
+        // sort by creation time
+        let versions = sqlx::query!(
+            r#"
+            SELECT COALESCE(bool_or(version_id = $3), false) AS "exists!",
+                COALESCE(bool_or(version_id > $3), false) AS "newer!"
+            FROM (
+                SELECT version_id
+                FROM objects
+                WHERE bucket_id = $1 AND key = $2
+                FOR UPDATE
+            ) versions
+            "#,
+            bucket.id,
+            object.key,
+            object.version_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if versions.exists {
+            return Ok(false);
+        }
+        if !versions.newer {
+            sqlx::query!(
+                r#"
+                UPDATE objects
+                SET is_latest = false, updated_at = NOW()
+                WHERE bucket_id = $1 AND key = $2 AND is_latest
+                "#,
+                bucket.id,
+                object.key
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
         sqlx::query!(
             r#"
-            INSERT INTO objects (bucket_id, key, size, version_id, owner_id, etag)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO objects (bucket_id, key, size, version_id, owner_id, etag, storage_class,
+                backend_id, backend_specific_name, backend_specific_id, inline_data, encryption,
+                compression, tags, retention_mode, retain_until, legal_hold,
+                website_redirect_location, replication_status, is_delete_marker, is_latest,
+                created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+                $18, $19, $20, $21, $22)
             "#,
             bucket.id,
             object.key,
             object.size,
             object.version_id,
             object.owner_id.to_string(),
-            object.etag
+            object.etag,
+            object.storage_class,
+            object.backend_id,
+            object.backend_specific_name,
+            object.backend_specific_id,
+            object.inline_data,
+            encryption,
+            compression,
+            tags,
+            object.retention_mode,
+            object.retain_until,
+            object.legal_hold,
+            object.website_redirect_location,
+            REPLICATION_REPLICA,
+            object.is_delete_marker,
+            !versions.newer,
+            object.last_modified
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn update_bucket_lifecycle(
+        &self,
+        bucket_id: uuid::Uuid,
+        lifecycle: Option<serde_json::Value>,
+        rules: Vec<types::LifecycleRuleRow>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            r#"
+            UPDATE buckets
+            SET lifecycle = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            bucket_id,
+            lifecycle
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            DELETE FROM lifecycle_rules
+            WHERE bucket_id = $1
+            "#,
+            bucket_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        for rule in rules {
+            sqlx::query!(
+                r#"
+                INSERT INTO lifecycle_rules (id, bucket_id, rule_id, rule_config, status)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                uuid::Uuid::now_v7(),
+                bucket_id,
+                rule.rule_id,
+                rule.rule_config,
+                rule.status
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn claim_lifecycle_rules(
+        &self,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<types::LifecycleRule>, sqlx::Error> {
+        // Due rules are leased by pushing next_execution_at past the expected
+        // run time. SKIP LOCKED keeps concurrent gateways from claiming the
+        // same rule, and a crashed run is retried once the lease expires.
+        let results = sqlx::query!(
+            r#"
+            WITH due AS (
+                SELECT id
+                FROM lifecycle_rules
+                WHERE status = 'active'
+                    AND (next_execution_at IS NULL OR next_execution_at <= NOW())
+                ORDER BY next_execution_at NULLS FIRST
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE lifecycle_rules r
+            SET next_execution_at = $1
+            FROM due, buckets b
+            WHERE r.id = due.id AND b.id = r.bucket_id
+            RETURNING r.id, r.bucket_id, r.rule_config, b.name AS bucket_name, b.user_id,
+                b.backend_id, b.versioning, b.notification, b.replication
+            "#,
+            lease_until,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(results
+            .into_iter()
+            .filter_map(|result| match serde_json::from_value(result.rule_config) {
+                Ok(rule) => Some(types::LifecycleRule {
+                    id: result.id,
+                    bucket_id: result.bucket_id,
+                    bucket_name: result.bucket_name,
+                    bucket_owner: result.user_id,
+                    bucket_backend_id: result.backend_id,
+                    versioning: result.versioning,
+                    bucket_notification: result
+                        .notification
+                        .and_then(|notification| serde_json::from_value(notification).ok()),
+                    bucket_replication: result
+                        .replication
+                        .and_then(|replication| serde_json::from_value(replication).ok()),
+                    rule,
+                }),
+                Err(e) => {
+                    tracing::warn!("Skipping invalid lifecycle rule {}: {:?}", result.id, e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    async fn complete_lifecycle_rule(
+        &self,
+        id: uuid::Uuid,
+        next_execution_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE lifecycle_rules
+            SET last_executed_at = NOW(), next_execution_at = $2
+            WHERE id = $1
+            "#,
+            id,
+            next_execution_at
         )
         .execute(&self.pool)
         .await?;
         Ok(())
     }
+
+    async fn delete_object_version(
+        &self,
+        bucket_id: uuid::Uuid,
+        key: &str,
+        version_id: uuid::Uuid,
+        bypass_governance: bool,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        // Checked in the statement so a retention set concurrently still holds
+        let deleted = sqlx::query!(
+            r#"
+            DELETE FROM objects
+            WHERE bucket_id = $1 AND key = $2 AND version_id = $3 AND NOT legal_hold
+                AND (retain_until IS NULL OR retain_until <= NOW()
+                    OR (retention_mode = 'GOVERNANCE' AND $4))
+            RETURNING size, backend_id, backend_specific_id, is_latest
+            "#,
+            bucket_id,
+            key,
+            version_id,
+            bypass_governance
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let deleted = match deleted {
+            Some(deleted) => deleted,
+            None => return Ok(false),
+        };
+        // The newest remaining version becomes the current one
+        if deleted.is_latest {
+            sqlx::query!(
+                r#"
+                UPDATE objects
+                SET is_latest = true, updated_at = NOW()
+                WHERE bucket_id = $1 AND key = $2 AND version_id = (
+                    SELECT version_id FROM objects
+                    WHERE bucket_id = $1 AND key = $2
+                    ORDER BY created_at DESC, version_id DESC
+                    LIMIT 1
+                )
+                "#,
+                bucket_id,
+                key
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        let packed = deleted
+            .backend_specific_id
+            .as_deref()
+            .and_then(types::PackedLocation::parse);
+        add_live_bytes(&mut tx, packed, -deleted.size).await?;
+        release_reference(
+            &mut tx,
+            bucket_id,
+            deleted.backend_id,
+            deleted.backend_specific_id.as_deref(),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn delete_object_versions(
+        &self,
+        bucket_id: uuid::Uuid,
+        key: &str,
+        bypass_governance: bool,
+    ) -> Result<Vec<types::Object>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let removed = remove_versions(&mut tx, bucket_id, key, bypass_governance).await?;
+        tx.commit().await?;
+        Ok(removed)
+    }
+
+    async fn put_delete_marker(
+        &self,
+        current: &types::Object,
+        marker: &types::Object,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        // Only the gateway that demotes the current version adds the marker
+        let result = sqlx::query!(
+            r#"
+            UPDATE objects
+            SET is_latest = false, updated_at = NOW()
+            WHERE bucket_id = $1 AND key = $2 AND version_id = $3 AND is_latest
+            "#,
+            current.bucket_id,
+            current.key,
+            current.version_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        sqlx::query!(
+            r#"
+            INSERT INTO objects (bucket_id, key, size, version_id, owner_id, etag, is_delete_marker,
+                replication_status)
+            VALUES ($1, $2, 0, $3, $4, '', true, $5)
+            "#,
+            marker.bucket_id,
+            marker.key,
+            marker.version_id,
+            marker.owner_id.to_string(),
+            marker.replication_status
+        )
+        .execute(&mut *tx)
+        .await?;
+        enqueue_replication(&mut tx, marker).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn create_multipart_upload(&self, upload: &types::Multipart) -> Result<(), sqlx::Error> {
+        let metadata =
+            serde_json::to_value(&upload.metadata).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+        sqlx::query!(
+            r#"
+            INSERT INTO multipart_uploads (id, bucket_id, object_name, upload_id, metadata,
+                owner_id, backend_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            upload.id,
+            upload.bucket_id,
+            upload.key,
+            upload.upload_id,
+            metadata,
+            upload.owner_id,
+            upload.backend_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn put_multipart_part(
+        &self,
+        id: uuid::Uuid,
+        part: &types::Part,
+    ) -> Result<(), sqlx::Error> {
+        let encryption = part
+            .encryption
+            .as_ref()
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+        sqlx::query!(
+            r#"
+            INSERT INTO multipart_parts (id, multipart_upload_id, part_number, size, etag,
+                encryption)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (multipart_upload_id, part_number)
+            DO UPDATE SET size = EXCLUDED.size, etag = EXCLUDED.etag,
+                encryption = EXCLUDED.encryption, created_at = NOW()
+            "#,
+            uuid::Uuid::now_v7(),
+            id,
+            part.part_number as i32,
+            part.size,
+            part.etag,
+            encryption
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        id: uuid::Uuid,
+        bucket: &types::Bucket,
+        object: &types::Object,
+    ) -> Result<Option<Vec<types::Object>>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            r#"
+            DELETE FROM multipart_parts
+            WHERE multipart_upload_id = $1
+            "#,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        let deleted = sqlx::query!(
+            r#"
+            DELETE FROM multipart_uploads
+            WHERE id = $1
+            "#,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if deleted.rows_affected() == 0 {
+            return Ok(None);
+        }
+        let replaced = insert_object(&mut tx, bucket, object).await?;
+        tx.commit().await?;
+        Ok(Some(replaced))
+    }
+
+    async fn abort_multipart_upload(&self, id: uuid::Uuid) -> Result<Vec<u32>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let parts = sqlx::query!(
+            r#"
+            DELETE FROM multipart_parts
+            WHERE multipart_upload_id = $1
+            RETURNING part_number
+            "#,
+            id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            DELETE FROM multipart_uploads
+            WHERE id = $1
+            "#,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(parts
+            .into_iter()
+            .map(|part| part.part_number as u32)
+            .collect())
+    }
+
+    async fn repoint_object(
+        &self,
+        object: &types::Object,
+        storage_class: &str,
+        backend_id: Option<uuid::Uuid>,
+        backend_specific_name: Option<&str>,
+        backend_specific_id: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        // Only repoint if the data is still where it was copied from
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query!(
+            r#"
+            UPDATE objects
+            SET storage_class = $4, backend_id = $5, backend_specific_name = $6,
+                backend_specific_id = $7, failover_from = NULL, updated_at = NOW()
+            WHERE bucket_id = $1 AND key = $2 AND version_id = $3
+                AND backend_id IS NOT DISTINCT FROM $8
+                AND backend_specific_id IS NOT DISTINCT FROM $9
+            "#,
+            object.bucket_id,
+            object.key,
+            object.version_id,
+            storage_class,
+            backend_id,
+            backend_specific_name,
+            backend_specific_id,
+            object.backend_id,
+            object.backend_specific_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        let packed = backend_specific_id.and_then(types::PackedLocation::parse);
+        add_live_bytes(&mut tx, object.packed(), -object.size).await?;
+        add_live_bytes(&mut tx, packed, object.size).await?;
+        if backend_specific_id != object.backend_specific_id.as_deref() {
+            release_reference(
+                &mut tx,
+                object.bucket_id,
+                object.backend_id,
+                object.backend_specific_id.as_deref(),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn create_volume(&self, volume: &types::Volume) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO volumes (id, bucket_id, backend_id, size, live_bytes)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            volume.id,
+            volume.bucket_id,
+            volume.backend_id,
+            volume.size,
+            volume.live_bytes
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_bucket_encryption(
+        &self,
+        bucket_id: uuid::Uuid,
+        encryption: Option<serde_json::Value>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE buckets
+            SET encryption = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            bucket_id,
+            encryption
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_bucket_tags(
+        &self,
+        bucket_id: uuid::Uuid,
+        tags: Option<serde_json::Value>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE buckets
+            SET tags = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            bucket_id,
+            tags
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_object_tags(
+        &self,
+        object: &types::Object,
+        tags: Option<serde_json::Value>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE objects
+            SET tags = $4, updated_at = NOW()
+            WHERE bucket_id = $1 AND key = $2 AND version_id = $3 AND NOT is_delete_marker
+            "#,
+            object.bucket_id,
+            object.key,
+            object.version_id,
+            tags
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn update_bucket_object_lock(
+        &self,
+        bucket_id: uuid::Uuid,
+        object_lock: serde_json::Value,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE buckets
+            SET object_lock = $2, updated_at = NOW()
+            WHERE id = $1 AND object_lock IS NOT NULL
+            "#,
+            bucket_id,
+            object_lock
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_bucket_versioning(
+        &self,
+        bucket_id: uuid::Uuid,
+        versioning: i16,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE buckets
+            SET versioning = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            bucket_id,
+            versioning
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_object_retention(
+        &self,
+        object: &types::Object,
+        retention_mode: Option<&str>,
+        retain_until: Option<DateTime<Utc>>,
+    ) -> Result<bool, sqlx::Error> {
+        // Only replaces the retention the change was checked against
+        let result = sqlx::query!(
+            r#"
+            UPDATE objects
+            SET retention_mode = $4, retain_until = $5, updated_at = NOW()
+            WHERE bucket_id = $1 AND key = $2 AND version_id = $3
+                AND retention_mode IS NOT DISTINCT FROM $6
+                AND retain_until IS NOT DISTINCT FROM $7
+            "#,
+            object.bucket_id,
+            object.key,
+            object.version_id,
+            retention_mode,
+            retain_until,
+            object.retention_mode,
+            object.retain_until
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn update_object_legal_hold(
+        &self,
+        object: &types::Object,
+        legal_hold: bool,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE objects
+            SET legal_hold = $4, updated_at = NOW()
+            WHERE bucket_id = $1 AND key = $2 AND version_id = $3
+            "#,
+            object.bucket_id,
+            object.key,
+            object.version_id,
+            legal_hold
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn update_bucket_dedup(
+        &self,
+        bucket_id: uuid::Uuid,
+        dedup: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE buckets
+            SET dedup = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            bucket_id,
+            dedup
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_bucket_fallback(
+        &self,
+        bucket_id: uuid::Uuid,
+        fallback_backend_id: Option<uuid::Uuid>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE buckets
+            SET fallback_backend_id = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            bucket_id,
+            fallback_backend_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn reserve_blob(
+        &self,
+        bucket_id: uuid::Uuid,
+        backend_id: uuid::Uuid,
+        sha256: &str,
+        size: i64,
+    ) -> Result<Option<bool>, sqlx::Error> {
+        // Take a reference before the data is written, so the collector
+        // leaves the blob alone. Blobs being collected are not reserved.
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO blobs (bucket_id, backend_id, sha256, size, refcount)
+            VALUES ($1, $2, $3, $4, 1)
+            ON CONFLICT (bucket_id, backend_id, sha256) DO UPDATE
+            SET refcount = blobs.refcount + 1, released_at = NULL
+            WHERE blobs.refcount >= 0
+            RETURNING stored
+            "#,
+            bucket_id,
+            backend_id,
+            sha256,
+            size
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result.map(|result| result.stored))
+    }
+
+    async fn mark_blob_stored(
+        &self,
+        bucket_id: uuid::Uuid,
+        backend_id: uuid::Uuid,
+        sha256: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE blobs
+            SET stored = true
+            WHERE bucket_id = $1 AND backend_id = $2 AND sha256 = $3
+            "#,
+            bucket_id,
+            backend_id,
+            sha256
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn release_blob(
+        &self,
+        bucket_id: uuid::Uuid,
+        backend_id: uuid::Uuid,
+        sha256: &str,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        release_reference(
+            &mut tx,
+            bucket_id,
+            Some(backend_id),
+            Some(&format!("sha256:{}", sha256)),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn claim_released_blobs(
+        &self,
+        released_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<types::Blob>, sqlx::Error> {
+        // A claimed blob is marked with a refcount of -1 so that it can no
+        // longer be reserved, and is claimed again if its data is not deleted
+        // within the grace period
+        let results = sqlx::query!(
+            r#"
+            WITH due AS (
+                SELECT bucket_id, backend_id, sha256
+                FROM blobs
+                WHERE refcount <= 0 AND released_at < $1
+                ORDER BY released_at
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE blobs bl
+            SET refcount = -1, released_at = NOW()
+            FROM due, buckets b
+            WHERE bl.bucket_id = due.bucket_id AND bl.backend_id = due.backend_id
+                AND bl.sha256 = due.sha256 AND b.id = bl.bucket_id
+            RETURNING bl.bucket_id, b.name AS bucket_name, bl.backend_id, bl.sha256
+            "#,
+            released_before,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(results
+            .into_iter()
+            .map(|result| types::Blob {
+                bucket_id: result.bucket_id,
+                bucket_name: result.bucket_name,
+                backend_id: result.backend_id,
+                sha256: result.sha256,
+            })
+            .collect())
+    }
+
+    async fn delete_blob(&self, blob: &types::Blob) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM blobs
+            WHERE bucket_id = $1 AND backend_id = $2 AND sha256 = $3 AND refcount = -1
+            "#,
+            blob.bucket_id,
+            blob.backend_id,
+            blob.sha256
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn claim_volume(
+        &self,
+        created_before: DateTime<Utc>,
+        max_live_ratio: f64,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Option<types::Volume>, sqlx::Error> {
+        // Emptiest volume first, it frees the most space for the least copying
+        let result = sqlx::query!(
+            r#"
+            WITH due AS (
+                SELECT id
+                FROM volumes
+                WHERE created_at < $1 AND live_bytes < size * $2::FLOAT8
+                    AND (lease_until IS NULL OR lease_until <= NOW())
+                ORDER BY live_bytes::FLOAT8 / GREATEST(size, 1)
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE volumes v
+            SET lease_until = $3
+            FROM due, buckets b
+            WHERE v.id = due.id AND b.id = v.bucket_id
+            RETURNING v.id, v.bucket_id, b.name AS bucket_name, v.backend_id, v.size,
+                v.live_bytes
+            "#,
+            created_before,
+            max_live_ratio,
+            lease_until
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result.map(|result| types::Volume {
+            id: result.id,
+            bucket_id: result.bucket_id,
+            bucket_name: result.bucket_name,
+            backend_id: result.backend_id,
+            size: result.size,
+            live_bytes: result.live_bytes,
+        }))
+    }
+
+    async fn delete_volume(&self, id: uuid::Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM volumes
+            WHERE id = $1 AND live_bytes <= 0
+            "#,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn register_storage_backend(
+        &self,
+        backend: &types::StorageBackend,
+    ) -> Result<uuid::Uuid, sqlx::Error> {
+        // Credentials from the config file are never written to the table,
+        // rows created here keep an empty credentials object.
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO storage_backends (id, name, provider, credentials, endpoint, region,
+                storage_class, config)
+            VALUES ($1, $2, CAST($3::TEXT AS storage_provider), '{}'::JSONB, $4, $5, $6, $7)
+            ON CONFLICT (name) DO UPDATE
+            SET provider = EXCLUDED.provider, endpoint = EXCLUDED.endpoint,
+                region = EXCLUDED.region, storage_class = EXCLUDED.storage_class,
+                config = EXCLUDED.config, updated_at = NOW()
+            RETURNING id
+            "#,
+            backend.id,
+            backend.name,
+            backend.provider,
+            backend.endpoint,
+            backend.region,
+            backend.storage_class,
+            backend.config
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(result.id)
+    }
+
+    async fn create_backend_migration(
+        &self,
+        migration: &types::BackendMigration,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO backend_migrations (id, bucket_id, source_backend_id, target_backend_id,
+                status, progress)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            migration.id,
+            migration.bucket_id,
+            migration.source_backend_id,
+            migration.target_backend_id,
+            migration.status,
+            serde_json::to_value(&migration.progress).unwrap_or_default()
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn claim_backend_migration(
+        &self,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Option<types::BackendMigration>, sqlx::Error> {
+        // Same leasing as lifecycle rules: the lease is renewed at every
+        // checkpoint, and runs whose gateway died are picked up once it expires
+        let result = sqlx::query!(
+            r#"
+            WITH due AS (
+                SELECT id
+                FROM backend_migrations
+                WHERE status = 'in_progress' AND (lease_until IS NULL OR lease_until <= NOW())
+                ORDER BY started_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE backend_migrations m
+            SET lease_until = $1, updated_at = NOW()
+            FROM due, buckets b
+            WHERE m.id = due.id AND b.id = m.bucket_id
+            RETURNING m.id, m.bucket_id, b.name AS bucket_name, m.source_backend_id,
+                m.target_backend_id, m.status, m.progress, m.started_at, m.completed_at, m.error
+            "#,
+            lease_until
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result.map(|result| types::BackendMigration {
+            id: result.id,
+            bucket_id: result.bucket_id,
+            bucket_name: result.bucket_name,
+            source_backend_id: result.source_backend_id,
+            target_backend_id: result.target_backend_id,
+            status: result.status,
+            progress: migration_progress(result.progress),
+            started_at: result.started_at,
+            completed_at: result.completed_at,
+            error: result.error,
+        }))
+    }
+
+    async fn checkpoint_backend_migration(
+        &self,
+        id: uuid::Uuid,
+        progress: &types::MigrationProgress,
+        lease_until: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE backend_migrations
+            SET progress = $2, lease_until = $3, updated_at = NOW()
+            WHERE id = $1 AND status = 'in_progress'
+            "#,
+            id,
+            serde_json::to_value(progress).unwrap_or_default(),
+            lease_until
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn complete_backend_migration(
+        &self,
+        migration: &types::BackendMigration,
+    ) -> Result<bool, sqlx::Error> {
+        // The bucket is flipped in the statement that completes the migration,
+        // and only once no version is left on the source backend
+        let result = sqlx::query!(
+            r#"
+            WITH done AS (
+                UPDATE backend_migrations
+                SET status = 'completed', completed_at = NOW(), lease_until = NULL,
+                    updated_at = NOW()
+                WHERE id = $1 AND status = 'in_progress' AND NOT EXISTS (
+                    SELECT 1
+                    FROM objects
+                    WHERE bucket_id = $2 AND (backend_id = $3 OR backend_id IS NULL)
+                        AND backend_specific_name IS NOT NULL
+                )
+                RETURNING bucket_id
+            )
+            UPDATE buckets
+            SET backend_id = $4, updated_at = NOW()
+            FROM done
+            WHERE buckets.id = done.bucket_id
+            "#,
+            migration.id,
+            migration.bucket_id,
+            migration.source_backend_id,
+            migration.target_backend_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn fail_backend_migration(&self, id: uuid::Uuid, error: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE backend_migrations
+            SET status = 'failed', error = $2, lease_until = NULL, updated_at = NOW()
+            WHERE id = $1 AND status = 'in_progress'
+            "#,
+            id,
+            error
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+    async fn claim_replication_items(
+        &self,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<types::ReplicationItem>, sqlx::Error> {
+        // Same leasing as lifecycle rules, a copy interrupted by a crash is
+        // retried once the lease expires
+        let results = sqlx::query!(
+            r#"
+            WITH due AS (
+                SELECT bucket_id, key, version_id
+                FROM replication_queue
+                WHERE status = 'pending' AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE replication_queue q
+            SET next_attempt_at = $1
+            FROM due, buckets b
+            WHERE q.bucket_id = due.bucket_id AND q.key = due.key
+                AND q.version_id = due.version_id AND b.id = q.bucket_id
+            RETURNING q.bucket_id, q.key, q.version_id, q.attempts, b.name AS bucket_name
+            "#,
+            lease_until,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut items: Vec<_> = results
+            .into_iter()
+            .map(|result| types::ReplicationItem {
+                bucket_id: result.bucket_id,
+                bucket_name: result.bucket_name,
+                key: result.key,
+                version_id: result.version_id,
+                attempts: result.attempts,
+            })
+            .collect();
+        // Versions of a key are copied in the order they were written
+        items.sort_by_key(|item| item.version_id);
+        Ok(items)
+    }
+
+    async fn complete_replication(&self, item: &types::ReplicationItem) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            r#"
+            UPDATE objects
+            SET replication_status = $4, updated_at = NOW()
+            WHERE bucket_id = $1 AND key = $2 AND version_id = $3
+            "#,
+            item.bucket_id,
+            item.key,
+            item.version_id,
+            REPLICATION_COMPLETED
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            DELETE FROM replication_queue
+            WHERE bucket_id = $1 AND key = $2 AND version_id = $3
+            "#,
+            item.bucket_id,
+            item.key,
+            item.version_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn retry_replication(
+        &self,
+        item: &types::ReplicationItem,
+        next_attempt_at: DateTime<Utc>,
+        error: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE replication_queue
+            SET attempts = attempts + 1, next_attempt_at = $4, last_error = $5
+            WHERE bucket_id = $1 AND key = $2 AND version_id = $3
+            "#,
+            item.bucket_id,
+            item.key,
+            item.version_id,
+            next_attempt_at,
+            error
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn fail_replication(
+        &self,
+        item: &types::ReplicationItem,
+        error: &str,
+    ) -> Result<(), sqlx::Error> {
+        // The row stays in the queue, marked failed, for operators to inspect
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            r#"
+            UPDATE objects
+            SET replication_status = $4, updated_at = NOW()
+            WHERE bucket_id = $1 AND key = $2 AND version_id = $3
+            "#,
+            item.bucket_id,
+            item.key,
+            item.version_id,
+            REPLICATION_FAILED
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            UPDATE replication_queue
+            SET status = 'failed', attempts = attempts + 1, last_error = $4
+            WHERE bucket_id = $1 AND key = $2 AND version_id = $3
+            "#,
+            item.bucket_id,
+            item.key,
+            item.version_id,
+            error
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+// Index a new latest version of an object
+async fn insert_object(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    bucket: &types::Bucket,
+    object: &types::Object,
+) -> Result<Vec<types::Object>, sqlx::Error> {
+    let encryption = object
+        .encryption
+        .as_ref()
+        .map(serde_json::to_value)
+        .transpose()
+        .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+    let compression = object
+        .compression
+        .as_ref()
+        .map(serde_json::to_value)
+        .transpose()
+        .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+    let tags = (!object.tags.is_empty())
+        .then(|| serde_json::to_value(&object.tags))
+        .transpose()
+        .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+    // A new version of an unversioned bucket replaces the key
+    let replaced = match bucket.versioning == VERSIONING_DISABLED as i16 {
+        true => remove_versions(tx, bucket.id, &object.key, false).await?,
+        false => vec![],
+    };
+    sqlx::query!(
+        r#"
+        UPDATE objects
+        SET is_latest = false, updated_at = NOW()
+        WHERE bucket_id = $1 AND key = $2 AND is_latest
+        "#,
+        bucket.id,
+        object.key
+    )
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO objects (bucket_id, key, size, version_id, owner_id, etag, storage_class,
+            backend_id, backend_specific_name, backend_specific_id, inline_data, encryption,
+            compression, tags, retention_mode, retain_until, legal_hold,
+            website_redirect_location, replication_status, failover_from)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+            $18, $19, $20)
+        "#,
+        bucket.id,
+        object.key,
+        object.size,
+        object.version_id,
+        object.owner_id.to_string(),
+        object.etag,
+        object.storage_class,
+        object.backend_id,
+        object.backend_specific_name,
+        object.backend_specific_id,
+        object.inline_data,
+        encryption,
+        compression,
+        tags,
+        object.retention_mode,
+        object.retain_until,
+        object.legal_hold,
+        object.website_redirect_location,
+        object.replication_status,
+        object.failover_from
+    )
+    .execute(&mut **tx)
+    .await?;
+    add_live_bytes(tx, object.packed(), object.size).await?;
+    enqueue_replication(tx, object).await?;
+    Ok(replaced)
+}
+
+// Remove the versions of `key` that Object Lock does not protect, with the
+// volume bytes and blob references they held. The removed versions are
+// returned for their data to be deleted once the transaction commits.
+async fn remove_versions(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    bucket_id: uuid::Uuid,
+    key: &str,
+    bypass_governance: bool,
+) -> Result<Vec<types::Object>, sqlx::Error> {
+    let results = sqlx::query!(
+        r#"
+        DELETE FROM objects
+        WHERE bucket_id = $1 AND key = $2 AND NOT legal_hold
+            AND (retain_until IS NULL OR retain_until <= NOW()
+                OR (retention_mode = 'GOVERNANCE' AND $3))
+        RETURNING version_id, size, etag, is_latest, is_delete_marker, backend_id,
+            backend_specific_name, backend_specific_id
+        "#,
+        bucket_id,
+        key,
+        bypass_governance
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+    let mut removed = Vec::with_capacity(results.len());
+    for result in results {
+        let object = types::Object {
+            bucket_id,
+            key: key.to_string(),
+            version_id: result.version_id,
+            size: result.size,
+            etag: result.etag,
+            is_latest: result.is_latest,
+            is_delete_marker: result.is_delete_marker,
+            backend_id: result.backend_id,
+            backend_specific_name: result.backend_specific_name,
+            backend_specific_id: result.backend_specific_id,
+            ..Default::default()
+        };
+        add_live_bytes(tx, object.packed(), -object.size).await?;
+        release_reference(
+            tx,
+            bucket_id,
+            object.backend_id,
+            object.backend_specific_id.as_deref(),
+        )
+        .await?;
+        removed.push(object);
+    }
+    Ok(removed)
+}
+
+// Keep the live bytes of a volume in step with the objects packed into it
+async fn add_live_bytes(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    packed: Option<types::PackedLocation>,
+    delta: i64,
+) -> Result<(), sqlx::Error> {
+    let packed = match packed {
+        Some(packed) => packed,
+        None => return Ok(()),
+    };
+    sqlx::query!(
+        r#"
+        UPDATE volumes
+        SET live_bytes = live_bytes + $2
+        WHERE id = $1
+        "#,
+        packed.volume_id,
+        delta
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+// Drop the reference an object held on a content addressed blob. The blob is
+// collected once no reference is left.
+async fn release_reference(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    bucket_id: uuid::Uuid,
+    backend_id: Option<uuid::Uuid>,
+    backend_specific_id: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let sha256 = match backend_specific_id.and_then(types::content_hash) {
+        Some(sha256) => sha256,
+        None => return Ok(()),
+    };
+    sqlx::query!(
+        r#"
+        UPDATE blobs
+        SET refcount = refcount - 1,
+            released_at = CASE WHEN refcount = 1 THEN NOW() ELSE released_at END
+        WHERE bucket_id = $1 AND backend_id = $2 AND sha256 = $3 AND refcount > 0
+        "#,
+        bucket_id,
+        backend_id,
+        sha256
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+// Queue a PENDING version for replication, in the transaction indexing it
+async fn enqueue_replication(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    object: &types::Object,
+) -> Result<(), sqlx::Error> {
+    if object.replication_status.as_deref() != Some(REPLICATION_PENDING) {
+        return Ok(());
+    }
+    sqlx::query!(
+        r#"
+        INSERT INTO replication_queue (bucket_id, key, version_id)
+        VALUES ($1, $2, $3)
+        "#,
+        object.bucket_id,
+        object.key,
+        object.version_id
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
 }
@@ -1,4 +1,5 @@
 use axum::async_trait;
+use chrono::{DateTime, Utc};
 
 use super::types;
 
@@ -19,10 +20,85 @@ pub trait IndexReader: Send + Sync {
         bucket_id: uuid::Uuid,
         key: &str,
     ) -> Result<types::Object, sqlx::Error>;
+    async fn get_object_version(
+        &self,
+        bucket_id: uuid::Uuid,
+        key: &str,
+        version_id: uuid::Uuid,
+    ) -> Result<Option<types::Object>, sqlx::Error>;
     async fn list_objects(&self, bucket_id: uuid::Uuid) -> Result<Vec<types::Object>, sqlx::Error>;
     async fn list_object_versions(&self, bucket: &str, key: &str) -> Result<(), sqlx::Error>;
     async fn list_parts(&self, bucket: &str, key: &str, upload_id: &str)
         -> Result<(), sqlx::Error>;
+    async fn list_rate_limits(&self) -> Result<Vec<types::RateLimit>, sqlx::Error>;
+    async fn list_expired_objects(
+        &self,
+        bucket_id: uuid::Uuid,
+        prefix: &str,
+        created_before: DateTime<Utc>,
+        after: &(String, uuid::Uuid),
+        limit: i64,
+    ) -> Result<Vec<types::Object>, sqlx::Error>;
+    async fn list_noncurrent_versions(
+        &self,
+        bucket_id: uuid::Uuid,
+        prefix: &str,
+        noncurrent_before: DateTime<Utc>,
+        newer_noncurrent_versions: i64,
+        after: &(String, uuid::Uuid),
+        limit: i64,
+    ) -> Result<Vec<types::Object>, sqlx::Error>;
+    async fn list_expired_delete_markers(
+        &self,
+        bucket_id: uuid::Uuid,
+        prefix: &str,
+        after: &(String, uuid::Uuid),
+        limit: i64,
+    ) -> Result<Vec<types::Object>, sqlx::Error>;
+    async fn list_stale_multipart_uploads(
+        &self,
+        bucket_id: uuid::Uuid,
+        prefix: &str,
+        created_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<types::Multipart>, sqlx::Error>;
+    async fn get_multipart_upload(
+        &self,
+        bucket_id: uuid::Uuid,
+        key: &str,
+        upload_id: &str,
+    ) -> Result<Option<types::Multipart>, sqlx::Error>;
+    async fn list_multipart_parts(&self, id: uuid::Uuid) -> Result<Vec<types::Part>, sqlx::Error>;
+    async fn list_transition_candidates(
+        &self,
+        bucket_id: uuid::Uuid,
+        prefix: &str,
+        created_before: DateTime<Utc>,
+        storage_class: &str,
+        after: &(String, uuid::Uuid),
+        limit: i64,
+    ) -> Result<Vec<types::Object>, sqlx::Error>;
+    async fn list_migration_candidates(
+        &self,
+        bucket_id: uuid::Uuid,
+        source_backend_id: uuid::Uuid,
+        after: &(String, uuid::Uuid),
+        limit: i64,
+    ) -> Result<Vec<types::Object>, sqlx::Error>;
+    async fn list_failover_writes(
+        &self,
+        after: &(uuid::Uuid, String, uuid::Uuid),
+        limit: i64,
+    ) -> Result<Vec<types::FailoverWrite>, sqlx::Error>;
+    async fn list_volume_objects(
+        &self,
+        volume: &types::Volume,
+    ) -> Result<Vec<types::Object>, sqlx::Error>;
+    async fn get_backend_migration(
+        &self,
+        id: uuid::Uuid,
+    ) -> Result<Option<types::BackendMigration>, sqlx::Error>;
+    async fn list_storage_backends(&self) -> Result<Vec<types::StorageBackend>, sqlx::Error>;
 }
 
 #[async_trait]
@@ -30,24 +106,239 @@ pub trait IndexWriter: Send + Sync {
     async fn create_bucket(&self, bucket: &types::Bucket) -> Result<(), sqlx::Error>;
     async fn delete_bucket(&self, bucket: &types::Bucket, user_id: &i64)
         -> Result<(), sqlx::Error>;
+    async fn update_bucket_cors(
+        &self,
+        bucket_id: uuid::Uuid,
+        cors: Option<serde_json::Value>,
+    ) -> Result<(), sqlx::Error>;
+    async fn update_bucket_website(
+        &self,
+        bucket_id: uuid::Uuid,
+        website: Option<serde_json::Value>,
+    ) -> Result<(), sqlx::Error>;
+    async fn update_bucket_logging(
+        &self,
+        bucket_id: uuid::Uuid,
+        logging: Option<serde_json::Value>,
+    ) -> Result<(), sqlx::Error>;
+    async fn update_bucket_notification(
+        &self,
+        bucket_id: uuid::Uuid,
+        notification: Option<serde_json::Value>,
+    ) -> Result<(), sqlx::Error>;
+    async fn update_bucket_replication(
+        &self,
+        bucket_id: uuid::Uuid,
+        replication: Option<serde_json::Value>,
+    ) -> Result<(), sqlx::Error>;
+    // Index a new version. In unversioned buckets it replaces the key, and
+    // the replaced versions are returned for their data to be deleted.
     async fn put_object(
         &self,
         bucket: &types::Bucket,
         object: &types::Object,
+    ) -> Result<Vec<types::Object>, sqlx::Error>;
+    // Index the copy of a version in a destination bucket. It only becomes
+    // the latest version when no newer one exists, and false is returned
+    // when the version is already there.
+    async fn put_replica(
+        &self,
+        bucket: &types::Bucket,
+        object: &types::Object,
+    ) -> Result<bool, sqlx::Error>;
+    async fn update_bucket_lifecycle(
+        &self,
+        bucket_id: uuid::Uuid,
+        lifecycle: Option<serde_json::Value>,
+        rules: Vec<types::LifecycleRuleRow>,
+    ) -> Result<(), sqlx::Error>;
+    async fn claim_lifecycle_rules(
+        &self,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<types::LifecycleRule>, sqlx::Error>;
+    async fn complete_lifecycle_rule(
+        &self,
+        id: uuid::Uuid,
+        next_execution_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+    // Versions under a legal hold or an unexpired retention are kept, and
+    // false is returned. GOVERNANCE retention is lifted by bypass_governance.
+    async fn delete_object_version(
+        &self,
+        bucket_id: uuid::Uuid,
+        key: &str,
+        version_id: uuid::Uuid,
+        bypass_governance: bool,
+    ) -> Result<bool, sqlx::Error>;
+    // Remove every version of `key` not under Object Lock, as deleting a key
+    // of an unversioned bucket does. Returns the removed versions.
+    async fn delete_object_versions(
+        &self,
+        bucket_id: uuid::Uuid,
+        key: &str,
+        bypass_governance: bool,
+    ) -> Result<Vec<types::Object>, sqlx::Error>;
+    async fn put_delete_marker(
+        &self,
+        current: &types::Object,
+        marker: &types::Object,
+    ) -> Result<bool, sqlx::Error>;
+    async fn create_multipart_upload(&self, upload: &types::Multipart) -> Result<(), sqlx::Error>;
+    // Replaces a part uploaded before under the same number
+    async fn put_multipart_part(
+        &self,
+        id: uuid::Uuid,
+        part: &types::Part,
+    ) -> Result<(), sqlx::Error>;
+    // Index the object assembled from an upload and remove the upload, see
+    // put_object. None is returned when the upload was completed or aborted
+    // meanwhile.
+    async fn complete_multipart_upload(
+        &self,
+        id: uuid::Uuid,
+        bucket: &types::Bucket,
+        object: &types::Object,
+    ) -> Result<Option<Vec<types::Object>>, sqlx::Error>;
+    async fn abort_multipart_upload(&self, id: uuid::Uuid) -> Result<Vec<u32>, sqlx::Error>;
+    async fn repoint_object(
+        &self,
+        object: &types::Object,
+        storage_class: &str,
+        backend_id: Option<uuid::Uuid>,
+        backend_specific_name: Option<&str>,
+        backend_specific_id: Option<&str>,
+    ) -> Result<bool, sqlx::Error>;
+    async fn create_volume(&self, volume: &types::Volume) -> Result<(), sqlx::Error>;
+    async fn update_bucket_encryption(
+        &self,
+        bucket_id: uuid::Uuid,
+        encryption: Option<serde_json::Value>,
+    ) -> Result<(), sqlx::Error>;
+    async fn update_bucket_tags(
+        &self,
+        bucket_id: uuid::Uuid,
+        tags: Option<serde_json::Value>,
+    ) -> Result<(), sqlx::Error>;
+    async fn update_object_tags(
+        &self,
+        object: &types::Object,
+        tags: Option<serde_json::Value>,
+    ) -> Result<bool, sqlx::Error>;
+    async fn update_bucket_object_lock(
+        &self,
+        bucket_id: uuid::Uuid,
+        object_lock: serde_json::Value,
+    ) -> Result<(), sqlx::Error>;
+    async fn update_bucket_versioning(
+        &self,
+        bucket_id: uuid::Uuid,
+        versioning: i16,
+    ) -> Result<(), sqlx::Error>;
+    async fn update_object_retention(
+        &self,
+        object: &types::Object,
+        retention_mode: Option<&str>,
+        retain_until: Option<DateTime<Utc>>,
+    ) -> Result<bool, sqlx::Error>;
+    async fn update_object_legal_hold(
+        &self,
+        object: &types::Object,
+        legal_hold: bool,
+    ) -> Result<bool, sqlx::Error>;
+    async fn update_bucket_dedup(
+        &self,
+        bucket_id: uuid::Uuid,
+        dedup: bool,
+    ) -> Result<(), sqlx::Error>;
+    async fn update_bucket_fallback(
+        &self,
+        bucket_id: uuid::Uuid,
+        fallback_backend_id: Option<uuid::Uuid>,
+    ) -> Result<(), sqlx::Error>;
+    async fn reserve_blob(
+        &self,
+        bucket_id: uuid::Uuid,
+        backend_id: uuid::Uuid,
+        sha256: &str,
+        size: i64,
+    ) -> Result<Option<bool>, sqlx::Error>;
+    async fn mark_blob_stored(
+        &self,
+        bucket_id: uuid::Uuid,
+        backend_id: uuid::Uuid,
+        sha256: &str,
+    ) -> Result<(), sqlx::Error>;
+    async fn release_blob(
+        &self,
+        bucket_id: uuid::Uuid,
+        backend_id: uuid::Uuid,
+        sha256: &str,
+    ) -> Result<(), sqlx::Error>;
+    async fn claim_released_blobs(
+        &self,
+        released_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<types::Blob>, sqlx::Error>;
+    async fn delete_blob(&self, blob: &types::Blob) -> Result<(), sqlx::Error>;
+    async fn claim_volume(
+        &self,
+        created_before: DateTime<Utc>,
+        max_live_ratio: f64,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Option<types::Volume>, sqlx::Error>;
+    async fn delete_volume(&self, id: uuid::Uuid) -> Result<bool, sqlx::Error>;
+    async fn register_storage_backend(
+        &self,
+        backend: &types::StorageBackend,
+    ) -> Result<uuid::Uuid, sqlx::Error>;
+    async fn create_backend_migration(
+        &self,
+        migration: &types::BackendMigration,
+    ) -> Result<(), sqlx::Error>;
+    async fn claim_backend_migration(
+        &self,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Option<types::BackendMigration>, sqlx::Error>;
+    async fn checkpoint_backend_migration(
+        &self,
+        id: uuid::Uuid,
+        progress: &types::MigrationProgress,
+        lease_until: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error>;
+    async fn complete_backend_migration(
+        &self,
+        migration: &types::BackendMigration,
+    ) -> Result<bool, sqlx::Error>;
+    async fn fail_backend_migration(&self, id: uuid::Uuid, error: &str) -> Result<(), sqlx::Error>;
+    async fn claim_replication_items(
+        &self,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<types::ReplicationItem>, sqlx::Error>;
+    async fn complete_replication(&self, item: &types::ReplicationItem) -> Result<(), sqlx::Error>;
+    async fn retry_replication(
+        &self,
+        item: &types::ReplicationItem,
+        next_attempt_at: DateTime<Utc>,
+        error: &str,
+    ) -> Result<(), sqlx::Error>;
+    async fn fail_replication(
+        &self,
+        item: &types::ReplicationItem,
+        error: &str,
     ) -> Result<(), sqlx::Error>;
-    async fn delete_object(&self, bucket: &str, key: &str) -> Result<(), sqlx::Error>;
-    async fn delete_objects(&self, bucket: &str, keys: Vec<String>) -> Result<(), sqlx::Error>;
 }
 
 #[async_trait]
 impl Indexer for Database {}
 
 pub struct Database {
-  pub pool: sqlx::PgPool,
+    pub pool: sqlx::PgPool,
 }
 
 impl Database {
-  pub fn new(pool: sqlx::PgPool) -> Self {
-      Self { pool }
-  }
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
 }
@@ -0,0 +1,309 @@
+use axum::body::Bytes;
+use s3_core::S3Error;
+
+use crate::{backend::types, config::CompressionConfig, crypto::CustomerKey};
+
+use super::FullstackBackend;
+
+static ZSTD: &str = "zstd";
+
+/// Compression of object data at rest with zstd.
+///
+/// The data is cut into frames of `frame_size` original bytes, each
+/// compressed on its own into a complete zstd frame. The frames back to
+/// back still form a valid zstd stream, and the compressed size of every
+/// frame is kept in the index so that a range is read and decompressed from
+/// the frames holding it alone. Clients only ever see the original data,
+/// size and ETag.
+pub(super) struct Compressor {
+    config: CompressionConfig,
+}
+
+impl Compressor {
+    pub(super) fn new(config: CompressionConfig) -> Self {
+        Self { config }
+    }
+
+    /// Compress `data` into frames. Returns None for data stored as is:
+    /// smaller than min_size, of an unlisted content type whose first frame
+    /// does not compress well, or not shrinking to max_ratio overall.
+    pub(super) fn compress(
+        &self,
+        content_type: Option<&str>,
+        data: &[u8],
+    ) -> Result<Option<(types::ObjectCompression, Vec<u8>)>, S3Error> {
+        if data.is_empty() || (data.len() as u64) < self.config.min_size {
+            return Ok(None);
+        }
+        let listed = self.listed(content_type);
+        if !listed && !self.config.detect {
+            return Ok(None);
+        }
+        let frame_size = self.config.frame_size.max(1);
+        let mut frames = vec![];
+        let mut compressed = vec![];
+        for (i, frame) in data.chunks(frame_size as usize).enumerate() {
+            let output = zstd::bulk::compress(frame, self.config.level).map_err(|e| {
+                tracing::error!("Error compressing object data: {:?}", e);
+                S3Error::InternalError
+            })?;
+            // Other content is judged by its first frame
+            if i == 0 && !listed && !self.worth_it(output.len(), frame.len()) {
+                return Ok(None);
+            }
+            frames.push(output.len() as u64);
+            compressed.extend_from_slice(&output);
+        }
+        if !self.worth_it(compressed.len(), data.len()) {
+            return Ok(None);
+        }
+        let compression = types::ObjectCompression {
+            codec: ZSTD.to_string(),
+            frame_size,
+            frames,
+        };
+        Ok(Some((compression, compressed)))
+    }
+
+    // Whether the content type is in the allowlist, parameters are ignored
+    fn listed(&self, content_type: Option<&str>) -> bool {
+        let content_type = match content_type {
+            Some(content_type) => content_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase(),
+            None => return false,
+        };
+        self.config
+            .content_types
+            .iter()
+            .any(|pattern| match pattern.strip_suffix("/*") {
+                Some(kind) => content_type
+                    .split_once('/')
+                    .is_some_and(|(t, _)| t.eq_ignore_ascii_case(kind)),
+                None => pattern.eq_ignore_ascii_case(&content_type),
+            })
+    }
+
+    fn worth_it(&self, compressed: usize, original: usize) -> bool {
+        compressed as f64 <= original as f64 * self.config.max_ratio
+    }
+}
+
+impl FullstackBackend {
+    /// Read the inclusive original `range` of a compressed object, or all
+    /// of it. Only the frames holding the range are read and decompressed.
+    pub(super) async fn read_compressed(
+        &self,
+        bucket: &types::Bucket,
+        object: &types::Object,
+        compression: &types::ObjectCompression,
+        range: Option<(u64, u64)>,
+        customer: Option<&CustomerKey>,
+    ) -> Result<Bytes, S3Error> {
+        let (start, end) = range.unwrap_or((0, (object.size as u64).saturating_sub(1)));
+        let frames = frame_range(compression, start, end).ok_or_else(|| {
+            tracing::error!("Frames of object {} do not cover its size", object.key);
+            S3Error::InternalError
+        })?;
+        let stored = self
+            .read_stored(
+                bucket,
+                object,
+                compression.stored_size(),
+                range.map(|_| frames.stored),
+                customer,
+            )
+            .await?;
+        let original = decompress_frames(compression, &stored, frames.first, frames.last)
+            .inspect_err(|_| {
+                tracing::error!("Error decompressing object {}", object.key);
+            })?;
+
+        // The frames start at a frame boundary, cut them down to the range
+        let offset = frames.first as u64 * compression.frame_size;
+        let (start, end) = ((start - offset) as usize, (end - offset) as usize);
+        if end >= original.len() {
+            tracing::error!("Object {} decompressed short of its size", object.key);
+            return Err(S3Error::InternalError);
+        }
+        Ok(Bytes::from(original).slice(start..=end))
+    }
+}
+
+// Frames holding an original range, and the inclusive range of the stored
+// data they take up
+#[derive(Debug, PartialEq)]
+struct FrameRange {
+    first: usize,
+    last: usize,
+    stored: (u64, u64),
+}
+
+fn frame_range(compression: &types::ObjectCompression, start: u64, end: u64) -> Option<FrameRange> {
+    let first = (start / compression.frame_size) as usize;
+    let last = (end / compression.frame_size) as usize;
+    let offset: u64 = compression.frames.get(..first)?.iter().sum();
+    let length: u64 = compression.frames.get(first..=last)?.iter().sum();
+    if length == 0 {
+        return None;
+    }
+    Some(FrameRange {
+        first,
+        last,
+        stored: (offset, offset + length - 1),
+    })
+}
+
+// Decompress the frames `first..=last`, read back to back into `data`
+fn decompress_frames(
+    compression: &types::ObjectCompression,
+    data: &[u8],
+    first: usize,
+    last: usize,
+) -> Result<Vec<u8>, S3Error> {
+    if compression.codec != ZSTD {
+        tracing::error!("Unknown compression codec {}", compression.codec);
+        return Err(S3Error::InternalError);
+    }
+    let frames = compression
+        .frames
+        .get(first..=last)
+        .ok_or(S3Error::InternalError)?;
+    let mut original = Vec::with_capacity(frames.len() * compression.frame_size as usize);
+    let mut offset = 0;
+    for length in frames {
+        let frame = data
+            .get(offset..offset + *length as usize)
+            .ok_or(S3Error::InternalError)?;
+        let output = zstd::bulk::decompress(frame, compression.frame_size as usize)
+            .map_err(|_| S3Error::InternalError)?;
+        original.extend_from_slice(&output);
+        offset += *length as usize;
+    }
+    if offset != data.len() {
+        return Err(S3Error::InternalError);
+    }
+    Ok(original)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compressor() -> Compressor {
+        Compressor::new(CompressionConfig {
+            frame_size: 1000,
+            min_size: 100,
+            content_types: vec!["text/*".to_string(), "application/json".to_string()],
+            ..Default::default()
+        })
+    }
+
+    // Runs of letters, which any codec shrinks
+    fn text(size: usize) -> Vec<u8> {
+        (0..size).map(|i| b'a' + (i / 50 % 26) as u8).collect()
+    }
+
+    fn noise(size: usize) -> Vec<u8> {
+        let mut state = 0x2545f491u32;
+        (0..size)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_listed() {
+        let compressor = compressor();
+        assert!(compressor.listed(Some("text/plain")));
+        assert!(compressor.listed(Some("Text/CSV; charset=utf-8")));
+        assert!(compressor.listed(Some("application/json")));
+        assert!(!compressor.listed(Some("application/octet-stream")));
+        assert!(!compressor.listed(Some("textual/plain")));
+        assert!(!compressor.listed(None));
+    }
+
+    #[test]
+    fn test_compress() {
+        let compressor = compressor();
+        let data = text(3500);
+        let (compression, compressed) = compressor
+            .compress(Some("text/plain"), &data)
+            .unwrap()
+            .unwrap();
+        assert_eq!(compression.frames.len(), 4);
+        assert_eq!(compression.stored_size(), compressed.len() as u64);
+        assert!(compressed.len() < data.len());
+        let original = decompress_frames(&compression, &compressed, 0, 3).unwrap();
+        assert_eq!(original, data);
+
+        // Detected from the data
+        assert!(compressor.compress(None, &data).unwrap().is_some());
+        assert!(compressor.compress(None, &noise(3500)).unwrap().is_none());
+        // Listed but not shrinking
+        assert!(compressor
+            .compress(Some("text/plain"), &noise(3500))
+            .unwrap()
+            .is_none());
+        assert!(compressor
+            .compress(Some("text/plain"), &data[..50])
+            .unwrap()
+            .is_none());
+
+        let compressor = Compressor::new(CompressionConfig {
+            detect: false,
+            ..compressor.config
+        });
+        assert!(compressor.compress(None, &data).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_frame_range() {
+        let compression = types::ObjectCompression {
+            codec: ZSTD.to_string(),
+            frame_size: 1000,
+            frames: vec![10, 20, 30, 5],
+        };
+        assert_eq!(
+            frame_range(&compression, 0, 3499),
+            Some(FrameRange {
+                first: 0,
+                last: 3,
+                stored: (0, 64)
+            })
+        );
+        assert_eq!(
+            frame_range(&compression, 1500, 2000),
+            Some(FrameRange {
+                first: 1,
+                last: 2,
+                stored: (10, 59)
+            })
+        );
+        assert_eq!(frame_range(&compression, 3000, 4000), None);
+    }
+
+    #[test]
+    fn test_decompress_range() {
+        let data = text(3500);
+        let (compression, compressed) = compressor()
+            .compress(Some("text/plain"), &data)
+            .unwrap()
+            .unwrap();
+        let frames = frame_range(&compression, 1500, 2100).unwrap();
+        let (start, end) = frames.stored;
+        let stored = &compressed[start as usize..=end as usize];
+        let original = decompress_frames(&compression, stored, frames.first, frames.last).unwrap();
+        assert_eq!(&original[500..=1100], &data[1500..=2100]);
+
+        // Truncated data is refused
+        assert!(decompress_frames(&compression, &stored[1..], frames.first, frames.last).is_err());
+    }
+}
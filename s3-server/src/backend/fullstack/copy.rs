@@ -0,0 +1,131 @@
+use axum::body::Bytes;
+use md5::Digest;
+use s3_core::{
+    copy::{CopyObjectResult, CopySource},
+    response::ResponseData,
+    versioning::VERSIONING_DISABLED,
+    S3Error, StorageClass,
+};
+use uuid::Uuid;
+
+use crate::{backend::types, filter::S3Data};
+
+use super::{
+    encryption::{check_customer_key, copy_source_customer_key, customer_key, encryption_headers},
+    notification::request_event,
+    object_lock::apply_object_lock,
+    replication::replication_status,
+    tagging::copy_tags,
+    website::request_redirect_location,
+    FullstackBackend,
+};
+
+static COPY_SOURCE_HEADER: &str = "x-amz-copy-source";
+static COPY_SOURCE_VERSION_HEADER: &str = "x-amz-copy-source-version-id";
+
+// A copy is a new version written like a PUT of the source's data. The data
+// is read in plaintext, with the copy source SSE-C key for SSE-C sources, and
+// stored as the copy's own encryption settings ask for, under a new data key.
+impl FullstackBackend {
+    pub async fn copy_object(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let header = data
+            .req
+            .headers()
+            .get(COPY_SOURCE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        let source = CopySource::parse(header)?;
+
+        let other_bucket;
+        let source_bucket = match source.bucket == bucket.name {
+            true => bucket,
+            false => {
+                other_bucket = self.get_bucket(&source.bucket).await?;
+                &other_bucket
+            }
+        };
+        let mut source_object = match &source.version_id {
+            Some(version_id) => {
+                let object = self
+                    .object_version(source_bucket, &source.key, version_id)
+                    .await?;
+                // A delete marker has no data to copy
+                if object.is_delete_marker {
+                    return Err(S3Error::InvalidRequest);
+                }
+                object
+            }
+            None => self.latest_object(source_bucket, &source.key).await?,
+        };
+        let source_customer = copy_source_customer_key(data)?;
+        check_customer_key(&source_object, source_customer.as_ref())?;
+        let bytes = match source_object.inline_data.take() {
+            Some(inline) => Bytes::from(inline),
+            None => {
+                self.read_object(
+                    source_bucket,
+                    &source_object,
+                    None,
+                    source_customer.as_ref(),
+                )
+                .await?
+            }
+        };
+
+        let version_id = Uuid::now_v7();
+        let mut object = types::Object {
+            bucket_id: bucket.id,
+            key: data.key.clone(),
+            owner_id: data.auth_key.user_id,
+            version_id,
+            is_latest: true,
+            size: bytes.len() as i64,
+            etag: const_hex::encode(md5::Md5::digest(&bytes)),
+            tags: copy_tags(data, &source_object.tags)?,
+            website_redirect_location: request_redirect_location(data.req.headers())?
+                .or(source_object.website_redirect_location.clone()),
+            storage_class: StorageClass::Standard.as_str().to_string(),
+            backend_id: bucket
+                .migration_target
+                .or(bucket.backend_id)
+                .or(self.storage.default_backend()),
+            backend_specific_name: Some(version_id.to_string()),
+            ..Default::default()
+        };
+        // Retention and legal hold are not copied, the copy gets its own
+        apply_object_lock(data.req.headers(), bucket, &mut object, chrono::Utc::now())?;
+        object.replication_status = replication_status(bucket, &object);
+
+        let customer = customer_key(data)?;
+        let encryption = self.encryption_request(data, bucket, customer.as_ref())?;
+        self.store_object(
+            bucket,
+            &mut object,
+            &bytes,
+            None,
+            encryption,
+            customer.as_ref(),
+        )
+        .await?;
+        let event = request_event("s3:ObjectCreated:Copy", data, bucket, &object);
+        self.notify(bucket.notification.as_ref(), event).await;
+
+        let result = CopyObjectResult::new(&object.etag, chrono::Utc::now());
+        data.res.with_bytes(result.to_xml().into());
+        data.res.with_status_code(200);
+        if source_bucket.versioning != VERSIONING_DISABLED as i16 {
+            data.res.with_header(
+                COPY_SOURCE_VERSION_HEADER.to_string(),
+                source_object.version_id.to_string(),
+            );
+        }
+        if let Some(envelope) = &object.encryption {
+            encryption_headers(&mut data.res, envelope, customer.as_ref());
+        }
+        Ok(data.res.clone())
+    }
+}
@@ -0,0 +1,125 @@
+use axum::body::Bytes;
+use chrono::{DateTime, Utc};
+use s3_core::S3Error;
+use sha2::Digest;
+use uuid::Uuid;
+
+use crate::backend::types;
+
+use super::FullstackBackend;
+
+// Objects of dedup buckets reference a blob named after the SHA-256 of their
+// data. References are counted in the blobs table, and the collector deletes
+// blobs that went without references for a grace period.
+impl FullstackBackend {
+    pub async fn set_bucket_dedup(&self, bucket_name: &str, enabled: bool) -> Result<(), S3Error> {
+        let bucket = self.get_bucket(bucket_name).await?;
+        self.database
+            .update_bucket_dedup(bucket.id, enabled)
+            .await
+            .map_err(index_error)
+    }
+
+    /// Take a reference on the blob holding `data`, writing the blob first
+    /// if no object stored it yet. Returns the SHA-256 of the data, or None
+    /// when the blob is being collected and the caller stores the data on
+    /// its own.
+    pub(super) async fn store_deduplicated(
+        &self,
+        bucket: &types::Bucket,
+        backend_id: Uuid,
+        data: &Bytes,
+    ) -> Result<Option<String>, S3Error> {
+        let sha256 = const_hex::encode(sha2::Sha256::digest(data));
+        let stored = match self
+            .database
+            .reserve_blob(bucket.id, backend_id, &sha256, data.len() as i64)
+            .await
+            .map_err(index_error)?
+        {
+            Some(stored) => stored,
+            None => return Ok(None),
+        };
+        if stored {
+            return Ok(Some(sha256));
+        }
+
+        // Concurrent PUTs of the same data may all write the blob, they write
+        // the same bytes under the same name
+        let result = match self.storage.get(Some(backend_id)) {
+            Ok(storage) => {
+                storage
+                    .save_file(
+                        &bucket.name,
+                        &types::blob_name(&sha256),
+                        data.clone().into(),
+                    )
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        self.record_write(Some(backend_id), &result);
+        let result = match result {
+            Ok(()) => self
+                .database
+                .mark_blob_stored(bucket.id, backend_id, &sha256)
+                .await
+                .map_err(index_error),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            self.release_content(bucket.id, backend_id, &sha256).await;
+            return Err(e);
+        }
+        Ok(Some(sha256))
+    }
+
+    // Drop a reference taken by store_deduplicated for an object that was
+    // not indexed. A failure only keeps the blob around.
+    pub(super) async fn release_content(&self, bucket_id: Uuid, backend_id: Uuid, sha256: &str) {
+        if let Err(e) = self
+            .database
+            .release_blob(bucket_id, backend_id, sha256)
+            .await
+        {
+            tracing::warn!(sha256, "Error releasing blob: {:?}", e);
+        }
+    }
+
+    /// Delete the data of blobs that have been unreferenced since
+    /// `released_before`, returning the number of blobs claimed.
+    pub async fn collect_blobs(
+        &self,
+        released_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<usize, S3Error> {
+        let blobs = self
+            .database
+            .claim_released_blobs(released_before, limit)
+            .await
+            .map_err(index_error)?;
+        for blob in &blobs {
+            let name = types::blob_name(&blob.sha256);
+            let result = match self.storage.get(Some(blob.backend_id)) {
+                Ok(storage) => storage.delete_file(&blob.bucket_name, &name).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => self.database.delete_blob(blob).await.map_err(index_error)?,
+                // Claimed again once the grace period has passed
+                Err(e) => tracing::warn!(
+                    bucket = blob.bucket_name,
+                    name,
+                    "Error deleting unreferenced blob: {:?}",
+                    e
+                ),
+            }
+        }
+        Ok(blobs.len())
+    }
+}
+
+fn index_error(e: sqlx::Error) -> S3Error {
+    tracing::error!("Error deduplicating object data: {:?}", e);
+    S3Error::InternalError
+}
@@ -0,0 +1,237 @@
+use s3_core::{
+    delete::{Delete, DeleteError, DeleteResult, DeletedObject, ObjectIdentifier},
+    response::ResponseData,
+    versioning::VERSIONING_DISABLED,
+    S3Error,
+};
+use uuid::Uuid;
+
+use crate::{backend::types, filter::S3Data};
+
+use super::{
+    notification::request_event, object_lock::bypass_governance, replication::replication_status,
+    FullstackBackend,
+};
+
+static DELETE_MARKER_HEADER: &str = "x-amz-delete-marker";
+static VERSION_ID_HEADER: &str = "x-amz-version-id";
+
+// Deleting a key of a versioned bucket adds a delete marker and keeps the
+// data, only deleting a version by its id removes it. Deleting a key of an
+// unversioned bucket removes all of its versions. Versions under a legal
+// hold or an unexpired retention are never removed, GOVERNANCE retention
+// gives way to x-amz-bypass-governance-retention.
+impl FullstackBackend {
+    pub async fn delete_object(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let bypass = bypass_governance(data.req.headers())?;
+        let target = ObjectIdentifier {
+            key: data.key.clone(),
+            version_id: data.query_param("versionId"),
+        };
+        let deleted = self.delete_key(data, bucket, &target, bypass).await?;
+
+        data.res.with_status_code(204);
+        if deleted.delete_marker {
+            data.res
+                .with_header(DELETE_MARKER_HEADER.to_string(), "true".to_string());
+        }
+        if let Some(version_id) = deleted.version_id.or(deleted.delete_marker_version_id) {
+            data.res
+                .with_header(VERSION_ID_HEADER.to_string(), version_id);
+        }
+        Ok(data.res.clone())
+    }
+
+    pub async fn delete_objects(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let bypass = bypass_governance(data.req.headers())?;
+        let request = Delete::from_xml(data.req.body())?;
+
+        let mut result = DeleteResult::default();
+        for target in &request.objects {
+            match self.delete_key(data, bucket, target, bypass).await {
+                // Quiet mode only reports the keys that could not be deleted
+                Ok(_) if request.quiet => {}
+                Ok(deleted) => result.deleted.push(deleted),
+                Err(e) => result.errors.push(DeleteError::new(target, &e)),
+            }
+        }
+
+        data.res.with_bytes(result.to_xml().into());
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    // Delete the version named by `target`, or the key when it names none
+    async fn delete_key(
+        &self,
+        data: &S3Data,
+        bucket: &types::Bucket,
+        target: &ObjectIdentifier,
+        bypass: bool,
+    ) -> Result<DeletedObject, S3Error> {
+        let mut deleted = DeletedObject {
+            key: target.key.clone(),
+            ..Default::default()
+        };
+        if let Some(version_id) = &target.version_id {
+            let object = self.object_version(bucket, &target.key, version_id).await?;
+            // Locked, or deleted meanwhile
+            if !self.delete_version(bucket, &object, bypass).await? {
+                return Err(S3Error::AccessDenied);
+            }
+            let event = request_event("s3:ObjectRemoved:Delete", data, bucket, &object);
+            self.notify(bucket.notification.as_ref(), event).await;
+            deleted.version_id = Some(object.version_id.to_string());
+            deleted.delete_marker = object.is_delete_marker;
+            return Ok(deleted);
+        }
+
+        let latest = match self.latest_object(bucket, &target.key).await {
+            Ok(latest) => latest,
+            // Deleting a missing key succeeds
+            Err(S3Error::NoSuchKey(_)) => return Ok(deleted),
+            Err(e) => return Err(e),
+        };
+        if bucket.versioning == VERSIONING_DISABLED as i16 {
+            // Every version goes, so no older one becomes current
+            let removed = self
+                .database
+                .delete_object_versions(bucket.id, &target.key, bypass)
+                .await
+                .map_err(delete_error)?;
+            for old in &removed {
+                self.remove_version_data(&bucket.name, bucket.backend_id, old)
+                    .await;
+            }
+            // Locked, or deleted meanwhile
+            if removed.is_empty() {
+                return Err(S3Error::AccessDenied);
+            }
+            let event = request_event("s3:ObjectRemoved:Delete", data, bucket, &latest);
+            self.notify(bucket.notification.as_ref(), event).await;
+            return Ok(deleted);
+        }
+        let mut marker = types::Object {
+            bucket_id: bucket.id,
+            key: target.key.clone(),
+            owner_id: latest.owner_id,
+            version_id: Uuid::now_v7(),
+            is_latest: true,
+            is_delete_marker: true,
+            ..Default::default()
+        };
+        // Replicated when the matching rule enables DeleteMarkerReplication
+        marker.replication_status = replication_status(bucket, &marker);
+        let created = self
+            .database
+            .put_delete_marker(&latest, &marker)
+            .await
+            .map_err(delete_error)?;
+        // Another delete demoted the version first and added its own marker
+        if created {
+            let event = request_event(
+                "s3:ObjectRemoved:DeleteMarkerCreated",
+                data,
+                bucket,
+                &marker,
+            );
+            self.notify(bucket.notification.as_ref(), event).await;
+            deleted.delete_marker = true;
+            deleted.delete_marker_version_id = Some(marker.version_id.to_string());
+        }
+        Ok(deleted)
+    }
+
+    // Remove a version from the index, and its data when no other version
+    // shares it. False when the version is locked or already gone.
+    async fn delete_version(
+        &self,
+        bucket: &types::Bucket,
+        object: &types::Object,
+        bypass_governance: bool,
+    ) -> Result<bool, S3Error> {
+        let removed = self
+            .database
+            .delete_object_version(bucket.id, &object.key, object.version_id, bypass_governance)
+            .await
+            .map_err(delete_error)?;
+        if removed {
+            self.remove_version_data(&bucket.name, bucket.backend_id, object)
+                .await;
+        }
+        Ok(removed)
+    }
+
+    // Delete the data of a version removed from the index
+    pub(super) async fn remove_version_data(
+        &self,
+        bucket_name: &str,
+        bucket_backend_id: Option<Uuid>,
+        object: &types::Object,
+    ) {
+        if let Some(name) = own_blob(object) {
+            let backend_id = object.backend_id.or(bucket_backend_id);
+            self.remove_blob(bucket_name, backend_id, name).await;
+        }
+    }
+}
+
+// Blob holding the data of `object` alone. Shared data is reclaimed by
+// volume compaction and the blob collector, inline data has no blob.
+fn own_blob(object: &types::Object) -> Option<&str> {
+    if object.packed().is_some() || object.content_hash().is_some() {
+        return None;
+    }
+    object.backend_specific_name.as_deref()
+}
+
+fn delete_error(e: sqlx::Error) -> S3Error {
+    tracing::error!("Error deleting object: {:?}", e);
+    S3Error::InternalError
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_own_blob() {
+        let sha256 = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        let deduplicated = |key: &str| types::Object {
+            key: key.to_string(),
+            version_id: Uuid::now_v7(),
+            backend_specific_name: Some(types::blob_name(sha256)),
+            backend_specific_id: Some(format!("sha256:{}", sha256)),
+            ..Default::default()
+        };
+        // Deleting either key only drops its reference, the blob stays for
+        // the other one until the collector finds it unreferenced
+        let (first, second) = (deduplicated("a.txt"), deduplicated("b.txt"));
+        assert_eq!(own_blob(&first), None);
+        assert_eq!(own_blob(&second), None);
+
+        let packed = types::Object {
+            backend_specific_name: Some(Uuid::now_v7().to_string()),
+            backend_specific_id: Some(format!("{}:0:5", Uuid::now_v7())),
+            ..Default::default()
+        };
+        assert_eq!(own_blob(&packed), None);
+        assert_eq!(own_blob(&types::Object::default()), None);
+
+        let version_id = Uuid::now_v7();
+        let plain = types::Object {
+            version_id,
+            backend_specific_name: Some(version_id.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(own_blob(&plain), Some(version_id.to_string().as_str()));
+    }
+}
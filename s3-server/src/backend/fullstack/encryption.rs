@@ -0,0 +1,658 @@
+use std::sync::Arc;
+
+use axum::{body::Bytes, http::HeaderMap};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use s3_core::{
+    encryption::{self, ServerSideEncryptionConfiguration},
+    response::ResponseData,
+    S3Error,
+};
+
+use crate::{
+    backend::types,
+    config::EncryptionConfig,
+    crypto::{encrypted_size, ChunkedCipher, CustomerKey, KeyManager},
+    filter::S3Data,
+};
+
+use super::FullstackBackend;
+
+static SSE_HEADER: &str = "x-amz-server-side-encryption";
+static SSE_KMS_KEY_HEADER: &str = "x-amz-server-side-encryption-aws-kms-key-id";
+static SSE_CONTEXT_HEADER: &str = "x-amz-server-side-encryption-context";
+static SSE_CUSTOMER_ALGORITHM_HEADER: &str = "x-amz-server-side-encryption-customer-algorithm";
+static SSE_CUSTOMER_KEY_HEADER: &str = "x-amz-server-side-encryption-customer-key";
+static SSE_CUSTOMER_KEY_MD5_HEADER: &str = "x-amz-server-side-encryption-customer-key-MD5";
+static COPY_SOURCE_CUSTOMER_ALGORITHM_HEADER: &str =
+    "x-amz-copy-source-server-side-encryption-customer-algorithm";
+static COPY_SOURCE_CUSTOMER_KEY_HEADER: &str =
+    "x-amz-copy-source-server-side-encryption-customer-key";
+static COPY_SOURCE_CUSTOMER_KEY_MD5_HEADER: &str =
+    "x-amz-copy-source-server-side-encryption-customer-key-MD5";
+
+/// Envelope encryption of object data. Every object gets a data key of its
+/// own, wrapped by a master key of the key manager, or by the customer key
+/// for SSE-C, and stored with the object in the index.
+pub(super) struct Encryptor {
+    keys: Arc<dyn KeyManager>,
+    config: EncryptionConfig,
+}
+
+// Encryption asked for by a PUT or the bucket default
+#[derive(Debug, PartialEq)]
+pub(super) struct EncryptionRequest {
+    algorithm: String,
+    key_id: Option<String>,
+    // Base64 of the encryption context
+    context: Option<String>,
+}
+
+impl Encryptor {
+    pub(super) fn new(keys: Arc<dyn KeyManager>, config: EncryptionConfig) -> Self {
+        Self { keys, config }
+    }
+}
+
+impl FullstackBackend {
+    pub async fn put_bucket_encryption(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let config = ServerSideEncryptionConfiguration::from_xml(data.req.body())?;
+        self.encryptor()?;
+        let config = serde_json::to_value(&config).map_err(|e| {
+            tracing::error!("Error serializing encryption configuration: {:?}", e);
+            S3Error::InternalError
+        })?;
+        self.database
+            .update_bucket_encryption(bucket.id, Some(config))
+            .await
+            .map_err(|e| {
+                tracing::error!("Error updating bucket encryption: {:?}", e);
+                S3Error::InternalError
+            })?;
+
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn get_bucket_encryption(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let config = bucket.encryption.as_ref().ok_or(
+            S3Error::ServerSideEncryptionConfigurationNotFoundError(bucket.name.clone()),
+        )?;
+
+        data.res.with_bytes(config.to_xml().into());
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn delete_bucket_encryption(
+        &self,
+        data: &mut S3Data,
+    ) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        self.database
+            .update_bucket_encryption(bucket.id, None)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting bucket encryption: {:?}", e);
+                S3Error::InternalError
+            })?;
+
+        data.res.with_status_code(204);
+        Ok(data.res.clone())
+    }
+
+    fn encryptor(&self) -> Result<&Encryptor, S3Error> {
+        self.encryptor.as_ref().ok_or_else(|| {
+            S3Error::InvalidArgument(
+                "Server side encryption is not enabled on this server".to_string(),
+            )
+        })
+    }
+
+    /// Encryption of a PUT: with its SSE-C key, or as asked for by its
+    /// x-amz-server-side-encryption headers or the bucket's default
+    /// encryption. Returns None for objects stored as is.
+    pub(super) fn encryption_request(
+        &self,
+        data: &S3Data,
+        bucket: &types::Bucket,
+        customer: Option<&CustomerKey>,
+    ) -> Result<Option<EncryptionRequest>, S3Error> {
+        match customer {
+            Some(_) if data.req.headers().contains_key(SSE_HEADER) => {
+                Err(S3Error::InvalidArgument(format!(
+                    "{} cannot be combined with customer provided keys",
+                    SSE_HEADER
+                )))
+            }
+            Some(_) => Ok(Some(EncryptionRequest {
+                algorithm: encryption::AES256.to_string(),
+                key_id: None,
+                context: None,
+            })),
+            None => match requested_encryption(data.req.headers(), bucket)? {
+                Some(request) => {
+                    self.encryptor()?;
+                    Ok(Some(request))
+                }
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// Encrypt `plaintext` as asked for by encryption_request, with a data
+    /// key wrapped by the SSE-C key when given.
+    pub(super) async fn encrypt_object(
+        &self,
+        request: EncryptionRequest,
+        customer: Option<&CustomerKey>,
+        plaintext: &[u8],
+    ) -> Result<(types::ObjectEncryption, Vec<u8>), S3Error> {
+        let mut envelope = self.upload_encryption(request, customer)?;
+        let (part, ciphertext) = self.encrypt_part(&envelope, customer, plaintext).await?;
+        envelope.key_id = part.key_id;
+        envelope.data_key = part.data_key;
+        Ok((envelope, ciphertext))
+    }
+
+    /// Envelope of the object of a multipart upload asking for encryption,
+    /// without data key. Every part gets its own from encrypt_part.
+    pub(super) fn upload_encryption(
+        &self,
+        request: EncryptionRequest,
+        customer: Option<&CustomerKey>,
+    ) -> Result<types::ObjectEncryption, S3Error> {
+        let chunk_size = self
+            .encryptor
+            .as_ref()
+            .map_or(EncryptionConfig::default().chunk_size, |encryptor| {
+                encryptor.config.chunk_size
+            });
+        Ok(types::ObjectEncryption {
+            algorithm: request.algorithm,
+            key_id: request.key_id.unwrap_or_default(),
+            data_key: String::new(),
+            context: request.context,
+            chunk_size,
+            customer_key: customer.map(CustomerKey::fingerprint).transpose()?,
+            parts: vec![],
+        })
+    }
+
+    /// Encrypt `plaintext` under a new data key, wrapped by the master key
+    /// of `envelope` or by `customer` for SSE-C, already checked against
+    /// the envelope.
+    pub(super) async fn encrypt_part(
+        &self,
+        envelope: &types::ObjectEncryption,
+        customer: Option<&CustomerKey>,
+        plaintext: &[u8],
+    ) -> Result<(types::EncryptedPart, Vec<u8>), S3Error> {
+        let keys: &dyn KeyManager = match customer {
+            Some(customer) => customer,
+            None => self.encryptor()?.keys.as_ref(),
+        };
+        let context = decode_context(envelope.context.as_deref())?;
+        let key_id = (!envelope.key_id.is_empty()).then_some(envelope.key_id.as_str());
+        let key = keys.generate_data_key(key_id, &context).await?;
+        let ciphertext = ChunkedCipher::new(&key.plaintext, envelope.chunk_size).encrypt(plaintext);
+        let part = types::EncryptedPart {
+            size: plaintext.len() as u64,
+            key_id: key.key_id,
+            data_key: STANDARD.encode(&key.wrapped),
+        };
+        Ok((part, ciphertext))
+    }
+
+    /// Read the inclusive plaintext `range` of an encrypted object, or all
+    /// of it. Only the chunks holding the range are read. `size` is that of
+    /// the plaintext, the compressed data of compressed objects. `customer`
+    /// is the SSE-C key of the request, already checked by
+    /// check_customer_key.
+    pub(super) async fn read_encrypted(
+        &self,
+        bucket: &types::Bucket,
+        object: &types::Object,
+        envelope: &types::ObjectEncryption,
+        size: u64,
+        range: Option<(u64, u64)>,
+        customer: Option<&CustomerKey>,
+    ) -> Result<Bytes, S3Error> {
+        let keys: &dyn KeyManager = match (&envelope.customer_key, customer) {
+            (Some(_), Some(customer)) => customer,
+            (Some(_), None) => return Err(S3Error::InvalidRequest),
+            (None, _) => match &self.encryptor {
+                Some(encryptor) => encryptor.keys.as_ref(),
+                None => {
+                    tracing::error!(
+                        "Object {} is encrypted but no keyring is configured",
+                        object.key
+                    );
+                    return Err(S3Error::InternalError);
+                }
+            },
+        };
+        if !envelope.parts.is_empty() {
+            return self
+                .read_encrypted_parts(bucket, object, envelope, keys, range)
+                .await;
+        }
+        let key = data_key(keys, object, envelope, &envelope.key_id, &envelope.data_key).await?;
+
+        let cipher = ChunkedCipher::new(&key, envelope.chunk_size);
+        let plaintext = match range {
+            Some((start, end)) => {
+                let chunks = cipher.chunk_range(start, end, size);
+                let ciphertext = self.read_blob(bucket, object, Some(chunks)).await?;
+                cipher.decrypt_range(&ciphertext, start, end, size)?
+            }
+            None => {
+                let ciphertext = self.read_blob(bucket, object, None).await?;
+                cipher.decrypt(&ciphertext, size)?
+            }
+        };
+        Ok(Bytes::from(plaintext))
+    }
+
+    // The parts of a multipart object are sealed on their own, a range is
+    // read part by part
+    async fn read_encrypted_parts(
+        &self,
+        bucket: &types::Bucket,
+        object: &types::Object,
+        envelope: &types::ObjectEncryption,
+        keys: &dyn KeyManager,
+        range: Option<(u64, u64)>,
+    ) -> Result<Bytes, S3Error> {
+        let size: u64 = envelope.parts.iter().map(|part| part.size).sum();
+        let (start, end) = match range {
+            Some(range) => range,
+            None if size == 0 => return Ok(Bytes::new()),
+            None => (0, size - 1),
+        };
+        let mut plaintext = Vec::with_capacity((end - start + 1) as usize);
+        // Offsets of the part in the plaintext and in the stored data
+        let (mut offset, mut stored) = (0, 0);
+        for part in &envelope.parts {
+            if part.size > 0 && start < offset + part.size && end >= offset {
+                let key = data_key(keys, object, envelope, &part.key_id, &part.data_key).await?;
+                let cipher = ChunkedCipher::new(&key, envelope.chunk_size);
+                let first = start.max(offset) - offset;
+                let last = end.min(offset + part.size - 1) - offset;
+                let (chunk_start, chunk_end) = cipher.chunk_range(first, last, part.size);
+                let ciphertext = self
+                    .read_blob(
+                        bucket,
+                        object,
+                        Some((stored + chunk_start, stored + chunk_end)),
+                    )
+                    .await?;
+                plaintext.extend(cipher.decrypt_range(&ciphertext, first, last, part.size)?);
+            }
+            offset += part.size;
+            stored += encrypted_size(part.size, envelope.chunk_size);
+        }
+        Ok(Bytes::from(plaintext))
+    }
+}
+
+// Unwrap a data key of an encrypted object
+async fn data_key(
+    keys: &dyn KeyManager,
+    object: &types::Object,
+    envelope: &types::ObjectEncryption,
+    key_id: &str,
+    data_key: &str,
+) -> Result<[u8; 32], S3Error> {
+    let wrapped = STANDARD.decode(data_key).map_err(|e| {
+        tracing::error!("Invalid data key of object {}: {:?}", object.key, e);
+        S3Error::InternalError
+    })?;
+    let context = decode_context(envelope.context.as_deref())?;
+    keys.decrypt_data_key(key_id, &wrapped, &context).await
+}
+
+/// The SSE-C key sent with a request, if any. Customer keys are refused
+/// over plain HTTP.
+pub(super) fn customer_key(data: &S3Data) -> Result<Option<CustomerKey>, S3Error> {
+    sse_c_key(
+        data,
+        [
+            SSE_CUSTOMER_ALGORITHM_HEADER,
+            SSE_CUSTOMER_KEY_HEADER,
+            SSE_CUSTOMER_KEY_MD5_HEADER,
+        ],
+    )
+}
+
+/// The SSE-C key of the source of a CopyObject, if any.
+pub(super) fn copy_source_customer_key(data: &S3Data) -> Result<Option<CustomerKey>, S3Error> {
+    sse_c_key(
+        data,
+        [
+            COPY_SOURCE_CUSTOMER_ALGORITHM_HEADER,
+            COPY_SOURCE_CUSTOMER_KEY_HEADER,
+            COPY_SOURCE_CUSTOMER_KEY_MD5_HEADER,
+        ],
+    )
+}
+
+// The key given by the algorithm, key and key MD5 headers `names`
+fn sse_c_key(data: &S3Data, names: [&str; 3]) -> Result<Option<CustomerKey>, S3Error> {
+    let header = |name: &str| {
+        data.req
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+    let (algorithm, key, md5) = match (header(names[0]), header(names[1]), header(names[2])) {
+        (None, None, None) => return Ok(None),
+        (Some(algorithm), Some(key), Some(md5)) => (algorithm, key, md5),
+        _ => {
+            return Err(S3Error::InvalidArgument(format!(
+                "{}, {} and {} must be given together",
+                names[0], names[1], names[2]
+            )))
+        }
+    };
+    if !data.secure {
+        tracing::debug!("Refusing a customer provided key sent over plain HTTP");
+        return Err(S3Error::InvalidRequest);
+    }
+    if algorithm != encryption::AES256 {
+        return Err(S3Error::InvalidArgument(format!(
+            "Unsupported customer key algorithm {}",
+            algorithm
+        )));
+    }
+    CustomerKey::parse(key, md5).map(Some)
+}
+
+/// Check the SSE-C key of a read against the object. Objects stored with a
+/// customer key need that key, other objects take none.
+pub(super) fn check_customer_key(
+    object: &types::Object,
+    customer: Option<&CustomerKey>,
+) -> Result<(), S3Error> {
+    check_envelope_key(object.encryption.as_ref(), customer)
+}
+
+/// Check the SSE-C key of a request against an envelope, e.g. that of the
+/// parts of a multipart upload.
+pub(super) fn check_envelope_key(
+    envelope: Option<&types::ObjectEncryption>,
+    customer: Option<&CustomerKey>,
+) -> Result<(), S3Error> {
+    let fingerprint = envelope.and_then(|envelope| envelope.customer_key.as_deref());
+    match (fingerprint, customer) {
+        (None, None) => Ok(()),
+        (Some(fingerprint), Some(customer)) if customer.matches(fingerprint) => Ok(()),
+        (Some(_), Some(_)) => Err(S3Error::AccessDenied),
+        _ => Err(S3Error::InvalidRequest),
+    }
+}
+
+/// Report the encryption of an object in the headers of a response.
+pub(super) fn encryption_headers(
+    res: &mut ResponseData,
+    envelope: &types::ObjectEncryption,
+    customer: Option<&CustomerKey>,
+) {
+    if let (Some(_), Some(customer)) = (&envelope.customer_key, customer) {
+        res.with_header(
+            SSE_CUSTOMER_ALGORITHM_HEADER.to_string(),
+            envelope.algorithm.clone(),
+        )
+        .with_header(
+            SSE_CUSTOMER_KEY_MD5_HEADER.to_string(),
+            customer.md5().to_string(),
+        );
+        return;
+    }
+    res.with_header(SSE_HEADER.to_string(), envelope.algorithm.clone());
+    if envelope.algorithm == encryption::AWS_KMS {
+        res.with_header(SSE_KMS_KEY_HEADER.to_string(), envelope.key_id.clone());
+    }
+}
+
+// The x-amz-server-side-encryption headers win over the bucket default
+fn requested_encryption(
+    headers: &HeaderMap,
+    bucket: &types::Bucket,
+) -> Result<Option<EncryptionRequest>, S3Error> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    let key_id = header(SSE_KMS_KEY_HEADER);
+    let context = header(SSE_CONTEXT_HEADER);
+    let algorithm = match header(SSE_HEADER) {
+        Some(algorithm) => algorithm,
+        None if key_id.is_some() || context.is_some() => {
+            return Err(S3Error::InvalidArgument(format!(
+                "{} and {} require {}",
+                SSE_KMS_KEY_HEADER, SSE_CONTEXT_HEADER, SSE_HEADER
+            )))
+        }
+        None => {
+            return Ok(bucket
+                .encryption
+                .as_ref()
+                .and_then(|config| config.default_encryption())
+                .map(|default| EncryptionRequest {
+                    algorithm: default.algorithm.clone(),
+                    key_id: default.kms_master_key_id.clone(),
+                    context: None,
+                }))
+        }
+    };
+    encryption::validate_algorithm(&algorithm, key_id.as_deref())?;
+    if context.is_some() && algorithm != encryption::AWS_KMS {
+        return Err(S3Error::InvalidArgument(format!(
+            "{} can only be given for aws:kms encryption",
+            SSE_CONTEXT_HEADER
+        )));
+    }
+    decode_context(context.as_deref())?;
+    Ok(Some(EncryptionRequest {
+        algorithm,
+        key_id,
+        context,
+    }))
+}
+
+fn decode_context(context: Option<&str>) -> Result<Vec<u8>, S3Error> {
+    match context {
+        Some(context) => STANDARD.decode(context).map_err(|_| {
+            S3Error::InvalidArgument(format!("{} must be base64", SSE_CONTEXT_HEADER))
+        }),
+        None => Ok(vec![]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(headers: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.insert(*name, value.parse().unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn test_requested_encryption() {
+        let mut bucket = types::Bucket::default();
+        assert_eq!(requested_encryption(&headers(&[]), &bucket).unwrap(), None);
+
+        bucket.encryption = Some(
+            ServerSideEncryptionConfiguration::from_xml(
+                b"<ServerSideEncryptionConfiguration><Rule><ApplyServerSideEncryptionByDefault>\
+                <SSEAlgorithm>aws:kms</SSEAlgorithm><KMSMasterKeyID>logs</KMSMasterKeyID>\
+                </ApplyServerSideEncryptionByDefault></Rule></ServerSideEncryptionConfiguration>",
+            )
+            .unwrap(),
+        );
+        let default = requested_encryption(&headers(&[]), &bucket)
+            .unwrap()
+            .unwrap();
+        assert_eq!(default.algorithm, "aws:kms");
+        assert_eq!(default.key_id.as_deref(), Some("logs"));
+
+        let sent = headers(&[(SSE_HEADER, "AES256")]);
+        let request = requested_encryption(&sent, &bucket).unwrap().unwrap();
+        assert_eq!(request.algorithm, "AES256");
+        assert_eq!(request.key_id, None);
+
+        let sent = headers(&[(SSE_HEADER, "AES256"), (SSE_KMS_KEY_HEADER, "logs")]);
+        assert!(requested_encryption(&sent, &bucket).is_err());
+        let sent = headers(&[(SSE_KMS_KEY_HEADER, "logs")]);
+        assert!(requested_encryption(&sent, &bucket).is_err());
+        let sent = headers(&[(SSE_HEADER, "aws:kms"), (SSE_CONTEXT_HEADER, "not base64!")]);
+        assert!(requested_encryption(&sent, &bucket).is_err());
+    }
+
+    fn sse_c(data: &mut S3Data, key: &[u8; 32]) {
+        use md5::{Digest, Md5};
+        let headers = data.req.headers_mut();
+        for (name, value) in [
+            (SSE_CUSTOMER_ALGORITHM_HEADER, "AES256".to_string()),
+            (SSE_CUSTOMER_KEY_HEADER, STANDARD.encode(key)),
+            (
+                SSE_CUSTOMER_KEY_MD5_HEADER,
+                STANDARD.encode(Md5::digest(key)),
+            ),
+        ] {
+            let name = axum::http::HeaderName::from_bytes(name.as_bytes()).unwrap();
+            headers.insert(name, value.parse().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_customer_key() {
+        let mut data = S3Data::new();
+        assert!(customer_key(&data).unwrap().is_none());
+
+        sse_c(&mut data, &[1; 32]);
+        // Refused over plain HTTP
+        assert!(customer_key(&data).is_err());
+        data.secure = true;
+        let key = customer_key(&data).unwrap().unwrap();
+
+        data.req.headers_mut().remove(SSE_CUSTOMER_KEY_MD5_HEADER);
+        assert!(customer_key(&data).is_err());
+        sse_c(&mut data, &[1; 32]);
+        data.req
+            .headers_mut()
+            .insert(SSE_CUSTOMER_ALGORITHM_HEADER, "aws:kms".parse().unwrap());
+        assert!(customer_key(&data).is_err());
+
+        let mut object = types::Object::default();
+        assert!(check_customer_key(&object, None).is_ok());
+        assert!(check_customer_key(&object, Some(&key)).is_err());
+        object.encryption = Some(types::ObjectEncryption {
+            algorithm: "AES256".to_string(),
+            key_id: String::new(),
+            data_key: String::new(),
+            context: None,
+            chunk_size: 1,
+            customer_key: Some(key.fingerprint().unwrap()),
+            parts: vec![],
+        });
+        assert!(check_customer_key(&object, Some(&key)).is_ok());
+        assert!(check_customer_key(&object, None).is_err());
+
+        let mut other = S3Data::new();
+        other.secure = true;
+        sse_c(&mut other, &[2; 32]);
+        let other = customer_key(&other).unwrap().unwrap();
+        assert!(matches!(
+            check_customer_key(&object, Some(&other)),
+            Err(S3Error::AccessDenied)
+        ));
+    }
+
+    #[test]
+    fn test_copy_source_customer_key() {
+        use md5::{Digest, Md5};
+        let mut data = S3Data::new();
+        data.secure = true;
+        sse_c(&mut data, &[1; 32]);
+        assert!(copy_source_customer_key(&data).unwrap().is_none());
+
+        let key = [2; 32];
+        let headers = data.req.headers_mut();
+        headers.insert(
+            COPY_SOURCE_CUSTOMER_ALGORITHM_HEADER,
+            "AES256".parse().unwrap(),
+        );
+        headers.insert(
+            COPY_SOURCE_CUSTOMER_KEY_HEADER,
+            STANDARD.encode(key).parse().unwrap(),
+        );
+        assert!(copy_source_customer_key(&data).is_err());
+        data.req.headers_mut().insert(
+            COPY_SOURCE_CUSTOMER_KEY_MD5_HEADER,
+            STANDARD.encode(Md5::digest(key)).parse().unwrap(),
+        );
+        let source = copy_source_customer_key(&data).unwrap().unwrap();
+        let destination = customer_key(&data).unwrap().unwrap();
+        assert!(!source.matches(&destination.fingerprint().unwrap()));
+    }
+
+    #[test]
+    fn test_part_envelope() {
+        // Envelopes of single part objects are stored without parts
+        let envelope: types::ObjectEncryption = serde_json::from_value(serde_json::json!({
+            "algorithm": "AES256",
+            "key_id": "local",
+            "data_key": "d3JhcHBlZA==",
+            "context": null,
+            "chunk_size": 65536,
+        }))
+        .unwrap();
+        assert!(envelope.parts.is_empty());
+        assert!(serde_json::to_value(&envelope)
+            .unwrap()
+            .get("parts")
+            .is_none());
+
+        let mut data = S3Data::new();
+        data.secure = true;
+        sse_c(&mut data, &[1; 32]);
+        let key = customer_key(&data).unwrap().unwrap();
+        let upload = types::ObjectEncryption {
+            data_key: String::new(),
+            customer_key: Some(key.fingerprint().unwrap()),
+            parts: vec![types::EncryptedPart {
+                size: 5,
+                key_id: String::new(),
+                data_key: "d3JhcHBlZA==".to_string(),
+            }],
+            ..envelope
+        };
+        let stored = serde_json::to_value(&upload).unwrap();
+        assert_eq!(
+            serde_json::from_value::<types::ObjectEncryption>(stored).unwrap(),
+            upload
+        );
+        // Every part of an SSE-C upload is sent with the key of the upload
+        assert!(check_envelope_key(Some(&upload), Some(&key)).is_ok());
+        assert!(check_envelope_key(Some(&upload), None).is_err());
+        assert!(check_envelope_key(None, Some(&key)).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use std::time::Instant;
+
+use aws_sdk_s3::primitives::ByteStream;
+use axum::body::Bytes;
+use s3_core::S3Error;
+use uuid::Uuid;
+
+use crate::backend::{storage::BackendStatus, types};
+
+use super::FullstackBackend;
+
+// A bucket may name a fallback backend. While the circuit of the backend a
+// new version is meant for is open, or writing to that backend fails, the data
+// is written to the fallback backend instead and the version records the
+// backend it was meant for in failover_from. Reconciliation moves these
+// versions back with move_object once their bucket's backend is healthy
+// again. Failed over versions always get a blob of their own, they are
+// neither packed nor deduplicated.
+impl FullstackBackend {
+    pub async fn set_bucket_fallback(
+        &self,
+        bucket_name: &str,
+        backend: Option<&str>,
+    ) -> Result<(), S3Error> {
+        let bucket = self.get_bucket(bucket_name).await?;
+        let fallback = match backend {
+            Some(name) => Some(self.storage.backend_by_name(name).ok_or_else(|| {
+                S3Error::InvalidArgument(format!("Unknown storage backend {}", name))
+            })?),
+            None => None,
+        };
+        if fallback.is_some() && fallback == bucket.backend_id.or(self.storage.default_backend()) {
+            return Err(S3Error::InvalidArgument(format!(
+                "Bucket {} is already on storage backend {}",
+                bucket_name,
+                backend.unwrap_or_default()
+            )));
+        }
+        self.database
+            .update_bucket_fallback(bucket.id, fallback)
+            .await
+            .map_err(failover_error)
+    }
+
+    pub fn storage_backend_statuses(&self) -> Vec<BackendStatus> {
+        self.storage.statuses()
+    }
+
+    /// Probe every loaded backend at once, each within the exec timeout of
+    /// its circuit breaker.
+    pub async fn probe_storage_backends(&self) {
+        let probes = self
+            .storage
+            .backend_ids()
+            .into_iter()
+            .map(|backend_id| self.probe_backend(backend_id));
+        for result in futures_util::future::join_all(probes).await {
+            if let Err(e) = result {
+                tracing::warn!("Failed to probe storage backend: {:?}", e);
+            }
+        }
+    }
+
+    async fn probe_backend(&self, backend_id: Uuid) -> Result<(), S3Error> {
+        let storage = self.storage.get(Some(backend_id))?;
+        let health = self.storage.health(Some(backend_id))?;
+        let circuit = health.circuit();
+        let started = Instant::now();
+        let error = match tokio::time::timeout(health.exec_timeout(), storage.probe()).await {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(format!("{:?}", e)),
+            Err(_) => Some("Timed out".to_string()),
+        };
+        health.record_probe(started.elapsed(), error);
+        if health.circuit() != circuit {
+            tracing::warn!(
+                backend = %backend_id,
+                from = circuit,
+                to = health.circuit(),
+                "Circuit of storage backend changed"
+            );
+        }
+        Ok(())
+    }
+
+    /// Backend a new version of `bucket` is written to: `backend_id`, or the
+    /// bucket's fallback backend while the circuit of `backend_id` is open.
+    pub(super) fn write_backend(
+        &self,
+        bucket: &types::Bucket,
+        backend_id: Option<Uuid>,
+    ) -> Option<Uuid> {
+        let fallback = match self.fallback_for(bucket, backend_id) {
+            Some(fallback) => fallback,
+            None => return backend_id,
+        };
+        match self.storage.health(backend_id) {
+            Ok(health) if health.allow() => backend_id,
+            _ => Some(fallback),
+        }
+    }
+
+    /// Write a blob of `bucket` to `backend_id`, or to the bucket's fallback
+    /// backend when that fails. Returns the backend that took the blob.
+    pub(super) async fn save_blob(
+        &self,
+        bucket: &types::Bucket,
+        backend_id: Option<Uuid>,
+        name: &str,
+        data: Bytes,
+    ) -> Result<Option<Uuid>, S3Error> {
+        let result = match self.storage.get(backend_id) {
+            Ok(storage) => {
+                storage
+                    .save_file(&bucket.name, name, ByteStream::from(data.clone()))
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        self.record_write(backend_id, &result);
+        let e = match result {
+            Ok(()) => return Ok(backend_id),
+            Err(e) => e,
+        };
+
+        let fallback = match self.fallback_for(bucket, backend_id) {
+            Some(fallback) => fallback,
+            None => return Err(e),
+        };
+        tracing::warn!(
+            bucket = %bucket.name,
+            name,
+            "Writing to fallback storage backend: {:?}",
+            e
+        );
+        self.storage
+            .get(Some(fallback))?
+            .save_file(&bucket.name, name, ByteStream::from(data))
+            .await?;
+        Ok(Some(fallback))
+    }
+
+    /// Feed the outcome of a write to `backend_id` to its circuit breaker.
+    pub(super) fn record_write(&self, backend_id: Option<Uuid>, result: &Result<(), S3Error>) {
+        let health = match self.storage.health(backend_id) {
+            Ok(health) => health,
+            Err(_) => return,
+        };
+        match result {
+            Ok(()) => health.record_available(),
+            Err(_) => health.record_failure(),
+        }
+    }
+
+    // The bucket's fallback backend, unless writes do not fail over or it is
+    // `backend_id` itself
+    pub(super) fn fallback_for(
+        &self,
+        bucket: &types::Bucket,
+        backend_id: Option<Uuid>,
+    ) -> Option<Uuid> {
+        fallback_backend(self.write_failover, bucket, backend_id)
+    }
+
+    /// Move the versions written to a fallback backend back to their bucket's
+    /// backend, leaving those whose backend is still down for a later pass.
+    /// Returns the number of versions moved.
+    pub async fn reconcile_failover_writes(&self, batch_size: i64) -> Result<usize, S3Error> {
+        let mut after = (Uuid::nil(), String::new(), Uuid::nil());
+        let mut moved = 0;
+        loop {
+            let writes = self
+                .database
+                .list_failover_writes(&after, batch_size)
+                .await
+                .map_err(failover_error)?;
+            for write in &writes {
+                match self.reconcile_write(write).await {
+                    Ok(true) => moved += 1,
+                    Ok(false) => {}
+                    Err(e) => tracing::warn!(
+                        bucket = %write.bucket_name,
+                        key = %write.object.key,
+                        version = %write.object.version_id,
+                        "Failed to move version back from fallback backend: {:?}",
+                        e
+                    ),
+                }
+            }
+            match writes.last() {
+                Some(last) if writes.len() as i64 >= batch_size => {
+                    after = (
+                        last.object.bucket_id,
+                        last.object.key.clone(),
+                        last.object.version_id,
+                    );
+                }
+                _ => return Ok(moved),
+            }
+        }
+    }
+
+    async fn reconcile_write(&self, write: &types::FailoverWrite) -> Result<bool, S3Error> {
+        let bucket = self.get_bucket(&write.bucket_name).await?;
+        // The bucket may have moved meanwhile, the version goes where new
+        // writes go rather than to the backend it was meant for
+        let target = bucket
+            .migration_target
+            .or(bucket.backend_id)
+            .or(self.storage.default_backend())
+            .ok_or(S3Error::InternalError)?;
+        if !self.storage.health(Some(target))?.allow() {
+            return Ok(false);
+        }
+        self.move_object(
+            &bucket.name,
+            &write.object,
+            write.object.backend_id,
+            target,
+            &write.object.storage_class,
+        )
+        .await
+    }
+}
+
+fn fallback_backend(
+    enabled: bool,
+    bucket: &types::Bucket,
+    backend_id: Option<Uuid>,
+) -> Option<Uuid> {
+    bucket
+        .fallback_backend_id
+        .filter(|fallback| enabled && Some(*fallback) != backend_id)
+}
+
+fn failover_error(e: sqlx::Error) -> S3Error {
+    tracing::error!("Error failing over storage backend: {:?}", e);
+    S3Error::InternalError
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_backend() {
+        let primary = Uuid::now_v7();
+        let fallback = Uuid::now_v7();
+        let bucket = types::Bucket {
+            backend_id: Some(primary),
+            fallback_backend_id: Some(fallback),
+            ..Default::default()
+        };
+        assert_eq!(
+            fallback_backend(true, &bucket, Some(primary)),
+            Some(fallback)
+        );
+        assert_eq!(fallback_backend(false, &bucket, Some(primary)), None);
+        // Writes already on the fallback backend have nowhere else to go
+        assert_eq!(fallback_backend(true, &bucket, Some(fallback)), None);
+        assert_eq!(
+            fallback_backend(true, &types::Bucket::default(), Some(primary)),
+            None
+        );
+    }
+}
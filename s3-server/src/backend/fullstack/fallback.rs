@@ -0,0 +1,363 @@
+use std::{future::Future, sync::Arc, time::Instant};
+
+use aws_sdk_s3::primitives::ByteStream;
+use axum::body::Bytes;
+use s3_core::{replication::REPLICATION_COMPLETED, S3Error};
+use tokio::sync::OwnedSemaphorePermit;
+use uuid::Uuid;
+
+use crate::backend::{storage::BackendHealth, types};
+
+use super::FullstackBackend;
+
+/// A copy of the bytes stored for an object.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct BlobCopy {
+    // Backend holding the copy, None for the default backend
+    pub backend_id: Option<Uuid>,
+    pub bucket: String,
+    pub name: String,
+    // Location within a volume blob for packed objects
+    pub packed: Option<types::PackedLocation>,
+}
+
+// A read a backend has started answering
+struct OpenRead {
+    stream: ByteStream,
+    health: Arc<BackendHealth>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl BlobCopy {
+    /// The copy the index points at.
+    pub fn primary(bucket: &types::Bucket, object: &types::Object) -> Result<Self, S3Error> {
+        let name = object
+            .backend_specific_name
+            .clone()
+            .ok_or(S3Error::NoSuchKey(object.key.clone()))?;
+        Ok(Self {
+            backend_id: object.backend_id.or(bucket.backend_id),
+            bucket: bucket.name.clone(),
+            name,
+            packed: object.packed(),
+        })
+    }
+
+    /// The copy a running migration makes of an object on its target
+    /// backend, see move_object. The index may still point at the source
+    /// after the copy has been repointed and the source removed.
+    fn migrated(bucket: &types::Bucket, object: &types::Object) -> Option<Self> {
+        let target = bucket.migration_target?;
+        if object.backend_id.or(bucket.backend_id) == Some(target) {
+            return None;
+        }
+        let shared = object.packed().is_some() || object.content_hash().is_some();
+        let name = match shared {
+            true => object.version_id.to_string(),
+            false => object.backend_specific_name.clone()?,
+        };
+        Some(Self {
+            backend_id: Some(target),
+            bucket: bucket.name.clone(),
+            name,
+            packed: None,
+        })
+    }
+
+    // The inclusive range of the blob to read for the inclusive `range` of
+    // the object, None for the whole blob
+    fn blob_range(&self, range: Option<(u64, u64)>) -> Option<(u64, u64)> {
+        match (self.packed, range) {
+            (Some(location), Some((start, end))) => {
+                Some((location.offset + start, location.offset + end))
+            }
+            (Some(location), None) => Some((location.offset, location.end())),
+            (None, range) => range,
+        }
+    }
+}
+
+// Reads fail over from the copy the index points at to the other copies of
+// the data: the replica of a replicated version and the copy a running
+// migration made on its target backend. Backends whose circuit is open are
+// skipped, and a read that is slower than usual to start may be hedged with a
+// second read of another copy, the first to answer wins.
+impl FullstackBackend {
+    pub(super) async fn read_with_fallback(
+        &self,
+        bucket: &types::Bucket,
+        object: &types::Object,
+        range: Option<(u64, u64)>,
+    ) -> Result<Bytes, S3Error> {
+        let primary = BlobCopy::primary(bucket, object)?;
+        let mut error = match self.read_primary(bucket, object, &primary, range).await {
+            Ok(data) => return Ok(data),
+            Err(e) => e,
+        };
+        for copy in self.secondary_copies(bucket, object).await {
+            match self.read_copy(&copy, range).await {
+                Ok(data) => {
+                    tracing::warn!(
+                        bucket = %bucket.name,
+                        key = %object.key,
+                        copy = %copy.bucket,
+                        "Read object data from another copy: {:?}",
+                        error
+                    );
+                    return Ok(data);
+                }
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+
+    async fn read_primary(
+        &self,
+        bucket: &types::Bucket,
+        object: &types::Object,
+        primary: &BlobCopy,
+        range: Option<(u64, u64)>,
+    ) -> Result<Bytes, S3Error> {
+        let health = self.storage.health(primary.backend_id)?;
+        let delay = match &self.read_fallback {
+            Some(config) if config.hedged_reads => Some(
+                health
+                    .latency_quantile(config.hedge_quantile, config.hedge_min_samples)
+                    .unwrap_or(std::time::Duration::from_millis(config.hedge_delay_ms)),
+            ),
+            _ => None,
+        };
+        let open = self.open_copy(primary, range);
+        let read = match delay {
+            Some(delay) => {
+                tokio::pin!(open);
+                match tokio::time::timeout(delay, &mut open).await {
+                    Ok(read) => read,
+                    Err(_) => match self.secondary_copies(bucket, object).await.first() {
+                        Some(copy) => first_ok(open, self.open_copy(copy, range)).await,
+                        None => open.await,
+                    },
+                }
+            }
+            None => open.await,
+        }?;
+        collect(read).await
+    }
+
+    async fn read_copy(
+        &self,
+        copy: &BlobCopy,
+        range: Option<(u64, u64)>,
+    ) -> Result<Bytes, S3Error> {
+        collect(self.open_copy(copy, range).await?).await
+    }
+
+    // Send a read to the backend of a copy, unless its circuit is open or it
+    // has no room for another read
+    async fn open_copy(
+        &self,
+        copy: &BlobCopy,
+        range: Option<(u64, u64)>,
+    ) -> Result<OpenRead, S3Error> {
+        let storage = self.storage.get(copy.backend_id)?;
+        let health = self.storage.health(copy.backend_id)?;
+        if !health.allow() {
+            tracing::debug!(backend = ?copy.backend_id, "Circuit of storage backend is open");
+            return Err(S3Error::InternalError);
+        }
+        let permit = health.try_acquire().ok_or_else(|| {
+            tracing::debug!(backend = ?copy.backend_id, "Storage backend has no room for reads");
+            S3Error::InternalError
+        })?;
+
+        let started = Instant::now();
+        let read = match copy.blob_range(range) {
+            Some((start, end)) => {
+                tokio::time::timeout(
+                    health.exec_timeout(),
+                    storage.get_file_range(&copy.bucket, &copy.name, start, end),
+                )
+                .await
+            }
+            None => {
+                tokio::time::timeout(
+                    health.exec_timeout(),
+                    storage.get_file(&copy.bucket, &copy.name),
+                )
+                .await
+            }
+        };
+        match read {
+            Ok(Ok(stream)) => {
+                health.record_success(started.elapsed());
+                Ok(OpenRead {
+                    stream,
+                    health,
+                    _permit: permit,
+                })
+            }
+            // A missing blob says nothing about the backend
+            Ok(Err(e @ S3Error::NoSuchKey(_))) => Err(e),
+            Ok(Err(e)) => {
+                health.record_failure();
+                Err(e)
+            }
+            Err(_) => {
+                tracing::warn!(
+                    backend = ?copy.backend_id,
+                    name = %copy.name,
+                    "Storage backend did not answer a read in time"
+                );
+                health.record_failure();
+                Err(S3Error::InternalError)
+            }
+        }
+    }
+
+    // Copies of the data of an object other than the one the index points at
+    async fn secondary_copies(
+        &self,
+        bucket: &types::Bucket,
+        object: &types::Object,
+    ) -> Vec<BlobCopy> {
+        let mut copies: Vec<_> = BlobCopy::migrated(bucket, object).into_iter().collect();
+        if object.replication_status.as_deref() == Some(REPLICATION_COMPLETED) {
+            copies.extend(self.replica_copy(bucket, object).await);
+        }
+        copies
+    }
+
+    async fn replica_copy(
+        &self,
+        bucket: &types::Bucket,
+        object: &types::Object,
+    ) -> Option<BlobCopy> {
+        let destination = bucket
+            .replication
+            .as_ref()?
+            .rule_for(&object.key, &object.tags)?
+            .destination
+            .bucket_name()?;
+        let target = self.get_bucket(destination).await.ok()?;
+        let replica = match self
+            .database
+            .get_object_version(target.id, &object.key, object.version_id)
+            .await
+        {
+            Ok(replica) => replica?,
+            Err(e) => {
+                tracing::warn!("Error looking up replica of {}: {:?}", object.key, e);
+                return None;
+            }
+        };
+        BlobCopy::primary(&target, &replica).ok()
+    }
+}
+
+// Read the rest of a stream a backend has started answering
+async fn collect(read: OpenRead) -> Result<Bytes, S3Error> {
+    match read.stream.collect().await {
+        Ok(data) => Ok(data.into_bytes()),
+        Err(e) => {
+            tracing::error!("Error reading object data: {:?}", e);
+            read.health.record_failure();
+            Err(S3Error::InternalError)
+        }
+    }
+}
+
+// The result of whichever of two reads succeeds first, or the error of the
+// last one to fail
+async fn first_ok<T>(
+    a: impl Future<Output = Result<T, S3Error>>,
+    b: impl Future<Output = Result<T, S3Error>>,
+) -> Result<T, S3Error> {
+    tokio::pin!(a);
+    tokio::pin!(b);
+    tokio::select! {
+        result = &mut a => match result {
+            Ok(value) => Ok(value),
+            Err(_) => b.await,
+        },
+        result = &mut b => match result {
+            Ok(value) => Ok(value),
+            Err(_) => a.await,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_migrated_copy() {
+        let source = Uuid::now_v7();
+        let target = Uuid::now_v7();
+        let bucket = types::Bucket {
+            name: "bucket".to_string(),
+            backend_id: Some(source),
+            migration_target: Some(target),
+            ..Default::default()
+        };
+        let object = types::Object {
+            version_id: Uuid::now_v7(),
+            backend_specific_name: Some("blob".to_string()),
+            ..Default::default()
+        };
+        let copy = BlobCopy::migrated(&bucket, &object).unwrap();
+        assert_eq!(copy.backend_id, Some(target));
+        assert_eq!(copy.name, "blob");
+
+        // Shared blobs are copied under the version id, on their own
+        let packed = types::Object {
+            backend_specific_id: Some(format!("{}:4096:10", Uuid::now_v7())),
+            ..object
+        };
+        let primary = BlobCopy::primary(&bucket, &packed).unwrap();
+        assert_eq!(primary.blob_range(Some((2, 4))), Some((4098, 4100)));
+        assert_eq!(primary.blob_range(None), Some((4096, 4105)));
+        let copy = BlobCopy::migrated(&bucket, &packed).unwrap();
+        assert_eq!(copy.name, packed.version_id.to_string());
+        assert_eq!(copy.blob_range(Some((2, 4))), Some((2, 4)));
+
+        // Nothing to fall back to once the version is on the target
+        let moved = types::Object {
+            backend_id: Some(target),
+            ..packed
+        };
+        assert_eq!(BlobCopy::migrated(&bucket, &moved), None);
+        let idle = types::Bucket {
+            migration_target: None,
+            ..bucket
+        };
+        assert_eq!(BlobCopy::migrated(&idle, &moved), None);
+    }
+
+    #[tokio::test]
+    async fn test_first_ok() {
+        let slow = |result: Result<u32, S3Error>, ms| async move {
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            result
+        };
+        assert_eq!(
+            first_ok(slow(Ok(1), 50), slow(Ok(2), 1)).await.ok(),
+            Some(2)
+        );
+        assert_eq!(
+            first_ok(slow(Ok(1), 50), slow(Err(S3Error::InternalError), 1))
+                .await
+                .ok(),
+            Some(1)
+        );
+        assert!(first_ok(
+            slow(Err(S3Error::InternalError), 1),
+            slow(Err(S3Error::InternalError), 2)
+        )
+        .await
+        .is_err());
+    }
+}
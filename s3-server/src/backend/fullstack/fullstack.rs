@@ -1,28 +1,213 @@
-use aws_sdk_s3::primitives::{ByteStream, SdkBody};
+use std::sync::Arc;
+
+use aws_sdk_s3::primitives::SdkBody;
+use axum::body::Bytes;
 use md5::Digest;
 use s3_core::{
+    cors::CorsConfiguration,
+    lifecycle::LifecycleConfiguration,
+    object_lock::ObjectLockConfiguration,
     response::{ListBucketsResponse, ResponseData},
     types::{BucketContainer, Owner},
-    S3Error,
+    versioning::VERSIONING_ENABLED,
+    S3Error, StorageClass,
 };
 use uuid::{timestamp::context, Timestamp, Uuid};
 
 use crate::{
     backend::{
+        storage::{
+            local::LocalStorage, rados::RadosStorage, storage::StorageBackend, StorageRegistry,
+        },
         types::{self, Bucket},
         FileStorage, Indexer,
     },
+    config::{
+        CircuitConfig, CompressionConfig, EncryptionConfig, PackingConfig, ReadFallbackConfig,
+    },
+    crypto::{CustomerKey, KeyManager},
     filter::S3Data,
+    notification::Notifier,
+};
+
+use super::{
+    compression::Compressor,
+    encryption::{
+        check_customer_key, customer_key, encryption_headers, EncryptionRequest, Encryptor,
+    },
+    notification::request_event,
+    object_lock::{apply_object_lock, object_lock_headers, object_lock_requested},
+    packing::Packer,
+    replication::replication_status,
+    tagging::{request_tags, TAGGING_COUNT_HEADER},
+    website::{request_redirect_location, website_headers},
 };
 
 pub struct FullstackBackend {
-    database: Box<dyn Indexer>,
-    storage: Box<dyn FileStorage>,
+    pub(super) database: Box<dyn Indexer>,
+    pub(super) storage: StorageRegistry,
+    pub(super) packer: Packer,
+    pub(super) encryptor: Option<Encryptor>,
+    pub(super) compressor: Option<Compressor>,
+    pub(super) notifier: Option<Notifier>,
+    pub(super) read_fallback: Option<ReadFallbackConfig>,
+    pub(super) write_failover: bool,
 }
 
 impl FullstackBackend {
-    pub fn new(database: Box<dyn Indexer>, storage: Box<dyn FileStorage>) -> Self {
-        Self { database, storage }
+    pub fn new(database: Box<dyn Indexer>) -> Self {
+        Self {
+            database,
+            storage: StorageRegistry::default(),
+            packer: Packer::new(PackingConfig::default()),
+            encryptor: None,
+            compressor: None,
+            notifier: None,
+            read_fallback: None,
+            write_failover: false,
+        }
+    }
+
+    /// Pack small objects into shared volume blobs, see `Packer`.
+    pub fn with_packing(mut self, config: PackingConfig) -> Self {
+        self.packer = Packer::new(config);
+        self
+    }
+
+    /// Encrypt objects with data keys wrapped by `keys`, as asked for by the
+    /// x-amz-server-side-encryption headers or the bucket default.
+    pub fn with_encryption(mut self, keys: Arc<dyn KeyManager>, config: EncryptionConfig) -> Self {
+        self.encryptor = Some(Encryptor::new(keys, config));
+        self
+    }
+
+    /// Compress the data of objects stored in a blob of their own, see
+    /// `Compressor`.
+    pub fn with_compression(mut self, config: CompressionConfig) -> Self {
+        self.compressor = Some(Compressor::new(config));
+        self
+    }
+
+    /// Queue events of the buckets' objects for the targets of their
+    /// notification configurations.
+    pub fn with_notifications(mut self, notifier: Notifier) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Circuit breakers of the storage backends, see BackendHealth. Must be
+    /// called before the backends are loaded.
+    pub fn with_circuit_breakers(self, config: CircuitConfig) -> Self {
+        self.storage.set_circuit_config(config);
+        self
+    }
+
+    /// Read other copies of the data when the backend holding an object
+    /// fails, see read_with_fallback.
+    pub fn with_read_fallback(mut self, config: ReadFallbackConfig) -> Self {
+        self.read_fallback = Some(config);
+        self
+    }
+
+    /// Write to the fallback backend of a bucket while the bucket's backend
+    /// is down, see write_backend.
+    pub fn with_write_failover(mut self) -> Self {
+        self.write_failover = true;
+        self
+    }
+
+    /// Connect to the backends of the storage_backends table. Backends of the
+    /// config file are registered in the table first and take their endpoint
+    /// and credentials from the config.
+    pub async fn load_storage_backends(
+        &self,
+        configured: &[types::StorageBackend],
+        default_backend: Option<&str>,
+    ) -> Result<usize, S3Error> {
+        let index_error = |e: sqlx::Error| {
+            tracing::error!("Error loading storage backends: {:?}", e);
+            S3Error::InternalError
+        };
+        for backend in configured {
+            self.database
+                .register_storage_backend(backend)
+                .await
+                .map_err(index_error)?;
+        }
+
+        let backends = self
+            .database
+            .list_storage_backends()
+            .await
+            .map_err(index_error)?;
+        for backend in &backends {
+            let configured = configured.iter().find(|c| c.name == backend.name);
+            let credential = |name: &str| {
+                configured
+                    .and_then(|c| c.credentials.get(name))
+                    .or_else(|| backend.credentials.get(name))
+                    .and_then(|value| value.as_str())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            let endpoint = configured
+                .and_then(|c| c.endpoint.clone())
+                .or_else(|| backend.endpoint.clone())
+                .unwrap_or_default();
+            let region = configured
+                .and_then(|c| c.region.clone())
+                .or_else(|| backend.region.clone())
+                .unwrap_or_default();
+            let config = configured.map_or(&backend.config, |c| &c.config);
+            let storage: Arc<dyn FileStorage> = match backend.provider.as_str() {
+                "LOCAL" => Arc::new(LocalStorage::new(provider_config(&backend.name, config)?)?),
+                // A CEPH backend with a pool is reached through librados, otherwise through RGW
+                "CEPH" if config.get("pool").is_some() => Arc::new(RadosStorage::connect(
+                    provider_config(&backend.name, config)?,
+                )?),
+                _ => Arc::new(
+                    StorageBackend::connect(
+                        &endpoint,
+                        &region,
+                        &credential("access_key_id"),
+                        &credential("secret_access_key"),
+                    )
+                    .with_bucket(
+                        config
+                            .get("bucket")
+                            .and_then(|bucket| bucket.as_str())
+                            .map(str::to_string),
+                    ),
+                ),
+            };
+            tracing::debug!(
+                name = backend.name,
+                provider = backend.provider,
+                storage_class = ?backend.storage_class,
+                "Loaded storage backend"
+            );
+            self.storage.insert(
+                backend.id,
+                &backend.name,
+                backend.storage_class.clone(),
+                storage,
+            );
+        }
+
+        // A single backend needs no explicit default
+        let default = match (default_backend, backends.as_slice()) {
+            (Some(name), _) => self.storage.backend_by_name(name).ok_or_else(|| {
+                tracing::error!("Default storage backend {} is not loaded", name);
+                S3Error::InternalError
+            })?,
+            (None, [backend]) => backend.id,
+            (None, _) => {
+                tracing::warn!("No default storage backend, buckets cannot be created");
+                return Ok(backends.len());
+            }
+        };
+        self.storage.set_default(default);
+        Ok(backends.len())
     }
 }
 
@@ -38,12 +223,28 @@ impl FullstackBackend {
             }
             return Err(S3Error::BucketAlreadyExists(data.bucket_name.clone()));
         }
+        // Object Lock needs versioning, which stays enabled for good
+        let (versioning, object_lock) = if object_lock_requested(data.req.headers()) {
+            (
+                VERSIONING_ENABLED as i16,
+                Some(ObjectLockConfiguration::enabled()),
+            )
+        } else {
+            Default::default()
+        };
         self.database
             .create_bucket(&Bucket {
                 id: Uuid::now_v7(),
                 name: data.bucket_name.clone(),
                 user_id: data.auth_key.user_id,
                 created_at: chrono::Utc::now(),
+                backend_id: Some(self.storage.default_backend().ok_or_else(|| {
+                    tracing::error!("No default storage backend to create bucket on");
+                    S3Error::InternalError
+                })?),
+                versioning,
+                object_lock,
+                ..Default::default()
             })
             .await
             .map_err(|e| {
@@ -96,6 +297,133 @@ impl FullstackBackend {
         Ok(data.res.clone())
     }
 
+    pub async fn put_bucket_cors(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let cors = CorsConfiguration::from_xml(data.req.body())?;
+        let cors = serde_json::to_value(&cors).map_err(|e| {
+            tracing::error!("Error serializing CORS configuration: {:?}", e);
+            S3Error::InternalError
+        })?;
+        self.database
+            .update_bucket_cors(bucket.id, Some(cors))
+            .await
+            .map_err(|e| {
+                tracing::error!("Error updating bucket CORS: {:?}", e);
+                S3Error::InternalError
+            })?;
+
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn get_bucket_cors(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let cors = bucket
+            .cors
+            .as_ref()
+            .ok_or(S3Error::NoSuchCORSConfiguration(bucket.name.clone()))?;
+
+        data.res.with_bytes(cors.to_xml().into());
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn delete_bucket_cors(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        self.database
+            .update_bucket_cors(bucket.id, None)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting bucket CORS: {:?}", e);
+                S3Error::InternalError
+            })?;
+
+        data.res.with_status_code(204);
+        Ok(data.res.clone())
+    }
+
+    pub async fn put_bucket_lifecycle(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let mut lifecycle = LifecycleConfiguration::from_xml(data.req.body())?;
+        lifecycle.assign_ids();
+
+        // Each rule is also stored on its own so that workers can schedule it
+        let rules = lifecycle
+            .rules
+            .iter()
+            .map(|rule| types::LifecycleRuleRow {
+                rule_id: rule.id.clone().unwrap_or_default(),
+                rule_config: serde_json::to_value(rule).unwrap_or_default(),
+                status: if rule.is_enabled() {
+                    "active".to_string()
+                } else {
+                    "disabled".to_string()
+                },
+            })
+            .collect();
+        let lifecycle = serde_json::to_value(&lifecycle).map_err(|e| {
+            tracing::error!("Error serializing lifecycle configuration: {:?}", e);
+            S3Error::InternalError
+        })?;
+        self.database
+            .update_bucket_lifecycle(bucket.id, Some(lifecycle), rules)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error updating bucket lifecycle: {:?}", e);
+                S3Error::InternalError
+            })?;
+
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn get_bucket_lifecycle(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let lifecycle = bucket
+            .lifecycle
+            .as_ref()
+            .ok_or(S3Error::NoSuchLifecycleConfiguration(bucket.name.clone()))?;
+
+        data.res.with_bytes(lifecycle.to_xml().into());
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn delete_bucket_lifecycle(
+        &self,
+        data: &mut S3Data,
+    ) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        self.database
+            .update_bucket_lifecycle(bucket.id, None, vec![])
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting bucket lifecycle: {:?}", e);
+                S3Error::InternalError
+            })?;
+
+        data.res.with_status_code(204);
+        Ok(data.res.clone())
+    }
+
     pub async fn put_object(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
         let bucket = data
             .bucket
@@ -126,7 +454,6 @@ impl FullstackBackend {
         let mut hasher = md5::Md5::new();
         hasher.update(bytes);
         let etag = const_hex::encode(hasher.finalize().to_vec());
-        let body = ByteStream::new(bytes.to_owned().into());
 
         // Validate Content-Length
         if content_length > s3_core::MAX_OBJECT_PART_SIZE as i64 {
@@ -136,46 +463,192 @@ impl FullstackBackend {
             return Err(S3Error::MissingContentLength);
         }
 
-        let object = types::Object {
+        let tags = request_tags(data)?;
+        let version_id = Uuid::now_v7();
+        let mut object = types::Object {
             bucket_id: bucket.id,
             key: data.key.clone(),
             owner_id: data.auth_key.user_id,
-            version_id: Uuid::now_v7(),
+            version_id,
             is_latest: true,
             size: content_length,
             etag: etag.clone(),
+            tags,
+            website_redirect_location: request_redirect_location(data.req.headers())?,
+            storage_class: StorageClass::Standard.as_str().to_string(),
+            // Pin the version to the backend it is written to, so it stays
+            // readable if the bucket is bound to another backend later. A
+            // bucket being migrated takes new writes on the target.
+            backend_id: bucket
+                .migration_target
+                .or(bucket.backend_id)
+                .or(self.storage.default_backend()),
+            // Every version gets its own blob so that versions never overwrite
+            // each other on the backend
+            backend_specific_name: Some(version_id.to_string()),
             ..Default::default()
         };
+        apply_object_lock(data.req.headers(), bucket, &mut object, chrono::Utc::now())?;
+        // Queued for replication in the transaction that indexes the version
+        object.replication_status = replication_status(bucket, &object);
 
-        // Insert into database backend
-        self.database
-            .put_object(&bucket, &object)
-            .await
-            .map_err(|e| {
-                tracing::error!("Error putting object: {:?}", e);
-                S3Error::InternalError
-            })?;
-        // Insert into storage backend
-        // self.storage
-        //     .save_file(&bucket.name, &object.key, body)
-        //     .await?;
+        let customer = customer_key(data)?;
+        let encryption = self.encryption_request(data, bucket, customer.as_ref())?;
+        let content_type = data
+            .req
+            .headers()
+            .get("Content-Type")
+            .and_then(|v| v.to_str().ok());
+        self.store_object(
+            bucket,
+            &mut object,
+            bytes,
+            content_type,
+            encryption,
+            customer.as_ref(),
+        )
+        .await?;
+        let event = request_event("s3:ObjectCreated:Put", data, bucket, &object);
+        self.notify(bucket.notification.as_ref(), event).await;
 
         data.res
             .with_status_code(200)
             .with_header("ETag".to_string(), etag);
+        if let Some(envelope) = &object.encryption {
+            encryption_headers(&mut data.res, envelope, customer.as_ref());
+        }
         Ok(data.res.clone())
     }
 
-    pub async fn delete_object(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
-        Err(S3Error::NotImplemented)
-    }
-
-    pub async fn delete_objects(
+    /// Store the data of a new version and index it. `object` is completed
+    /// with where and how the data was stored.
+    pub(super) async fn store_object(
         &self,
-        bucket_name: &str,
-        keys: Vec<String>,
+        bucket: &types::Bucket,
+        object: &mut types::Object,
+        bytes: &Bytes,
+        content_type: Option<&str>,
+        encryption: Option<EncryptionRequest>,
+        customer: Option<&CustomerKey>,
     ) -> Result<(), S3Error> {
-        Err(S3Error::NotImplemented)
+        // Encrypted objects always get a blob of their own
+        let plaintext = encryption.is_none();
+
+        // So do versions written to the fallback backend while the bucket's
+        // backend is down
+        let primary = object.backend_id;
+        object.backend_id = self.write_backend(bucket, primary);
+        let mut shareable = plaintext && object.backend_id == primary;
+        // Shared blobs stay on the bucket's backend. When it fails to take
+        // one, a bucket with a fallback stores the version on its own there.
+        let fallback = self.fallback_for(bucket, primary).is_some();
+
+        // Insert into storage backend first so the index never points to
+        // missing data
+        let size = bytes.len() as u64;
+        let inline = plaintext && self.packer.inlines(size);
+        let content_hash = match object.backend_id {
+            Some(backend_id) if shareable && bucket.dedup && !inline => {
+                match self.store_deduplicated(bucket, backend_id, bytes).await {
+                    Err(e) if fallback => {
+                        tracing::warn!(bucket = %bucket.name, "Error storing shared blob: {:?}", e);
+                        shareable = false;
+                        None
+                    }
+                    result => result?,
+                }
+            }
+            _ => None,
+        };
+        let packed = match (object.backend_id, &content_hash) {
+            (Some(backend_id), None) if shareable && !inline && self.packer.accepts(size) => {
+                match self.pack_object(bucket, backend_id, bytes).await {
+                    Err(e) if fallback => {
+                        tracing::warn!(bucket = %bucket.name, "Error packing object: {:?}", e);
+                        None
+                    }
+                    result => Some(result?),
+                }
+            }
+            _ => None,
+        };
+        match (content_hash, packed) {
+            (Some(sha256), _) => {
+                object.backend_specific_name = Some(types::blob_name(&sha256));
+                object.backend_specific_id = Some(format!("sha256:{}", sha256));
+            }
+            (None, Some(location)) => {
+                object.backend_specific_name = Some(location.volume_id.to_string());
+                object.backend_specific_id = Some(location.to_string());
+            }
+            // Tiny payloads are kept in the index, no backend holds them
+            _ if inline => {
+                object.backend_id = None;
+                object.backend_specific_name = None;
+                object.inline_data = Some(bytes.to_vec());
+            }
+            _ => {
+                // Compressed before encryption, ciphertext does not compress
+                let compressed = match &self.compressor {
+                    Some(compressor) => compressor.compress(content_type, bytes)?,
+                    None => None,
+                };
+                let stored = match compressed {
+                    Some((compression, compressed)) => {
+                        object.compression = Some(compression);
+                        compressed
+                    }
+                    None => bytes.to_vec(),
+                };
+                let body = match encryption {
+                    Some(request) => {
+                        let (envelope, ciphertext) =
+                            self.encrypt_object(request, customer, &stored).await?;
+                        object.encryption = Some(envelope);
+                        ciphertext
+                    }
+                    None => stored,
+                };
+                let backend_id = self
+                    .save_blob(
+                        bucket,
+                        object.backend_id,
+                        &object.version_id.to_string(),
+                        body.into(),
+                    )
+                    .await?;
+                if backend_id != primary {
+                    object.failover_from = primary;
+                }
+                object.backend_id = backend_id;
+            }
+        }
+
+        // Insert into database backend
+        let replaced = match self.database.put_object(bucket, object).await {
+            Ok(replaced) => replaced,
+            Err(e) => {
+                tracing::error!("Error putting object: {:?}", e);
+                // Unindexed bytes of a volume are reclaimed by compaction
+                if let (Some(backend_id), Some(sha256)) = (object.backend_id, object.content_hash())
+                {
+                    self.release_content(bucket.id, backend_id, sha256).await;
+                } else if object.packed().is_none() && object.inline_data.is_none() {
+                    self.remove_blob(
+                        &bucket.name,
+                        object.backend_id,
+                        &object.version_id.to_string(),
+                    )
+                    .await;
+                }
+                return Err(S3Error::InternalError);
+            }
+        };
+        for old in &replaced {
+            self.remove_version_data(&bucket.name, bucket.backend_id, old)
+                .await;
+        }
+        Ok(())
     }
 
     pub async fn get_bucket(&self, bucket_name: &str) -> Result<types::Bucket, S3Error> {
@@ -187,6 +660,13 @@ impl FullstackBackend {
         Ok(bucket)
     }
 
+    pub async fn list_rate_limits(&self) -> Result<Vec<types::RateLimit>, S3Error> {
+        self.database.list_rate_limits().await.map_err(|e| {
+            tracing::error!("Error listing rate limits: {:?}", e);
+            S3Error::InternalError
+        })
+    }
+
     pub async fn list_buckets(&self, data: &mut S3Data) -> Result<ListBucketsResponse, S3Error> {
         let user_id = &data.auth_key.user_id;
         let buckets = self
@@ -211,8 +691,207 @@ impl FullstackBackend {
         })
     }
 
-    pub async fn get_object(&self, _data: &mut S3Data) -> Result<ResponseData, S3Error> {
-        Err(S3Error::NotImplemented)
+    pub async fn get_object(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let mut object = self.latest_object(bucket, &data.key).await?;
+        let customer = customer_key(data)?;
+        check_customer_key(&object, customer.as_ref())?;
+
+        let range = match data.req.headers().get("Range") {
+            Some(value) => {
+                s3_core::parse_range(value.to_str().unwrap_or_default(), object.size as u64)?
+            }
+            None => None,
+        };
+        let read = match object.inline_data.take() {
+            Some(inline) => Ok(match range {
+                Some((start, end)) => Bytes::from(inline).slice(start as usize..=end as usize),
+                None => Bytes::from(inline),
+            }),
+            None => {
+                self.read_object(bucket, &object, range, customer.as_ref())
+                    .await
+            }
+        };
+        let bytes = match read {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                // The data may have been moved to another backend, e.g. by a
+                // lifecycle transition, or to another volume by compaction
+                // after the index was read.
+                let current = self
+                    .database
+                    .get_object_version(bucket.id, &object.key, object.version_id)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!("Error getting object: {:?}", e);
+                        S3Error::InternalError
+                    })?
+                    .ok_or(S3Error::NoSuchKey(data.key.clone()))?;
+                if current.backend_id == object.backend_id
+                    && current.backend_specific_id == object.backend_specific_id
+                {
+                    return Err(e);
+                }
+                self.read_object(bucket, &current, range, customer.as_ref())
+                    .await?
+            }
+        };
+
+        match range {
+            Some((start, end)) => data.res.with_status_code(206).with_header(
+                "Content-Range".to_string(),
+                format!("bytes {}-{}/{}", start, end, object.size),
+            ),
+            None => data.res.with_status_code(200),
+        };
+        if !object.tags.is_empty() {
+            data.res.with_header(
+                TAGGING_COUNT_HEADER.to_string(),
+                object.tags.len().to_string(),
+            );
+        }
+        object_headers(&mut data.res, object, customer.as_ref());
+        data.res.with_bytes(bytes);
+        Ok(data.res.clone())
+    }
+
+    pub async fn head_object(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let object = self.latest_object(bucket, &data.key).await?;
+        let customer = customer_key(data)?;
+        check_customer_key(&object, customer.as_ref())?;
+
+        data.res
+            .with_status_code(200)
+            .with_header("Content-Length".to_string(), object.size.to_string());
+        object_headers(&mut data.res, object, customer.as_ref());
+        Ok(data.res.clone())
+    }
+
+    // Latest version of `key`, a delete marker counts as no object
+    pub(super) async fn latest_object(
+        &self,
+        bucket: &types::Bucket,
+        key: &str,
+    ) -> Result<types::Object, S3Error> {
+        let object = self
+            .database
+            .get_object(bucket.id, key)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => S3Error::NoSuchKey(key.to_string()),
+                _ => {
+                    tracing::error!("Error getting object: {:?}", e);
+                    S3Error::InternalError
+                }
+            })?;
+        if object.is_delete_marker {
+            return Err(S3Error::NoSuchKey(key.to_string()));
+        }
+        Ok(object)
+    }
+
+    // Version `version_id` of `key`, which may be a delete marker
+    pub(super) async fn object_version(
+        &self,
+        bucket: &types::Bucket,
+        key: &str,
+        version_id: &str,
+    ) -> Result<types::Object, S3Error> {
+        let version_id = Uuid::parse_str(version_id)
+            .map_err(|_| S3Error::InvalidArgument("Invalid version id specified".to_string()))?;
+        self.database
+            .get_object_version(bucket.id, key, version_id)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting object version: {:?}", e);
+                S3Error::InternalError
+            })?
+            .ok_or(S3Error::NoSuchVersion(version_id.to_string()))
+    }
+
+    // Read the inclusive `range` of the data of an object, or all of it
+    pub(super) async fn read_object(
+        &self,
+        bucket: &types::Bucket,
+        object: &types::Object,
+        range: Option<(u64, u64)>,
+        customer: Option<&CustomerKey>,
+    ) -> Result<Bytes, S3Error> {
+        match &object.compression {
+            Some(compression) => {
+                self.read_compressed(bucket, object, compression, range, customer)
+                    .await
+            }
+            None => {
+                self.read_stored(bucket, object, object.size as u64, range, customer)
+                    .await
+            }
+        }
+    }
+
+    // Read the inclusive `range` of the data stored for an object before
+    // encryption, `size` bytes long
+    pub(super) async fn read_stored(
+        &self,
+        bucket: &types::Bucket,
+        object: &types::Object,
+        size: u64,
+        range: Option<(u64, u64)>,
+        customer: Option<&CustomerKey>,
+    ) -> Result<Bytes, S3Error> {
+        match &object.encryption {
+            Some(envelope) => {
+                self.read_encrypted(bucket, object, envelope, size, range, customer)
+                    .await
+            }
+            None => self.read_blob(bucket, object, range).await,
+        }
+    }
+
+    // Read the inclusive `range` of the bytes stored for an object, which
+    // is ciphertext for encrypted objects
+    pub(super) async fn read_blob(
+        &self,
+        bucket: &types::Bucket,
+        object: &types::Object,
+        range: Option<(u64, u64)>,
+    ) -> Result<Bytes, S3Error> {
+        if self.read_fallback.is_some() {
+            return self.read_with_fallback(bucket, object, range).await;
+        }
+        let name = object
+            .backend_specific_name
+            .as_ref()
+            .ok_or(S3Error::NoSuchKey(object.key.clone()))?;
+        let storage = self.storage.get(object.backend_id.or(bucket.backend_id))?;
+        let range = match (object.packed(), range) {
+            (Some(location), Some((start, end))) => {
+                Some((location.offset + start, location.offset + end))
+            }
+            (Some(location), None) => Some((location.offset, location.end())),
+            (None, range) => range,
+        };
+        let stream = match range {
+            Some((start, end)) => {
+                storage
+                    .get_file_range(&bucket.name, name, start, end)
+                    .await?
+            }
+            None => storage.get_file(&bucket.name, name).await?,
+        };
+        let data = stream.collect().await.map_err(|e| {
+            tracing::error!("Error reading object data: {:?}", e);
+            S3Error::InternalError
+        })?;
+        Ok(data.into_bytes())
     }
 
     pub async fn list_objects(&self, _data: &mut S3Data) -> Result<ResponseData, S3Error> {
@@ -227,3 +906,37 @@ impl FullstackBackend {
         Err(S3Error::NotImplemented)
     }
 }
+
+// Metadata headers of GetObject and HeadObject
+fn object_headers(res: &mut ResponseData, object: types::Object, customer: Option<&CustomerKey>) {
+    object_lock_headers(res, &object);
+    website_headers(res, &object);
+    res.with_header("ETag".to_string(), object.etag)
+        .with_header(
+            "Last-Modified".to_string(),
+            object
+                .last_modified
+                .format("%a, %d %b %Y %H:%M:%S GMT")
+                .to_string(),
+        );
+    if object.storage_class != StorageClass::Standard.as_str() {
+        res.with_header("x-amz-storage-class".to_string(), object.storage_class);
+    }
+    if let Some(status) = object.replication_status {
+        res.with_header("x-amz-replication-status".to_string(), status);
+    }
+    if let Some(envelope) = &object.encryption {
+        encryption_headers(res, envelope, customer);
+    }
+}
+
+// Provider specific part of a backend's config, see LocalConfig and RadosConfig
+fn provider_config<T: serde::de::DeserializeOwned>(
+    name: &str,
+    config: &serde_json::Value,
+) -> Result<T, S3Error> {
+    serde_json::from_value(config.clone()).map_err(|e| {
+        tracing::error!("Invalid config of storage {}: {:?}", name, e);
+        S3Error::InternalError
+    })
+}
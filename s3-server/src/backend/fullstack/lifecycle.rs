@@ -0,0 +1,436 @@
+use chrono::{DateTime, Utc};
+use std::str::FromStr;
+
+use s3_core::{
+    lifecycle::{days_cutoff, RuleCriteria},
+    versioning::VERSIONING_DISABLED,
+    S3Error, StorageClass,
+};
+use uuid::Uuid;
+
+use crate::backend::types;
+
+use super::{notification::lifecycle_event, replication::pending_replication, FullstackBackend};
+
+// Lifecycle actions are idempotent: every index update is conditional on the
+// row still being in the state it was listed in, so a rule that is applied
+// twice, e.g. after its lease expired, never removes more than once.
+impl FullstackBackend {
+    pub async fn claim_lifecycle_rules(
+        &self,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<types::LifecycleRule>, S3Error> {
+        self.database
+            .claim_lifecycle_rules(lease_until, limit)
+            .await
+            .map_err(index_error)
+    }
+
+    pub async fn complete_lifecycle_rule(
+        &self,
+        id: Uuid,
+        next_execution_at: DateTime<Utc>,
+    ) -> Result<(), S3Error> {
+        self.database
+            .complete_lifecycle_rule(id, next_execution_at)
+            .await
+            .map_err(index_error)
+    }
+
+    /// Apply every action of `rule` that is due at `now`, returning the
+    /// number of objects and uploads acted on.
+    pub async fn apply_lifecycle_rule(
+        &self,
+        rule: &types::LifecycleRule,
+        now: DateTime<Utc>,
+        batch_size: i64,
+    ) -> Result<usize, S3Error> {
+        let criteria = rule.rule.criteria();
+        let mut actions = 0;
+
+        if let Some(expiration) = &rule.rule.expiration {
+            if let Some(cutoff) = expiration.cutoff(now) {
+                actions += self
+                    .expire_current_versions(rule, &criteria, cutoff, batch_size)
+                    .await?;
+            }
+            // Days based expiration also cleans up the delete markers it left
+            // behind once their noncurrent versions are gone.
+            let versioned = rule.versioning != VERSIONING_DISABLED as i16;
+            if expiration.expired_object_delete_marker == Some(true)
+                || (expiration.days.is_some() && versioned && criteria.tags.is_empty())
+            {
+                actions += self
+                    .remove_expired_delete_markers(rule, &criteria, batch_size)
+                    .await?;
+            }
+        }
+
+        // Expired objects are gone by now, so only survivors are moved
+        for transition in &rule.rule.transitions {
+            if let (Some(cutoff), Ok(storage_class)) = (transition.cutoff(now), transition.class())
+            {
+                actions += self
+                    .transition_current_versions(rule, &criteria, cutoff, storage_class, batch_size)
+                    .await?;
+            }
+        }
+
+        if let Some(noncurrent) = &rule.rule.noncurrent_version_expiration {
+            let cutoff = days_cutoff(noncurrent.noncurrent_days, now);
+            let newer = noncurrent.newer_noncurrent_versions.unwrap_or(0) as i64;
+            actions += self
+                .expire_noncurrent_versions(rule, &criteria, cutoff, newer, batch_size)
+                .await?;
+        }
+
+        if let Some(abort) = &rule.rule.abort_incomplete_multipart_upload {
+            let cutoff = days_cutoff(abort.days_after_initiation, now);
+            actions += self
+                .abort_stale_uploads(rule, &criteria, cutoff, batch_size)
+                .await?;
+        }
+
+        Ok(actions)
+    }
+
+    async fn expire_current_versions(
+        &self,
+        rule: &types::LifecycleRule,
+        criteria: &RuleCriteria,
+        cutoff: DateTime<Utc>,
+        batch_size: i64,
+    ) -> Result<usize, S3Error> {
+        let mut actions = 0;
+        let mut after = (String::new(), Uuid::nil());
+        loop {
+            let objects = self
+                .database
+                .list_expired_objects(rule.bucket_id, &criteria.prefix, cutoff, &after, batch_size)
+                .await
+                .map_err(index_error)?;
+
+            for object in objects
+                .iter()
+                .filter(|object| criteria.matches(&object.key, object.size, &object.tags))
+            {
+                let expired = if rule.versioning == VERSIONING_DISABLED as i16 {
+                    self.remove_key(rule, object).await?
+                } else {
+                    // Versioned buckets keep the data as a noncurrent version
+                    let mut marker = types::Object {
+                        bucket_id: rule.bucket_id,
+                        key: object.key.clone(),
+                        owner_id: rule.bucket_owner,
+                        version_id: Uuid::now_v7(),
+                        is_latest: true,
+                        is_delete_marker: true,
+                        ..Default::default()
+                    };
+                    marker.replication_status =
+                        pending_replication(rule.bucket_replication.as_ref(), &marker);
+                    let created = self
+                        .database
+                        .put_delete_marker(object, &marker)
+                        .await
+                        .map_err(index_error)?;
+                    if created {
+                        let event = lifecycle_event(
+                            "s3:LifecycleExpiration:DeleteMarkerCreated",
+                            rule,
+                            &marker,
+                        );
+                        self.notify(rule.bucket_notification.as_ref(), event).await;
+                    }
+                    created
+                };
+                if expired {
+                    actions += 1;
+                }
+            }
+
+            match next_page(&objects, batch_size) {
+                Some(next) => after = next,
+                None => return Ok(actions),
+            }
+        }
+    }
+
+    async fn transition_current_versions(
+        &self,
+        rule: &types::LifecycleRule,
+        criteria: &RuleCriteria,
+        cutoff: DateTime<Utc>,
+        storage_class: StorageClass,
+        batch_size: i64,
+    ) -> Result<usize, S3Error> {
+        let target = match self.storage.backend_for_class(storage_class.as_str()) {
+            Some(target) => target,
+            None => {
+                tracing::warn!(
+                    bucket = %rule.bucket_name,
+                    "No storage backend holds {} objects, skipping transition",
+                    storage_class.as_str()
+                );
+                return Ok(0);
+            }
+        };
+
+        let mut actions = 0;
+        let mut after = (String::new(), Uuid::nil());
+        loop {
+            let objects = self
+                .database
+                .list_transition_candidates(
+                    rule.bucket_id,
+                    &criteria.prefix,
+                    cutoff,
+                    storage_class.as_str(),
+                    &after,
+                    batch_size,
+                )
+                .await
+                .map_err(index_error)?;
+
+            // Objects only ever move to colder classes
+            for object in objects.iter().filter(|object| {
+                criteria.matches(&object.key, object.size, &object.tags)
+                    && StorageClass::from_str(&object.storage_class)
+                        .map_or(true, |current| current < storage_class)
+            }) {
+                match self
+                    .transition_object(rule, object, storage_class, target)
+                    .await
+                {
+                    Ok(true) => {
+                        let event = lifecycle_event("s3:LifecycleTransition", rule, object);
+                        self.notify(rule.bucket_notification.as_ref(), event).await;
+                        actions += 1;
+                    }
+                    Ok(false) => {}
+                    // Leave the object where it is, the next run retries it
+                    Err(e) => tracing::warn!(
+                        bucket = %rule.bucket_name,
+                        key = object.key,
+                        "Error transitioning object: {:?}",
+                        e
+                    ),
+                }
+            }
+
+            match next_page(&objects, batch_size) {
+                Some(next) => after = next,
+                None => return Ok(actions),
+            }
+        }
+    }
+
+    async fn transition_object(
+        &self,
+        rule: &types::LifecycleRule,
+        object: &types::Object,
+        storage_class: StorageClass,
+        target: Uuid,
+    ) -> Result<bool, S3Error> {
+        let source = object.backend_id.or(rule.bucket_backend_id);
+        self.move_object(
+            &rule.bucket_name,
+            object,
+            source,
+            target,
+            storage_class.as_str(),
+        )
+        .await
+    }
+
+    async fn expire_noncurrent_versions(
+        &self,
+        rule: &types::LifecycleRule,
+        criteria: &RuleCriteria,
+        cutoff: DateTime<Utc>,
+        newer_noncurrent_versions: i64,
+        batch_size: i64,
+    ) -> Result<usize, S3Error> {
+        let mut actions = 0;
+        let mut after = (String::new(), Uuid::nil());
+        loop {
+            let objects = self
+                .database
+                .list_noncurrent_versions(
+                    rule.bucket_id,
+                    &criteria.prefix,
+                    cutoff,
+                    newer_noncurrent_versions,
+                    &after,
+                    batch_size,
+                )
+                .await
+                .map_err(index_error)?;
+
+            for object in objects.iter().filter(|object| {
+                object.is_delete_marker || criteria.matches(&object.key, object.size, &object.tags)
+            }) {
+                if self.remove_version(rule, object).await? {
+                    actions += 1;
+                }
+            }
+
+            match next_page(&objects, batch_size) {
+                Some(next) => after = next,
+                None => return Ok(actions),
+            }
+        }
+    }
+
+    async fn remove_expired_delete_markers(
+        &self,
+        rule: &types::LifecycleRule,
+        criteria: &RuleCriteria,
+        batch_size: i64,
+    ) -> Result<usize, S3Error> {
+        let mut actions = 0;
+        let mut after = (String::new(), Uuid::nil());
+        loop {
+            let markers = self
+                .database
+                .list_expired_delete_markers(rule.bucket_id, &criteria.prefix, &after, batch_size)
+                .await
+                .map_err(index_error)?;
+
+            for marker in &markers {
+                if self.remove_version(rule, marker).await? {
+                    actions += 1;
+                }
+            }
+
+            match next_page(&markers, batch_size) {
+                Some(next) => after = next,
+                None => return Ok(actions),
+            }
+        }
+    }
+
+    async fn abort_stale_uploads(
+        &self,
+        rule: &types::LifecycleRule,
+        criteria: &RuleCriteria,
+        cutoff: DateTime<Utc>,
+        batch_size: i64,
+    ) -> Result<usize, S3Error> {
+        let mut actions = 0;
+        loop {
+            // Aborted uploads are deleted, so every page starts from the top
+            let uploads = self
+                .database
+                .list_stale_multipart_uploads(rule.bucket_id, &criteria.prefix, cutoff, batch_size)
+                .await
+                .map_err(index_error)?;
+
+            for upload in &uploads {
+                let parts = self
+                    .database
+                    .abort_multipart_upload(upload.id)
+                    .await
+                    .map_err(index_error)?;
+                // The upload is gone from the index, so leftover parts only
+                // leak storage
+                let backend_id = upload.backend_id.or(rule.bucket_backend_id);
+                let result = match self.storage.get(backend_id) {
+                    Ok(storage) => {
+                        storage
+                            .abort_multipart(&rule.bucket_name, &upload.upload_id, &parts)
+                            .await
+                    }
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    tracing::warn!(
+                        bucket = %rule.bucket_name,
+                        upload_id = upload.upload_id,
+                        "Error deleting parts of aborted upload: {:?}",
+                        e
+                    );
+                }
+                actions += 1;
+            }
+
+            if (uploads.len() as i64) < batch_size {
+                return Ok(actions);
+            }
+        }
+    }
+
+    // Remove a version from the index, then its data. Returns false when
+    // another worker removed it first or Object Lock protects it.
+    async fn remove_version(
+        &self,
+        rule: &types::LifecycleRule,
+        object: &types::Object,
+    ) -> Result<bool, S3Error> {
+        let removed = self
+            .database
+            .delete_object_version(rule.bucket_id, &object.key, object.version_id, false)
+            .await
+            .map_err(index_error)?;
+        if removed {
+            let event = lifecycle_event("s3:LifecycleExpiration:Delete", rule, object);
+            self.notify(rule.bucket_notification.as_ref(), event).await;
+            self.remove_version_data(&rule.bucket_name, rule.bucket_backend_id, object)
+                .await;
+        }
+        Ok(removed)
+    }
+
+    // Remove every version of the key of an unversioned bucket, so that no
+    // older version becomes current. Returns false when nothing was removed.
+    async fn remove_key(
+        &self,
+        rule: &types::LifecycleRule,
+        object: &types::Object,
+    ) -> Result<bool, S3Error> {
+        let removed = self
+            .database
+            .delete_object_versions(rule.bucket_id, &object.key, false)
+            .await
+            .map_err(index_error)?;
+        if removed
+            .iter()
+            .any(|old| old.version_id == object.version_id)
+        {
+            let event = lifecycle_event("s3:LifecycleExpiration:Delete", rule, object);
+            self.notify(rule.bucket_notification.as_ref(), event).await;
+        }
+        for old in &removed {
+            self.remove_version_data(&rule.bucket_name, rule.bucket_backend_id, old)
+                .await;
+        }
+        Ok(!removed.is_empty())
+    }
+
+    pub(super) async fn remove_blob(&self, bucket: &str, backend_id: Option<Uuid>, name: &str) {
+        // The index no longer references the blob, so a failure only leaks
+        // storage and must not stop the rule.
+        let result = match self.storage.get(backend_id) {
+            Ok(storage) => storage.delete_file(bucket, name).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::warn!(bucket, name, "Error deleting unreferenced data: {:?}", e);
+        }
+    }
+}
+
+fn next_page(objects: &[types::Object], batch_size: i64) -> Option<(String, Uuid)> {
+    if (objects.len() as i64) < batch_size {
+        return None;
+    }
+    objects
+        .last()
+        .map(|object| (object.key.clone(), object.version_id))
+}
+
+fn index_error(e: sqlx::Error) -> S3Error {
+    tracing::error!("Error applying lifecycle rule: {:?}", e);
+    S3Error::InternalError
+}
@@ -0,0 +1,91 @@
+use axum::{body::Bytes, http::Method};
+use s3_core::{logging::BucketLoggingStatus, response::ResponseData, S3Error};
+
+use crate::filter::S3Data;
+
+use super::FullstackBackend;
+
+impl FullstackBackend {
+    pub async fn put_bucket_logging(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let status = BucketLoggingStatus::from_xml(data.req.body())?;
+        let logging = match status.logging_enabled {
+            Some(logging) => {
+                // Logs are delivered as the owner of both buckets
+                let target = self.get_bucket(&logging.target_bucket).await.map_err(|_| {
+                    S3Error::InvalidTargetBucketForLogging(format!(
+                        "The target bucket for logging does not exist: {}",
+                        logging.target_bucket
+                    ))
+                })?;
+                if target.user_id != bucket.user_id {
+                    return Err(S3Error::InvalidTargetBucketForLogging(
+                        "The owner for the bucket to be logged and the target bucket must be the same."
+                            .to_string(),
+                    ));
+                }
+                Some(serde_json::to_value(&logging).map_err(|e| {
+                    tracing::error!("Error serializing bucket logging: {:?}", e);
+                    S3Error::InternalError
+                })?)
+            }
+            None => None,
+        };
+        self.database
+            .update_bucket_logging(bucket.id, logging)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error updating bucket logging: {:?}", e);
+                S3Error::InternalError
+            })?;
+
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn get_bucket_logging(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+
+        // A bucket without logging has an empty status
+        let status = BucketLoggingStatus {
+            logging_enabled: bucket.logging.clone(),
+        };
+        data.res.with_bytes(status.to_xml().into());
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    /// Write a batch of access log records as an object of the target
+    /// bucket. The object is stored like an upload of the bucket's owner, so
+    /// the target's default encryption and retention apply to it.
+    pub async fn write_access_log(
+        &self,
+        target_bucket: &str,
+        key: String,
+        body: Bytes,
+    ) -> Result<(), S3Error> {
+        let bucket = self.get_bucket(target_bucket).await?;
+        let mut data = S3Data::new();
+        data.req = axum::http::Request::builder()
+            .method(Method::PUT)
+            .header("Content-Length", body.len())
+            .header("Content-Type", "text/plain")
+            .body(body)
+            .map_err(|e| {
+                tracing::error!("Error building access log upload: {:?}", e);
+                S3Error::InternalError
+            })?;
+        data.auth_key.user_id = bucket.user_id;
+        data.bucket_name = bucket.name.clone();
+        data.bucket = Some(bucket);
+        data.key = key;
+        self.put_object(&mut data).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+use chrono::{DateTime, Utc};
+use s3_core::S3Error;
+use uuid::Uuid;
+
+use crate::backend::types;
+
+use super::FullstackBackend;
+
+// A bucket is migrated one version at a time: the data is copied to the
+// target backend and the version is repointed there, so every version is
+// always read from the backend that holds it. New writes go to the target as
+// soon as the migration starts, and the bucket's backend is flipped once no
+// version is left on the source.
+impl FullstackBackend {
+    pub async fn start_backend_migration(
+        &self,
+        bucket_name: &str,
+        target: &str,
+    ) -> Result<types::BackendMigration, S3Error> {
+        let bucket = self.get_bucket(bucket_name).await?;
+        let target_backend_id = self.storage.backend_by_name(target).ok_or_else(|| {
+            S3Error::InvalidArgument(format!("Unknown storage backend {}", target))
+        })?;
+        let source_backend_id = bucket
+            .backend_id
+            .or(self.storage.default_backend())
+            .ok_or_else(|| {
+                tracing::error!("No default storage backend to migrate {} from", bucket_name);
+                S3Error::InternalError
+            })?;
+        if source_backend_id == target_backend_id {
+            return Err(S3Error::InvalidArgument(format!(
+                "Bucket {} is already on storage backend {}",
+                bucket_name, target
+            )));
+        }
+
+        let migration = types::BackendMigration {
+            id: Uuid::now_v7(),
+            bucket_id: bucket.id,
+            bucket_name: bucket.name,
+            source_backend_id,
+            target_backend_id,
+            status: "in_progress".to_string(),
+            progress: types::MigrationProgress::default(),
+            started_at: Utc::now(),
+            completed_at: None,
+            error: None,
+        };
+        self.database
+            .create_backend_migration(&migration)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(e) if e.is_unique_violation() => S3Error::InvalidArgument(
+                    format!("Bucket {} is already being migrated", bucket_name),
+                ),
+                _ => migration_error(e),
+            })?;
+        Ok(migration)
+    }
+
+    pub async fn get_backend_migration(
+        &self,
+        id: Uuid,
+    ) -> Result<Option<types::BackendMigration>, S3Error> {
+        self.database
+            .get_backend_migration(id)
+            .await
+            .map_err(migration_error)
+    }
+
+    pub async fn claim_backend_migration(
+        &self,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Option<types::BackendMigration>, S3Error> {
+        self.database
+            .claim_backend_migration(lease_until)
+            .await
+            .map_err(migration_error)
+    }
+
+    /// Move every version still on the source backend, resuming from the
+    /// last checkpoint. Returns true once the bucket has been flipped to the
+    /// target backend.
+    pub async fn run_backend_migration(
+        &self,
+        migration: &types::BackendMigration,
+        batch_size: i64,
+        lease: chrono::Duration,
+    ) -> Result<bool, S3Error> {
+        let source = migration.source_backend_id;
+        let target = migration.target_backend_id;
+        if let Err(e) = self
+            .storage
+            .get(Some(source))
+            .and(self.storage.get(Some(target)))
+        {
+            // Retrying cannot help until the backend is configured again
+            self.database
+                .fail_backend_migration(migration.id, "Storage backend is not loaded")
+                .await
+                .map_err(migration_error)?;
+            return Err(e);
+        }
+
+        let mut progress = migration.progress.clone();
+        loop {
+            let after = (progress.key.clone(), progress.version_id);
+            let objects = self
+                .database
+                .list_migration_candidates(migration.bucket_id, source, &after, batch_size)
+                .await
+                .map_err(migration_error)?;
+
+            for object in &objects {
+                let moved = self
+                    .move_object(
+                        &migration.bucket_name,
+                        object,
+                        Some(source),
+                        target,
+                        &object.storage_class,
+                    )
+                    .await?;
+                if moved {
+                    progress.objects += 1;
+                    progress.bytes += object.size;
+                }
+            }
+            if let Some(last) = objects.last() {
+                progress.key = last.key.clone();
+                progress.version_id = last.version_id;
+            }
+
+            let leased = self
+                .database
+                .checkpoint_backend_migration(migration.id, &progress, Utc::now() + lease)
+                .await
+                .map_err(migration_error)?;
+            if !leased {
+                // Failed or completed by another gateway meanwhile
+                return Ok(false);
+            }
+
+            if (objects.len() as i64) < batch_size {
+                let flipped = self
+                    .database
+                    .complete_backend_migration(migration)
+                    .await
+                    .map_err(migration_error)?;
+                if flipped {
+                    return Ok(true);
+                }
+                // Writes that raced the start of the migration left versions
+                // behind the cursor, go over the bucket again
+                progress.key = String::new();
+                progress.version_id = Uuid::nil();
+            }
+        }
+    }
+
+    /// Copy the data of `object` from `source` to `target`, repoint the index
+    /// in a single conditional update, then remove the source copy. Readers
+    /// that resolved the old location before the switch retry against the
+    /// index. Returns false when the version changed meanwhile.
+    ///
+    /// A packed or deduplicated object leaves its shared blob and becomes a
+    /// blob of its own on the target, the shared blob keeps its other objects.
+    pub(super) async fn move_object(
+        &self,
+        bucket_name: &str,
+        object: &types::Object,
+        source: Option<Uuid>,
+        target: Uuid,
+        storage_class: &str,
+    ) -> Result<bool, S3Error> {
+        let name = match &object.backend_specific_name {
+            Some(name) => name,
+            None => return Ok(false),
+        };
+        let index_error = |e: sqlx::Error| {
+            tracing::error!("Error moving object: {:?}", e);
+            S3Error::InternalError
+        };
+        let moves_data = source != Some(target);
+        let packed = object.packed();
+        let shared = packed.is_some() || object.content_hash().is_some();
+        let (target_name, target_id) = match shared {
+            true if moves_data => (object.version_id.to_string(), None),
+            _ => (name.clone(), object.backend_specific_id.clone()),
+        };
+
+        if moves_data {
+            let storage = self.storage.get(source)?;
+            let data = match packed {
+                Some(location) => {
+                    storage
+                        .get_file_range(bucket_name, name, location.offset, location.end())
+                        .await?
+                }
+                None => storage.get_file(bucket_name, name).await?,
+            };
+            self.storage
+                .get(Some(target))?
+                .save_file(bucket_name, &target_name, data)
+                .await?;
+        }
+
+        let moved = self
+            .database
+            .repoint_object(
+                object,
+                storage_class,
+                Some(target),
+                Some(&target_name),
+                target_id.as_deref(),
+            )
+            .await
+            .map_err(index_error)?;
+        if !moves_data {
+            return Ok(moved);
+        }
+
+        if moved {
+            if !shared {
+                self.remove_blob(bucket_name, source, name).await;
+            }
+        } else {
+            // The version was deleted or moved meanwhile. Drop the copy unless
+            // the index now points at it.
+            let current = self
+                .database
+                .get_object_version(object.bucket_id, &object.key, object.version_id)
+                .await
+                .map_err(index_error)?;
+            if current.is_none_or(|current| current.backend_id != Some(target)) {
+                self.remove_blob(bucket_name, Some(target), &target_name)
+                    .await;
+            }
+        }
+        Ok(moved)
+    }
+}
+
+fn migration_error(e: sqlx::Error) -> S3Error {
+    tracing::error!("Error migrating bucket: {:?}", e);
+    S3Error::InternalError
+}
@@ -1,2 +1,20 @@
+mod compression;
+mod copy;
+mod dedup;
+mod delete;
+mod encryption;
+mod failover;
+mod fallback;
 pub mod fullstack;
+mod lifecycle;
+mod logging;
+mod migration;
+mod multipart;
+mod notification;
+mod object_lock;
+mod packing;
+mod replication;
+mod tagging;
+mod versioning;
+mod website;
 pub use fullstack::*;
@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+
+use aws_sdk_s3::primitives::ByteStream;
+use md5::Digest;
+use s3_core::{
+    multipart::{
+        parse_part_number, CompleteMultipartUpload, CompleteMultipartUploadResult,
+        InitiateMultipartUploadResult,
+    },
+    response::ResponseData,
+    S3Error, StorageClass,
+};
+use uuid::Uuid;
+
+use crate::{backend::types, filter::S3Data};
+
+use super::{
+    encryption::{check_envelope_key, customer_key, encryption_headers},
+    notification::request_event,
+    object_lock::apply_object_lock,
+    replication::replication_status,
+    tagging::request_tags,
+    website::request_redirect_location,
+    FullstackBackend,
+};
+
+// Parts are staged on the backend chosen when the upload is created, through
+// FileStorage::save_part, and assembled there by complete_multipart into the
+// blob of the new version. An upload never moves between backends: a backend
+// failing while parts are uploaded fails those parts, it does not fail over.
+impl FullstackBackend {
+    pub async fn create_multipart_upload(
+        &self,
+        data: &mut S3Data,
+    ) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+
+        // The settings of the object are those of its CreateMultipartUpload
+        let mut template = types::Object::default();
+        apply_object_lock(
+            data.req.headers(),
+            bucket,
+            &mut template,
+            chrono::Utc::now(),
+        )?;
+        let customer = customer_key(data)?;
+        let encryption = match self.encryption_request(data, bucket, customer.as_ref())? {
+            Some(request) => Some(self.upload_encryption(request, customer.as_ref())?),
+            None => None,
+        };
+
+        let primary = bucket
+            .migration_target
+            .or(bucket.backend_id)
+            .or(self.storage.default_backend());
+        let backend_id = self.write_backend(bucket, primary);
+        let upload = types::Multipart {
+            id: Uuid::now_v7(),
+            bucket_id: bucket.id,
+            upload_id: Uuid::now_v7().to_string(),
+            key: data.key.clone(),
+            created_at: chrono::Utc::now(),
+            owner_id: data.auth_key.user_id,
+            backend_id,
+            metadata: types::UploadMetadata {
+                tags: request_tags(data)?,
+                website_redirect_location: request_redirect_location(data.req.headers())?,
+                retention_mode: template.retention_mode,
+                retain_until: template.retain_until,
+                legal_hold: template.legal_hold,
+                failover_from: primary.filter(|_| backend_id != primary),
+                encryption,
+            },
+            ..Default::default()
+        };
+        self.database
+            .create_multipart_upload(&upload)
+            .await
+            .map_err(multipart_error)?;
+
+        let result = InitiateMultipartUploadResult {
+            bucket: bucket.name.clone(),
+            key: upload.key.clone(),
+            upload_id: upload.upload_id.clone(),
+        };
+        data.res.with_bytes(result.to_xml().into());
+        data.res.with_status_code(200);
+        if let Some(envelope) = &upload.metadata.encryption {
+            encryption_headers(&mut data.res, envelope, customer.as_ref());
+        }
+        Ok(data.res.clone())
+    }
+
+    pub async fn upload_part(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let part_number = parse_part_number(&data.query_param("partNumber").unwrap_or_default())?;
+        let upload = self.multipart_upload(data, bucket).await?;
+        // Parts of SSE-C uploads are sent with the key of the upload
+        let customer = customer_key(data)?;
+        let envelope = upload.metadata.encryption.as_ref();
+        check_envelope_key(envelope, customer.as_ref())?;
+
+        let bytes = data.req.body();
+        let etag = const_hex::encode(md5::Md5::digest(bytes));
+        let (encryption, body) = match envelope {
+            Some(envelope) => {
+                let (part, ciphertext) = self
+                    .encrypt_part(envelope, customer.as_ref(), bytes)
+                    .await?;
+                (Some(part), ciphertext.into())
+            }
+            None => (None, bytes.clone()),
+        };
+        let result = match self.storage.get(upload.backend_id) {
+            Ok(storage) => {
+                storage
+                    .save_part(
+                        &bucket.name,
+                        &upload.upload_id,
+                        part_number,
+                        ByteStream::from(body),
+                    )
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        self.record_write(upload.backend_id, &result);
+        result?;
+
+        let part = types::Part {
+            part_number,
+            size: bytes.len() as i64,
+            etag: etag.clone(),
+            encryption,
+        };
+        self.database
+            .put_multipart_part(upload.id, &part)
+            .await
+            .map_err(multipart_error)?;
+
+        data.res
+            .with_status_code(200)
+            .with_header("ETag".to_string(), etag);
+        if let Some(envelope) = envelope {
+            encryption_headers(&mut data.res, envelope, customer.as_ref());
+        }
+        Ok(data.res.clone())
+    }
+
+    pub async fn complete_multipart_upload(
+        &self,
+        data: &mut S3Data,
+    ) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let request = CompleteMultipartUpload::from_xml(data.req.body())?;
+        let upload = self.multipart_upload(data, bucket).await?;
+
+        let mut uploaded: HashMap<u32, types::Part> = self
+            .database
+            .list_multipart_parts(upload.id)
+            .await
+            .map_err(multipart_error)?
+            .into_iter()
+            .map(|part| (part.part_number, part))
+            .collect();
+        let mut parts = Vec::with_capacity(request.parts.len());
+        for (i, completed) in request.parts.iter().enumerate() {
+            let part = match uploaded.remove(&completed.part_number) {
+                Some(part) if part.etag == completed.etag => part,
+                _ => {
+                    return Err(S3Error::InvalidPart(format!(
+                        "Part {} was not uploaded or its ETag does not match",
+                        completed.part_number
+                    )))
+                }
+            };
+            let last = i + 1 == request.parts.len();
+            if !last && (part.size as usize) < s3_core::MIN_OBJECT_PART_SIZE {
+                return Err(S3Error::EntityTooSmall);
+            }
+            parts.push(part);
+        }
+        let numbers: Vec<u32> = parts.iter().map(|part| part.part_number).collect();
+
+        let version_id = Uuid::now_v7();
+        let metadata = upload.metadata;
+        // Every part was sealed with a data key of its own
+        let encryption = match metadata.encryption {
+            Some(envelope) => Some(types::ObjectEncryption {
+                parts: parts
+                    .iter()
+                    .map(|part| part.encryption.clone())
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| {
+                        tracing::error!(
+                            "Part of encrypted upload {} has no data key",
+                            upload.upload_id
+                        );
+                        S3Error::InternalError
+                    })?,
+                ..envelope
+            }),
+            None => None,
+        };
+        let mut object = types::Object {
+            bucket_id: bucket.id,
+            key: upload.key,
+            owner_id: upload.owner_id,
+            version_id,
+            is_latest: true,
+            size: parts.iter().map(|part| part.size).sum(),
+            etag: multipart_etag(&parts),
+            tags: metadata.tags,
+            website_redirect_location: metadata.website_redirect_location,
+            retention_mode: metadata.retention_mode,
+            retain_until: metadata.retain_until,
+            legal_hold: metadata.legal_hold,
+            storage_class: StorageClass::Standard.as_str().to_string(),
+            backend_id: upload.backend_id,
+            backend_specific_name: Some(version_id.to_string()),
+            failover_from: metadata.failover_from,
+            encryption,
+            ..Default::default()
+        };
+        object.replication_status = replication_status(bucket, &object);
+
+        // Assembled on the backend first so the index never points to
+        // missing data
+        let storage = self.storage.get(upload.backend_id)?;
+        let result = storage
+            .complete_multipart(
+                &bucket.name,
+                &upload.upload_id,
+                &numbers,
+                &version_id.to_string(),
+            )
+            .await;
+        self.record_write(upload.backend_id, &result);
+        result?;
+        // Uploaded parts left out of the object
+        let unused: Vec<u32> = uploaded.into_keys().collect();
+        if !unused.is_empty() {
+            if let Err(e) = storage
+                .abort_multipart(&bucket.name, &upload.upload_id, &unused)
+                .await
+            {
+                tracing::warn!(
+                    bucket = %bucket.name,
+                    upload_id = upload.upload_id,
+                    "Error deleting unused parts: {:?}",
+                    e
+                );
+            }
+        }
+
+        let completed = self
+            .database
+            .complete_multipart_upload(upload.id, bucket, &object)
+            .await;
+        if !matches!(completed, Ok(Some(_))) {
+            self.remove_blob(&bucket.name, object.backend_id, &version_id.to_string())
+                .await;
+        }
+        match completed {
+            Ok(Some(replaced)) => {
+                for old in &replaced {
+                    self.remove_version_data(&bucket.name, bucket.backend_id, old)
+                        .await;
+                }
+            }
+            // Completed or aborted meanwhile
+            Ok(None) => return Err(S3Error::NoSuchUpload(upload.upload_id)),
+            Err(e) => return Err(multipart_error(e)),
+        }
+        let event = request_event(
+            "s3:ObjectCreated:CompleteMultipartUpload",
+            data,
+            bucket,
+            &object,
+        );
+        self.notify(bucket.notification.as_ref(), event).await;
+
+        let result = CompleteMultipartUploadResult {
+            location: format!("/{}/{}", bucket.name, object.key),
+            bucket: bucket.name.clone(),
+            key: object.key.clone(),
+            etag: object.etag.clone(),
+        };
+        data.res.with_bytes(result.to_xml().into());
+        data.res.with_status_code(200);
+        // The key of SSE-C objects is not sent with the completion
+        if let Some(envelope) = object
+            .encryption
+            .as_ref()
+            .filter(|e| e.customer_key.is_none())
+        {
+            encryption_headers(&mut data.res, envelope, None);
+        }
+        Ok(data.res.clone())
+    }
+
+    pub async fn abort_multipart_upload(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let upload = self.multipart_upload(data, bucket).await?;
+
+        let parts = self
+            .database
+            .abort_multipart_upload(upload.id)
+            .await
+            .map_err(multipart_error)?;
+        // The upload is gone from the index, so leftover parts only leak
+        // storage
+        let result = match self.storage.get(upload.backend_id) {
+            Ok(storage) => {
+                storage
+                    .abort_multipart(&bucket.name, &upload.upload_id, &parts)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::warn!(
+                bucket = %bucket.name,
+                upload_id = upload.upload_id,
+                "Error deleting parts of aborted upload: {:?}",
+                e
+            );
+        }
+
+        data.res.with_status_code(204);
+        Ok(data.res.clone())
+    }
+
+    // The upload named by the uploadId of the request
+    async fn multipart_upload(
+        &self,
+        data: &S3Data,
+        bucket: &types::Bucket,
+    ) -> Result<types::Multipart, S3Error> {
+        let upload_id = data.query_param("uploadId").unwrap_or_default();
+        self.database
+            .get_multipart_upload(bucket.id, &data.key, &upload_id)
+            .await
+            .map_err(multipart_error)?
+            .ok_or(S3Error::NoSuchUpload(upload_id))
+    }
+}
+
+/// ETag of a multipart object: the MD5 of the binary MD5s of its parts,
+/// followed by the number of parts.
+fn multipart_etag(parts: &[types::Part]) -> String {
+    let mut hasher = md5::Md5::new();
+    for part in parts {
+        hasher.update(const_hex::decode(&part.etag).unwrap_or_default());
+    }
+    format!("{}-{}", const_hex::encode(hasher.finalize()), parts.len())
+}
+
+fn multipart_error(e: sqlx::Error) -> S3Error {
+    tracing::error!("Error indexing multipart upload: {:?}", e);
+    S3Error::InternalError
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multipart_etag() {
+        let part = |data: &[u8]| types::Part {
+            etag: const_hex::encode(md5::Md5::digest(data)),
+            ..Default::default()
+        };
+        let parts = [part(b"first"), part(b"second")];
+
+        let mut concatenated = md5::Md5::digest(b"first").to_vec();
+        concatenated.extend_from_slice(&md5::Md5::digest(b"second"));
+        assert_eq!(
+            multipart_etag(&parts),
+            format!("{}-2", const_hex::encode(md5::Md5::digest(&concatenated)))
+        );
+    }
+}
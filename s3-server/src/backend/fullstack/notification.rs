@@ -0,0 +1,228 @@
+use std::collections::HashSet;
+
+use s3_core::{
+    notification::NotificationConfiguration, response::ResponseData,
+    versioning::VERSIONING_DISABLED, S3Error,
+};
+
+use crate::{
+    backend::types,
+    filter::S3Data,
+    notification::{EventMessage, EventRecord, ObjectEvent, TestEvent},
+};
+
+use super::FullstackBackend;
+
+// Principal of the events of actions the gateway takes on its own, such as
+// lifecycle actions
+static SERVICE_PRINCIPAL: &str = "s3.amazonaws.com";
+
+impl FullstackBackend {
+    pub async fn put_bucket_notification_configuration(
+        &self,
+        data: &mut S3Data,
+    ) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let config = NotificationConfiguration::from_xml(data.req.body())?;
+
+        // Every destination must be a target of the gateway
+        let targets: HashSet<&str> = config.rules().iter().map(|rule| rule.target()).collect();
+        if let Some(target) = targets.iter().find(|target| {
+            !self
+                .notifier
+                .as_ref()
+                .is_some_and(|notifier| notifier.has_target(target))
+        }) {
+            return Err(S3Error::InvalidArgument(format!(
+                "Unable to validate the following destination configurations: {}",
+                target
+            )));
+        }
+
+        let notification = if config.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_value(&config).map_err(|e| {
+                tracing::error!("Error serializing notification configuration: {:?}", e);
+                S3Error::InternalError
+            })?)
+        };
+        self.database
+            .update_bucket_notification(bucket.id, notification)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error updating bucket notification: {:?}", e);
+                S3Error::InternalError
+            })?;
+
+        if let Some(notifier) = &self.notifier {
+            let test = TestEvent::new(&bucket.name, &data.request_id, chrono::Utc::now());
+            let body = serde_json::to_vec(&test).unwrap_or_default();
+            for target in targets {
+                if let Err(e) = notifier.send(target, &body).await {
+                    tracing::error!(destination = target, "Error queueing test event: {:?}", e);
+                }
+            }
+        }
+
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn get_bucket_notification_configuration(
+        &self,
+        data: &mut S3Data,
+    ) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+
+        // A bucket without notifications has an empty configuration
+        let config = bucket.notification.clone().unwrap_or_default();
+        data.res.with_bytes(config.to_xml().into());
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    /// Queue an event for every target of the bucket's configuration that
+    /// subscribes to it. The event is on disk when this returns, a failure
+    /// to queue it does not fail the operation that caused it.
+    pub(super) async fn notify(
+        &self,
+        notification: Option<&NotificationConfiguration>,
+        event: ObjectEvent,
+    ) {
+        let (notifier, notification) = match (&self.notifier, notification) {
+            (Some(notifier), Some(notification)) => (notifier, notification),
+            _ => return,
+        };
+        for rule in notification.matching_rules(&event.name, &event.key) {
+            let message = EventMessage {
+                records: vec![EventRecord::new(
+                    &event,
+                    notifier.region(),
+                    rule.id.unwrap_or_default(),
+                )],
+            };
+            let body = serde_json::to_vec(&message).unwrap_or_default();
+            if let Err(e) = notifier.send(rule.target(), &body).await {
+                tracing::error!(
+                    bucket = event.bucket,
+                    key = event.key,
+                    destination = rule.target(),
+                    "Error queueing {} event: {:?}",
+                    event.name,
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// Event of a request on a version of an object.
+pub(super) fn request_event(
+    name: &str,
+    data: &S3Data,
+    bucket: &types::Bucket,
+    object: &types::Object,
+) -> ObjectEvent {
+    ObjectEvent {
+        principal: data.auth_key.user_id.to_string(),
+        source_ip: data.client_ip.to_string(),
+        request_id: data.request_id.clone(),
+        ..object_event(
+            name,
+            &bucket.name,
+            bucket.user_id,
+            bucket.versioning,
+            object,
+        )
+    }
+}
+
+/// Event of a lifecycle action on a version of an object.
+pub(super) fn lifecycle_event(
+    name: &str,
+    rule: &types::LifecycleRule,
+    object: &types::Object,
+) -> ObjectEvent {
+    ObjectEvent {
+        principal: SERVICE_PRINCIPAL.to_string(),
+        ..object_event(
+            name,
+            &rule.bucket_name,
+            rule.bucket_owner,
+            rule.versioning,
+            object,
+        )
+    }
+}
+
+/// Event of the replication of a version of an object.
+pub(super) fn replication_event(
+    name: &str,
+    bucket: &types::Bucket,
+    object: &types::Object,
+) -> ObjectEvent {
+    ObjectEvent {
+        principal: SERVICE_PRINCIPAL.to_string(),
+        ..object_event(
+            name,
+            &bucket.name,
+            bucket.user_id,
+            bucket.versioning,
+            object,
+        )
+    }
+}
+
+fn object_event(
+    name: &str,
+    bucket: &str,
+    bucket_owner: i64,
+    versioning: i16,
+    object: &types::Object,
+) -> ObjectEvent {
+    let versioned = versioning != VERSIONING_DISABLED as i16;
+    ObjectEvent {
+        name: name.to_string(),
+        time: chrono::Utc::now(),
+        bucket: bucket.to_string(),
+        bucket_owner,
+        key: object.key.clone(),
+        size: (!object.is_delete_marker).then_some(object.size),
+        etag: (!object.etag.is_empty()).then(|| object.etag.clone()),
+        version_id: versioned.then(|| object.version_id.to_string()),
+        sequencer: sequencer(object),
+        ..Default::default()
+    }
+}
+
+// Version ids start with their creation time, so the events of later
+// versions of a key have greater sequencers
+fn sequencer(object: &types::Object) -> String {
+    const_hex::encode_upper(object.version_id.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequencer() {
+        let first = types::Object {
+            version_id: uuid::Uuid::now_v7(),
+            ..Default::default()
+        };
+        let second = types::Object {
+            version_id: uuid::Uuid::now_v7(),
+            ..Default::default()
+        };
+        assert_eq!(sequencer(&first).len(), 32);
+        assert!(sequencer(&first) < sequencer(&second));
+    }
+}
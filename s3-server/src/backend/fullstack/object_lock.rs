@@ -0,0 +1,391 @@
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use s3_core::{
+    object_lock::{self, LegalHold, ObjectLockConfiguration, ObjectRetention},
+    response::ResponseData,
+    S3Error,
+};
+
+use crate::{backend::types, filter::S3Data};
+
+use super::FullstackBackend;
+
+static OBJECT_LOCK_ENABLED_HEADER: &str = "x-amz-bucket-object-lock-enabled";
+static MODE_HEADER: &str = "x-amz-object-lock-mode";
+static RETAIN_UNTIL_HEADER: &str = "x-amz-object-lock-retain-until-date";
+static LEGAL_HOLD_HEADER: &str = "x-amz-object-lock-legal-hold";
+static BYPASS_GOVERNANCE_HEADER: &str = "x-amz-bypass-governance-retention";
+
+impl FullstackBackend {
+    pub async fn put_object_lock_configuration(
+        &self,
+        data: &mut S3Data,
+    ) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let config = ObjectLockConfiguration::from_xml(data.req.body())?;
+        // Object Lock is chosen when the bucket is created
+        if bucket.object_lock.is_none() {
+            return Err(S3Error::InvalidBucketState(
+                "Object Lock configuration cannot be enabled on existing buckets".to_string(),
+            ));
+        }
+        let config = serde_json::to_value(&config).map_err(|e| {
+            tracing::error!("Error serializing object lock configuration: {:?}", e);
+            S3Error::InternalError
+        })?;
+        self.database
+            .update_bucket_object_lock(bucket.id, config)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error updating bucket object lock: {:?}", e);
+                S3Error::InternalError
+            })?;
+
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn get_object_lock_configuration(
+        &self,
+        data: &mut S3Data,
+    ) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let config =
+            bucket
+                .object_lock
+                .as_ref()
+                .ok_or(S3Error::ObjectLockConfigurationNotFoundError(
+                    bucket.name.clone(),
+                ))?;
+
+        data.res.with_bytes(config.to_xml().into());
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn put_object_retention(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        if bucket.object_lock.is_none() {
+            return Err(S3Error::InvalidRequest);
+        }
+        let retention = ObjectRetention::from_xml(data.req.body())?.parse()?;
+        let object = self.locked_object(data, bucket).await?;
+        check_retention_change(
+            &object,
+            retention.as_ref(),
+            Utc::now(),
+            bypass_governance(data.req.headers())?,
+        )?;
+        let (mode, until) = match &retention {
+            Some((mode, until)) => (Some(mode.as_str()), Some(*until)),
+            None => (None, None),
+        };
+        let updated = self
+            .database
+            .update_object_retention(&object, mode, until)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error updating object retention: {:?}", e);
+                S3Error::InternalError
+            })?;
+        // The retention changed since it was checked
+        if !updated {
+            return Err(S3Error::OperationAborted);
+        }
+
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn get_object_retention(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        if bucket.object_lock.is_none() {
+            return Err(S3Error::InvalidRequest);
+        }
+        let object = self.locked_object(data, bucket).await?;
+        let retention = match (&object.retention_mode, object.retain_until) {
+            (Some(mode), Some(until)) => ObjectRetention::new(mode, until),
+            _ => return Err(S3Error::NoSuchObjectLockConfiguration),
+        };
+
+        data.res.with_bytes(retention.to_xml().into());
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn put_object_legal_hold(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        if bucket.object_lock.is_none() {
+            return Err(S3Error::InvalidRequest);
+        }
+        let hold = LegalHold::from_xml(data.req.body())?;
+        let object = self.locked_object(data, bucket).await?;
+        let updated = self
+            .database
+            .update_object_legal_hold(&object, hold.is_on())
+            .await
+            .map_err(|e| {
+                tracing::error!("Error updating object legal hold: {:?}", e);
+                S3Error::InternalError
+            })?;
+        if !updated {
+            return Err(S3Error::NoSuchKey(object.key.clone()));
+        }
+
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn get_object_legal_hold(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        if bucket.object_lock.is_none() {
+            return Err(S3Error::InvalidRequest);
+        }
+        let object = self.locked_object(data, bucket).await?;
+
+        data.res
+            .with_bytes(LegalHold::new(object.legal_hold).to_xml().into());
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    // Version named by the versionId of the request, or the latest one
+    async fn locked_object(
+        &self,
+        data: &S3Data,
+        bucket: &types::Bucket,
+    ) -> Result<types::Object, S3Error> {
+        let version_id = match data.query_param("versionId") {
+            Some(version_id) => version_id,
+            None => return self.latest_object(bucket, &data.key).await,
+        };
+        let object = self.object_version(bucket, &data.key, &version_id).await?;
+        // Delete markers have no retention or legal hold
+        if object.is_delete_marker {
+            return Err(S3Error::MethodNotAllowed);
+        }
+        Ok(object)
+    }
+}
+
+/// Whether a CreateBucket asks for Object Lock.
+pub(super) fn object_lock_requested(headers: &HeaderMap) -> bool {
+    headers
+        .get(OBJECT_LOCK_ENABLED_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("true"))
+}
+
+/// Set the retention and legal hold of a new version from the
+/// x-amz-object-lock headers of its PUT, or from the bucket's default
+/// retention.
+pub(super) fn apply_object_lock(
+    headers: &HeaderMap,
+    bucket: &types::Bucket,
+    object: &mut types::Object,
+    now: DateTime<Utc>,
+) -> Result<(), S3Error> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let (mode, until, hold) = (
+        header(MODE_HEADER),
+        header(RETAIN_UNTIL_HEADER),
+        header(LEGAL_HOLD_HEADER),
+    );
+    let config = match &bucket.object_lock {
+        Some(config) => config,
+        None if mode.is_none() && until.is_none() && hold.is_none() => return Ok(()),
+        None => return Err(S3Error::InvalidRequest),
+    };
+    match (mode, until) {
+        (Some(mode), Some(until)) => {
+            object_lock::validate_mode(mode)?;
+            let until = object_lock::parse_date(until)?;
+            if until <= now {
+                return Err(S3Error::InvalidArgument(
+                    "The retain until date must be in the future".to_string(),
+                ));
+            }
+            object.retention_mode = Some(mode.to_string());
+            object.retain_until = Some(until);
+        }
+        (None, None) => {
+            if let Some(retention) = config.default_retention() {
+                object.retention_mode = Some(retention.mode.clone());
+                object.retain_until = Some(retention.retain_until(now));
+            }
+        }
+        _ => {
+            return Err(S3Error::InvalidArgument(format!(
+                "{} and {} must be given together",
+                MODE_HEADER, RETAIN_UNTIL_HEADER
+            )))
+        }
+    }
+    if let Some(hold) = hold {
+        object.legal_hold = object_lock::parse_legal_hold(hold)?;
+    }
+    Ok(())
+}
+
+/// Report the retention and legal hold of a version in the headers of a
+/// response.
+pub(super) fn object_lock_headers(res: &mut ResponseData, object: &types::Object) {
+    if let (Some(mode), Some(until)) = (&object.retention_mode, object.retain_until) {
+        res.with_header(MODE_HEADER.to_string(), mode.clone())
+            .with_header(
+                RETAIN_UNTIL_HEADER.to_string(),
+                object_lock::format_date(until),
+            );
+    }
+    if object.legal_hold {
+        res.with_header(
+            LEGAL_HOLD_HEADER.to_string(),
+            object_lock::LEGAL_HOLD_ON.to_string(),
+        );
+    }
+}
+
+pub(super) fn bypass_governance(headers: &HeaderMap) -> Result<bool, S3Error> {
+    match headers.get(BYPASS_GOVERNANCE_HEADER) {
+        Some(value) => match value.to_str().map(|value| value.to_ascii_lowercase()) {
+            Ok(value) if value == "true" => Ok(true),
+            Ok(value) if value == "false" => Ok(false),
+            _ => Err(S3Error::InvalidArgument(format!(
+                "The header {} must be true or false",
+                BYPASS_GOVERNANCE_HEADER
+            ))),
+        },
+        None => Ok(false),
+    }
+}
+
+// An unexpired retention may only be extended, or turned from GOVERNANCE
+// to COMPLIANCE. GOVERNANCE retention may be shortened or removed with
+// x-amz-bypass-governance-retention, COMPLIANCE retention never.
+fn check_retention_change(
+    object: &types::Object,
+    retention: Option<&(String, DateTime<Utc>)>,
+    now: DateTime<Utc>,
+    bypass_governance: bool,
+) -> Result<(), S3Error> {
+    if let Some((_, until)) = retention {
+        if *until <= now {
+            return Err(S3Error::InvalidArgument(
+                "The retain until date must be in the future".to_string(),
+            ));
+        }
+    }
+    let (mode, until) = match (&object.retention_mode, object.retain_until) {
+        (Some(mode), Some(until)) if until > now => (mode, until),
+        _ => return Ok(()),
+    };
+    let weakened = match retention {
+        Some((new_mode, new_until)) => {
+            *new_until < until
+                || (mode == object_lock::COMPLIANCE && new_mode == object_lock::GOVERNANCE)
+        }
+        None => true,
+    };
+    if !weakened || (mode == object_lock::GOVERNANCE && bypass_governance) {
+        return Ok(());
+    }
+    Err(S3Error::AccessDenied)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn locked(mode: &str, until: DateTime<Utc>) -> types::Object {
+        types::Object {
+            retention_mode: Some(mode.to_string()),
+            retain_until: Some(until),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check_retention_change() {
+        let now = Utc::now();
+        let (soon, later) = (now + Duration::days(1), now + Duration::days(30));
+        let governance = |until| Some((object_lock::GOVERNANCE.to_string(), until));
+        let compliance = |until| Some((object_lock::COMPLIANCE.to_string(), until));
+
+        let object = types::Object::default();
+        assert!(check_retention_change(&object, governance(soon).as_ref(), now, false).is_ok());
+        assert!(check_retention_change(&object, governance(now).as_ref(), now, false).is_err());
+
+        let object = locked(object_lock::GOVERNANCE, soon);
+        assert!(check_retention_change(&object, governance(later).as_ref(), now, false).is_ok());
+        assert!(check_retention_change(&object, compliance(soon).as_ref(), now, false).is_ok());
+        assert!(check_retention_change(&object, None, now, false).is_err());
+        assert!(check_retention_change(&object, None, now, true).is_ok());
+
+        let object = locked(object_lock::COMPLIANCE, later);
+        assert!(check_retention_change(&object, compliance(later).as_ref(), now, false).is_ok());
+        for retention in [compliance(soon), governance(later), None] {
+            assert!(matches!(
+                check_retention_change(&object, retention.as_ref(), now, true),
+                Err(S3Error::AccessDenied)
+            ));
+        }
+
+        // Expired retention no longer restricts anything
+        let object = locked(object_lock::COMPLIANCE, now - Duration::days(1));
+        assert!(check_retention_change(&object, None, now, false).is_ok());
+    }
+
+    #[test]
+    fn test_apply_object_lock() {
+        let now = Utc::now();
+        let mut bucket = types::Bucket::default();
+        let mut object = types::Object::default();
+        let mut headers = HeaderMap::new();
+        assert!(apply_object_lock(&headers, &bucket, &mut object, now).is_ok());
+
+        headers.insert(LEGAL_HOLD_HEADER, "ON".parse().unwrap());
+        assert!(apply_object_lock(&headers, &bucket, &mut object, now).is_err());
+
+        bucket.object_lock = Some(
+            ObjectLockConfiguration::from_xml(
+                b"<ObjectLockConfiguration><ObjectLockEnabled>Enabled</ObjectLockEnabled>\
+                <Rule><DefaultRetention><Mode>GOVERNANCE</Mode><Days>10</Days>\
+                </DefaultRetention></Rule></ObjectLockConfiguration>",
+            )
+            .unwrap(),
+        );
+        apply_object_lock(&headers, &bucket, &mut object, now).unwrap();
+        assert!(object.legal_hold);
+        assert_eq!(object.retention_mode.as_deref(), Some("GOVERNANCE"));
+        assert_eq!(object.retain_until, Some(now + Duration::days(10)));
+
+        headers.insert(MODE_HEADER, "COMPLIANCE".parse().unwrap());
+        assert!(apply_object_lock(&headers, &bucket, &mut object, now).is_err());
+        headers.insert(RETAIN_UNTIL_HEADER, "2020-01-01T00:00:00Z".parse().unwrap());
+        assert!(apply_object_lock(&headers, &bucket, &mut object, now).is_err());
+        let until = object_lock::format_date(now + Duration::days(400));
+        headers.insert(RETAIN_UNTIL_HEADER, until.parse().unwrap());
+        apply_object_lock(&headers, &bucket, &mut object, now).unwrap();
+        assert_eq!(object.retention_mode.as_deref(), Some("COMPLIANCE"));
+    }
+}
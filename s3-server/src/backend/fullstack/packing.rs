@@ -0,0 +1,409 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use aws_sdk_s3::primitives::ByteStream;
+use chrono::{DateTime, Utc};
+use s3_core::S3Error;
+use tokio::sync::{oneshot, Notify};
+use uuid::Uuid;
+
+use crate::{backend::types, config::PackingConfig};
+
+use super::FullstackBackend;
+
+/// Groups small PUTs into volume blobs.
+///
+/// The first small object for a bucket and backend opens a volume, and every
+/// object arriving within `window_ms` is appended to it. The PUT that opened
+/// the volume writes it once the window closes or the volume is full, and
+/// every PUT waits for that write before indexing its object. Volumes are
+/// never appended to once written, so packing works on any FileStorage.
+pub(super) struct Packer {
+    config: PackingConfig,
+    state: Mutex<PackerState>,
+}
+
+#[derive(Default)]
+struct PackerState {
+    // Volume collecting objects for a bucket and backend
+    open: HashMap<(Uuid, Uuid), Uuid>,
+    pending: HashMap<Uuid, PendingVolume>,
+}
+
+struct PendingVolume {
+    bucket_id: Uuid,
+    backend_id: Uuid,
+    data: Vec<u8>,
+    // Told whether the volume was written
+    waiters: Vec<oneshot::Sender<bool>>,
+    full: Arc<Notify>,
+}
+
+// Held by the PUT that opened a volume. Should that PUT go away before the
+// volume is written, the waiting PUTs fail instead of waiting forever.
+struct OpenVolume<'a> {
+    packer: &'a Packer,
+    id: Uuid,
+}
+
+impl Packer {
+    pub(super) fn new(config: PackingConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(PackerState::default()),
+        }
+    }
+
+    pub(super) fn inlines(&self, size: u64) -> bool {
+        self.config.inline_max_size > 0 && size <= self.config.inline_max_size
+    }
+
+    pub(super) fn accepts(&self, size: u64) -> bool {
+        self.config.enabled && size > 0 && size < self.config.max_object_size
+    }
+
+    // Append `data` to the open volume of the bucket and backend. Also
+    // returns the full signal when the caller opened the volume.
+    fn append(
+        &self,
+        bucket_id: Uuid,
+        backend_id: Uuid,
+        data: &[u8],
+    ) -> (
+        types::PackedLocation,
+        oneshot::Receiver<bool>,
+        Option<Arc<Notify>>,
+    ) {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        let key = (bucket_id, backend_id);
+        let (id, opened) = match state.open.get(&key) {
+            Some(id) => (*id, None),
+            None => {
+                let id = Uuid::now_v7();
+                let full = Arc::new(Notify::new());
+                state.open.insert(key, id);
+                state.pending.insert(
+                    id,
+                    PendingVolume {
+                        bucket_id,
+                        backend_id,
+                        data: vec![],
+                        waiters: vec![],
+                        full: full.clone(),
+                    },
+                );
+                (id, Some(full))
+            }
+        };
+
+        let volume = state
+            .pending
+            .get_mut(&id)
+            .expect("open volumes are pending");
+        let location = types::PackedLocation {
+            volume_id: id,
+            offset: volume.data.len() as u64,
+            length: data.len() as u64,
+        };
+        volume.data.extend_from_slice(data);
+        let (done, written) = oneshot::channel();
+        volume.waiters.push(done);
+        if volume.data.len() as u64 >= self.config.volume_size {
+            // Later objects go to a new volume
+            state.open.remove(&key);
+            volume.full.notify_one();
+        }
+        (location, written, opened)
+    }
+
+    fn take(&self, id: Uuid) -> Option<PendingVolume> {
+        let mut state = self.state.lock().unwrap();
+        let volume = state.pending.remove(&id)?;
+        let key = (volume.bucket_id, volume.backend_id);
+        if state.open.get(&key) == Some(&id) {
+            state.open.remove(&key);
+        }
+        Some(volume)
+    }
+}
+
+impl Drop for OpenVolume<'_> {
+    fn drop(&mut self) {
+        if self.packer.take(self.id).is_some() {
+            tracing::warn!(volume = %self.id, "Volume abandoned before it was written");
+        }
+    }
+}
+
+// Packed objects share a volume blob named after the volume id. Deleting an
+// object only lowers the live bytes of its volume, and the compactor copies
+// the live objects of mostly dead volumes into a new volume.
+impl FullstackBackend {
+    /// Add `data` to a volume on `backend_id` and return its location once
+    /// the volume is written.
+    pub(super) async fn pack_object(
+        &self,
+        bucket: &types::Bucket,
+        backend_id: Uuid,
+        data: &[u8],
+    ) -> Result<types::PackedLocation, S3Error> {
+        let (location, written, opened) = self.packer.append(bucket.id, backend_id, data);
+        if let Some(full) = opened {
+            let open = OpenVolume {
+                packer: &self.packer,
+                id: location.volume_id,
+            };
+            let window = Duration::from_millis(self.packer.config.window_ms);
+            tokio::select! {
+                _ = tokio::time::sleep(window) => {}
+                _ = full.notified() => {}
+            }
+            if let Some(volume) = self.packer.take(open.id) {
+                let result = self
+                    .store_volume(
+                        &bucket.name,
+                        open.id,
+                        volume.bucket_id,
+                        volume.backend_id,
+                        volume.data,
+                    )
+                    .await;
+                for waiter in volume.waiters {
+                    let _ = waiter.send(result.is_ok());
+                }
+            }
+        }
+
+        match written.await {
+            Ok(true) => Ok(location),
+            _ => Err(S3Error::InternalError),
+        }
+    }
+
+    // Write a volume blob, then its row. Objects are indexed into the volume
+    // afterwards and raise its live bytes.
+    async fn store_volume(
+        &self,
+        bucket_name: &str,
+        id: Uuid,
+        bucket_id: Uuid,
+        backend_id: Uuid,
+        data: Vec<u8>,
+    ) -> Result<(), S3Error> {
+        let name = id.to_string();
+        let size = data.len() as i64;
+        let result = match self.storage.get(Some(backend_id)) {
+            Ok(storage) => {
+                storage
+                    .save_file(bucket_name, &name, ByteStream::from(data))
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        self.record_write(Some(backend_id), &result);
+        result?;
+
+        let volume = types::Volume {
+            id,
+            bucket_id,
+            bucket_name: bucket_name.to_string(),
+            backend_id,
+            size,
+            live_bytes: 0,
+        };
+        if let Err(e) = self.database.create_volume(&volume).await {
+            tracing::error!("Error creating volume: {:?}", e);
+            self.remove_blob(bucket_name, Some(backend_id), &name).await;
+            return Err(S3Error::InternalError);
+        }
+        Ok(())
+    }
+
+    pub async fn claim_volume(
+        &self,
+        created_before: DateTime<Utc>,
+        max_live_ratio: f64,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Option<types::Volume>, S3Error> {
+        self.database
+            .claim_volume(created_before, max_live_ratio, lease_until)
+            .await
+            .map_err(index_error)
+    }
+
+    /// Copy the live objects of `volume` into a new volume and remove it.
+    /// Returns false when objects were added to the volume meanwhile and it
+    /// is kept.
+    pub async fn compact_volume(&self, volume: &types::Volume) -> Result<bool, S3Error> {
+        let name = volume.id.to_string();
+        let objects = self
+            .database
+            .list_volume_objects(volume)
+            .await
+            .map_err(index_error)?;
+
+        if !objects.is_empty() {
+            let data = self
+                .storage
+                .get(Some(volume.backend_id))?
+                .get_file(&volume.bucket_name, &name)
+                .await?
+                .collect()
+                .await
+                .map_err(|e| {
+                    tracing::error!("Error reading volume {}: {:?}", volume.id, e);
+                    S3Error::InternalError
+                })?
+                .into_bytes();
+
+            let id = Uuid::now_v7();
+            let mut packed = Vec::with_capacity(volume.live_bytes.max(0) as usize);
+            let mut moves = vec![];
+            for object in &objects {
+                let range = object.packed().and_then(|location| {
+                    data.get(location.offset as usize..=location.end() as usize)
+                });
+                let range = match range {
+                    Some(range) => range,
+                    None => {
+                        tracing::error!(
+                            volume = %volume.id,
+                            key = object.key,
+                            "Packed object is outside its volume"
+                        );
+                        continue;
+                    }
+                };
+                let location = types::PackedLocation {
+                    volume_id: id,
+                    offset: packed.len() as u64,
+                    length: range.len() as u64,
+                };
+                packed.extend_from_slice(range);
+                moves.push((object, location));
+            }
+            self.store_volume(
+                &volume.bucket_name,
+                id,
+                volume.bucket_id,
+                volume.backend_id,
+                packed,
+            )
+            .await?;
+
+            let id = id.to_string();
+            for (object, location) in moves {
+                // Objects deleted meanwhile are left behind as dead bytes of
+                // the new volume
+                self.database
+                    .repoint_object(
+                        object,
+                        &object.storage_class,
+                        object.backend_id,
+                        Some(&id),
+                        Some(&location.to_string()),
+                    )
+                    .await
+                    .map_err(index_error)?;
+            }
+        }
+
+        let removed = self
+            .database
+            .delete_volume(volume.id)
+            .await
+            .map_err(index_error)?;
+        if removed {
+            self.remove_blob(&volume.bucket_name, Some(volume.backend_id), &name)
+                .await;
+        }
+        Ok(removed)
+    }
+}
+
+fn index_error(e: sqlx::Error) -> S3Error {
+    tracing::error!("Error compacting volume: {:?}", e);
+    S3Error::InternalError
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packer(volume_size: u64) -> Packer {
+        Packer::new(PackingConfig {
+            enabled: true,
+            volume_size,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_append() {
+        let packer = packer(10);
+        let (bucket, backend) = (Uuid::now_v7(), Uuid::now_v7());
+
+        let (first, _, opened) = packer.append(bucket, backend, b"hello");
+        assert!(opened.is_some());
+        assert_eq!((first.offset, first.length), (0, 5));
+        let (second, _, opened) = packer.append(bucket, backend, b"world");
+        assert!(opened.is_none());
+        assert_eq!(second.volume_id, first.volume_id);
+        assert_eq!((second.offset, second.length), (5, 5));
+
+        // The volume is full, the next object opens another one
+        let (third, _, opened) = packer.append(bucket, backend, b"!");
+        assert!(opened.is_some());
+        assert_ne!(third.volume_id, first.volume_id);
+        let (other, _, opened) = packer.append(Uuid::now_v7(), backend, b"!");
+        assert!(opened.is_some());
+        assert_ne!(other.volume_id, third.volume_id);
+
+        let volume = packer.take(first.volume_id).unwrap();
+        assert_eq!(volume.data, b"helloworld");
+        assert_eq!(volume.waiters.len(), 2);
+        assert!(packer.take(first.volume_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_volume() {
+        let packer = packer(1024);
+        let (bucket, backend) = (Uuid::now_v7(), Uuid::now_v7());
+        let (location, _, _) = packer.append(bucket, backend, b"hello");
+        let (_, written, _) = packer.append(bucket, backend, b"world");
+
+        drop(OpenVolume {
+            packer: &packer,
+            id: location.volume_id,
+        });
+        assert!(written.await.is_err());
+        // A new volume is opened after the abandoned one
+        let (_, _, opened) = packer.append(bucket, backend, b"again");
+        assert!(opened.is_some());
+    }
+
+    #[test]
+    fn test_accepts() {
+        let packer = packer(1024);
+        assert!(packer.accepts(1));
+        assert!(!packer.accepts(0));
+        assert!(!packer.accepts(64 * 1024));
+        assert!(!Packer::new(PackingConfig::default()).accepts(1));
+    }
+
+    #[test]
+    fn test_inlines() {
+        let packer = Packer::new(PackingConfig {
+            inline_max_size: 256,
+            ..Default::default()
+        });
+        assert!(packer.inlines(0));
+        assert!(packer.inlines(256));
+        assert!(!packer.inlines(257));
+        assert!(!Packer::new(PackingConfig::default()).inlines(0));
+    }
+}
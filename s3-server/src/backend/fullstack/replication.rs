@@ -0,0 +1,351 @@
+use std::time::Duration;
+
+use aws_sdk_s3::primitives::ByteStream;
+use chrono::{DateTime, Utc};
+use s3_core::{
+    replication::{ReplicationConfiguration, REPLICATION_PENDING, REPLICATION_REPLICA},
+    response::ResponseData,
+    versioning::VERSIONING_ENABLED,
+    S3Error,
+};
+
+use crate::{backend::types, config::ReplicationConfig, filter::S3Data};
+
+use super::{notification::replication_event, FullstackBackend};
+
+// Versions of a bucket with a replication configuration are indexed with a
+// PENDING status and queued in the same transaction. Replication workers copy
+// the stored bytes of each queued version, encrypted or compressed as they
+// are, to the destination bucket's backend and index the copy with the same
+// version id and a REPLICA status. The rule is evaluated again when the copy
+// is made, so a configuration changed meanwhile applies.
+impl FullstackBackend {
+    pub async fn put_bucket_replication(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let config = ReplicationConfiguration::from_xml(data.req.body())?;
+        if bucket.versioning != VERSIONING_ENABLED as i16 {
+            return Err(S3Error::InvalidBucketState(
+                "Versioning must be 'Enabled' on the bucket to apply a replication configuration"
+                    .to_string(),
+            ));
+        }
+        for name in config.destination_buckets() {
+            if name == bucket.name {
+                return Err(S3Error::InvalidArgument(
+                    "Destination bucket cannot be the same as the source bucket".to_string(),
+                ));
+            }
+            let destination = self.get_bucket(name).await.map_err(|_| {
+                S3Error::InvalidArgument(format!("Destination bucket must exist: {}", name))
+            })?;
+            // Copies are written as the owner of both buckets
+            if destination.user_id != bucket.user_id {
+                return Err(S3Error::AccessDenied);
+            }
+            if destination.versioning != VERSIONING_ENABLED as i16 {
+                return Err(S3Error::InvalidBucketState(format!(
+                    "Destination bucket must have versioning enabled: {}",
+                    name
+                )));
+            }
+        }
+
+        let config = serde_json::to_value(&config).map_err(|e| {
+            tracing::error!("Error serializing replication configuration: {:?}", e);
+            S3Error::InternalError
+        })?;
+        self.database
+            .update_bucket_replication(bucket.id, Some(config))
+            .await
+            .map_err(|e| {
+                tracing::error!("Error updating bucket replication: {:?}", e);
+                S3Error::InternalError
+            })?;
+
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn get_bucket_replication(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let config =
+            bucket
+                .replication
+                .as_ref()
+                .ok_or(S3Error::ReplicationConfigurationNotFoundError(
+                    bucket.name.clone(),
+                ))?;
+
+        data.res.with_bytes(config.to_xml().into());
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn delete_bucket_replication(
+        &self,
+        data: &mut S3Data,
+    ) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        self.database
+            .update_bucket_replication(bucket.id, None)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting bucket replication: {:?}", e);
+                S3Error::InternalError
+            })?;
+
+        data.res.with_status_code(204);
+        Ok(data.res.clone())
+    }
+
+    pub async fn claim_replication_items(
+        &self,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<types::ReplicationItem>, S3Error> {
+        self.database
+            .claim_replication_items(lease_until, limit)
+            .await
+            .map_err(replication_error)
+    }
+
+    /// Copy a queued version to its destination. A failed copy is retried
+    /// with exponential backoff, and the version is marked FAILED once it has
+    /// used up its attempts.
+    pub async fn run_replication_item(
+        &self,
+        item: &types::ReplicationItem,
+        config: &ReplicationConfig,
+    ) -> Result<(), S3Error> {
+        let e = match self.replicate_version(item).await {
+            Ok(()) => {
+                return self
+                    .database
+                    .complete_replication(item)
+                    .await
+                    .map_err(replication_error)
+            }
+            Err(e) => e,
+        };
+        let error = format!("{:?}", e);
+        let attempts = item.attempts as u32 + 1;
+        if attempts < config.max_attempts {
+            let retry_at = Utc::now() + backoff(config, attempts);
+            self.database
+                .retry_replication(item, retry_at, &error)
+                .await
+                .map_err(replication_error)?;
+            return Err(e);
+        }
+
+        self.database
+            .fail_replication(item, &error)
+            .await
+            .map_err(replication_error)?;
+        if let Ok(bucket) = self.get_bucket(&item.bucket_name).await {
+            if let Ok(Some(object)) = self
+                .database
+                .get_object_version(item.bucket_id, &item.key, item.version_id)
+                .await
+            {
+                let event = replication_event(
+                    "s3:Replication:OperationFailedReplication",
+                    &bucket,
+                    &object,
+                );
+                self.notify(bucket.notification.as_ref(), event).await;
+            }
+        }
+        Err(e)
+    }
+
+    async fn replicate_version(&self, item: &types::ReplicationItem) -> Result<(), S3Error> {
+        let index_error = |e: sqlx::Error| {
+            tracing::error!("Error replicating object: {:?}", e);
+            S3Error::InternalError
+        };
+        let bucket = self.get_bucket(&item.bucket_name).await?;
+        let object = match self
+            .database
+            .get_object_version(item.bucket_id, &item.key, item.version_id)
+            .await
+            .map_err(index_error)?
+        {
+            Some(object) => object,
+            // Deleted before it could be copied
+            None => return Ok(()),
+        };
+        let rule = bucket
+            .replication
+            .as_ref()
+            .and_then(|config| config.rule_for(&object.key, &object.tags))
+            .filter(|rule| !object.is_delete_marker || rule.replicates_delete_markers())
+            .ok_or(S3Error::ReplicationConfigurationNotFoundError(
+                bucket.name.clone(),
+            ))?;
+        let destination = rule
+            .destination
+            .bucket_name()
+            .ok_or(S3Error::InvalidRequest)?;
+        let target = self.get_bucket(destination).await?;
+
+        // A previous attempt may have indexed the copy before failing, the
+        // data must not be written again under the name of a live replica
+        if self
+            .database
+            .get_object_version(target.id, &object.key, object.version_id)
+            .await
+            .map_err(index_error)?
+            .is_some()
+        {
+            return Ok(());
+        }
+
+        let stored = !object.is_delete_marker && object.inline_data.is_none();
+        let backend_id = target
+            .migration_target
+            .or(target.backend_id)
+            .or(self.storage.default_backend());
+        let name = object.version_id.to_string();
+        if stored {
+            let data = self.read_blob(&bucket, &object, None).await?;
+            self.storage
+                .get(backend_id)?
+                .save_file(&target.name, &name, ByteStream::from(data))
+                .await?;
+        }
+
+        // Retention only holds in buckets with Object Lock
+        let locked = target.object_lock.is_some();
+        let replica = types::Object {
+            bucket_id: target.id,
+            is_latest: true,
+            storage_class: rule
+                .destination
+                .storage_class
+                .clone()
+                .unwrap_or(object.storage_class),
+            backend_id: if stored { backend_id } else { None },
+            backend_specific_name: stored.then_some(name.clone()),
+            backend_specific_id: None,
+            retention_mode: object.retention_mode.filter(|_| locked),
+            retain_until: object.retain_until.filter(|_| locked),
+            legal_hold: object.legal_hold && locked,
+            replication_status: Some(REPLICATION_REPLICA.to_string()),
+            ..object
+        };
+        if let Err(e) = self.database.put_replica(&target, &replica).await {
+            if stored {
+                self.remove_blob(&target.name, backend_id, &name).await;
+            }
+            return Err(index_error(e));
+        }
+        Ok(())
+    }
+}
+
+/// Replication status of a new version of `bucket`, PENDING when a rule of
+/// the bucket's configuration copies it.
+pub(super) fn replication_status(bucket: &types::Bucket, object: &types::Object) -> Option<String> {
+    pending_replication(bucket.replication.as_ref(), object)
+}
+
+/// Same as replication_status, for callers holding only the configuration.
+pub(super) fn pending_replication(
+    configuration: Option<&ReplicationConfiguration>,
+    object: &types::Object,
+) -> Option<String> {
+    configuration?
+        .rule_for(&object.key, &object.tags)
+        .filter(|rule| !object.is_delete_marker || rule.replicates_delete_markers())
+        .map(|_| REPLICATION_PENDING.to_string())
+}
+
+// Delay before attempt `attempts + 1` of a copy
+fn backoff(config: &ReplicationConfig, attempts: u32) -> Duration {
+    let factor = 2u64.saturating_pow(attempts.saturating_sub(1));
+    Duration::from_secs(
+        config
+            .retry_base_secs
+            .saturating_mul(factor)
+            .min(config.retry_max_secs),
+    )
+}
+
+fn replication_error(e: sqlx::Error) -> S3Error {
+    tracing::error!("Error replicating object: {:?}", e);
+    S3Error::InternalError
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let config = ReplicationConfig::default();
+        assert_eq!(backoff(&config, 1), Duration::from_secs(5));
+        assert_eq!(backoff(&config, 3), Duration::from_secs(20));
+        assert_eq!(backoff(&config, 40), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_replication_status() {
+        let config = ReplicationConfiguration::from_xml(
+            br#"<ReplicationConfiguration>
+                <Rule>
+                    <Status>Enabled</Status>
+                    <Filter><Prefix>logs/</Prefix></Filter>
+                    <Destination><Bucket>arn:aws:s3:::archive</Bucket></Destination>
+                </Rule>
+            </ReplicationConfiguration>"#,
+        )
+        .unwrap();
+        let bucket = types::Bucket {
+            replication: Some(config),
+            ..Default::default()
+        };
+        let object = |key: &str, is_delete_marker| types::Object {
+            key: key.to_string(),
+            is_delete_marker,
+            ..Default::default()
+        };
+        assert_eq!(
+            replication_status(&bucket, &object("logs/a", false)).as_deref(),
+            Some(REPLICATION_PENDING)
+        );
+        assert_eq!(replication_status(&bucket, &object("data/a", false)), None);
+        // Delete markers need DeleteMarkerReplication
+        assert_eq!(replication_status(&bucket, &object("logs/a", true)), None);
+        assert_eq!(
+            replication_status(&types::Bucket::default(), &object("logs/a", false)),
+            None
+        );
+
+        // Markers of lifecycle expiration only know the bucket configuration
+        let config = ReplicationConfiguration::from_xml(
+            br#"<ReplicationConfiguration>
+                <Rule>
+                    <Status>Enabled</Status>
+                    <DeleteMarkerReplication><Status>Enabled</Status></DeleteMarkerReplication>
+                    <Destination><Bucket>arn:aws:s3:::archive</Bucket></Destination>
+                </Rule>
+            </ReplicationConfiguration>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            pending_replication(Some(&config), &object("logs/a", true)).as_deref(),
+            Some(REPLICATION_PENDING)
+        );
+        assert_eq!(pending_replication(None, &object("logs/a", true)), None);
+    }
+}
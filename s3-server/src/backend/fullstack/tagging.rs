@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use s3_core::{
+    response::ResponseData,
+    tagging::{Tagging, MAX_BUCKET_TAGS, MAX_OBJECT_TAGS},
+    S3Error,
+};
+
+use crate::{backend::types, filter::S3Data};
+
+use super::{notification::request_event, FullstackBackend};
+
+static TAGGING_HEADER: &str = "x-amz-tagging";
+static TAGGING_DIRECTIVE_HEADER: &str = "x-amz-tagging-directive";
+pub(super) static TAGGING_COUNT_HEADER: &str = "x-amz-tagging-count";
+
+// Object tags are matched by lifecycle rule filters. The gateway does not
+// evaluate bucket policies, PutBucketPolicy is not implemented, so there is
+// nothing yet to apply the s3:ExistingObjectTag and s3:RequestObjectTag
+// condition keys to.
+impl FullstackBackend {
+    pub async fn put_object_tagging(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let tags = Tagging::from_xml(data.req.body(), MAX_OBJECT_TAGS)?.into_map();
+        let object = self.latest_object(bucket, &data.key).await?;
+        self.update_object_tags(&object, &tags).await?;
+        let event = request_event("s3:ObjectTagging:Put", data, bucket, &object);
+        self.notify(bucket.notification.as_ref(), event).await;
+
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn get_object_tagging(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let object = self.latest_object(bucket, &data.key).await?;
+
+        // An object without tags has an empty tag set
+        data.res
+            .with_bytes(Tagging::from_map(&object.tags).to_xml().into());
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn delete_object_tagging(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let object = self.latest_object(bucket, &data.key).await?;
+        self.update_object_tags(&object, &HashMap::new()).await?;
+        let event = request_event("s3:ObjectTagging:Delete", data, bucket, &object);
+        self.notify(bucket.notification.as_ref(), event).await;
+
+        data.res.with_status_code(204);
+        Ok(data.res.clone())
+    }
+
+    pub async fn put_bucket_tagging(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let tags = Tagging::from_xml(data.req.body(), MAX_BUCKET_TAGS)?.into_map();
+        let tags = serde_json::to_value(&tags).map_err(|e| {
+            tracing::error!("Error serializing bucket tags: {:?}", e);
+            S3Error::InternalError
+        })?;
+        self.database
+            .update_bucket_tags(bucket.id, Some(tags))
+            .await
+            .map_err(|e| {
+                tracing::error!("Error updating bucket tags: {:?}", e);
+                S3Error::InternalError
+            })?;
+
+        data.res.with_status_code(204);
+        Ok(data.res.clone())
+    }
+
+    pub async fn get_bucket_tagging(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        if bucket.tags.is_empty() {
+            return Err(S3Error::NoSuchTagSet(bucket.name.clone()));
+        }
+
+        data.res
+            .with_bytes(Tagging::from_map(&bucket.tags).to_xml().into());
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn delete_bucket_tagging(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        self.database
+            .update_bucket_tags(bucket.id, None)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting bucket tags: {:?}", e);
+                S3Error::InternalError
+            })?;
+
+        data.res.with_status_code(204);
+        Ok(data.res.clone())
+    }
+
+    async fn update_object_tags(
+        &self,
+        object: &types::Object,
+        tags: &HashMap<String, String>,
+    ) -> Result<(), S3Error> {
+        let value = if tags.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_value(tags).map_err(|e| {
+                tracing::error!("Error serializing object tags: {:?}", e);
+                S3Error::InternalError
+            })?)
+        };
+        let updated = self
+            .database
+            .update_object_tags(object, value)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error updating object tags: {:?}", e);
+                S3Error::InternalError
+            })?;
+        // The version went away since it was read
+        if !updated {
+            return Err(S3Error::NoSuchKey(object.key.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// Tags given in the x-amz-tagging header of an upload.
+pub(super) fn request_tags(data: &S3Data) -> Result<HashMap<String, String>, S3Error> {
+    match data.req.headers().get(TAGGING_HEADER) {
+        Some(value) => {
+            let value = value.to_str().map_err(|_| {
+                S3Error::InvalidArgument(format!("The header {} is invalid", TAGGING_HEADER))
+            })?;
+            Ok(Tagging::from_header(value)?.into_map())
+        }
+        None => Ok(HashMap::new()),
+    }
+}
+
+/// Tags of the copy made by a CopyObject: those of the source, or those of
+/// its x-amz-tagging header when x-amz-tagging-directive is REPLACE.
+pub(super) fn copy_tags(
+    data: &S3Data,
+    source: &HashMap<String, String>,
+) -> Result<HashMap<String, String>, S3Error> {
+    let directive = data
+        .req
+        .headers()
+        .get(TAGGING_DIRECTIVE_HEADER)
+        .map(|value| value.to_str().unwrap_or_default());
+    match directive {
+        None | Some("COPY") => Ok(source.clone()),
+        Some("REPLACE") => request_tags(data),
+        Some(directive) => Err(S3Error::InvalidArgument(format!(
+            "Unknown {} {}",
+            TAGGING_DIRECTIVE_HEADER, directive
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_tags() {
+        let mut data = S3Data::new();
+        assert!(request_tags(&data).unwrap().is_empty());
+
+        data.req.headers_mut().insert(
+            TAGGING_HEADER,
+            "env=prod&owner=data%2Fteam".parse().unwrap(),
+        );
+        let tags = request_tags(&data).unwrap();
+        assert_eq!(tags["env"], "prod");
+        assert_eq!(tags["owner"], "data/team");
+
+        let too_many = (0..=MAX_OBJECT_TAGS)
+            .map(|i| format!("k{}=v", i))
+            .collect::<Vec<_>>()
+            .join("&");
+        data.req
+            .headers_mut()
+            .insert(TAGGING_HEADER, too_many.parse().unwrap());
+        assert!(matches!(request_tags(&data), Err(S3Error::InvalidTag(_))));
+    }
+
+    #[test]
+    fn test_copy_tags() {
+        let source = HashMap::from([("env".to_string(), "prod".to_string())]);
+        let mut data = S3Data::new();
+        data.req
+            .headers_mut()
+            .insert(TAGGING_HEADER, "env=test".parse().unwrap());
+        // The header only counts with the REPLACE directive
+        assert_eq!(copy_tags(&data, &source).unwrap(), source);
+
+        data.req
+            .headers_mut()
+            .insert(TAGGING_DIRECTIVE_HEADER, "REPLACE".parse().unwrap());
+        assert_eq!(copy_tags(&data, &source).unwrap()["env"], "test");
+        data.req.headers_mut().remove(TAGGING_HEADER);
+        assert!(copy_tags(&data, &source).unwrap().is_empty());
+
+        data.req
+            .headers_mut()
+            .insert(TAGGING_DIRECTIVE_HEADER, "MERGE".parse().unwrap());
+        assert!(copy_tags(&data, &source).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use s3_core::{
+    response::ResponseData,
+    versioning::{
+        VersioningConfiguration, VersioningStatus, VERSIONING_DISABLED, VERSIONING_SUSPENDED,
+    },
+    S3Error,
+};
+
+use crate::filter::S3Data;
+
+use super::FullstackBackend;
+
+impl FullstackBackend {
+    pub async fn put_bucket_versioning(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let config = VersioningConfiguration::from_xml(data.req.body())?;
+        // MFA delete needs the x-amz-mfa header, which is not supported
+        if config.mfa_delete_enabled() {
+            return Err(S3Error::NotImplemented);
+        }
+        let status = config.versioning_status()?;
+        check_versioning_change(status, bucket.object_lock.is_some())?;
+        // A configuration without Status leaves versioning as it is
+        if status != VERSIONING_DISABLED && bucket.versioning != status as i16 {
+            self.database
+                .update_bucket_versioning(bucket.id, status as i16)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Error updating bucket versioning: {:?}", e);
+                    S3Error::InternalError
+                })?;
+        }
+
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn get_bucket_versioning(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+
+        // A bucket that was never versioned has an empty configuration
+        let config = VersioningConfiguration::new(bucket.versioning as VersioningStatus);
+        data.res.with_bytes(config.to_xml().into());
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+}
+
+// Versioning can be enabled on any bucket but never turned off again.
+// Suspending needs the null version of S3, which the index does not have.
+fn check_versioning_change(requested: VersioningStatus, object_lock: bool) -> Result<(), S3Error> {
+    match requested {
+        VERSIONING_SUSPENDED if object_lock => Err(S3Error::InvalidBucketState(
+            "An Object Lock configuration is present on this bucket, so the versioning state cannot be changed."
+                .to_string(),
+        )),
+        VERSIONING_SUSPENDED => Err(S3Error::NotImplemented),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use s3_core::versioning::VERSIONING_ENABLED;
+
+    use super::*;
+
+    #[test]
+    fn test_check_versioning_change() {
+        assert!(check_versioning_change(VERSIONING_ENABLED, false).is_ok());
+        assert!(check_versioning_change(VERSIONING_ENABLED, true).is_ok());
+        // No Status leaves the bucket as it is
+        assert!(check_versioning_change(VERSIONING_DISABLED, true).is_ok());
+        assert!(matches!(
+            check_versioning_change(VERSIONING_SUSPENDED, true),
+            Err(S3Error::InvalidBucketState(_))
+        ));
+        assert!(matches!(
+            check_versioning_change(VERSIONING_SUSPENDED, false),
+            Err(S3Error::NotImplemented)
+        ));
+    }
+}
@@ -0,0 +1,249 @@
+use axum::http::{header, HeaderMap, Method};
+use s3_core::{
+    response::ResponseData,
+    website::{RoutingRule, WebsiteConfiguration},
+    S3Error,
+};
+
+use crate::{backend::types, filter::S3Data};
+
+use super::FullstackBackend;
+
+static WEBSITE_REDIRECT_HEADER: &str = "x-amz-website-redirect-location";
+
+// Longest x-amz-website-redirect-location S3 accepts
+static MAX_REDIRECT_LOCATION_LENGTH: usize = 2048;
+
+impl FullstackBackend {
+    pub async fn put_bucket_website(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let website = WebsiteConfiguration::from_xml(data.req.body())?;
+        let website = serde_json::to_value(&website).map_err(|e| {
+            tracing::error!("Error serializing website configuration: {:?}", e);
+            S3Error::InternalError
+        })?;
+        self.database
+            .update_bucket_website(bucket.id, Some(website))
+            .await
+            .map_err(|e| {
+                tracing::error!("Error updating bucket website: {:?}", e);
+                S3Error::InternalError
+            })?;
+
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn get_bucket_website(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let website = bucket
+            .website
+            .as_ref()
+            .ok_or(S3Error::NoSuchWebsiteConfiguration(bucket.name.clone()))?;
+
+        data.res.with_bytes(website.to_xml().into());
+        data.res.with_status_code(200);
+        Ok(data.res.clone())
+    }
+
+    pub async fn delete_bucket_website(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        self.database
+            .update_bucket_website(bucket.id, None)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting bucket website: {:?}", e);
+                S3Error::InternalError
+            })?;
+
+        data.res.with_status_code(204);
+        Ok(data.res.clone())
+    }
+
+    /// Serve an unauthenticated GET or HEAD of the website endpoint. Errors
+    /// are redirected by the routing rules or answered with the error
+    /// document where configured, the caller renders the others as HTML.
+    pub async fn website_request(&self, data: &mut S3Data) -> Result<ResponseData, S3Error> {
+        if data.req.method() != Method::GET && data.req.method() != Method::HEAD {
+            return Err(S3Error::MethodNotAllowed);
+        }
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let website = bucket
+            .website
+            .clone()
+            .ok_or(S3Error::NoSuchWebsiteConfiguration(bucket.name.clone()))?;
+        let protocol = if data.secure { "https" } else { "http" };
+        let key = data.key.clone();
+
+        if let Some(redirect) = &website.redirect_all_requests_to {
+            let location = redirect.location(&key, protocol);
+            return Ok(redirect_response(data, 301, location));
+        }
+        if let Some(rule) = website.routing_rule(&key, None) {
+            return Ok(rule_redirect(data, rule, &key, protocol));
+        }
+
+        let error = match self.serve_website_key(data, &website, &key).await {
+            Ok(response) => return Ok(response),
+            Err(e) => e,
+        };
+        let status = error.status_code();
+        if let Some(rule) = website.routing_rule(&key, Some(status)) {
+            return Ok(rule_redirect(data, rule, &key, protocol));
+        }
+        match &website.error_document {
+            Some(document) if (400..500).contains(&status) => {
+                self.serve_error_document(data, &document.key, status)
+                    .await
+                    // A missing error document leaves the original error
+                    .map_err(|_| error)
+            }
+            _ => Err(error),
+        }
+    }
+
+    async fn serve_website_key(
+        &self,
+        data: &mut S3Data,
+        website: &WebsiteConfiguration,
+        key: &str,
+    ) -> Result<ResponseData, S3Error> {
+        let bucket = data
+            .bucket
+            .as_ref()
+            .ok_or(S3Error::NoSuchBucket(data.bucket_name.clone()))?;
+        let object_key = website.index_key(key).unwrap_or(key.to_string());
+        let object = match self.latest_object(bucket, &object_key).await {
+            Ok(object) => object,
+            // A "directory" requested without its trailing slash
+            Err(S3Error::NoSuchKey(_)) if object_key == key => {
+                let index_key = website.index_key(&format!("{}/", key));
+                match index_key {
+                    Some(index_key) if self.latest_object(bucket, &index_key).await.is_ok() => {
+                        return Ok(redirect_response(data, 302, format!("/{}/", key)));
+                    }
+                    _ => return Err(S3Error::NoSuchKey(key.to_string())),
+                }
+            }
+            Err(e) => return Err(e),
+        };
+        if let Some(location) = object.website_redirect_location {
+            return Ok(redirect_response(data, 301, location));
+        }
+
+        data.key = object_key;
+        if data.req.method() == Method::HEAD {
+            self.head_object(data).await
+        } else {
+            self.get_object(data).await
+        }
+    }
+
+    // The error document is served whole with the status of the error
+    async fn serve_error_document(
+        &self,
+        data: &mut S3Data,
+        key: &str,
+        status: u16,
+    ) -> Result<ResponseData, S3Error> {
+        data.req.headers_mut().remove(header::RANGE);
+        data.res = ResponseData::new();
+        data.key = key.to_string();
+        let mut response = if data.req.method() == Method::HEAD {
+            self.head_object(data).await?
+        } else {
+            self.get_object(data).await?
+        };
+        response.with_status_code(status);
+        Ok(response)
+    }
+}
+
+/// Redirect location given in the x-amz-website-redirect-location header of
+/// an upload.
+pub(super) fn request_redirect_location(headers: &HeaderMap) -> Result<Option<String>, S3Error> {
+    let location = match headers.get(WEBSITE_REDIRECT_HEADER) {
+        Some(location) => location.to_str().map_err(|_| {
+            S3Error::InvalidArgument(format!("The header {} is invalid", WEBSITE_REDIRECT_HEADER))
+        })?,
+        None => return Ok(None),
+    };
+    if !(location.starts_with('/')
+        || location.starts_with("http://")
+        || location.starts_with("https://"))
+    {
+        return Err(S3Error::InvalidArgument(
+            "The website redirect location must have a prefix of 'http://' or 'https://' or '/'"
+                .to_string(),
+        ));
+    }
+    if location.len() > MAX_REDIRECT_LOCATION_LENGTH {
+        return Err(S3Error::InvalidArgument(
+            "The length of website redirect location cannot exceed 2,048 characters".to_string(),
+        ));
+    }
+    Ok(Some(location.to_string()))
+}
+
+/// Report the redirect of a version in the headers of a response.
+pub(super) fn website_headers(res: &mut ResponseData, object: &types::Object) {
+    if let Some(location) = &object.website_redirect_location {
+        res.with_header(WEBSITE_REDIRECT_HEADER.to_string(), location.clone());
+    }
+}
+
+fn rule_redirect(data: &mut S3Data, rule: &RoutingRule, key: &str, protocol: &str) -> ResponseData {
+    let host = data
+        .req
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let location = rule.location(key, &host, protocol);
+    redirect_response(data, rule.status_code(), location)
+}
+
+fn redirect_response(data: &mut S3Data, status: u16, location: String) -> ResponseData {
+    data.res
+        .with_header("Location".to_string(), location)
+        .with_status_code(status);
+    data.res.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_redirect_location() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(request_redirect_location(&headers).unwrap(), None);
+
+        for location in ["/other.html", "https://example.com/page"] {
+            headers.insert(WEBSITE_REDIRECT_HEADER, location.parse().unwrap());
+            assert_eq!(
+                request_redirect_location(&headers).unwrap().as_deref(),
+                Some(location)
+            );
+        }
+
+        headers.insert(WEBSITE_REDIRECT_HEADER, "other.html".parse().unwrap());
+        assert!(request_redirect_location(&headers).is_err());
+        let long = format!("/{}", "a".repeat(MAX_REDIRECT_LOCATION_LENGTH));
+        headers.insert(WEBSITE_REDIRECT_HEADER, long.parse().unwrap());
+        assert!(request_redirect_location(&headers).is_err());
+    }
+}
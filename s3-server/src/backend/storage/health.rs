@@ -0,0 +1,315 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use uuid::Uuid;
+
+use crate::config::CircuitConfig;
+
+// Read latencies kept per backend for the hedging quantile
+const LATENCY_SAMPLES: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CircuitState {
+    // Reads go through, failures since window_start are counted
+    Closed {
+        failures: u32,
+        window_start: Instant,
+    },
+    // Reads are refused until the sleep window has passed
+    Open {
+        since: Instant,
+    },
+    // Trial reads go through, enough successes close the circuit again and
+    // a failure opens it
+    HalfOpen {
+        successes: u32,
+    },
+}
+
+/// Outcome of the last health probe of a backend.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeResult {
+    pub at: DateTime<Utc>,
+    pub latency_ms: u64,
+    // None when the backend answered
+    pub error: Option<String>,
+}
+
+/// Health of a storage backend as reported by the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendStatus {
+    pub id: Uuid,
+    pub name: String,
+    pub default: bool,
+    pub circuit: &'static str,
+    pub last_probe: Option<ProbeResult>,
+}
+
+/// Circuit breaker, read latencies and last probe of a storage backend.
+///
+/// The circuit opens once `open_threshold` requests failed within
+/// `check_interval`, and refuses requests for `close_sleep_window`. It then
+/// lets requests through again and closes after `close_required_count` of
+/// them succeeded in a row. Reads, writes and the periodic health probes all
+/// count.
+pub struct BackendHealth {
+    config: CircuitConfig,
+    state: Mutex<CircuitState>,
+    latencies: Mutex<VecDeque<Duration>>,
+    permits: Option<Arc<Semaphore>>,
+    probe: Mutex<Option<ProbeResult>>,
+}
+
+impl BackendHealth {
+    pub fn new(config: CircuitConfig) -> Self {
+        let permits = usize::try_from(config.exec_max_concurrent)
+            .ok()
+            .map(|permits| Arc::new(Semaphore::new(permits)));
+        Self {
+            config,
+            state: Mutex::new(CircuitState::Closed {
+                failures: 0,
+                window_start: Instant::now(),
+            }),
+            latencies: Mutex::new(VecDeque::with_capacity(LATENCY_SAMPLES)),
+            permits,
+            probe: Mutex::new(None),
+        }
+    }
+
+    /// Time a read may take to start answering before it counts as failed.
+    pub fn exec_timeout(&self) -> Duration {
+        Duration::from_secs(self.config.exec_timeout)
+    }
+
+    /// Whether a read may be sent to the backend now.
+    pub fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            CircuitState::Open { since }
+                if since.elapsed() >= Duration::from_secs(self.config.close_sleep_window) =>
+            {
+                *state = CircuitState::HalfOpen { successes: 0 };
+                true
+            }
+            CircuitState::Open { .. } => false,
+            _ => true,
+        }
+    }
+
+    /// A slot among the concurrent reads allowed on the backend, None when
+    /// they are all taken. Reads without a limit always get one.
+    pub fn try_acquire(&self) -> Option<Option<OwnedSemaphorePermit>> {
+        match &self.permits {
+            Some(permits) => permits.clone().try_acquire_owned().ok().map(Some),
+            None => Some(None),
+        }
+    }
+
+    /// A read answered after `latency`.
+    pub fn record_success(&self, latency: Duration) {
+        self.record_available();
+
+        let mut latencies = self.latencies.lock().unwrap();
+        if latencies.len() == LATENCY_SAMPLES {
+            latencies.pop_front();
+        }
+        latencies.push_back(latency);
+    }
+
+    /// A request other than a read succeeded.
+    pub fn record_available(&self) {
+        let mut state = self.state.lock().unwrap();
+        if let CircuitState::HalfOpen { successes } = *state {
+            *state = if successes + 1 >= self.config.close_required_count {
+                CircuitState::Closed {
+                    failures: 0,
+                    window_start: Instant::now(),
+                }
+            } else {
+                CircuitState::HalfOpen {
+                    successes: successes + 1,
+                }
+            };
+        }
+    }
+
+    /// A health probe answered after `latency`, or failed with `error`. A
+    /// probe counts as a trial request once the sleep window has passed.
+    pub fn record_probe(&self, latency: Duration, error: Option<String>) {
+        match error {
+            Some(_) => self.record_failure(),
+            None if self.allow() => self.record_available(),
+            None => {}
+        }
+        *self.probe.lock().unwrap() = Some(ProbeResult {
+            at: Utc::now(),
+            latency_ms: latency.as_millis() as u64,
+            error,
+        });
+    }
+
+    pub fn last_probe(&self) -> Option<ProbeResult> {
+        self.probe.lock().unwrap().clone()
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        *state = match *state {
+            CircuitState::Closed {
+                failures,
+                window_start,
+            } => {
+                let (failures, window_start) =
+                    if now.duration_since(window_start) > self.check_interval() {
+                        (1, now)
+                    } else {
+                        (failures + 1, window_start)
+                    };
+                if failures >= self.config.open_threshold {
+                    CircuitState::Open { since: now }
+                } else {
+                    CircuitState::Closed {
+                        failures,
+                        window_start,
+                    }
+                }
+            }
+            CircuitState::HalfOpen { .. } => CircuitState::Open { since: now },
+            open => open,
+        };
+    }
+
+    /// closed, open or half_open
+    pub fn circuit(&self) -> &'static str {
+        match *self.state.lock().unwrap() {
+            CircuitState::Closed { .. } => "closed",
+            CircuitState::Open { .. } => "open",
+            CircuitState::HalfOpen { .. } => "half_open",
+        }
+    }
+
+    /// The `quantile` of the latencies of recent successful reads, None until
+    /// `min_samples` reads have been seen.
+    pub fn latency_quantile(&self, quantile: f64, min_samples: usize) -> Option<Duration> {
+        let mut latencies: Vec<_> = self.latencies.lock().unwrap().iter().copied().collect();
+        if latencies.is_empty() || latencies.len() < min_samples {
+            return None;
+        }
+        latencies.sort_unstable();
+        let index = ((latencies.len() - 1) as f64 * quantile.clamp(0.0, 1.0)).round() as usize;
+        Some(latencies[index])
+    }
+
+    fn check_interval(&self) -> Duration {
+        Duration::from_secs(self.config.check_interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> CircuitConfig {
+        CircuitConfig {
+            check_interval: 60,
+            close_sleep_window: 0,
+            close_required_count: 2,
+            open_threshold: 2,
+            exec_timeout: 5,
+            exec_max_concurrent: 1,
+        }
+    }
+
+    #[test]
+    fn test_circuit() {
+        let health = BackendHealth::new(CircuitConfig {
+            close_sleep_window: 3600,
+            ..config()
+        });
+        health.record_failure();
+        assert_eq!(health.circuit(), "closed");
+        health.record_failure();
+        assert_eq!(health.circuit(), "open");
+        assert!(!health.allow());
+
+        // Trial reads close the circuit once enough of them succeed
+        let health = BackendHealth::new(config());
+        health.record_failure();
+        health.record_failure();
+        assert!(health.allow());
+        assert_eq!(health.circuit(), "half_open");
+        health.record_success(Duration::from_millis(1));
+        assert_eq!(health.circuit(), "half_open");
+        health.record_success(Duration::from_millis(1));
+        assert_eq!(health.circuit(), "closed");
+
+        // A failed trial opens it again
+        health.record_failure();
+        health.record_failure();
+        assert!(health.allow());
+        health.record_failure();
+        assert_eq!(health.circuit(), "open");
+    }
+
+    #[test]
+    fn test_permits() {
+        let health = BackendHealth::new(config());
+        let permit = health.try_acquire().unwrap();
+        assert!(permit.is_some());
+        assert!(health.try_acquire().is_none());
+        drop(permit);
+        assert!(health.try_acquire().is_some());
+
+        let health = BackendHealth::new(CircuitConfig {
+            exec_max_concurrent: -1,
+            ..config()
+        });
+        assert!(health.try_acquire().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_probes() {
+        let health = BackendHealth::new(config());
+        assert!(health.last_probe().is_none());
+        health.record_probe(Duration::from_millis(3), Some("timed out".to_string()));
+        health.record_probe(Duration::from_millis(3), Some("timed out".to_string()));
+        assert_eq!(health.circuit(), "open");
+        let probe = health.last_probe().unwrap();
+        assert_eq!(probe.latency_ms, 3);
+        assert_eq!(probe.error.as_deref(), Some("timed out"));
+
+        // Probes close the circuit without counting as read latencies
+        health.record_probe(Duration::from_millis(1), None);
+        assert_eq!(health.circuit(), "half_open");
+        health.record_probe(Duration::from_millis(1), None);
+        assert_eq!(health.circuit(), "closed");
+        assert!(health.last_probe().unwrap().error.is_none());
+        assert_eq!(health.latency_quantile(0.5, 1), None);
+    }
+
+    #[test]
+    fn test_latency_quantile() {
+        let health = BackendHealth::new(config());
+        assert_eq!(health.latency_quantile(0.99, 1), None);
+        for ms in 1..=100 {
+            health.record_success(Duration::from_millis(ms));
+        }
+        assert_eq!(health.latency_quantile(0.99, 200), None);
+        assert_eq!(
+            health.latency_quantile(0.99, 100),
+            Some(Duration::from_millis(99))
+        );
+        assert_eq!(
+            health.latency_quantile(0.5, 100),
+            Some(Duration::from_millis(51))
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use aws_sdk_s3::primitives::{ByteStream, Length};
+use axum::async_trait;
+use s3_core::S3Error;
+use serde::Deserialize;
+use sha2::Digest;
+use tokio::{fs, io::AsyncWriteExt};
+
+use super::FileStorage;
+
+const TMP_DIR: &str = ".tmp";
+const MULTIPART_DIR: &str = ".multipart";
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FsyncMode {
+    // Leave flushing to the OS, data written shortly before a crash may be lost
+    None,
+    // Flush every blob before it becomes visible
+    #[default]
+    File,
+    // Also flush the directory entry, so the rename survives a crash
+    Full,
+}
+
+// Provider specific configuration of a LOCAL backend
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LocalConfig {
+    pub path: PathBuf,
+    pub fsync: FsyncMode,
+}
+
+impl Default for LocalConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("/var/lib/rust-gateway/data"),
+            fsync: FsyncMode::File,
+        }
+    }
+}
+
+/// Blobs stored as files under a root directory.
+///
+/// Every bucket is a directory, and a blob lives at
+/// `<bucket>/<aa>/<bb>/<sha256 of the key>` so that no directory grows past
+/// a few thousand entries. Files are written under `<bucket>/.tmp` and
+/// renamed into place, so readers never see a partial blob. Multipart parts
+/// are staged under `<bucket>/.multipart/<upload id>`.
+pub struct LocalStorage {
+    root: PathBuf,
+    fsync: FsyncMode,
+}
+
+impl LocalStorage {
+    pub fn new(config: LocalConfig) -> Result<Self, S3Error> {
+        std::fs::create_dir_all(&config.path).map_err(|e| {
+            tracing::error!("Error creating storage root {:?}: {:?}", config.path, e);
+            S3Error::InternalError
+        })?;
+        Ok(Self {
+            root: config.path,
+            fsync: config.fsync,
+        })
+    }
+
+    fn bucket_dir(&self, bucket: &str) -> Result<PathBuf, S3Error> {
+        Ok(self.root.join(path_component(bucket)?))
+    }
+
+    fn blob_path(&self, bucket: &str, key: &str) -> Result<PathBuf, S3Error> {
+        Ok(self.bucket_dir(bucket)?.join(shard_path(key)))
+    }
+
+    fn upload_dir(&self, bucket: &str, upload_id: &str) -> Result<PathBuf, S3Error> {
+        Ok(self
+            .bucket_dir(bucket)?
+            .join(MULTIPART_DIR)
+            .join(path_component(upload_id)?))
+    }
+
+    async fn create_temp(&self, bucket: &str) -> Result<(fs::File, PathBuf), S3Error> {
+        let dir = self.bucket_dir(bucket)?.join(TMP_DIR);
+        fs::create_dir_all(&dir).await.map_err(io_error)?;
+        let path = dir.join(uuid::Uuid::new_v4().to_string());
+        let file = fs::File::create(&path).await.map_err(io_error)?;
+        Ok((file, path))
+    }
+
+    // Flush a fully written temp file and move it to `dest`
+    async fn commit_temp(
+        &self,
+        mut file: fs::File,
+        tmp: &Path,
+        dest: &Path,
+    ) -> Result<(), S3Error> {
+        file.flush().await.map_err(io_error)?;
+        if self.fsync != FsyncMode::None {
+            file.sync_all().await.map_err(io_error)?;
+        }
+        drop(file);
+
+        let parent = dest.parent().unwrap_or(&self.root);
+        fs::create_dir_all(parent).await.map_err(io_error)?;
+        fs::rename(tmp, dest).await.map_err(io_error)?;
+        if self.fsync == FsyncMode::Full {
+            let dir = fs::File::open(parent).await.map_err(io_error)?;
+            dir.sync_all().await.map_err(io_error)?;
+        }
+        Ok(())
+    }
+
+    async fn write_file(&self, bucket: &str, dest: &Path, data: ByteStream) -> Result<(), S3Error> {
+        let (mut file, tmp) = self.create_temp(bucket).await?;
+        let mut reader = data.into_async_read();
+        let result = match tokio::io::copy_buf(&mut reader, &mut file).await {
+            Ok(_) => self.commit_temp(file, &tmp, dest).await,
+            Err(e) => Err(io_error(e)),
+        };
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp).await;
+        }
+        result
+    }
+
+    async fn read_range(
+        &self,
+        bucket: &str,
+        key: &str,
+        range: Option<(u64, u64)>,
+    ) -> Result<ByteStream, S3Error> {
+        let path = self.blob_path(bucket, key)?;
+        let size = match fs::metadata(&path).await {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(S3Error::NoSuchKey(key.to_string()))
+            }
+            Err(e) => return Err(io_error(e)),
+        };
+
+        let (offset, length) = match range {
+            None => (0, size),
+            // Inclusive end, as in an HTTP Range header
+            Some((start, end)) if start < size && start <= end => {
+                (start, end.min(size - 1) - start + 1)
+            }
+            Some(_) => {
+                return Err(S3Error::InvalidArgument(
+                    "The requested range is not satisfiable".to_string(),
+                ))
+            }
+        };
+        ByteStream::read_from()
+            .path(&path)
+            .offset(offset)
+            .length(Length::Exact(length))
+            .build()
+            .await
+            .map_err(|e| {
+                tracing::error!("Error reading {:?}: {:?}", path, e);
+                S3Error::InternalError
+            })
+    }
+}
+
+#[async_trait]
+impl FileStorage for LocalStorage {
+    async fn get_file(&self, bucket: &str, key: &str) -> Result<ByteStream, S3Error> {
+        self.read_range(bucket, key, None).await
+    }
+
+    async fn get_file_range(
+        &self,
+        bucket: &str,
+        key: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<ByteStream, S3Error> {
+        self.read_range(bucket, key, Some((start, end))).await
+    }
+
+    async fn save_file(&self, bucket: &str, key: &str, data: ByteStream) -> Result<(), S3Error> {
+        let dest = self.blob_path(bucket, key)?;
+        self.write_file(bucket, &dest, data).await
+    }
+
+    async fn delete_file(&self, bucket: &str, key: &str) -> Result<(), S3Error> {
+        match fs::remove_file(self.blob_path(bucket, key)?).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(io_error(e)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn probe(&self) -> Result<(), S3Error> {
+        let metadata = fs::metadata(&self.root).await.map_err(io_error)?;
+        if !metadata.is_dir() {
+            tracing::error!("Storage root {:?} is not a directory", self.root);
+            return Err(S3Error::InternalError);
+        }
+        Ok(())
+    }
+
+    async fn save_part(
+        &self,
+        bucket: &str,
+        upload_id: &str,
+        part_number: u32,
+        data: ByteStream,
+    ) -> Result<(), S3Error> {
+        let dest = self
+            .upload_dir(bucket, upload_id)?
+            .join(part_number.to_string());
+        self.write_file(bucket, &dest, data).await
+    }
+
+    async fn complete_multipart(
+        &self,
+        bucket: &str,
+        upload_id: &str,
+        parts: &[u32],
+        key: &str,
+    ) -> Result<(), S3Error> {
+        let upload_dir = self.upload_dir(bucket, upload_id)?;
+        let dest = self.blob_path(bucket, key)?;
+        let (mut file, tmp) = self.create_temp(bucket).await?;
+
+        let mut result = Ok(());
+        for part in parts {
+            let part_path = upload_dir.join(part.to_string());
+            result = match fs::File::open(&part_path).await {
+                Ok(mut part_file) => tokio::io::copy(&mut part_file, &mut file)
+                    .await
+                    .map(|_| ())
+                    .map_err(io_error),
+                Err(e) if e.kind() == ErrorKind::NotFound => Err(S3Error::InvalidArgument(
+                    format!("Part {} was not uploaded", part),
+                )),
+                Err(e) => Err(io_error(e)),
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        if result.is_ok() {
+            result = self.commit_temp(file, &tmp, &dest).await;
+        }
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp).await;
+            return result;
+        }
+        self.abort_multipart(bucket, upload_id, parts).await
+    }
+
+    async fn abort_multipart(
+        &self,
+        bucket: &str,
+        upload_id: &str,
+        _parts: &[u32],
+    ) -> Result<(), S3Error> {
+        match fs::remove_dir_all(self.upload_dir(bucket, upload_id)?).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(io_error(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+// `<aa>/<bb>/<sha256>` of a key. Hashing keeps arbitrary keys out of the
+// file system namespace and spreads blobs evenly over the shard directories.
+fn shard_path(key: &str) -> PathBuf {
+    let hash = const_hex::encode(sha2::Sha256::digest(key.as_bytes()));
+    [&hash[0..2], &hash[2..4], hash.as_str()].iter().collect()
+}
+
+// Bucket names and upload ids become directory names as they are
+fn path_component(name: &str) -> Result<&str, S3Error> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\', '\0']) {
+        return Err(S3Error::InvalidArgument(format!(
+            "Invalid path component {}",
+            name
+        )));
+    }
+    Ok(name)
+}
+
+fn io_error(e: std::io::Error) -> S3Error {
+    tracing::error!("Error accessing local storage: {:?}", e);
+    S3Error::InternalError
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_path() {
+        let path = shard_path("0192d4b8-7c2a-7b3e-9f1a-3c5d7e9f1b2d");
+        let parts: Vec<_> = path.iter().map(|p| p.to_str().unwrap()).collect();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[2].len(), 64);
+        assert!(parts[2].starts_with(&format!("{}{}", parts[0], parts[1])));
+        assert_eq!(path, shard_path("0192d4b8-7c2a-7b3e-9f1a-3c5d7e9f1b2d"));
+        assert_ne!(path, shard_path("0192d4b8-7c2a-7b3e-9f1a-3c5d7e9f1b2e"));
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let root = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let storage = LocalStorage::new(LocalConfig {
+            path: root.clone(),
+            fsync: FsyncMode::Full,
+        })
+        .unwrap();
+        let read = |stream: ByteStream| async { stream.collect().await.unwrap().into_bytes() };
+
+        storage
+            .save_file("bucket", "key", ByteStream::from_static(b"hello world"))
+            .await
+            .unwrap();
+        let data = read(storage.get_file("bucket", "key").await.unwrap()).await;
+        assert_eq!(&data[..], b"hello world");
+        let data = read(
+            storage
+                .get_file_range("bucket", "key", 6, 100)
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(&data[..], b"world");
+        assert!(storage
+            .get_file_range("bucket", "key", 11, 20)
+            .await
+            .is_err());
+
+        storage
+            .save_part("bucket", "upload", 2, ByteStream::from_static(b"world"))
+            .await
+            .unwrap();
+        storage
+            .save_part("bucket", "upload", 1, ByteStream::from_static(b"hello "))
+            .await
+            .unwrap();
+        storage
+            .complete_multipart("bucket", "upload", &[1, 2], "assembled")
+            .await
+            .unwrap();
+        let data = read(storage.get_file("bucket", "assembled").await.unwrap()).await;
+        assert_eq!(&data[..], b"hello world");
+        assert!(!root
+            .join("bucket")
+            .join(MULTIPART_DIR)
+            .join("upload")
+            .exists());
+
+        storage.delete_file("bucket", "key").await.unwrap();
+        storage.delete_file("bucket", "key").await.unwrap();
+        assert!(matches!(
+            storage.get_file("bucket", "key").await,
+            Err(S3Error::NoSuchKey(_))
+        ));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_path_component() {
+        assert!(path_component("my-bucket").is_ok());
+        assert!(path_component("").is_err());
+        assert!(path_component("..").is_err());
+        assert!(path_component(".tmp").is_err());
+        assert!(path_component("a/b").is_err());
+    }
+}
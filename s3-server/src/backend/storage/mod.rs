@@ -1,6 +1,12 @@
+mod health;
+pub mod local;
+pub mod rados;
+mod registry;
 pub mod storage;
 use aws_sdk_s3::primitives::ByteStream;
 use axum::async_trait;
+pub use health::{BackendHealth, BackendStatus};
+pub use registry::StorageRegistry;
 use s3_core::S3Error;
 
 #[async_trait]
@@ -15,4 +21,47 @@ pub trait FileStorage: Send + Sync {
     ) -> Result<ByteStream, S3Error>;
     async fn save_file(&self, bucket: &str, key: &str, data: ByteStream) -> Result<(), S3Error>;
     async fn delete_file(&self, bucket: &str, key: &str) -> Result<(), S3Error>;
+    // Cheap request that only succeeds while the backend is reachable, sent
+    // by the health probes
+    async fn probe(&self) -> Result<(), S3Error>;
+
+    // Multipart uploads. Backends without a staging area keep every part as
+    // a blob of its own until the upload is completed.
+
+    async fn save_part(
+        &self,
+        bucket: &str,
+        upload_id: &str,
+        part_number: u32,
+        data: ByteStream,
+    ) -> Result<(), S3Error> {
+        self.save_file(bucket, &part_name(upload_id, part_number), data)
+            .await
+    }
+
+    // Assemble the parts, in order, into the blob `key` and drop them
+    async fn complete_multipart(
+        &self,
+        bucket: &str,
+        upload_id: &str,
+        parts: &[u32],
+        key: &str,
+    ) -> Result<(), S3Error>;
+
+    async fn abort_multipart(
+        &self,
+        bucket: &str,
+        upload_id: &str,
+        parts: &[u32],
+    ) -> Result<(), S3Error> {
+        for part in parts {
+            self.delete_file(bucket, &part_name(upload_id, *part))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+fn part_name(upload_id: &str, part_number: u32) -> String {
+    format!("{}/{}", upload_id, part_number)
 }
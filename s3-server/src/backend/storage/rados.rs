@@ -0,0 +1,485 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use aws_sdk_s3::primitives::ByteStream;
+use axum::{async_trait, body::Bytes};
+use ceph::{
+    ceph::{connect_to_ceph, IoCtx, Rados},
+    error::RadosError,
+};
+use hyper::body::{Body, Frame, SizeHint};
+use s3_core::S3Error;
+use serde::Deserialize;
+use tokio::task::JoinHandle;
+
+use super::{part_name, FileStorage};
+
+// Written on the head object once all stripes are in place, `<size>:<stripe size>`
+const LAYOUT_XATTR: &str = "gateway.layout";
+// Looked up by the health probes, never written
+const PROBE_OBJECT: &str = ".gateway-probe";
+
+// Provider specific configuration of a CEPH backend reached through librados
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RadosConfig {
+    pub pool: String,
+    pub user: String,
+    pub conf: String,
+    pub stripe_size: u64,
+}
+
+impl Default for RadosConfig {
+    fn default() -> Self {
+        Self {
+            pool: String::new(),
+            user: "admin".to_string(),
+            conf: "/etc/ceph/ceph.conf".to_string(),
+            stripe_size: 4 * 1024 * 1024,
+        }
+    }
+}
+
+/// Blobs stored directly in a RADOS pool.
+///
+/// A blob is striped over RADOS objects of `stripe_size` bytes. The first
+/// stripe is the head object `<bucket>/<key>`, the following ones are
+/// `<bucket>/<key>.<index as 8 hex digits>`. The head is written last and
+/// carries the blob size and stripe size in an xattr, so a blob without the
+/// xattr is treated as missing and the stripe size can change without
+/// breaking existing blobs.
+pub struct RadosStorage {
+    pool: Arc<Pool>,
+}
+
+// librados calls block, they run on the blocking thread pool
+struct Pool {
+    // Dropped before the cluster handle it belongs to
+    ioctx: IoCtx,
+    _cluster: Rados,
+    stripe_size: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Layout {
+    size: u64,
+    stripe_size: u64,
+}
+
+impl RadosStorage {
+    pub fn connect(config: RadosConfig) -> Result<Self, S3Error> {
+        if config.pool.is_empty() {
+            return Err(S3Error::InvalidArgument(
+                "A RADOS backend needs a pool".to_string(),
+            ));
+        }
+        let cluster = connect_to_ceph(&config.user, &config.conf).map_err(rados_error)?;
+        let ioctx = cluster.get_rados_ioctx(&config.pool).map_err(rados_error)?;
+        Ok(Self {
+            pool: Arc::new(Pool {
+                ioctx,
+                _cluster: cluster,
+                stripe_size: config.stripe_size.max(1),
+            }),
+        })
+    }
+
+    async fn blocking<T, F>(&self, f: F) -> Result<T, S3Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Pool) -> Result<T, S3Error> + Send + 'static,
+    {
+        let pool = self.pool.clone();
+        tokio::task::spawn_blocking(move || f(&pool))
+            .await
+            .map_err(|e| {
+                tracing::error!("RADOS task failed: {:?}", e);
+                S3Error::InternalError
+            })?
+    }
+
+    async fn read_range(
+        &self,
+        bucket: &str,
+        key: &str,
+        range: Option<(u64, u64)>,
+    ) -> Result<ByteStream, S3Error> {
+        let name = object_name(bucket, key);
+        let layout = {
+            let name = name.clone();
+            let key = key.to_string();
+            self.blocking(move |pool| pool.layout(&name)?.ok_or(S3Error::NoSuchKey(key)))
+                .await?
+        };
+        let (offset, length) = match range {
+            None => (0, layout.size),
+            // Inclusive end, as in an HTTP Range header
+            Some((start, end)) if start < layout.size && start <= end => {
+                (start, end.min(layout.size - 1) - start + 1)
+            }
+            Some(_) => {
+                return Err(S3Error::InvalidArgument(
+                    "The requested range is not satisfiable".to_string(),
+                ))
+            }
+        };
+        Ok(ByteStream::from_body_1_x(StripeReader {
+            pool: self.pool.clone(),
+            name,
+            reads: stripe_reads(offset, length, layout.stripe_size).into_iter(),
+            remaining: length,
+            pending: None,
+        }))
+    }
+
+    async fn write_stripe(&self, name: &str, index: u64, data: Vec<u8>) -> Result<(), S3Error> {
+        let name = stripe_name(name, index);
+        self.blocking(move |pool| {
+            pool.ioctx
+                .rados_object_write_full(&name, &data)
+                .map_err(rados_error)
+        })
+        .await
+    }
+}
+
+impl Pool {
+    fn layout(&self, name: &str) -> Result<Option<Layout>, S3Error> {
+        let mut buf = [0u8; 64];
+        let len = match self
+            .ioctx
+            .rados_object_getxattr(name, LAYOUT_XATTR, &mut buf)
+        {
+            Ok(len) => len as usize,
+            Err(e) if is_not_found(&e) => return Ok(None),
+            Err(e) => return Err(rados_error(e)),
+        };
+        std::str::from_utf8(&buf[..len.min(buf.len())])
+            .ok()
+            .and_then(Layout::parse)
+            .map(Some)
+            .ok_or_else(|| {
+                tracing::error!("Invalid layout of RADOS object {}", name);
+                S3Error::InternalError
+            })
+    }
+
+    // Read `len` bytes at `offset` of stripe `index` of a blob
+    fn read_stripe(
+        &self,
+        name: &str,
+        index: u64,
+        offset: u64,
+        len: u64,
+    ) -> Result<Vec<u8>, S3Error> {
+        let mut buf = Vec::with_capacity(len as usize);
+        let read = self
+            .ioctx
+            .rados_object_read(&stripe_name(name, index), &mut buf, offset)
+            .map_err(rados_error)?;
+        if (read as u64) < len {
+            tracing::error!("RADOS object {} is missing stripe {}", name, index);
+            return Err(S3Error::InternalError);
+        }
+        buf.truncate(len as usize);
+        Ok(buf)
+    }
+
+    // Stripes go first and the head last, so a delete that fails half way
+    // leaves a blob that is still visible and can be deleted again
+    fn remove(&self, name: &str) -> Result<(), S3Error> {
+        let layout = match self.layout(name)? {
+            Some(layout) => layout,
+            None => return self.remove_object(name),
+        };
+        let stripes = layout.size.div_ceil(layout.stripe_size).max(1);
+        for index in (0..stripes).rev() {
+            self.remove_object(&stripe_name(name, index))?;
+        }
+        Ok(())
+    }
+
+    fn remove_object(&self, name: &str) -> Result<(), S3Error> {
+        match self.ioctx.rados_object_remove(name) {
+            Err(e) if !is_not_found(&e) => Err(rados_error(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Layout {
+    fn parse(value: &str) -> Option<Self> {
+        let (size, stripe_size) = value.split_once(':')?;
+        let layout = Self {
+            size: size.parse().ok()?,
+            stripe_size: stripe_size.parse().ok()?,
+        };
+        (layout.stripe_size > 0).then_some(layout)
+    }
+
+    fn encode(&self) -> String {
+        format!("{}:{}", self.size, self.stripe_size)
+    }
+}
+
+#[async_trait]
+impl FileStorage for RadosStorage {
+    async fn get_file(&self, bucket: &str, key: &str) -> Result<ByteStream, S3Error> {
+        self.read_range(bucket, key, None).await
+    }
+
+    async fn get_file_range(
+        &self,
+        bucket: &str,
+        key: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<ByteStream, S3Error> {
+        self.read_range(bucket, key, Some((start, end))).await
+    }
+
+    async fn save_file(&self, bucket: &str, key: &str, data: ByteStream) -> Result<(), S3Error> {
+        let mut writer = StripeWriter::new(self, object_name(bucket, key));
+        writer.write_stream(data).await?;
+        writer.finish().await
+    }
+
+    async fn delete_file(&self, bucket: &str, key: &str) -> Result<(), S3Error> {
+        let name = object_name(bucket, key);
+        self.blocking(move |pool| pool.remove(&name)).await
+    }
+
+    async fn probe(&self) -> Result<(), S3Error> {
+        // Answered by the OSD holding the name, whether or not it exists
+        self.blocking(|pool| pool.layout(PROBE_OBJECT).map(drop))
+            .await
+    }
+
+    // Parts are staged as blobs of their own and streamed into the object
+    async fn complete_multipart(
+        &self,
+        bucket: &str,
+        upload_id: &str,
+        parts: &[u32],
+        key: &str,
+    ) -> Result<(), S3Error> {
+        let mut writer = StripeWriter::new(self, object_name(bucket, key));
+        for part in parts {
+            let data = self.get_file(bucket, &part_name(upload_id, *part)).await?;
+            writer.write_stream(data).await?;
+        }
+        writer.finish().await?;
+        self.abort_multipart(bucket, upload_id, parts).await
+    }
+}
+
+/// Writes a blob stripe by stripe as its data comes in. The head stripe is
+/// held back until `finish`, see RadosStorage.
+struct StripeWriter<'a> {
+    storage: &'a RadosStorage,
+    name: String,
+    stripe_size: usize,
+    pending: Vec<u8>,
+    head: Option<Vec<u8>>,
+    index: u64,
+    size: u64,
+}
+
+impl<'a> StripeWriter<'a> {
+    fn new(storage: &'a RadosStorage, name: String) -> Self {
+        let stripe_size = storage.pool.stripe_size as usize;
+        Self {
+            storage,
+            name,
+            stripe_size,
+            pending: Vec::with_capacity(stripe_size),
+            head: None,
+            index: 0,
+            size: 0,
+        }
+    }
+
+    async fn write_stream(&mut self, mut data: ByteStream) -> Result<(), S3Error> {
+        while let Some(chunk) = data.next().await {
+            let chunk = chunk.map_err(|e| {
+                tracing::error!("Error reading upload body: {:?}", e);
+                S3Error::InternalError
+            })?;
+            self.pending.extend_from_slice(&chunk);
+            while self.pending.len() >= self.stripe_size {
+                let rest = self.pending.split_off(self.stripe_size);
+                let stripe = std::mem::replace(&mut self.pending, rest);
+                self.write_stripe(stripe).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn write_stripe(&mut self, stripe: Vec<u8>) -> Result<(), S3Error> {
+        self.size += stripe.len() as u64;
+        if self.index == 0 {
+            self.head = Some(stripe);
+        } else {
+            self.storage
+                .write_stripe(&self.name, self.index, stripe)
+                .await?;
+        }
+        self.index += 1;
+        Ok(())
+    }
+
+    async fn finish(mut self) -> Result<(), S3Error> {
+        if !self.pending.is_empty() || self.index == 0 {
+            let stripe = std::mem::take(&mut self.pending);
+            self.write_stripe(stripe).await?;
+        }
+
+        let name = self.name;
+        let head = self.head.unwrap_or_default();
+        let mut layout = Layout {
+            size: self.size,
+            stripe_size: self.stripe_size as u64,
+        }
+        .encode()
+        .into_bytes();
+        self.storage
+            .blocking(move |pool| {
+                pool.ioctx
+                    .rados_object_write_full(&name, &head)
+                    .map_err(rados_error)?;
+                pool.ioctx
+                    .rados_object_setxattr(&name, LAYOUT_XATTR, &mut layout)
+                    .map_err(rados_error)
+            })
+            .await
+    }
+}
+
+/// Body of a blob range, read one stripe per blocking call so that only
+/// the stripe being sent is held in memory.
+struct StripeReader {
+    pool: Arc<Pool>,
+    name: String,
+    // Stripes left to read, see stripe_reads
+    reads: std::vec::IntoIter<(u64, u64, u64)>,
+    remaining: u64,
+    pending: Option<JoinHandle<Result<Vec<u8>, S3Error>>>,
+}
+
+impl Body for StripeReader {
+    type Data = Bytes;
+    type Error = std::io::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, std::io::Error>>> {
+        let this = &mut *self;
+        let pending = match &mut this.pending {
+            Some(pending) => pending,
+            None => {
+                let Some((index, offset, len)) = this.reads.next() else {
+                    return Poll::Ready(None);
+                };
+                let (pool, name) = (this.pool.clone(), this.name.clone());
+                this.pending.insert(tokio::task::spawn_blocking(move || {
+                    pool.read_stripe(&name, index, offset, len)
+                }))
+            }
+        };
+        let result = ready!(Pin::new(pending).poll(cx));
+        this.pending = None;
+        let stripe = result
+            .map_err(|e| {
+                tracing::error!("RADOS task failed: {:?}", e);
+                S3Error::InternalError
+            })
+            .and_then(|stripe| stripe)
+            .map_err(|e| std::io::Error::other(format!("{:?}", e)))?;
+        this.remaining -= stripe.len() as u64;
+        Poll::Ready(Some(Ok(Frame::data(Bytes::from(stripe)))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining)
+    }
+}
+
+// Bucket names cannot contain a slash, so the prefix keeps buckets apart
+// within a shared pool
+fn object_name(bucket: &str, key: &str) -> String {
+    format!("{}/{}", bucket, key)
+}
+
+fn stripe_name(name: &str, index: u64) -> String {
+    match index {
+        0 => name.to_string(),
+        _ => format!("{}.{:08x}", name, index),
+    }
+}
+
+// (stripe index, offset within the stripe, length) of every stripe
+// overlapping `length` bytes at `offset`
+fn stripe_reads(offset: u64, length: u64, stripe_size: u64) -> Vec<(u64, u64, u64)> {
+    let mut reads = vec![];
+    let mut pos = offset;
+    let end = offset + length;
+    while pos < end {
+        let stripe_offset = pos % stripe_size;
+        let len = (stripe_size - stripe_offset).min(end - pos);
+        reads.push((pos / stripe_size, stripe_offset, len));
+        pos += len;
+    }
+    reads
+}
+
+fn is_not_found(e: &RadosError) -> bool {
+    match e {
+        RadosError::ApiError(errno) => {
+            std::io::Error::from_raw_os_error(*errno as i32).kind() == std::io::ErrorKind::NotFound
+        }
+        _ => false,
+    }
+}
+
+fn rados_error(e: RadosError) -> S3Error {
+    tracing::error!("Error accessing RADOS: {:?}", e);
+    S3Error::InternalError
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stripe_reads() {
+        assert_eq!(
+            stripe_reads(0, 10, 4),
+            vec![(0, 0, 4), (1, 0, 4), (2, 0, 2)]
+        );
+        assert_eq!(stripe_reads(5, 2, 4), vec![(1, 1, 2)]);
+        assert_eq!(stripe_reads(3, 6, 4), vec![(0, 3, 1), (1, 0, 4), (2, 0, 1)]);
+        assert!(stripe_reads(8, 0, 4).is_empty());
+    }
+
+    #[test]
+    fn test_layout() {
+        let layout = Layout {
+            size: 10,
+            stripe_size: 4,
+        };
+        assert_eq!(Layout::parse(&layout.encode()), Some(layout));
+        assert_eq!(Layout::parse("10:0"), None);
+        assert_eq!(Layout::parse("10"), None);
+        assert_eq!(stripe_name("bucket/key", 0), "bucket/key");
+        assert_eq!(stripe_name("bucket/key", 26), "bucket/key.0000001a");
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use s3_core::S3Error;
+use uuid::Uuid;
+
+use crate::config::CircuitConfig;
+
+use super::{BackendHealth, BackendStatus, FileStorage};
+
+/// Storage backends object data can live on, one per row of the
+/// storage_backends table.
+///
+/// Every bucket is bound to a backend, buckets without one use the default
+/// backend. A backend may also hold one storage class for lifecycle
+/// transitions. Each backend has a circuit breaker, see BackendHealth.
+#[derive(Default)]
+pub struct StorageRegistry {
+    default: RwLock<Option<Uuid>>,
+    backends: RwLock<HashMap<Uuid, Arc<dyn FileStorage>>>,
+    health: RwLock<HashMap<Uuid, Arc<BackendHealth>>>,
+    circuit: RwLock<CircuitConfig>,
+    names: RwLock<HashMap<String, Uuid>>,
+    classes: RwLock<HashMap<String, Uuid>>,
+}
+
+impl StorageRegistry {
+    pub fn insert(
+        &self,
+        id: Uuid,
+        name: &str,
+        storage_class: Option<String>,
+        storage: Arc<dyn FileStorage>,
+    ) {
+        self.backends.write().unwrap().insert(id, storage);
+        let health = BackendHealth::new(self.circuit.read().unwrap().clone());
+        self.health.write().unwrap().insert(id, Arc::new(health));
+        self.names.write().unwrap().insert(name.to_string(), id);
+        if let Some(storage_class) = storage_class {
+            self.classes.write().unwrap().insert(storage_class, id);
+        }
+    }
+
+    /// Circuit breaker settings of the backends inserted from now on
+    pub fn set_circuit_config(&self, config: CircuitConfig) {
+        *self.circuit.write().unwrap() = config;
+    }
+
+    pub fn set_default(&self, id: Uuid) {
+        *self.default.write().unwrap() = Some(id);
+    }
+
+    /// Backend new buckets are bound to
+    pub fn default_backend(&self) -> Option<Uuid> {
+        *self.default.read().unwrap()
+    }
+
+    pub fn get(&self, backend_id: Option<Uuid>) -> Result<Arc<dyn FileStorage>, S3Error> {
+        let backend_id = self.resolve(backend_id)?;
+        self.backends
+            .read()
+            .unwrap()
+            .get(&backend_id)
+            .cloned()
+            .ok_or_else(|| {
+                tracing::error!("Storage backend {} is not loaded", backend_id);
+                S3Error::InternalError
+            })
+    }
+
+    /// Circuit breaker and read latencies of a backend
+    pub fn health(&self, backend_id: Option<Uuid>) -> Result<Arc<BackendHealth>, S3Error> {
+        let backend_id = self.resolve(backend_id)?;
+        self.health
+            .read()
+            .unwrap()
+            .get(&backend_id)
+            .cloned()
+            .ok_or_else(|| {
+                tracing::error!("Storage backend {} is not loaded", backend_id);
+                S3Error::InternalError
+            })
+    }
+
+    // The backend itself, or the default backend for None
+    fn resolve(&self, backend_id: Option<Uuid>) -> Result<Uuid, S3Error> {
+        backend_id
+            .or_else(|| self.default_backend())
+            .ok_or_else(|| {
+                tracing::error!("No default storage backend is configured");
+                S3Error::InternalError
+            })
+    }
+
+    /// Every loaded backend with its health, ordered by name
+    pub fn statuses(&self) -> Vec<BackendStatus> {
+        let default = self.default_backend();
+        let health = self.health.read().unwrap();
+        let mut statuses: Vec<_> = self
+            .names
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|(name, id)| {
+                let health = health.get(id)?;
+                Some(BackendStatus {
+                    id: *id,
+                    name: name.clone(),
+                    default: default == Some(*id),
+                    circuit: health.circuit(),
+                    last_probe: health.last_probe(),
+                })
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        statuses
+    }
+
+    /// Ids of every loaded backend
+    pub fn backend_ids(&self) -> Vec<Uuid> {
+        self.backends.read().unwrap().keys().copied().collect()
+    }
+
+    pub fn backend_by_name(&self, name: &str) -> Option<Uuid> {
+        self.names.read().unwrap().get(name).copied()
+    }
+
+    /// Backend holding objects transitioned to `storage_class`
+    pub fn backend_for_class(&self, storage_class: &str) -> Option<Uuid> {
+        self.classes.read().unwrap().get(storage_class).copied()
+    }
+}
@@ -1,30 +1,140 @@
-use aws_sdk_s3::primitives::ByteStream;
+use aws_config::Region;
+use aws_sdk_s3::{
+    config::{Credentials, SharedCredentialsProvider},
+    error::SdkError,
+    operation::get_object::GetObjectError,
+    primitives::ByteStream,
+    types::{CompletedMultipartUpload, CompletedPart},
+};
 use axum::async_trait;
 use s3_core::S3Error;
 
-use super::FileStorage;
+use super::{part_name, FileStorage};
 
+/// Blobs stored in an S3 compatible backend.
+///
+/// With a backend bucket configured, every gateway bucket is a prefix of it:
+/// the blob `<key>` of gateway bucket `<bucket>` lives at `<bucket>/<key>`.
+/// Without one, gateway buckets map to backend buckets of the same name,
+/// which must exist and, on providers with a global bucket namespace such as
+/// Spaces, may belong to someone else.
 pub struct StorageBackend {
     s3_client: aws_sdk_s3::Client,
+    bucket: Option<String>,
 }
 
 impl StorageBackend {
     pub fn new(s3_client: aws_sdk_s3::Client) -> Self {
-        Self { s3_client }
+        Self {
+            s3_client,
+            bucket: None,
+        }
+    }
+
+    /// Keep the blobs of every gateway bucket in the backend bucket `bucket`.
+    pub fn with_bucket(mut self, bucket: Option<String>) -> Self {
+        self.bucket = bucket;
+        self
+    }
+
+    // Backend bucket and key of the blob `key` of gateway bucket `bucket`
+    fn location<'a>(&'a self, bucket: &'a str, key: &str) -> (&'a str, String) {
+        backend_location(self.bucket.as_deref(), bucket, key)
+    }
+
+    /// Client for an S3 compatible endpoint, e.g. DigitalOcean Spaces or a
+    /// Ceph RADOS gateway.
+    pub fn connect(
+        endpoint: &str,
+        region: &str,
+        access_key_id: &str,
+        secret_access_key: &str,
+    ) -> Self {
+        let sdk_config = aws_config::SdkConfig::builder()
+            .region(Region::new(region.to_string()))
+            .endpoint_url(endpoint)
+            .credentials_provider(SharedCredentialsProvider::new(Credentials::new(
+                access_key_id,
+                secret_access_key,
+                None,
+                None,
+                "",
+            )))
+            .build();
+        Self::new(aws_sdk_s3::Client::new(&sdk_config))
+    }
+
+    async fn copy_parts(
+        &self,
+        bucket: &str,
+        upload_id: &str,
+        parts: &[u32],
+        key: &str,
+        backend_upload_id: &str,
+    ) -> Result<(), S3Error> {
+        let (backend_bucket, backend_key) = self.location(bucket, key);
+        let mut completed = Vec::with_capacity(parts.len());
+        for (i, part) in parts.iter().enumerate() {
+            // Parts are renumbered, the upload may have skipped numbers
+            let part_number = i as i32 + 1;
+            let (source_bucket, source_key) = self.location(bucket, &part_name(upload_id, *part));
+            let response = self
+                .s3_client
+                .upload_part_copy()
+                .bucket(backend_bucket)
+                .key(&backend_key)
+                .upload_id(backend_upload_id)
+                .part_number(part_number)
+                .copy_source(format!("{}/{}", source_bucket, source_key))
+                .send()
+                .await
+                .map_err(|e| {
+                    tracing::error!("Error copying part {} of {}: {:?}", part, key, e);
+                    S3Error::InternalError
+                })?;
+            let etag = response
+                .copy_part_result()
+                .and_then(|result| result.e_tag())
+                .map(str::to_string);
+            completed.push(
+                CompletedPart::builder()
+                    .part_number(part_number)
+                    .set_e_tag(etag)
+                    .build(),
+            );
+        }
+        self.s3_client
+            .complete_multipart_upload()
+            .bucket(backend_bucket)
+            .key(&backend_key)
+            .upload_id(backend_upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(completed))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|e| {
+                tracing::error!("Error completing multipart upload of {}: {:?}", key, e);
+                S3Error::InternalError
+            })?;
+        Ok(())
     }
 }
 
 #[async_trait]
 impl FileStorage for StorageBackend {
     async fn get_file(&self, bucket: &str, key: &str) -> Result<ByteStream, S3Error> {
+        let (backend_bucket, backend_key) = self.location(bucket, key);
         let response = self
             .s3_client
             .get_object()
-            .bucket(bucket)
-            .key(key)
+            .bucket(backend_bucket)
+            .key(backend_key)
             .send()
             .await
-            .map_err(|_| S3Error::NoSuchKey(key.to_string()))?;
+            .map_err(|e| get_error(key, e))?;
 
         Ok(response.body)
     }
@@ -36,25 +146,26 @@ impl FileStorage for StorageBackend {
         start: u64,
         end: u64,
     ) -> Result<ByteStream, S3Error> {
+        let (backend_bucket, backend_key) = self.location(bucket, key);
         let response = self
             .s3_client
             .get_object()
-            .bucket(bucket)
-            .key(key)
+            .bucket(backend_bucket)
+            .key(backend_key)
             .range(format!("bytes={}-{}", start, end))
             .send()
             .await
-            .map_err(|_| S3Error::NoSuchKey(key.to_string()))?;
+            .map_err(|e| get_error(key, e))?;
 
         Ok(response.body)
     }
 
     async fn save_file(&self, bucket: &str, key: &str, data: ByteStream) -> Result<(), S3Error> {
-        let response = self
-            .s3_client
+        let (backend_bucket, backend_key) = self.location(bucket, key);
+        self.s3_client
             .put_object()
-            .bucket(bucket)
-            .key(key)
+            .bucket(backend_bucket)
+            .key(backend_key)
             .body(data)
             .send()
             .await
@@ -64,15 +175,114 @@ impl FileStorage for StorageBackend {
     }
 
     async fn delete_file(&self, bucket: &str, key: &str) -> Result<(), S3Error> {
-        let response = self
-            .s3_client
+        let (backend_bucket, backend_key) = self.location(bucket, key);
+        self.s3_client
             .delete_object()
-            .bucket(bucket)
-            .key(key)
+            .bucket(backend_bucket)
+            .key(backend_key)
             .send()
             .await
             .map_err(|_| S3Error::InternalError)?;
 
         Ok(())
     }
+
+    async fn probe(&self) -> Result<(), S3Error> {
+        match self.s3_client.list_buckets().send().await {
+            Ok(_) => Ok(()),
+            // Credentials scoped to their buckets may not list them, any
+            // answer short of a server error shows the backend is up
+            Err(SdkError::ServiceError(e)) if !e.raw().status().is_server_error() => Ok(()),
+            Err(e) => {
+                tracing::warn!("Error probing storage backend: {:?}", e);
+                Err(S3Error::InternalError)
+            }
+        }
+    }
+
+    // Parts are copied into a multipart upload of the backend, so the
+    // object is assembled without its data passing through the gateway
+    async fn complete_multipart(
+        &self,
+        bucket: &str,
+        upload_id: &str,
+        parts: &[u32],
+        key: &str,
+    ) -> Result<(), S3Error> {
+        let (backend_bucket, backend_key) = self.location(bucket, key);
+        let upload = self
+            .s3_client
+            .create_multipart_upload()
+            .bucket(backend_bucket)
+            .key(&backend_key)
+            .send()
+            .await
+            .map_err(|e| {
+                tracing::error!("Error starting multipart upload of {}: {:?}", key, e);
+                S3Error::InternalError
+            })?;
+        let backend_upload_id = upload.upload_id().unwrap_or_default().to_string();
+
+        let result = self
+            .copy_parts(bucket, upload_id, parts, key, &backend_upload_id)
+            .await;
+        if result.is_err() {
+            if let Err(e) = self
+                .s3_client
+                .abort_multipart_upload()
+                .bucket(backend_bucket)
+                .key(&backend_key)
+                .upload_id(&backend_upload_id)
+                .send()
+                .await
+            {
+                tracing::warn!("Error aborting multipart upload of {}: {:?}", key, e);
+            }
+            return result;
+        }
+        self.abort_multipart(bucket, upload_id, parts).await
+    }
+}
+
+// A missing object is NoSuchKey, any other failure is one of the backend
+fn get_error<R: std::fmt::Debug>(key: &str, e: SdkError<GetObjectError, R>) -> S3Error {
+    if e.as_service_error().is_some_and(|e| e.is_no_such_key()) {
+        return S3Error::NoSuchKey(key.to_string());
+    }
+    tracing::error!("Error reading {} from storage backend: {:?}", key, e);
+    S3Error::InternalError
+}
+
+// Location of a blob, see StorageBackend
+fn backend_location<'a>(
+    backend_bucket: Option<&'a str>,
+    bucket: &'a str,
+    key: &str,
+) -> (&'a str, String) {
+    match backend_bucket {
+        Some(backend_bucket) => (backend_bucket, format!("{}/{}", bucket, key)),
+        None => (bucket, key.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_location() {
+        assert_eq!(
+            backend_location(Some("gateway-data"), "photos", "0190c3c1/1"),
+            ("gateway-data", "photos/0190c3c1/1".to_string())
+        );
+        // Tenants' buckets of the same backend bucket never share a key
+        assert_ne!(
+            backend_location(Some("gateway-data"), "photos", "a"),
+            backend_location(Some("gateway-data"), "videos", "a")
+        );
+        assert_eq!(
+            backend_location(None, "photos", "a"),
+            ("photos", "a".to_string())
+        );
+    }
 }
@@ -1,9 +1,42 @@
+use std::collections::HashMap;
+
+use s3_core::{
+    cors::CorsConfiguration, encryption::ServerSideEncryptionConfiguration,
+    lifecycle::LifecycleConfiguration, logging::LoggingEnabled,
+    notification::NotificationConfiguration, object_lock::ObjectLockConfiguration,
+    replication::ReplicationConfiguration, website::WebsiteConfiguration,
+};
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug, Default)]
 pub struct Bucket {
     pub id: uuid::Uuid,
     pub name: String,
     pub user_id: i64,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    // Backend holding the bucket's objects, None for the default backend
+    pub backend_id: Option<uuid::Uuid>,
+    // Backend the bucket is being migrated to, new writes go there
+    pub migration_target: Option<uuid::Uuid>,
+    // Backend taking new writes while the bucket's backend is down
+    pub fallback_backend_id: Option<uuid::Uuid>,
+    // Payloads are stored once per SHA-256 and shared between objects
+    pub dedup: bool,
+    // One of the s3_core::versioning statuses
+    pub versioning: i16,
+    pub cors: Option<CorsConfiguration>,
+    pub lifecycle: Option<LifecycleConfiguration>,
+    pub encryption: Option<ServerSideEncryptionConfiguration>,
+    pub tags: HashMap<String, String>,
+    // Set for buckets created with Object Lock, which cannot be turned off
+    pub object_lock: Option<ObjectLockConfiguration>,
+    pub website: Option<WebsiteConfiguration>,
+    // Target bucket and prefix of the bucket's server access logs
+    pub logging: Option<LoggingEnabled>,
+    // Events of the bucket's objects sent to notification targets
+    pub notification: Option<NotificationConfiguration>,
+    // Rules copying new versions to destination buckets
+    pub replication: Option<ReplicationConfiguration>,
 }
 
 #[derive(Debug, Default)]
@@ -17,16 +50,341 @@ pub struct Object {
     pub is_delete_marker: bool,
     pub last_modified: chrono::DateTime<chrono::Utc>,
     pub etag: String,
+    pub tags: HashMap<String, String>,
+    pub storage_class: String,
+    // Backend holding the data, None for the bucket's backend
+    pub backend_id: Option<uuid::Uuid>,
+    pub backend_specific_name: Option<String>,
+    // Location within a volume blob for packed objects, see PackedLocation,
+    // or `sha256:<hex digest>` for objects sharing a content addressed blob
+    pub backend_specific_id: Option<String>,
+    // Payload of a tiny object kept in the index, only read by get_object
+    // and replication
+    pub inline_data: Option<Vec<u8>>,
+    // Set when the gateway encrypted the data
+    pub encryption: Option<ObjectEncryption>,
+    // Set when the gateway compressed the data, before encrypting it
+    pub compression: Option<ObjectCompression>,
+    // Object Lock retention, GOVERNANCE or COMPLIANCE until retain_until
+    pub retention_mode: Option<String>,
+    pub retain_until: Option<chrono::DateTime<chrono::Utc>>,
+    pub legal_hold: bool,
+    // Served by the website endpoint as a redirect instead of the data
+    pub website_redirect_location: Option<String>,
+    // One of the s3_core::replication statuses, None when not replicated
+    pub replication_status: Option<String>,
+    // Backend the version was meant for when its data was written to the
+    // bucket's fallback backend instead, until it is moved back
+    pub failover_from: Option<uuid::Uuid>,
+}
+
+impl Object {
+    /// Location of the data when the object is packed into a volume.
+    pub fn packed(&self) -> Option<PackedLocation> {
+        self.backend_specific_id
+            .as_deref()
+            .and_then(PackedLocation::parse)
+    }
+
+    /// SHA-256 of the content addressed blob holding the data of a
+    /// deduplicated object.
+    pub fn content_hash(&self) -> Option<&str> {
+        self.backend_specific_id.as_deref().and_then(content_hash)
+    }
+}
+
+pub fn content_hash(backend_specific_id: &str) -> Option<&str> {
+    backend_specific_id.strip_prefix("sha256:")
+}
+
+// Name of a content addressed blob on the backend
+pub fn blob_name(sha256: &str) -> String {
+    format!("sha256/{}", sha256)
+}
+
+// Range of a volume blob holding a packed object, stored as
+// `<volume id>:<offset>:<length>` in objects.backend_specific_id
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PackedLocation {
+    pub volume_id: uuid::Uuid,
+    pub offset: u64,
+    pub length: u64,
+}
+
+impl PackedLocation {
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.splitn(3, ':');
+        let location = Self {
+            volume_id: parts.next()?.parse().ok()?,
+            offset: parts.next()?.parse().ok()?,
+            length: parts.next()?.parse().ok()?,
+        };
+        (location.length > 0).then_some(location)
+    }
+
+    // Inclusive end, as passed to FileStorage::get_file_range
+    pub fn end(&self) -> u64 {
+        self.offset + self.length - 1
+    }
+}
+
+impl std::fmt::Display for PackedLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.volume_id, self.offset, self.length)
+    }
+}
+
+// Envelope of an object encrypted by the gateway, stored as JSON in
+// objects.encryption. The data is sealed in chunks, see ChunkedCipher.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectEncryption {
+    // AES256 or aws:kms, as returned in x-amz-server-side-encryption
+    pub algorithm: String,
+    // Master key the data key is wrapped with, empty for SSE-C
+    #[serde(default)]
+    pub key_id: String,
+    // Base64 of the wrapped data key
+    pub data_key: String,
+    // Base64 of the encryption context the data key is bound to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    pub chunk_size: u64,
+    // Salted HMAC of the key of an SSE-C object, which wraps the data key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub customer_key: Option<String>,
+    // The object of a multipart upload is a run of parts sealed on their
+    // own, each with a data key of its own, and data_key is empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<EncryptedPart>,
+}
+
+// Part of an encrypted multipart object, stored with its part in
+// multipart_parts.encryption until the upload is completed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EncryptedPart {
+    // Plaintext size of the part
+    pub size: u64,
+    #[serde(default)]
+    pub key_id: String,
+    // Base64 of the wrapped data key of the part
+    pub data_key: String,
+}
+
+// Compression of an object's data, stored as JSON in objects.compression.
+// The data is a run of independently compressed frames of `frame_size`
+// original bytes, so that a range is read from the frames holding it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectCompression {
+    // Only zstd
+    pub codec: String,
+    pub frame_size: u64,
+    // Compressed size of each frame
+    pub frames: Vec<u64>,
+}
+
+impl ObjectCompression {
+    /// Number of bytes stored for the object, before encryption.
+    pub fn stored_size(&self) -> u64 {
+        self.frames.iter().sum()
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct Multipart {
+    pub id: uuid::Uuid,
     pub bucket_id: uuid::Uuid,
     pub object_id: uuid::Uuid,
-    pub upload_id: uuid::Uuid,
+    pub upload_id: String,
+    pub key: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub owner_id: i64,
+    // Backend the parts are staged on and the object is assembled on
+    pub backend_id: Option<uuid::Uuid>,
+    pub metadata: UploadMetadata,
 }
 
-#[derive(Debug, Default)]
+// Settings of the object of a multipart upload, taken from its
+// CreateMultipartUpload and stored as JSON in multipart_uploads.metadata
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UploadMetadata {
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tags: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub website_redirect_location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention_mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retain_until: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    pub legal_hold: bool,
+    // Backend the object was meant for when the upload went to the bucket's
+    // fallback backend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failover_from: Option<uuid::Uuid>,
+    // Encryption every part is sealed with, without data key, see
+    // EncryptedPart
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<ObjectEncryption>,
+}
+
+// Row of the multipart_parts table
+#[derive(Debug, Default, Clone)]
 pub struct Part {
+    pub part_number: u32,
+    pub size: i64,
+    pub etag: String,
+    pub encryption: Option<EncryptedPart>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct RateLimit {
+    pub id: uuid::Uuid,
+    pub user_id: Option<i64>,
+    pub rps: i32,
+    pub dimension: String,
+    pub value: String,
+    pub weight: i32,
+    pub exclusive: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct LifecycleRule {
+    pub id: uuid::Uuid,
+    pub bucket_id: uuid::Uuid,
+    pub bucket_name: String,
+    pub bucket_owner: i64,
+    pub bucket_backend_id: Option<uuid::Uuid>,
+    pub versioning: i16,
+    pub bucket_notification: Option<NotificationConfiguration>,
+    pub bucket_replication: Option<ReplicationConfiguration>,
+    pub rule: s3_core::lifecycle::LifecycleRule,
+}
+
+// Row of the replication_queue table, a version waiting to be copied to its
+// destination
+#[derive(Debug, Clone)]
+pub struct ReplicationItem {
+    pub bucket_id: uuid::Uuid,
+    pub bucket_name: String,
+    pub key: String,
+    pub version_id: uuid::Uuid,
+    // Failed copies so far
+    pub attempts: i32,
+}
+
+// Version whose data was written to its bucket's fallback backend, waiting
+// to be moved back
+#[derive(Debug)]
+pub struct FailoverWrite {
+    pub bucket_name: String,
+    pub object: Object,
+}
+
+// Row of the lifecycle_rules table as written by PutBucketLifecycleConfiguration
+#[derive(Debug, Clone)]
+pub struct LifecycleRuleRow {
+    pub rule_id: String,
+    pub rule_config: serde_json::Value,
+    pub status: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct StorageBackend {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub provider: String,
+    pub credentials: serde_json::Value,
+    pub endpoint: Option<String>,
+    pub region: Option<String>,
+    pub storage_class: Option<String>,
+    // Provider specific settings, e.g. the root directory of a LOCAL backend
+    pub config: serde_json::Value,
+}
+
+// Row of the volumes table, a blob that small objects are packed into
+#[derive(Debug, Clone)]
+pub struct Volume {
+    pub id: uuid::Uuid,
+    pub bucket_id: uuid::Uuid,
+    pub bucket_name: String,
+    pub backend_id: uuid::Uuid,
+    pub size: i64,
+    pub live_bytes: i64,
+}
+
+// Row of the blobs table, a content addressed blob of a dedup bucket
+#[derive(Debug, Clone)]
+pub struct Blob {
+    pub bucket_id: uuid::Uuid,
+    pub bucket_name: String,
+    pub backend_id: uuid::Uuid,
+    pub sha256: String,
+}
+
+// Row of the backend_migrations table
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendMigration {
+    pub id: uuid::Uuid,
     pub bucket_id: uuid::Uuid,
+    pub bucket_name: String,
+    pub source_backend_id: uuid::Uuid,
+    pub target_backend_id: uuid::Uuid,
+    pub status: String,
+    pub progress: MigrationProgress,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub error: Option<String>,
+}
+
+// Checkpoint of a migration: the last version copied and the totals so far
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MigrationProgress {
+    pub key: String,
+    pub version_id: uuid::Uuid,
+    pub objects: i64,
+    pub bytes: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packed_location() {
+        let location = PackedLocation {
+            volume_id: uuid::Uuid::now_v7(),
+            offset: 4096,
+            length: 100,
+        };
+        assert_eq!(PackedLocation::parse(&location.to_string()), Some(location));
+        assert_eq!(location.end(), 4195);
+        assert_eq!(PackedLocation::parse("not-a-volume:0:1"), None);
+        assert_eq!(
+            PackedLocation::parse(&format!("{}:0:0", location.volume_id)),
+            None
+        );
+        assert_eq!(PackedLocation::parse(&location.volume_id.to_string()), None);
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let object = Object {
+            backend_specific_id: Some("sha256:abc".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(object.content_hash(), Some("abc"));
+        assert_eq!(object.packed(), None);
+        let object = Object {
+            backend_specific_id: Some(
+                PackedLocation {
+                    volume_id: uuid::Uuid::now_v7(),
+                    offset: 0,
+                    length: 1,
+                }
+                .to_string(),
+            ),
+            ..Default::default()
+        };
+        assert_eq!(object.content_hash(), None);
+    }
 }
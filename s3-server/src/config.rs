@@ -1,4 +1,4 @@
-use std::error::Error;
+use std::{collections::HashMap, error::Error};
 
 use serde::Deserialize;
 
@@ -6,8 +6,18 @@ use serde::Deserialize;
 pub struct Config {
     pub region: String,
     pub s3domain: Vec<String>,
+    // Host suffixes of the static website endpoint, buckets are served at
+    // <bucket>.<suffix>
+    #[serde(default)]
+    pub website_domain: Vec<String>,
     pub log_path: String,
     pub log_level: String,
+    // Local copy of the server access log, not written when empty
+    #[serde(default)]
+    pub access_log_path: String,
+    // {combined}, {json} or {s3}, the format of the local access log
+    #[serde(default)]
+    pub access_log_format: String,
     pub debug_mode: bool,
     pub bind_api_address: String,
     pub bind_admin_http_address: String,
@@ -20,6 +30,548 @@ pub struct Config {
     pub redis_password: String,
     pub redis_connect_timeout: u64,
     pub redis_read_timeout: u64,
+    // Circuit breakers of the storage backends, the cache_circuit_* keys
+    #[serde(flatten)]
+    pub circuit: CircuitConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+    #[serde(default)]
+    pub lifecycle: LifecycleConfig,
+    #[serde(default)]
+    pub migration: MigrationConfig,
+    #[serde(default)]
+    pub packing: PackingConfig,
+    #[serde(default)]
+    pub dedup: DedupConfig,
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+    // Compress object data at rest, tuned by [compression]
+    #[serde(default)]
+    pub enable_compression: bool,
+    #[serde(default)]
+    pub compression: CompressionConfig,
+    #[serde(default)]
+    pub access_logging: AccessLoggingConfig,
+    #[serde(default)]
+    pub notification: NotificationConfig,
+    #[serde(default)]
+    pub replication: ReplicationConfig,
+    #[serde(default)]
+    pub read_fallback: ReadFallbackConfig,
+    #[serde(default)]
+    pub failover: FailoverConfig,
+    // Bearer token of the admin API, the API is disabled when empty
+    #[serde(default)]
+    pub admin_key: String,
+    // Name of the [storage.*] backend new buckets are bound to
+    #[serde(default)]
+    pub default_storage_backend: Option<String>,
+    #[serde(default)]
+    pub storage: HashMap<String, StorageConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    // Requests per second allowed per client across all gateways
+    pub rps: u32,
+    // Number of requests a client may burst above the sustained rate
+    pub burst: u32,
+    // Time budget for a Redis round trip before falling back to the local limiter
+    pub redis_timeout_ms: u64,
+    // How often rules are reloaded from the rate_limits table
+    pub rules_refresh_secs: u64,
+    // Byte-rate limits applied on this node, 0 disables the limit
+    pub user_ingress_bytes_per_sec: u32,
+    pub user_egress_bytes_per_sec: u32,
+    pub bucket_ingress_bytes_per_sec: u32,
+    pub bucket_egress_bytes_per_sec: u32,
+    // Requests in flight per access key on this node, 0 disables the limit
+    pub max_concurrent_requests_per_key: usize,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            rps: 10,
+            burst: 10,
+            redis_timeout_ms: 50,
+            rules_refresh_secs: 30,
+            user_ingress_bytes_per_sec: 0,
+            user_egress_bytes_per_sec: 0,
+            bucket_ingress_bytes_per_sec: 0,
+            bucket_egress_bytes_per_sec: 0,
+            max_concurrent_requests_per_key: 0,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ProxyConfig {
+    // CIDRs of load balancers allowed to forward the client address
+    pub trusted_proxies: Vec<String>,
+    // Expect a PROXY protocol v1/v2 header on every connection
+    pub proxy_protocol: bool,
+    // Time allowed for the PROXY protocol header to arrive
+    pub header_timeout_ms: u64,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            trusted_proxies: vec![],
+            proxy_protocol: false,
+            header_timeout_ms: 5000,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LifecycleConfig {
+    // Run the lifecycle worker on this gateway
+    pub enabled: bool,
+    // How often the worker looks for due rules
+    pub poll_secs: u64,
+    // Time between two runs of the same rule
+    pub interval_secs: u64,
+    // Time a worker owns a rule before another worker may retry it
+    pub lease_secs: u64,
+    // Number of rules claimed per poll
+    pub rules_per_poll: i64,
+    // Number of objects listed per index query
+    pub batch_size: i64,
+}
+
+impl Default for LifecycleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            poll_secs: 60,
+            interval_secs: 3600,
+            lease_secs: 1800,
+            rules_per_poll: 10,
+            batch_size: 1000,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MigrationConfig {
+    // Run bucket migrations between storage backends on this gateway
+    pub enabled: bool,
+    // How often the worker looks for migrations to run
+    pub poll_secs: u64,
+    // Time a worker owns a migration without checkpointing before another
+    // worker may resume it
+    pub lease_secs: u64,
+    // Number of object versions moved between two checkpoints
+    pub batch_size: i64,
+}
+
+impl Default for MigrationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            poll_secs: 30,
+            lease_secs: 600,
+            batch_size: 100,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PackingConfig {
+    // Pack small objects into shared volume blobs
+    pub enabled: bool,
+    // Objects of at most this many bytes are kept in the index database
+    // instead of a storage backend, 0 disables inlining
+    pub inline_max_size: u64,
+    // Objects smaller than this many bytes are packed
+    pub max_object_size: u64,
+    // A volume is written once it reaches this many bytes
+    pub volume_size: u64,
+    // Time a volume collects objects before it is written. Every PUT waits
+    // for its volume, so this adds to the latency of small PUTs.
+    pub window_ms: u64,
+    // Run the volume compactor on this gateway
+    pub compaction_enabled: bool,
+    // How often the compactor looks for volumes to compact
+    pub compaction_poll_secs: u64,
+    // Volumes whose live bytes fall below this share of their size are compacted
+    pub compaction_max_live_ratio: f64,
+    // Age before a volume is compacted, so that PUTs still indexing into it finish
+    pub compaction_min_age_secs: u64,
+    // Time a compactor owns a volume before another one may retry it
+    pub compaction_lease_secs: u64,
+}
+
+impl Default for PackingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            inline_max_size: 0,
+            max_object_size: 64 * 1024,
+            volume_size: 8 * 1024 * 1024,
+            window_ms: 10,
+            compaction_enabled: false,
+            compaction_poll_secs: 300,
+            compaction_max_live_ratio: 0.5,
+            compaction_min_age_secs: 3600,
+            compaction_lease_secs: 600,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DedupConfig {
+    // Run the collector of unreferenced blobs of dedup buckets on this gateway
+    pub enabled: bool,
+    // How often the collector looks for unreferenced blobs
+    pub poll_secs: u64,
+    // Time a blob stays unreferenced before its data is deleted, it must
+    // exceed the time a PUT takes from reusing a blob to indexing its object
+    pub grace_secs: u64,
+    // Number of blobs claimed per poll
+    pub batch_size: i64,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            poll_secs: 300,
+            grace_secs: 3600,
+            batch_size: 100,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AccessLoggingConfig {
+    // How often the records buffered for each target bucket are written
+    pub flush_secs: u64,
+    // Records of a target bucket written as soon as this many are buffered
+    pub max_batch_records: usize,
+    // Records waiting to be batched, records beyond it are dropped
+    pub queue_size: usize,
+}
+
+impl Default for AccessLoggingConfig {
+    fn default() -> Self {
+        Self {
+            flush_secs: 300,
+            max_batch_records: 10000,
+            queue_size: 100000,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NotificationConfig {
+    // Directory of the durable event queues, one per target. Notifications
+    // are disabled when empty
+    pub queue_dir: String,
+    // Deliveries of an event to a webhook before it is dead-lettered
+    pub max_attempts: u32,
+    // Delay before the first retry, doubled on each further attempt
+    pub retry_base_secs: u64,
+    pub retry_max_secs: u64,
+    // How often webhook workers look for queued events
+    pub poll_ms: u64,
+    pub timeout_secs: u64,
+    // Destinations named by the last field of a configuration's ARN
+    pub targets: HashMap<String, NotificationTarget>,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            queue_dir: String::new(),
+            max_attempts: 10,
+            retry_base_secs: 5,
+            retry_max_secs: 3600,
+            poll_ms: 1000,
+            timeout_secs: 10,
+            targets: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CircuitConfig {
+    // Seconds over which failed requests to a backend are counted
+    #[serde(rename = "cache_circuit_check_interval")]
+    pub check_interval: u64,
+    // Seconds an open circuit refuses requests before letting trial ones through
+    #[serde(rename = "cache_circuit_close_sleep_window")]
+    pub close_sleep_window: u64,
+    // Successful trial requests that close the circuit again
+    #[serde(rename = "cache_circuit_close_required_count")]
+    pub close_required_count: u32,
+    // Failed requests within check_interval that open the circuit
+    #[serde(rename = "cache_circuit_open_threshold")]
+    pub open_threshold: u32,
+    // Seconds a backend may take to start answering a read or a probe
+    #[serde(rename = "cache_circuit_exec_timeout")]
+    pub exec_timeout: u64,
+    // Concurrent reads per backend, -1 for no limit
+    #[serde(rename = "cache_circuit_exec_max_concurrent")]
+    pub exec_max_concurrent: i64,
+}
+
+impl Default for CircuitConfig {
+    fn default() -> Self {
+        Self {
+            check_interval: 3,
+            close_sleep_window: 1,
+            close_required_count: 3,
+            open_threshold: 1,
+            exec_timeout: 5,
+            exec_max_concurrent: -1,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ReadFallbackConfig {
+    // Read another copy of the data, a replica or the copy of a running
+    // migration, when its backend fails, times out or has an open circuit
+    pub enabled: bool,
+    // Also ask another copy when a backend is slower to answer than usual
+    pub hedged_reads: bool,
+    // Latency quantile of a backend after which a read is hedged
+    pub hedge_quantile: f64,
+    // Reads a backend must have answered before its latencies are used,
+    // hedge_delay_ms applies until then
+    pub hedge_min_samples: usize,
+    pub hedge_delay_ms: u64,
+}
+
+impl Default for ReadFallbackConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hedged_reads: false,
+            hedge_quantile: 0.99,
+            hedge_min_samples: 100,
+            hedge_delay_ms: 200,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct FailoverConfig {
+    // Probe every storage backend, and write to a bucket's fallback backend
+    // while the bucket's own backend is down
+    pub enabled: bool,
+    // How often every backend is probed
+    pub probe_secs: u64,
+    // How often versions written to a fallback backend are moved back
+    pub reconcile_secs: u64,
+    // Versions looked at per reconciliation batch
+    pub batch_size: i64,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            probe_secs: 10,
+            reconcile_secs: 60,
+            batch_size: 100,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ReplicationConfig {
+    // Run the replication worker on this gateway
+    pub enabled: bool,
+    // How often the worker looks for versions to copy
+    pub poll_secs: u64,
+    // Time a worker owns a queued version before another worker may retry it
+    pub lease_secs: u64,
+    // Number of queued versions claimed per poll
+    pub batch_size: i64,
+    // Copies of a version before it is marked FAILED
+    pub max_attempts: u32,
+    // Delay before the first retry, doubled on each further attempt
+    pub retry_base_secs: u64,
+    pub retry_max_secs: u64,
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            poll_secs: 5,
+            lease_secs: 300,
+            batch_size: 100,
+            max_attempts: 10,
+            retry_base_secs: 5,
+            retry_max_secs: 3600,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct NotificationTarget {
+    // webhook events are POSTed to the endpoint, queue events are left in
+    // the target's queue directory for local consumers
+    pub kind: String,
+    pub endpoint: String,
+    // Sent as a Bearer token to the webhook when set
+    pub auth_token: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct EncryptionConfig {
+    // TOML file of master keys, see LocalKeyring. Requests for server side
+    // encryption are refused when empty
+    pub keyring: String,
+    // Plaintext bytes per authenticated chunk of an encrypted object, the
+    // unit of range reads
+    pub chunk_size: u64,
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            keyring: String::new(),
+            chunk_size: 64 * 1024,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CompressionConfig {
+    // zstd compression level
+    pub level: i32,
+    // Original bytes per independently compressed frame, the unit of range
+    // reads
+    pub frame_size: u64,
+    // Objects smaller than this many bytes are stored as is
+    pub min_size: u64,
+    // Content types always tried, `text/*` matches any subtype
+    pub content_types: Vec<String>,
+    // Also try objects of other types when their first frame compresses well
+    pub detect: bool,
+    // Compressed data is only kept when at most this share of the original
+    pub max_ratio: f64,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            level: 3,
+            frame_size: 1024 * 1024,
+            min_size: 4096,
+            content_types: [
+                "text/*",
+                "application/json",
+                "application/xml",
+                "application/javascript",
+                "application/x-ndjson",
+                "image/svg+xml",
+            ]
+            .iter()
+            .map(|t| t.to_string())
+            .collect(),
+            detect: true,
+            max_ratio: 0.9,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct StorageConfig {
+    // One of AWS, GCP, AZURE, DO, CEPH and LOCAL
+    pub provider: String,
+    // S3 compatible endpoint of the backend
+    pub endpoint: String,
+    // Regions of a multi-region provider, each served from a subdomain of the endpoint
+    pub regions: Vec<String>,
+    // Signing region, defaults to the gateway region
+    pub region: Option<String>,
+    // Backend bucket holding the blobs of every gateway bucket, each under a
+    // prefix of its own. Without it gateway buckets map to backend buckets of
+    // the same name, which must already exist.
+    pub bucket: Option<String>,
+    // Falls back to AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY when unset
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    // Storage class held by this backend for lifecycle transitions
+    pub storage_class: Option<String>,
+    // Root directory of a LOCAL backend
+    pub path: Option<String>,
+    // Flushing of a LOCAL backend: none, file or full
+    pub fsync: Option<String>,
+    // RADOS pool of a CEPH backend, which is then reached through librados instead of RGW
+    pub pool: Option<String>,
+    // Ceph client name and config file used to connect to the pool
+    pub ceph_user: Option<String>,
+    pub ceph_conf: Option<String>,
+    // Size of the RADOS objects a blob is striped across
+    pub stripe_size: Option<u64>,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            provider: "AWS".to_string(),
+            endpoint: String::new(),
+            regions: vec![],
+            region: None,
+            bucket: None,
+            access_key_id: None,
+            secret_access_key: None,
+            storage_class: None,
+            path: None,
+            fsync: None,
+            pool: None,
+            ceph_user: None,
+            ceph_conf: None,
+            stripe_size: None,
+        }
+    }
+}
+
+impl StorageConfig {
+    /// Endpoint and signing region used by a gateway running in `region`.
+    /// Multi-region providers are reached through the gateway's region when
+    /// they serve it and through their first region otherwise.
+    pub fn endpoint(&self, region: &str) -> (String, String) {
+        let region = match self.regions.first() {
+            Some(first) if !self.regions.iter().any(|r| r == region) => first.as_str(),
+            Some(_) => region,
+            None => {
+                let region = self.region.as_deref().unwrap_or(region);
+                return (self.endpoint.clone(), region.to_string());
+            }
+        };
+        let endpoint = match self.endpoint.split_once("://") {
+            Some((scheme, host)) => format!("{}://{}.{}", scheme, region, host),
+            None => format!("{}.{}", region, self.endpoint),
+        };
+        (endpoint, region.to_string())
+    }
 }
 
 impl Config {
@@ -0,0 +1,187 @@
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
+use s3_core::S3Error;
+
+// Authentication tag appended to every chunk
+pub const TAG_LEN: u64 = 16;
+
+/// AES-256-GCM over fixed size chunks of an object.
+///
+/// Every chunk of `chunk_size` plaintext bytes, the last one shorter, is
+/// sealed on its own and stored as ciphertext followed by its tag, so any
+/// plaintext range maps to a ciphertext range of whole chunks that can be
+/// read and authenticated without the rest of the object. Each object has
+/// its own data key, the nonce of a chunk is its index and a flag marking
+/// the last chunk, so chunks cannot be reordered and truncation is detected.
+pub struct ChunkedCipher {
+    key: LessSafeKey,
+    chunk_size: u64,
+}
+
+impl ChunkedCipher {
+    pub fn new(data_key: &[u8; 32], chunk_size: u64) -> Self {
+        let key = UnboundKey::new(&AES_256_GCM, data_key).expect("data keys are 32 bytes");
+        Self {
+            key: LessSafeKey::new(key),
+            chunk_size: chunk_size.max(1),
+        }
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        let size = plaintext.len() as u64;
+        let chunks = chunk_count(size, self.chunk_size);
+        let mut ciphertext = Vec::with_capacity(encrypted_size(size, self.chunk_size) as usize);
+        for index in 0..chunks {
+            let start = (index * self.chunk_size) as usize;
+            let end = (start + self.chunk_size as usize).min(plaintext.len());
+            let mut chunk = plaintext[start..end].to_vec();
+            let tag = self
+                .key
+                .seal_in_place_separate_tag(chunk_nonce(index, chunks), Aad::empty(), &mut chunk)
+                .expect("chunks are far below the AES-GCM limit");
+            ciphertext.extend_from_slice(&chunk);
+            ciphertext.extend_from_slice(tag.as_ref());
+        }
+        ciphertext
+    }
+
+    /// Decrypt a whole object of `size` plaintext bytes.
+    pub fn decrypt(&self, ciphertext: &[u8], size: u64) -> Result<Vec<u8>, S3Error> {
+        let plaintext = self.open_chunks(ciphertext, 0, size)?;
+        check_length(plaintext, size)
+    }
+
+    /// Decrypt the inclusive plaintext range `start..=end` of an object of
+    /// `size` bytes from the ciphertext range given by `chunk_range`.
+    pub fn decrypt_range(
+        &self,
+        ciphertext: &[u8],
+        start: u64,
+        end: u64,
+        size: u64,
+    ) -> Result<Vec<u8>, S3Error> {
+        let first = start / self.chunk_size;
+        let mut plaintext = self.open_chunks(ciphertext, first, size)?;
+        let skip = (start - first * self.chunk_size) as usize;
+        plaintext.drain(..skip.min(plaintext.len()));
+        plaintext.truncate((end - start + 1) as usize);
+        check_length(plaintext, end - start + 1)
+    }
+
+    /// Inclusive range of ciphertext holding the chunks of the inclusive
+    /// plaintext range `start..=end` of an object of `size` bytes.
+    pub fn chunk_range(&self, start: u64, end: u64, size: u64) -> (u64, u64) {
+        let sealed_size = self.chunk_size + TAG_LEN;
+        let first = start / self.chunk_size;
+        let last = end / self.chunk_size;
+        let ciphertext_end = ((last + 1) * sealed_size).min(encrypted_size(size, self.chunk_size));
+        (first * sealed_size, ciphertext_end - 1)
+    }
+
+    // Open consecutive chunks starting at chunk `first`
+    fn open_chunks(&self, ciphertext: &[u8], first: u64, size: u64) -> Result<Vec<u8>, S3Error> {
+        let chunks = chunk_count(size, self.chunk_size);
+        let sealed_size = (self.chunk_size + TAG_LEN) as usize;
+        let mut plaintext = Vec::with_capacity(ciphertext.len());
+        for (offset, sealed) in ciphertext.chunks(sealed_size).enumerate() {
+            let index = first + offset as u64;
+            let mut chunk = sealed.to_vec();
+            let opened = self
+                .key
+                .open_in_place(chunk_nonce(index, chunks), Aad::empty(), &mut chunk)
+                .map_err(|_| {
+                    tracing::error!(
+                        "Chunk {} of an encrypted object failed authentication",
+                        index
+                    );
+                    S3Error::InternalError
+                })?;
+            plaintext.extend_from_slice(opened);
+        }
+        Ok(plaintext)
+    }
+}
+
+// Chunks missing at the end of the data pass authentication, the length
+// gives them away
+fn check_length(plaintext: Vec<u8>, expected: u64) -> Result<Vec<u8>, S3Error> {
+    if plaintext.len() as u64 != expected {
+        tracing::error!(
+            "Encrypted object holds {} bytes instead of {}",
+            plaintext.len(),
+            expected
+        );
+        return Err(S3Error::InternalError);
+    }
+    Ok(plaintext)
+}
+
+// An empty object still has one chunk, so its tag covers the empty payload
+fn chunk_count(size: u64, chunk_size: u64) -> u64 {
+    size.div_ceil(chunk_size).max(1)
+}
+
+/// Size of an object of `size` plaintext bytes once encrypted.
+pub fn encrypted_size(size: u64, chunk_size: u64) -> u64 {
+    size + chunk_count(size, chunk_size.max(1)) * TAG_LEN
+}
+
+fn chunk_nonce(index: u64, chunks: u64) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[..8].copy_from_slice(&index.to_be_bytes());
+    nonce[11] = (index + 1 == chunks) as u8;
+    Nonce::assume_unique_for_key(nonce)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let cipher = ChunkedCipher::new(&[7; 32], 4);
+        let plaintext = b"hello world";
+        let ciphertext = cipher.encrypt(plaintext);
+        assert_eq!(ciphertext.len() as u64, encrypted_size(11, 4));
+        assert_eq!(ciphertext.len(), 11 + 3 * 16);
+        assert_eq!(cipher.decrypt(&ciphertext, 11).unwrap(), plaintext);
+
+        let empty = cipher.encrypt(b"");
+        assert_eq!(empty.len(), 16);
+        assert!(cipher.decrypt(&empty, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_decrypt_range() {
+        let cipher = ChunkedCipher::new(&[7; 32], 4);
+        let ciphertext = cipher.encrypt(b"hello world");
+
+        // " worl" lies in the second and the last chunk
+        let (start, end) = cipher.chunk_range(5, 9, 11);
+        assert_eq!((start, end), (20, 58));
+        let range = &ciphertext[start as usize..=end as usize];
+        assert_eq!(cipher.decrypt_range(range, 5, 9, 11).unwrap(), b" worl");
+
+        assert_eq!(cipher.chunk_range(0, 3, 11), (0, 19));
+        let range = &ciphertext[..20];
+        assert_eq!(cipher.decrypt_range(range, 1, 2, 11).unwrap(), b"el");
+        // The range needs more chunks than were read
+        assert!(cipher.decrypt_range(range, 1, 5, 11).is_err());
+    }
+
+    #[test]
+    fn test_tampering() {
+        let cipher = ChunkedCipher::new(&[7; 32], 4);
+        let mut ciphertext = cipher.encrypt(b"hello world");
+
+        assert!(cipher.decrypt(&ciphertext[..40], 11).is_err());
+        // Without the last chunk the previous one is not marked final
+        assert!(cipher.decrypt(&ciphertext[..40], 8).is_err());
+        // Chunks are bound to their position
+        assert!(cipher.decrypt_range(&ciphertext[20..40], 0, 3, 11).is_err());
+        assert!(ChunkedCipher::new(&[8; 32], 4)
+            .decrypt(&ciphertext, 11)
+            .is_err());
+        ciphertext[0] ^= 1;
+        assert!(cipher.decrypt(&ciphertext, 11).is_err());
+    }
+}
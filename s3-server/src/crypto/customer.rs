@@ -0,0 +1,151 @@
+use axum::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use md5::{Digest, Md5};
+use ring::{
+    aead::{LessSafeKey, UnboundKey, AES_256_GCM},
+    hmac,
+    rand::{SecureRandom, SystemRandom},
+};
+use s3_core::S3Error;
+
+use super::{random_key, unwrap_key, wrap_key, DataKey, KeyManager};
+
+/// Key given by the client in the SSE-C headers of a request.
+///
+/// The key never leaves the request. It wraps the data key of the object,
+/// and the object keeps a salted HMAC of it so that reads with another key
+/// are refused before any data is read.
+pub struct CustomerKey {
+    key: LessSafeKey,
+    raw: [u8; 32],
+    // Base64 MD5 of the key, echoed in responses
+    md5: String,
+    rng: SystemRandom,
+}
+
+impl CustomerKey {
+    /// Build the key from the base64 key and key MD5 headers.
+    pub fn parse(key: &str, md5: &str) -> Result<Self, S3Error> {
+        let raw: [u8; 32] = STANDARD
+            .decode(key.trim())
+            .ok()
+            .and_then(|key| key.try_into().ok())
+            .ok_or_else(|| {
+                S3Error::InvalidArgument(
+                    "The secret key was invalid for the specified algorithm".to_string(),
+                )
+            })?;
+        let digest = STANDARD.encode(Md5::digest(raw));
+        if digest != md5.trim() {
+            return Err(S3Error::InvalidArgument(
+                "The calculated MD5 hash of the key did not match the hash that was provided"
+                    .to_string(),
+            ));
+        }
+        let key = UnboundKey::new(&AES_256_GCM, &raw).map_err(|_| S3Error::InternalError)?;
+        Ok(Self {
+            key: LessSafeKey::new(key),
+            raw,
+            md5: digest,
+            rng: SystemRandom::new(),
+        })
+    }
+
+    pub fn md5(&self) -> &str {
+        &self.md5
+    }
+
+    /// Salted HMAC-SHA256 of the key, `<salt>:<hmac>` in base64.
+    pub fn fingerprint(&self) -> Result<String, S3Error> {
+        let mut salt = [0u8; 16];
+        self.rng
+            .fill(&mut salt)
+            .map_err(|_| S3Error::InternalError)?;
+        let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &salt), &self.raw);
+        Ok(format!(
+            "{}:{}",
+            STANDARD.encode(salt),
+            STANDARD.encode(tag.as_ref())
+        ))
+    }
+
+    /// Whether this is the key a fingerprint was made of.
+    pub fn matches(&self, fingerprint: &str) -> bool {
+        let (salt, tag) = match fingerprint.split_once(':') {
+            Some(parts) => parts,
+            None => return false,
+        };
+        match (STANDARD.decode(salt), STANDARD.decode(tag)) {
+            (Ok(salt), Ok(tag)) => {
+                hmac::verify(&hmac::Key::new(hmac::HMAC_SHA256, &salt), &self.raw, &tag).is_ok()
+            }
+            _ => false,
+        }
+    }
+}
+
+// The customer key is the only master key, key ids are not used
+#[async_trait]
+impl KeyManager for CustomerKey {
+    async fn generate_data_key(
+        &self,
+        _key_id: Option<&str>,
+        context: &[u8],
+    ) -> Result<DataKey, S3Error> {
+        let plaintext = random_key(&self.rng)?;
+        let wrapped = wrap_key(&self.rng, &self.key, &plaintext, context)?;
+        Ok(DataKey {
+            key_id: String::new(),
+            plaintext,
+            wrapped,
+        })
+    }
+
+    async fn decrypt_data_key(
+        &self,
+        _key_id: &str,
+        wrapped: &[u8],
+        context: &[u8],
+    ) -> Result<[u8; 32], S3Error> {
+        unwrap_key(&self.key, wrapped, context)
+            .inspect_err(|_| tracing::error!("Failed to unwrap a data key with a customer key"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> CustomerKey {
+        let raw = [byte; 32];
+        CustomerKey::parse(&STANDARD.encode(raw), &STANDARD.encode(Md5::digest(raw))).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let raw = STANDARD.encode([1u8; 32]);
+        assert!(CustomerKey::parse(&raw, &STANDARD.encode(Md5::digest([2u8; 32]))).is_err());
+        let short = STANDARD.encode([1u8; 16]);
+        assert!(CustomerKey::parse(&short, &STANDARD.encode(Md5::digest([1u8; 16]))).is_err());
+        assert_eq!(key(1).md5(), STANDARD.encode(Md5::digest([1u8; 32])));
+    }
+
+    #[tokio::test]
+    async fn test_fingerprint() {
+        let (key, other) = (key(1), key(2));
+        let fingerprint = key.fingerprint().unwrap();
+        assert!(key.matches(&fingerprint));
+        assert!(!other.matches(&fingerprint));
+        // Salted, the same key never gives the same fingerprint
+        assert_ne!(key.fingerprint().unwrap(), fingerprint);
+        assert!(!key.matches("garbage"));
+
+        let data_key = key.generate_data_key(None, b"").await.unwrap();
+        let unwrapped = key.decrypt_data_key("", &data_key.wrapped, b"").await;
+        assert_eq!(unwrapped.unwrap(), data_key.plaintext);
+        assert!(other
+            .decrypt_data_key("", &data_key.wrapped, b"")
+            .await
+            .is_err());
+    }
+}
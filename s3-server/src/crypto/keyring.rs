@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use axum::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::{
+    aead::{LessSafeKey, UnboundKey, AES_256_GCM},
+    rand::SystemRandom,
+};
+use s3_core::S3Error;
+use serde::Deserialize;
+
+use super::{random_key, unwrap_key, wrap_key, DataKey, KeyManager};
+
+#[derive(Deserialize)]
+struct KeyringFile {
+    default: String,
+    // Key id to base64 of a 32 byte key
+    keys: HashMap<String, String>,
+}
+
+/// Master keys read from a TOML file:
+///
+/// ```toml
+/// default = "master-1"
+///
+/// [keys]
+/// master-1 = "<base64 of 32 random bytes>"
+/// ```
+///
+/// Data keys are sealed with AES-256-GCM under the master key, with the key
+/// id and the encryption context as associated data. Retired master keys
+/// stay in the file for as long as objects use them.
+pub struct LocalKeyring {
+    default: String,
+    keys: HashMap<String, LessSafeKey>,
+    rng: SystemRandom,
+}
+
+impl LocalKeyring {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read keyring {}: {}", path, e))?;
+        Self::parse(&content)
+    }
+
+    fn parse(content: &str) -> Result<Self, String> {
+        let file: KeyringFile =
+            toml::from_str(content).map_err(|e| format!("Invalid keyring: {}", e))?;
+        let mut keys = HashMap::new();
+        for (id, key) in file.keys {
+            let key = STANDARD
+                .decode(key.trim())
+                .ok()
+                .and_then(|key| UnboundKey::new(&AES_256_GCM, &key).ok())
+                .filter(|_| !id.is_empty())
+                .ok_or_else(|| format!("Invalid master key {}", id))?;
+            keys.insert(id, LessSafeKey::new(key));
+        }
+        if !keys.contains_key(&file.default) {
+            return Err(format!("Default master key {} is missing", file.default));
+        }
+        Ok(Self {
+            default: file.default,
+            keys,
+            rng: SystemRandom::new(),
+        })
+    }
+
+    fn master_key(&self, key_id: &str) -> Result<&LessSafeKey, S3Error> {
+        self.keys.get(key_id).ok_or_else(|| {
+            S3Error::InvalidArgument(format!("The master key {} does not exist", key_id))
+        })
+    }
+}
+
+#[async_trait]
+impl KeyManager for LocalKeyring {
+    async fn generate_data_key(
+        &self,
+        key_id: Option<&str>,
+        context: &[u8],
+    ) -> Result<DataKey, S3Error> {
+        let key_id = key_id.unwrap_or(&self.default);
+        let master = self.master_key(key_id)?;
+        let plaintext = random_key(&self.rng)?;
+        let wrapped = wrap_key(
+            &self.rng,
+            master,
+            &plaintext,
+            &associated_data(key_id, context),
+        )?;
+        Ok(DataKey {
+            key_id: key_id.to_string(),
+            plaintext,
+            wrapped,
+        })
+    }
+
+    async fn decrypt_data_key(
+        &self,
+        key_id: &str,
+        wrapped: &[u8],
+        context: &[u8],
+    ) -> Result<[u8; 32], S3Error> {
+        let master = self.master_key(key_id)?;
+        unwrap_key(master, wrapped, &associated_data(key_id, context)).inspect_err(|_| {
+            tracing::error!("Failed to unwrap a data key of master key {}", key_id);
+        })
+    }
+}
+
+// Key id and context, the key id is length prefixed so the two cannot run
+// into each other
+fn associated_data(key_id: &str, context: &[u8]) -> Vec<u8> {
+    let mut data = (key_id.len() as u32).to_be_bytes().to_vec();
+    data.extend_from_slice(key_id.as_bytes());
+    data.extend_from_slice(context);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyring() -> LocalKeyring {
+        LocalKeyring::parse(&format!(
+            "default = \"new\"\n[keys]\nold = \"{}\"\nnew = \"{}\"\n",
+            STANDARD.encode([1u8; 32]),
+            STANDARD.encode([2u8; 32])
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_wrap_data_key() {
+        let keyring = keyring();
+        let key = keyring.generate_data_key(None, b"ctx").await.unwrap();
+        assert_eq!(key.key_id, "new");
+        let unwrapped = keyring
+            .decrypt_data_key("new", &key.wrapped, b"ctx")
+            .await
+            .unwrap();
+        assert_eq!(unwrapped, key.plaintext);
+
+        assert!(keyring
+            .decrypt_data_key("new", &key.wrapped, b"other")
+            .await
+            .is_err());
+        assert!(keyring
+            .decrypt_data_key("old", &key.wrapped, b"ctx")
+            .await
+            .is_err());
+        assert!(keyring
+            .decrypt_data_key("new", &key.wrapped[..12], b"ctx")
+            .await
+            .is_err());
+
+        let key = keyring.generate_data_key(Some("old"), b"").await.unwrap();
+        assert_eq!(key.key_id, "old");
+        assert!(keyring.generate_data_key(Some("gone"), b"").await.is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(LocalKeyring::parse("default = \"a\"\n[keys]\n").is_err());
+        assert!(LocalKeyring::parse("default = \"a\"\n[keys]\na = \"c2hvcnQ=\"\n").is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use axum::async_trait;
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+use s3_core::S3Error;
+
+mod chunked;
+mod customer;
+mod keyring;
+pub use chunked::{encrypted_size, ChunkedCipher};
+pub use customer::CustomerKey;
+pub use keyring::LocalKeyring;
+
+/// Data key of an object, in plaintext to encrypt the object with and
+/// wrapped by a master key to be stored with it.
+pub struct DataKey {
+    pub key_id: String,
+    pub plaintext: [u8; 32],
+    pub wrapped: Vec<u8>,
+}
+
+/// Holder of the master keys that data keys are wrapped with, a local
+/// keyring or an external KMS.
+///
+/// `context` is the encryption context of the object, it has to be given
+/// again to unwrap the data key.
+#[async_trait]
+pub trait KeyManager: Send + Sync {
+    /// Create a data key wrapped by the master key `key_id`, or by the
+    /// default master key when None.
+    async fn generate_data_key(
+        &self,
+        key_id: Option<&str>,
+        context: &[u8],
+    ) -> Result<DataKey, S3Error>;
+
+    /// Unwrap a data key created by generate_data_key.
+    async fn decrypt_data_key(
+        &self,
+        key_id: &str,
+        wrapped: &[u8],
+        context: &[u8],
+    ) -> Result<[u8; 32], S3Error>;
+}
+
+fn random_key(rng: &SystemRandom) -> Result<[u8; 32], S3Error> {
+    let mut key = [0u8; 32];
+    rng.fill(&mut key).map_err(|_| {
+        tracing::error!("Failed to generate a data key");
+        S3Error::InternalError
+    })?;
+    Ok(key)
+}
+
+// Seal a data key under `master`, the wrapped key is nonce, sealed key, tag
+fn wrap_key(
+    rng: &SystemRandom,
+    master: &LessSafeKey,
+    key: &[u8; 32],
+    aad: &[u8],
+) -> Result<Vec<u8>, S3Error> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut nonce).map_err(|_| S3Error::InternalError)?;
+    let mut sealed = key.to_vec();
+    master
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(aad),
+            &mut sealed,
+        )
+        .map_err(|_| S3Error::InternalError)?;
+    let mut wrapped = nonce.to_vec();
+    wrapped.extend_from_slice(&sealed);
+    Ok(wrapped)
+}
+
+fn unwrap_key(master: &LessSafeKey, wrapped: &[u8], aad: &[u8]) -> Result<[u8; 32], S3Error> {
+    if wrapped.len() <= NONCE_LEN {
+        return Err(S3Error::InternalError);
+    }
+    let (nonce, sealed) = wrapped.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| S3Error::InternalError)?;
+    let mut sealed = sealed.to_vec();
+    let key = master
+        .open_in_place(nonce, Aad::from(aad), &mut sealed)
+        .map_err(|_| S3Error::InternalError)?;
+    key.try_into().map_err(|_| S3Error::InternalError)
+}
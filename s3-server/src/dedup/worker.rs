@@ -0,0 +1,46 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::config::DedupConfig;
+
+/// Deletes the content addressed blobs of dedup buckets once no object has
+/// referenced them for `grace_secs`.
+///
+/// Blobs are claimed in the index before their data is deleted, so every
+/// gateway may run a collector.
+pub struct BlobCollector {
+    fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+    config: DedupConfig,
+}
+
+impl BlobCollector {
+    pub fn new(fullstack: Arc<Box<crate::backend::FullstackBackend>>, config: DedupConfig) -> Self {
+        Self { fullstack, config }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(self.run());
+    }
+
+    async fn run(self) {
+        let grace = chrono::Duration::seconds(self.config.grace_secs as i64);
+        loop {
+            match self
+                .fullstack
+                .collect_blobs(chrono::Utc::now() - grace, self.config.batch_size)
+                .await
+            {
+                Ok(collected) => {
+                    if collected > 0 {
+                        tracing::info!("Collected {} unreferenced blobs", collected);
+                    }
+                    // More blobs may be due, look again right away
+                    if collected as i64 >= self.config.batch_size {
+                        continue;
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to collect unreferenced blobs: {:?}", e),
+            }
+            tokio::time::sleep(Duration::from_secs(self.config.poll_secs)).await;
+        }
+    }
+}
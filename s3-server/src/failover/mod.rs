@@ -0,0 +1,5 @@
+mod probe;
+mod reconcile;
+
+pub use probe::HealthProbe;
+pub use reconcile::ReconcileWorker;
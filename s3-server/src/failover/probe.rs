@@ -0,0 +1,33 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::config::FailoverConfig;
+
+/// Probes every storage backend in the background.
+///
+/// Probe results drive the circuit breakers of the backends, so writes fail
+/// over to a bucket's fallback backend before requests have to fail first,
+/// and are shown on the admin API. Every gateway probes on its own.
+pub struct HealthProbe {
+    fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+    config: FailoverConfig,
+}
+
+impl HealthProbe {
+    pub fn new(
+        fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+        config: FailoverConfig,
+    ) -> Self {
+        Self { fullstack, config }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(self.run());
+    }
+
+    async fn run(self) {
+        loop {
+            self.fullstack.probe_storage_backends().await;
+            tokio::time::sleep(Duration::from_secs(self.config.probe_secs)).await;
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::config::FailoverConfig;
+
+/// Moves versions written to a fallback backend back to their bucket's
+/// backend once it is healthy again.
+///
+/// Every gateway runs a worker. Versions are repointed with a conditional
+/// update, so two workers moving the same version leave a single copy.
+pub struct ReconcileWorker {
+    fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+    config: FailoverConfig,
+}
+
+impl ReconcileWorker {
+    pub fn new(
+        fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+        config: FailoverConfig,
+    ) -> Self {
+        Self { fullstack, config }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(self.run());
+    }
+
+    async fn run(self) {
+        loop {
+            match self
+                .fullstack
+                .reconcile_failover_writes(self.config.batch_size)
+                .await
+            {
+                Ok(0) => {}
+                Ok(moved) => tracing::info!("Moved {} versions back from fallback backends", moved),
+                Err(e) => tracing::warn!("Failed to list versions on fallback backends: {:?}", e),
+            }
+            tokio::time::sleep(Duration::from_secs(self.config.reconcile_secs)).await;
+        }
+    }
+}
@@ -18,6 +18,9 @@ impl AuthenticationFilter {
 #[async_trait]
 impl Filter for AuthenticationFilter {
     async fn handle(&self, data: &mut S3Data) -> Result<(), S3Error> {
+        if data.anonymous {
+            return Ok(());
+        }
         data.auth_key = self.authz.check(&data.req).await?;
         Ok(())
     }
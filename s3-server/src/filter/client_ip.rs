@@ -0,0 +1,30 @@
+use axum::async_trait;
+use s3_core::S3Error;
+
+use crate::proxy::{protocol::ClientTls, TrustedProxies};
+
+use super::{Filter, S3Data};
+
+pub struct ClientIpFilter {
+    trusted_proxies: TrustedProxies,
+}
+
+impl ClientIpFilter {
+    pub fn new(trusted_proxies: TrustedProxies) -> Self {
+        Self { trusted_proxies }
+    }
+}
+
+#[async_trait]
+impl Filter for ClientIpFilter {
+    async fn handle(&self, data: &mut S3Data) -> Result<(), S3Error> {
+        data.client_ip = self
+            .trusted_proxies
+            .resolve(data.peer_addr.ip(), data.req.headers());
+        data.secure = data.req.extensions().get::<ClientTls>().is_some()
+            || self
+                .trusted_proxies
+                .is_secure(data.peer_addr.ip(), data.req.headers());
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 mod authentication;
 mod bucket;
+mod client_ip;
 mod parser;
 mod rate_limiter;
 mod request_id;
@@ -8,6 +9,7 @@ mod types;
 
 pub use authentication::AuthenticationFilter;
 pub use bucket::BucketFilter;
+pub use client_ip::ClientIpFilter;
 pub use parser::ParserFilter;
 pub use rate_limiter::RateLimitFilter;
 pub use request_id::RequestIdFilter;
@@ -1,5 +1,5 @@
 use axum::async_trait;
-use s3_core::S3Error;
+use s3_core::{S3Action, S3Error};
 
 use crate::router::Router;
 
@@ -10,9 +10,9 @@ pub struct ParserFilter {
 }
 
 impl ParserFilter {
-    pub fn new(hosts: Vec<String>) -> Self {
+    pub fn new(hosts: Vec<String>, website_hosts: Vec<String>) -> Self {
         Self {
-            router: Router::new(hosts),
+            router: Router::new(hosts, website_hosts),
         }
     }
 }
@@ -21,6 +21,8 @@ impl ParserFilter {
 impl Filter for ParserFilter {
     async fn handle(&self, data: &mut S3Data) -> Result<(), S3Error> {
         let result = self.router.match_result(&data.req);
+        // Known before validating so that website errors are HTML pages
+        data.action = result.action;
 
         if !result.key.is_empty() {
             if result.key.len() > 1024 {
@@ -32,7 +34,11 @@ impl Filter for ParserFilter {
             return Err(S3Error::InvalidBucketName(result.bucket));
         }
 
-        data.action = result.action;
+        // Browsers never sign preflight requests, website visitors never sign
+        data.anonymous = matches!(
+            data.action,
+            S3Action::OptionsPreflight | S3Action::WebsiteRequest
+        );
         data.bucket_name = result.bucket;
         data.key = result.key;
         data.host = result.host;
@@ -1,53 +1,156 @@
+use std::{num::NonZero, sync::Arc, time::Duration};
+
 use axum::async_trait;
+use governor::clock::{Clock, DefaultClock};
 use s3_core::S3Error;
+use tokio::sync::RwLock;
+
+use crate::{
+    config::RateLimitConfig,
+    ratelimit::{ConcurrencyLimiter, Decision, RedisRateLimiter, RuleSet, Subjects},
+};
 
 use super::{Filter, S3Data};
 
 pub struct RateLimitFilter {
-    redis_client: redis::cluster::ClusterClient,
+    redis_rate_limiter: RedisRateLimiter,
     local_rate_limiter: governor::DefaultKeyedRateLimiter<String>,
+    rules: Arc<RwLock<Arc<RuleSet>>>,
+    concurrency_limiter: ConcurrencyLimiter,
+    config: RateLimitConfig,
 }
 
 impl RateLimitFilter {
     pub fn new(
         redis_client: redis::cluster::ClusterClient,
         local_rate_limiter: governor::DefaultKeyedRateLimiter<String>,
+        config: RateLimitConfig,
+        fullstack: Arc<Box<crate::backend::FullstackBackend>>,
     ) -> Self {
+        let rules = Arc::new(RwLock::new(Arc::new(RuleSet::default())));
+
+        // Refresh rules
+        tokio::spawn(Self::refresh_rules(
+            fullstack,
+            rules.clone(),
+            Duration::from_secs(config.rules_refresh_secs),
+        ));
+
         Self {
-            redis_client,
+            redis_rate_limiter: RedisRateLimiter::new(
+                redis_client,
+                Duration::from_millis(config.redis_timeout_ms),
+            ),
             local_rate_limiter,
+            rules,
+            concurrency_limiter: ConcurrencyLimiter::new(config.max_concurrent_requests_per_key),
+            config,
+        }
+    }
+
+    async fn refresh_rules(
+        fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+        rules: Arc<RwLock<Arc<RuleSet>>>,
+        interval: Duration,
+    ) {
+        loop {
+            match fullstack.list_rate_limits().await {
+                Ok(limits) => {
+                    let rule_set = RuleSet::new(limits);
+                    tracing::debug!("Loaded {} rate limit rules", rule_set.len());
+                    *rules.write().await = Arc::new(rule_set);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to refresh rate limit rules: {:?}", e);
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn check(
+        &self,
+        key: &str,
+        rps: u32,
+        burst: u32,
+        weight: u32,
+        local: &governor::DefaultKeyedRateLimiter<String>,
+    ) -> Result<(), S3Error> {
+        // Redis is the source of truth so the limit holds across every gateway.
+        // The local limiter only takes over while Redis is unreachable.
+        match self.redis_rate_limiter.check(key, rps, burst, weight).await {
+            Ok(Decision::Allowed) => Ok(()),
+            Ok(Decision::Limited(retry_after)) => Err(S3Error::SlowDown(retry_secs(retry_after))),
+            Err(e) => {
+                tracing::warn!(
+                    "Redis rate limiter unavailable, using local limiter: {:?}",
+                    e
+                );
+                let weight = NonZero::new(weight).unwrap_or(NonZero::<u32>::MIN);
+                match local.check_key_n(&key.to_string(), weight) {
+                    Ok(Ok(_)) => Ok(()),
+                    Ok(Err(not_until)) => Err(S3Error::SlowDown(retry_secs(
+                        not_until.wait_time_from(DefaultClock::default().now()),
+                    ))),
+                    // The weight can never be satisfied by this quota
+                    Err(_) => Err(S3Error::SlowDown(1)),
+                }
+            }
         }
     }
 }
 
+fn retry_secs(retry_after: Duration) -> u64 {
+    retry_after.as_secs_f64().ceil().max(1.0) as u64
+}
+
 #[async_trait]
 impl Filter for RateLimitFilter {
     async fn handle(&self, data: &mut S3Data) -> Result<(), S3Error> {
-        // Take token from both local and redis rate limiters
-        // Use local rate limiter first
-        let mut ip = None;
+        let ip = data.client_ip.to_string();
 
-        if let Some(forwarded_for) = data.req.headers().get("x-forwarded-for") {
-            ip = forwarded_for.to_str().ok();
-        }
-        if let Some(real_ip) = data.req.headers().get("x-real-ip") {
-            ip = real_ip.to_str().ok();
-        }
-        if let Some(cf_ip) = data.req.headers().get("cf-connecting-ip") {
-            ip = cf_ip.to_str().ok();
+        let subjects = Subjects {
+            user_id: data.auth_key.user_id,
+            access_key: data.auth_key.access_key.clone(),
+            bucket: data.bucket_name.clone(),
+            ip: ip.clone(),
+            action: data.action.class(),
+        };
+
+        let rules = self.rules.read().await.clone();
+        let matched = rules.matching(&subjects);
+
+        // The default per client limit applies unless an exclusive rule
+        // replaced it.
+        if !matched.iter().any(|(rule, _)| rule.limit.exclusive) {
+            self.check(
+                &ip,
+                self.config.rps,
+                self.config.burst,
+                1,
+                &self.local_rate_limiter,
+            )
+            .await?;
         }
-        if ip.is_none() {
-            return Err(S3Error::InvalidRequest);
+
+        for (rule, key) in matched {
+            self.check(&key, rule.rps, rule.burst, rule.weight, &rule.local)
+                .await?;
         }
 
-        match self.local_rate_limiter.check_key(&ip.unwrap().to_string()) {
-            Ok(_) => {}
-            Err(_) => {
-                return Err(S3Error::SlowDown);
-            }
+        if self.concurrency_limiter.is_enabled() {
+            let key = if data.anonymous {
+                &ip
+            } else {
+                &data.auth_key.access_key
+            };
+            let permit = self
+                .concurrency_limiter
+                .try_acquire(key)
+                .ok_or(S3Error::SlowDown(1))?;
+            data.concurrency_permit = Some(permit);
         }
 
-        // TODO: Implement rate limiting via redis and possibly tokio timeout
         Ok(())
     }
 }
@@ -21,6 +21,9 @@ impl SecretKeyFilter {
 #[async_trait]
 impl Filter for SecretKeyFilter {
     async fn handle(&self, data: &mut S3Data) -> Result<(), S3Error> {
+        if data.anonymous {
+            return Ok(());
+        }
         if data.auth_key.secret_key.is_empty()
             || self
                 .keys
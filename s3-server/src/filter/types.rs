@@ -1,7 +1,11 @@
-use std::sync::Arc;
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
 
 use axum::{async_trait, body::Bytes};
 use s3_core::{response::ResponseData, S3Action, S3Error};
+use tokio::sync::OwnedSemaphorePermit;
 
 use crate::{backend::types, signature::Key};
 #[async_trait]
@@ -16,6 +20,16 @@ pub struct S3Data {
     // Request ID
     pub request_id: String,
 
+    // Address of the connection peer, which may be a proxy
+    pub peer_addr: SocketAddr,
+
+    // Address of the client that originated the request
+    pub client_ip: IpAddr,
+
+    // Whether the client connected over TLS, as reported by a PROXY protocol
+    // header or TrustedProxies::is_secure
+    pub secure: bool,
+
     pub auth_key: Key,
 
     // Bucket the request is for - backend bucket type
@@ -31,6 +45,12 @@ pub struct S3Data {
     pub host: String,
 
     pub action: S3Action,
+
+    // Set for actions served without a signature, e.g. CORS preflight
+    pub anonymous: bool,
+
+    // Held while the request is in flight to enforce concurrency limits
+    pub concurrency_permit: Option<OwnedSemaphorePermit>,
 }
 
 impl S3Data {
@@ -39,6 +59,9 @@ impl S3Data {
             req: axum::http::Request::new(Bytes::default()),
             res: ResponseData::new(),
             request_id: "".to_string(),
+            peer_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+            client_ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            secure: false,
             auth_key: Key {
                 access_key: "".to_string(),
                 secret_key: "".to_string(),
@@ -49,8 +72,19 @@ impl S3Data {
             key: "".to_string(),
             host: "".to_string(),
             action: S3Action::Unknown,
+            anonymous: false,
+            concurrency_permit: None,
         }
     }
+
+    /// Decoded value of a parameter of the request's query string, e.g.
+    /// the uploadId of a multipart upload request.
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        self.req.uri().query()?.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (s3_core::url_decode(key)? == name).then(|| s3_core::url_decode(value))?
+        })
+    }
 }
 
 pub struct FilterChain {
@@ -69,7 +103,7 @@ impl FilterChain {
             filter.handle(data).await?;
         }
 
-        tracing::debug!(operation = ?data.action, "Request completed");
+        tracing::debug!(operation = ?data.action, client_ip = %data.client_ip, "Request completed");
         Ok(())
     }
 }
@@ -22,6 +22,54 @@ impl Server {
         axum::response::IntoResponse::into_response(response)
     }
 
+    pub async fn put_bucket_cors(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.put_bucket_cors(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn get_bucket_cors(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.get_bucket_cors(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn delete_bucket_cors(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.delete_bucket_cors(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn put_bucket_lifecycle(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.put_bucket_lifecycle(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn get_bucket_lifecycle(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.get_bucket_lifecycle(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn delete_bucket_lifecycle(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.delete_bucket_lifecycle(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
     pub async fn delete_bucket(
         state: &Arc<AppState>,
         data: &mut S3Data,
@@ -29,4 +77,170 @@ impl Server {
         let response = state.fullstack.delete_bucket(data).await;
         axum::response::IntoResponse::into_response(response)
     }
+
+    pub async fn put_bucket_encryption(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.put_bucket_encryption(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn get_bucket_encryption(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.get_bucket_encryption(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn delete_bucket_encryption(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.delete_bucket_encryption(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn put_bucket_tagging(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.put_bucket_tagging(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn get_bucket_tagging(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.get_bucket_tagging(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn delete_bucket_tagging(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.delete_bucket_tagging(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn put_object_lock_configuration(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.put_object_lock_configuration(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn get_object_lock_configuration(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.get_object_lock_configuration(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn put_bucket_versioning(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.put_bucket_versioning(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn get_bucket_versioning(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.get_bucket_versioning(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn put_bucket_website(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.put_bucket_website(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn get_bucket_website(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.get_bucket_website(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn delete_bucket_website(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.delete_bucket_website(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn put_bucket_logging(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.put_bucket_logging(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn get_bucket_logging(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.get_bucket_logging(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn put_bucket_notification_configuration(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state
+            .fullstack
+            .put_bucket_notification_configuration(data)
+            .await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn get_bucket_notification_configuration(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state
+            .fullstack
+            .get_bucket_notification_configuration(data)
+            .await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn put_bucket_replication(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.put_bucket_replication(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn get_bucket_replication(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.get_bucket_replication(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn delete_bucket_replication(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.delete_bucket_replication(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
 }
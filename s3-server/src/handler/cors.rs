@@ -0,0 +1,113 @@
+use axum::{
+    http::{HeaderMap, HeaderName, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use s3_core::{cors::CorsRule, response::ResponseData, S3Error};
+
+use crate::{filter::S3Data, server::Server};
+
+impl Server {
+    pub fn options_preflight(data: &mut S3Data) -> Response {
+        let headers = data.req.headers();
+        let origin = header(headers, "origin");
+        let method = header(headers, "access-control-request-method");
+        let request_headers = header(headers, "access-control-request-headers")
+            .split(',')
+            .map(|header| header.trim().to_string())
+            .filter(|header| !header.is_empty())
+            .collect::<Vec<_>>();
+
+        let rule = data
+            .bucket
+            .as_ref()
+            .and_then(|bucket| bucket.cors.as_ref())
+            .and_then(|cors| cors.find_rule(&origin, &method, &request_headers));
+        let rule = match rule {
+            Some(rule) => rule,
+            None => return S3Error::AccessForbidden.into_response(),
+        };
+
+        let mut res = ResponseData::new();
+        for (key, value) in cors_headers(rule, &origin) {
+            res.with_header(key, value);
+        }
+        if !request_headers.is_empty() {
+            res.with_header(
+                "Access-Control-Allow-Headers".to_string(),
+                request_headers.join(", "),
+            );
+        }
+        res.with_status_code(200);
+        res.into_response()
+    }
+
+    /// Add the CORS headers of the first rule matching the request's Origin
+    /// and method. Requests without Origin or a matching rule are untouched.
+    pub fn apply_cors(data: &S3Data, response: &mut Response) {
+        if data.anonymous {
+            return;
+        }
+        let headers = data.req.headers();
+        let origin = header(headers, "origin");
+        if origin.is_empty() {
+            return;
+        }
+
+        let rule = data
+            .bucket
+            .as_ref()
+            .and_then(|bucket| bucket.cors.as_ref())
+            .and_then(|cors| cors.find_rule(&origin, data.req.method().as_str(), &[]));
+        if let Some(rule) = rule {
+            for (key, value) in cors_headers(rule, &origin) {
+                if let (Ok(key), Ok(value)) =
+                    (HeaderName::try_from(key), HeaderValue::try_from(value))
+                {
+                    response.headers_mut().insert(key, value);
+                }
+            }
+        }
+    }
+}
+
+fn header(headers: &HeaderMap, name: &str) -> String {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn cors_headers(rule: &CorsRule, origin: &str) -> Vec<(String, String)> {
+    let mut headers = vec![(
+        "Access-Control-Allow-Methods".to_string(),
+        rule.allowed_methods.join(", "),
+    )];
+    // A wildcard rule answers "*" and never allows credentials, as S3 does
+    if rule.allows_any_origin() {
+        headers.push(("Access-Control-Allow-Origin".to_string(), "*".to_string()));
+    } else {
+        headers.push((
+            "Access-Control-Allow-Origin".to_string(),
+            origin.to_string(),
+        ));
+        headers.push((
+            "Access-Control-Allow-Credentials".to_string(),
+            "true".to_string(),
+        ));
+    }
+    if !rule.expose_headers.is_empty() {
+        headers.push((
+            "Access-Control-Expose-Headers".to_string(),
+            rule.expose_headers.join(", "),
+        ));
+    }
+    if let Some(max_age) = rule.max_age_seconds {
+        headers.push(("Access-Control-Max-Age".to_string(), max_age.to_string()));
+    }
+    headers.push((
+        "Vary".to_string(),
+        "Origin, Access-Control-Request-Headers, Access-Control-Request-Method".to_string(),
+    ));
+    headers
+}
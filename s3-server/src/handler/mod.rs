@@ -1,2 +1,3 @@
 mod bucket;
+mod cors;
 mod object;
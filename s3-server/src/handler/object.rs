@@ -15,4 +15,126 @@ impl Server {
         let response = state.fullstack.get_object(data).await;
         axum::response::IntoResponse::into_response(response)
     }
+
+    pub async fn head_object(state: &Arc<AppState>, data: &mut S3Data) -> axum::response::Response {
+        let response = state.fullstack.head_object(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn delete_object(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.delete_object(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn delete_objects(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.delete_objects(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn copy_object(state: &Arc<AppState>, data: &mut S3Data) -> axum::response::Response {
+        let response = state.fullstack.copy_object(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn create_multipart_upload(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.create_multipart_upload(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn upload_part(state: &Arc<AppState>, data: &mut S3Data) -> axum::response::Response {
+        let response = state.fullstack.upload_part(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn complete_multipart_upload(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.complete_multipart_upload(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn abort_multipart_upload(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.abort_multipart_upload(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn put_object_tagging(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.put_object_tagging(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn get_object_tagging(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.get_object_tagging(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn delete_object_tagging(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.delete_object_tagging(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn put_object_retention(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.put_object_retention(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn get_object_retention(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.get_object_retention(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn put_object_legal_hold(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.put_object_legal_hold(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    pub async fn get_object_legal_hold(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        let response = state.fullstack.get_object_legal_hold(data).await;
+        axum::response::IntoResponse::into_response(response)
+    }
+
+    // Errors of the website endpoint are HTML pages
+    pub async fn website_request(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+    ) -> axum::response::Response {
+        match state.fullstack.website_request(data).await {
+            Ok(response) => axum::response::IntoResponse::into_response(response),
+            Err(e) => e.into_html_response(),
+        }
+    }
 }
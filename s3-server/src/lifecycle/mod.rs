@@ -0,0 +1,3 @@
+mod worker;
+
+pub use worker::LifecycleWorker;
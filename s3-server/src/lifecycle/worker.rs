@@ -0,0 +1,80 @@
+use std::{sync::Arc, time::Duration};
+
+use s3_core::S3Error;
+
+use crate::config::LifecycleConfig;
+
+/// Applies bucket lifecycle rules in the background.
+///
+/// Every gateway runs a worker. Rules are leased from the lifecycle_rules
+/// table, so each due rule is applied by a single worker at a time.
+pub struct LifecycleWorker {
+    fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+    config: LifecycleConfig,
+}
+
+impl LifecycleWorker {
+    pub fn new(
+        fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+        config: LifecycleConfig,
+    ) -> Self {
+        Self { fullstack, config }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(self.run());
+    }
+
+    async fn run(self) {
+        loop {
+            match self.run_once().await {
+                Ok(0) => {}
+                Ok(rules) => tracing::debug!("Applied {} lifecycle rules", rules),
+                Err(e) => tracing::warn!("Failed to claim lifecycle rules: {:?}", e),
+            }
+            tokio::time::sleep(Duration::from_secs(self.config.poll_secs)).await;
+        }
+    }
+
+    async fn run_once(&self) -> Result<usize, S3Error> {
+        let now = chrono::Utc::now();
+        let lease = chrono::Duration::seconds(self.config.lease_secs as i64);
+        let rules = self
+            .fullstack
+            .claim_lifecycle_rules(now + lease, self.config.rules_per_poll)
+            .await?;
+
+        for rule in &rules {
+            match self
+                .fullstack
+                .apply_lifecycle_rule(rule, now, self.config.batch_size)
+                .await
+            {
+                Ok(actions) => {
+                    tracing::debug!(
+                        bucket = %rule.bucket_name,
+                        rule = ?rule.rule.id,
+                        "Lifecycle rule applied to {} objects",
+                        actions
+                    );
+                    let interval = chrono::Duration::seconds(self.config.interval_secs as i64);
+                    if let Err(e) = self
+                        .fullstack
+                        .complete_lifecycle_rule(rule.id, chrono::Utc::now() + interval)
+                        .await
+                    {
+                        tracing::warn!("Failed to reschedule lifecycle rule: {:?}", e);
+                    }
+                }
+                // The rule is retried by any worker once its lease expires
+                Err(e) => tracing::warn!(
+                    bucket = %rule.bucket_name,
+                    rule = ?rule.rule.id,
+                    "Failed to apply lifecycle rule: {:?}",
+                    e
+                ),
+            }
+        }
+        Ok(rules.len())
+    }
+}
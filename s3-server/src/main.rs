@@ -1,40 +1,85 @@
 use std::{num::NonZero, str::FromStr, sync::Arc, vec};
 
-use aws_config::Region;
-use aws_sdk_s3::config::{Credentials, SharedCredentialsProvider};
 use config::Config;
 use tonic::transport::Endpoint;
 use tracing_subscriber::EnvFilter;
 
+mod access_log;
+mod admin;
 mod backend;
 mod config;
+mod crypto;
+mod dedup;
+mod failover;
 mod filter;
 mod handler;
+mod lifecycle;
+mod migration;
+mod notification;
+mod packing;
+mod proxy;
+mod ratelimit;
+mod replication;
 mod router;
 mod server;
 mod signature;
 
-fn create_storage(config: &Config) -> Box<crate::backend::storage::storage::StorageBackend> {
-    let access_key_id = std::env::var("AWS_ACCESS_KEY_ID").unwrap_or_default();
-    let secret_access_key = std::env::var("AWS_SECRET_ACCESS_KEY").unwrap_or_default();
-
-    tracing::debug!("Using AWS credentials: {}", access_key_id);
-
-    let sdk_config = aws_config::SdkConfig::builder()
-        .region(Region::new(config.region.clone()))
-        .endpoint_url("https://sfo3.digitaloceanspaces.com")
-        .credentials_provider(SharedCredentialsProvider::new(Credentials::new(
-            access_key_id,
-            secret_access_key,
-            None,
-            None,
-            "",
-        )))
-        .build();
-
-    let storage =
-        backend::storage::storage::StorageBackend::new(aws_sdk_s3::Client::new(&sdk_config));
-    Box::new(storage)
+fn configured_storage_backends(config: &Config) -> Vec<backend::types::StorageBackend> {
+    config
+        .storage
+        .iter()
+        .map(|(name, storage)| {
+            let (endpoint, region) = storage.endpoint(&config.region);
+            let mut credentials = serde_json::Map::new();
+            let access_key_id = storage
+                .access_key_id
+                .clone()
+                .or_else(|| std::env::var("AWS_ACCESS_KEY_ID").ok());
+            let secret_access_key = storage
+                .secret_access_key
+                .clone()
+                .or_else(|| std::env::var("AWS_SECRET_ACCESS_KEY").ok());
+            if let (Some(access_key_id), Some(secret_access_key)) =
+                (access_key_id, secret_access_key)
+            {
+                tracing::debug!("Using credentials {} for storage {}", access_key_id, name);
+                credentials.insert("access_key_id".to_string(), access_key_id.into());
+                credentials.insert("secret_access_key".to_string(), secret_access_key.into());
+            }
+            let mut options = serde_json::Map::new();
+            if let Some(bucket) = &storage.bucket {
+                options.insert("bucket".to_string(), bucket.clone().into());
+            }
+            if let Some(path) = &storage.path {
+                options.insert("path".to_string(), path.clone().into());
+            }
+            if let Some(fsync) = &storage.fsync {
+                options.insert("fsync".to_string(), fsync.clone().into());
+            }
+            if let Some(pool) = &storage.pool {
+                options.insert("pool".to_string(), pool.clone().into());
+            }
+            if let Some(user) = &storage.ceph_user {
+                options.insert("user".to_string(), user.clone().into());
+            }
+            if let Some(conf) = &storage.ceph_conf {
+                options.insert("conf".to_string(), conf.clone().into());
+            }
+            if let Some(stripe_size) = storage.stripe_size {
+                options.insert("stripe_size".to_string(), stripe_size.into());
+            }
+            backend::types::StorageBackend {
+                id: uuid::Uuid::now_v7(),
+                name: name.clone(),
+                provider: storage.provider.clone(),
+                credentials: credentials.into(),
+                endpoint: Some(endpoint),
+                region: Some(region),
+                storage_class: storage.storage_class.clone(),
+                config: options.into(),
+            }
+        })
+        .collect()
 }
 
 fn create_indexer(config: &Config) -> Result<Box<crate::backend::FullstackBackend>, String> {
@@ -51,8 +96,27 @@ fn create_indexer(config: &Config) -> Result<Box<crate::backend::FullstackBacken
                 .map_err(|e| format!("Failed to connect to postgres: {}", e))?;
 
             let postgres = Box::new(backend::Database::new(pool));
-            let storage = create_storage(config);
-            Ok(Box::new(backend::FullstackBackend::new(postgres, storage)))
+            let mut fullstack = backend::FullstackBackend::new(postgres)
+                .with_packing(config.packing.clone())
+                .with_circuit_breakers(config.circuit.clone());
+            if !config.encryption.keyring.is_empty() {
+                let keyring = crypto::LocalKeyring::from_file(&config.encryption.keyring)?;
+                fullstack = fullstack.with_encryption(Arc::new(keyring), config.encryption.clone());
+            }
+            if config.enable_compression {
+                fullstack = fullstack.with_compression(config.compression.clone());
+            }
+            if config.read_fallback.enabled {
+                fullstack = fullstack.with_read_fallback(config.read_fallback.clone());
+            }
+            if config.failover.enabled {
+                fullstack = fullstack.with_write_failover();
+            }
+            if !config.notification.queue_dir.is_empty() {
+                let notifier = notification::Notifier::new(&config.notification, &config.region)?;
+                fullstack = fullstack.with_notifications(notifier);
+            }
+            Ok(Box::new(fullstack))
         }
         _ => Err(format!("Unknown meta_store: {}", config.meta_store)),
     }
@@ -85,7 +149,66 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let endpoint = Endpoint::from_str(&config.iam_address)?;
     let iam_client = s3_iam::iampb::iam::iam_client::IamClient::new(endpoint.connect_lazy());
 
-    let backend = create_indexer(&config)?;
+    let backend = Arc::new(create_indexer(&config)?);
+    let count = backend
+        .load_storage_backends(
+            &configured_storage_backends(&config),
+            config.default_storage_backend.as_deref(),
+        )
+        .await
+        .map_err(|e| format!("Failed to load storage backends: {:?}", e))?;
+    tracing::info!("Loaded {} storage backends", count);
+
+    if config.lifecycle.enabled {
+        lifecycle::LifecycleWorker::new(backend.clone(), config.lifecycle).start();
+    }
+    if config.migration.enabled {
+        migration::MigrationWorker::new(backend.clone(), config.migration).start();
+    }
+    if config.packing.enabled && config.packing.compaction_enabled {
+        packing::CompactionWorker::new(backend.clone(), config.packing.clone()).start();
+    }
+    if config.dedup.enabled {
+        dedup::BlobCollector::new(backend.clone(), config.dedup.clone()).start();
+    }
+    if !config.notification.queue_dir.is_empty() {
+        let queue = notification::EventQueue::new(&config.notification.queue_dir);
+        for (name, target) in &config.notification.targets {
+            if target.kind == notification::WEBHOOK_TARGET {
+                notification::WebhookWorker::new(
+                    name.clone(),
+                    target.clone(),
+                    queue.clone(),
+                    config.notification.clone(),
+                )
+                .start();
+            }
+        }
+    }
+    if config.replication.enabled {
+        replication::ReplicationWorker::new(backend.clone(), config.replication.clone()).start();
+    }
+    if config.failover.enabled {
+        failover::HealthProbe::new(backend.clone(), config.failover.clone()).start();
+        failover::ReconcileWorker::new(backend.clone(), config.failover.clone()).start();
+    }
+    if !config.admin_key.is_empty() {
+        admin::AdminServer::new(
+            config.bind_admin_http_address.clone(),
+            config.admin_key.clone(),
+            backend.clone(),
+        )
+        .start();
+    }
+
+    let access_logger = access_log::AccessLogWriter::new(
+        backend.clone(),
+        config.access_logging.clone(),
+        &config.access_log_path,
+        &config.access_log_format,
+    )
+    .map_err(|e| format!("Failed to open {}: {:?}", config.access_log_path, e))?
+    .start();
 
     let redis_client = redis::cluster::ClusterClientBuilder::new(vec![config.redis_address])
         .username(config.redis_username.clone())
@@ -94,16 +217,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .response_timeout(std::time::Duration::from_secs(config.redis_read_timeout))
         .build()?;
     let local_rate_limiter: governor::DefaultKeyedRateLimiter<String> =
-        governor::RateLimiter::keyed(governor::Quota::per_second(NonZero::new(10).unwrap()));
+        governor::RateLimiter::keyed(
+            governor::Quota::per_second(NonZero::new(config.rate_limit.rps.max(1)).unwrap())
+                .allow_burst(NonZero::new(config.rate_limit.burst.max(1)).unwrap()),
+        );
+
+    let trusted_proxies = proxy::TrustedProxies::new(&config.proxy.trusted_proxies)?;
 
     tracing::info!("Starting server on {}", &config.bind_api_address);
+    let settings = server::ServerSettings {
+        addr: config.bind_api_address,
+        hosts: config.s3domain,
+        website_hosts: config.website_domain,
+        rate_limit: config.rate_limit,
+        trusted_proxies,
+        proxy: config.proxy,
+    };
     let server = server::Server::new(
-        config.bind_api_address,
-        config.s3domain,
+        settings,
         iam_client,
-        Arc::new(backend),
+        backend,
         redis_client,
         local_rate_limiter,
+        access_logger,
     )
     .await;
     Ok(server.start().await?)
@@ -0,0 +1,80 @@
+use std::{sync::Arc, time::Duration};
+
+use s3_core::S3Error;
+
+use crate::config::MigrationConfig;
+
+/// Runs bucket migrations between storage backends in the background.
+///
+/// Migrations are started through the admin API. Every gateway runs a
+/// worker, and a running migration is leased from the backend_migrations
+/// table, so a crashed run is resumed by any worker from its last checkpoint.
+pub struct MigrationWorker {
+    fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+    config: MigrationConfig,
+}
+
+impl MigrationWorker {
+    pub fn new(
+        fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+        config: MigrationConfig,
+    ) -> Self {
+        Self { fullstack, config }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(self.run());
+    }
+
+    async fn run(self) {
+        loop {
+            match self.run_once().await {
+                // Look for the next migration right away
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Failed to claim bucket migration: {:?}", e),
+            }
+            tokio::time::sleep(Duration::from_secs(self.config.poll_secs)).await;
+        }
+    }
+
+    async fn run_once(&self) -> Result<bool, S3Error> {
+        let lease = chrono::Duration::seconds(self.config.lease_secs as i64);
+        let migration = match self
+            .fullstack
+            .claim_backend_migration(chrono::Utc::now() + lease)
+            .await?
+        {
+            Some(migration) => migration,
+            None => return Ok(false),
+        };
+
+        tracing::info!(
+            bucket = %migration.bucket_name,
+            migration = %migration.id,
+            "Migrating bucket from storage backend {} to {}",
+            migration.source_backend_id,
+            migration.target_backend_id
+        );
+        match self
+            .fullstack
+            .run_backend_migration(&migration, self.config.batch_size, lease)
+            .await
+        {
+            Ok(true) => tracing::info!(
+                bucket = %migration.bucket_name,
+                migration = %migration.id,
+                "Bucket migration completed"
+            ),
+            Ok(false) => {}
+            // The migration is resumed by any worker once its lease expires
+            Err(e) => tracing::warn!(
+                bucket = %migration.bucket_name,
+                migration = %migration.id,
+                "Failed to migrate bucket: {:?}",
+                e
+            ),
+        }
+        Ok(true)
+    }
+}
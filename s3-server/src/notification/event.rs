@@ -0,0 +1,229 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Body of a notification, the standard S3 event message.
+///
+/// https://docs.aws.amazon.com/AmazonS3/latest/userguide/notification-content-structure.html
+#[derive(Serialize, Debug, Clone)]
+pub struct EventMessage {
+    #[serde(rename = "Records")]
+    pub records: Vec<EventRecord>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EventRecord {
+    pub event_version: String,
+    pub event_source: String,
+    pub aws_region: String,
+    pub event_time: String,
+    // The event type without its s3: prefix, e.g. ObjectCreated:Put
+    pub event_name: String,
+    pub user_identity: Identity,
+    pub request_parameters: RequestParameters,
+    pub response_elements: ResponseElements,
+    pub s3: S3Entity,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Identity {
+    pub principal_id: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RequestParameters {
+    #[serde(rename = "sourceIPAddress")]
+    pub source_ip_address: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ResponseElements {
+    #[serde(rename = "x-amz-request-id")]
+    pub request_id: String,
+    #[serde(rename = "x-amz-id-2")]
+    pub host_id: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct S3Entity {
+    pub s3_schema_version: String,
+    pub configuration_id: String,
+    pub bucket: BucketEntity,
+    pub object: ObjectEntity,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BucketEntity {
+    pub name: String,
+    pub owner_identity: Identity,
+    pub arn: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectEntity {
+    // URL encoded like a form value
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e_tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+    // Orders the events of a key, later events have greater sequencers
+    pub sequencer: String,
+}
+
+/// What happened to which object, as reported by the backend.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectEvent {
+    // e.g. s3:ObjectCreated:Put
+    pub name: String,
+    pub time: DateTime<Utc>,
+    pub bucket: String,
+    pub bucket_owner: i64,
+    pub key: String,
+    pub size: Option<i64>,
+    pub etag: Option<String>,
+    pub version_id: Option<String>,
+    pub sequencer: String,
+    // User id of the requester, or the service for lifecycle actions
+    pub principal: String,
+    pub source_ip: String,
+    pub request_id: String,
+}
+
+/// Message sent to the targets of a notification configuration when it is
+/// set, so that their owner can tell they are reachable.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct TestEvent {
+    pub service: String,
+    pub event: String,
+    pub time: String,
+    pub bucket: String,
+    pub request_id: String,
+    pub host_id: String,
+}
+
+impl TestEvent {
+    pub fn new(bucket: &str, request_id: &str, time: DateTime<Utc>) -> Self {
+        Self {
+            service: "Amazon S3".to_string(),
+            event: "s3:TestEvent".to_string(),
+            time: event_time(time),
+            bucket: bucket.to_string(),
+            request_id: request_id.to_string(),
+            host_id: String::new(),
+        }
+    }
+}
+
+impl EventRecord {
+    pub fn new(event: &ObjectEvent, region: &str, configuration_id: &str) -> Self {
+        Self {
+            event_version: "2.1".to_string(),
+            event_source: "aws:s3".to_string(),
+            aws_region: region.to_string(),
+            event_time: event_time(event.time),
+            event_name: event
+                .name
+                .strip_prefix("s3:")
+                .unwrap_or(&event.name)
+                .to_string(),
+            user_identity: Identity {
+                principal_id: event.principal.clone(),
+            },
+            request_parameters: RequestParameters {
+                source_ip_address: event.source_ip.clone(),
+            },
+            response_elements: ResponseElements {
+                request_id: event.request_id.clone(),
+                host_id: String::new(),
+            },
+            s3: S3Entity {
+                s3_schema_version: "1.0".to_string(),
+                configuration_id: configuration_id.to_string(),
+                bucket: BucketEntity {
+                    name: event.bucket.clone(),
+                    owner_identity: Identity {
+                        principal_id: event.bucket_owner.to_string(),
+                    },
+                    arn: format!("arn:aws:s3:::{}", event.bucket),
+                },
+                object: ObjectEntity {
+                    key: form_encode(&event.key),
+                    size: event.size,
+                    e_tag: event.etag.clone(),
+                    version_id: event.version_id.clone(),
+                    sequencer: event.sequencer.clone(),
+                },
+            },
+        }
+    }
+}
+
+fn event_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+// Keys are encoded like HTML form values, spaces become +
+fn form_encode(key: &str) -> String {
+    let mut encoded = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'*' | b'/' => {
+                encoded.push(byte as char)
+            }
+            b' ' => encoded.push('+'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_event_record() {
+        let event = ObjectEvent {
+            name: "s3:ObjectCreated:Put".to_string(),
+            time: Utc.with_ymd_and_hms(2024, 2, 6, 0, 0, 38).unwrap(),
+            bucket: "photos".to_string(),
+            bucket_owner: 42,
+            key: "summer 2024/café.jpg".to_string(),
+            size: Some(113),
+            etag: Some("d41d8cd98f00b204e9800998ecf8427e".to_string()),
+            sequencer: "0192D4B87C2A7B3E".to_string(),
+            principal: "42".to_string(),
+            source_ip: "192.0.2.3".to_string(),
+            request_id: "3E57427F3EXAMPLE".to_string(),
+            ..Default::default()
+        };
+        let message = EventMessage {
+            records: vec![EventRecord::new(&event, "sfo3", "images")],
+        };
+        let json = serde_json::to_value(&message).unwrap();
+        let record = &json["Records"][0];
+        assert_eq!(record["eventName"], "ObjectCreated:Put");
+        assert_eq!(record["eventTime"], "2024-02-06T00:00:38.000Z");
+        assert_eq!(record["awsRegion"], "sfo3");
+        assert_eq!(record["requestParameters"]["sourceIPAddress"], "192.0.2.3");
+        assert_eq!(
+            record["responseElements"]["x-amz-request-id"],
+            "3E57427F3EXAMPLE"
+        );
+        assert_eq!(record["s3"]["configurationId"], "images");
+        assert_eq!(record["s3"]["bucket"]["arn"], "arn:aws:s3:::photos");
+        assert_eq!(record["s3"]["object"]["key"], "summer+2024/caf%C3%A9.jpg");
+        assert_eq!(record["s3"]["object"]["size"], 113);
+        assert!(record["s3"]["object"].get("versionId").is_none());
+    }
+}
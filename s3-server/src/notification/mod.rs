@@ -0,0 +1,9 @@
+mod event;
+mod notifier;
+mod queue;
+mod worker;
+
+pub use event::{EventMessage, EventRecord, ObjectEvent, TestEvent};
+pub use notifier::{Notifier, WEBHOOK_TARGET};
+pub use queue::{EventQueue, QueuedEvent};
+pub use worker::WebhookWorker;
@@ -0,0 +1,50 @@
+use std::collections::HashSet;
+
+use crate::config::NotificationConfig;
+
+use super::EventQueue;
+
+pub static WEBHOOK_TARGET: &str = "webhook";
+pub static QUEUE_TARGET: &str = "queue";
+
+/// Queues the events of buckets for the targets of the config file.
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    region: String,
+    targets: HashSet<String>,
+    queue: EventQueue,
+}
+
+impl Notifier {
+    pub fn new(config: &NotificationConfig, region: &str) -> Result<Self, String> {
+        for (name, target) in &config.targets {
+            if target.kind == WEBHOOK_TARGET && target.endpoint.is_empty() {
+                return Err(format!("Notification target {} has no endpoint", name));
+            }
+            if target.kind != WEBHOOK_TARGET && target.kind != QUEUE_TARGET {
+                return Err(format!(
+                    "Unknown kind of notification target {}: {}",
+                    name, target.kind
+                ));
+            }
+        }
+        Ok(Self {
+            region: region.to_string(),
+            targets: config.targets.keys().cloned().collect(),
+            queue: EventQueue::new(&config.queue_dir),
+        })
+    }
+
+    pub fn region(&self) -> &str {
+        &self.region
+    }
+
+    pub fn has_target(&self, name: &str) -> bool {
+        self.targets.contains(name)
+    }
+
+    /// Queue a message for a target, it is on disk when this returns.
+    pub async fn send(&self, target: &str, body: &[u8]) -> Result<(), std::io::Error> {
+        self.queue.push(target, body).await
+    }
+}
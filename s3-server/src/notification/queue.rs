@@ -0,0 +1,167 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use tokio::{fs, io::AsyncWriteExt};
+
+const TMP_DIR: &str = ".tmp";
+const DEAD_LETTER_DIR: &str = "dead";
+
+/// Events waiting for delivery, kept as files under a directory per target.
+///
+/// An event is `<target>/<id>.json`, or `<target>/<id>.<attempts>.json` once
+/// its delivery has failed. Ids sort in the order events were queued. Files
+/// are written under `<target>/.tmp` and renamed into place, so consumers
+/// never see a partial event. Events that ran out of attempts are moved to
+/// `<target>/dead`.
+#[derive(Debug, Clone)]
+pub struct EventQueue {
+    root: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueuedEvent {
+    pub path: PathBuf,
+    pub id: String,
+    // Failed deliveries so far
+    pub attempts: u32,
+}
+
+impl EventQueue {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn target_dir(&self, target: &str) -> PathBuf {
+        self.root.join(target)
+    }
+
+    /// Queue an event, it is on disk when this returns.
+    pub async fn push(&self, target: &str, body: &[u8]) -> Result<(), std::io::Error> {
+        let dir = self.target_dir(target);
+        let tmp_dir = dir.join(TMP_DIR);
+        fs::create_dir_all(&tmp_dir).await?;
+
+        let name = format!("{}.json", uuid::Uuid::now_v7().simple());
+        let tmp = tmp_dir.join(&name);
+        let mut file = fs::File::create(&tmp).await?;
+        file.write_all(body).await?;
+        file.sync_all().await?;
+        drop(file);
+        fs::rename(&tmp, dir.join(&name)).await?;
+        sync_dir(&dir).await
+    }
+
+    /// The oldest queued events of a target.
+    pub async fn pending(
+        &self,
+        target: &str,
+        limit: usize,
+    ) -> Result<Vec<QueuedEvent>, std::io::Error> {
+        let mut entries = match fs::read_dir(self.target_dir(target)).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+        let mut events = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_type().await?.is_file() {
+                continue;
+            }
+            if let Some(event) = queued_event(entry.path()) {
+                events.push(event);
+            }
+        }
+        events.sort_by(|a, b| a.id.cmp(&b.id));
+        events.truncate(limit);
+        Ok(events)
+    }
+
+    /// Remove a delivered event.
+    pub async fn complete(&self, event: &QueuedEvent) -> Result<(), std::io::Error> {
+        match fs::remove_file(&event.path).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Record a failed delivery of an event.
+    pub async fn retry(&self, event: &QueuedEvent) -> Result<QueuedEvent, std::io::Error> {
+        let attempts = event.attempts + 1;
+        let path = event
+            .path
+            .with_file_name(format!("{}.{}.json", event.id, attempts));
+        fs::rename(&event.path, &path).await?;
+        Ok(QueuedEvent {
+            path,
+            id: event.id.clone(),
+            attempts,
+        })
+    }
+
+    /// Move an event that will not be delivered to the dead letters of its
+    /// target.
+    pub async fn dead_letter(&self, event: &QueuedEvent) -> Result<(), std::io::Error> {
+        let dir = match event.path.parent() {
+            Some(dir) => dir.join(DEAD_LETTER_DIR),
+            None => return Ok(()),
+        };
+        fs::create_dir_all(&dir).await?;
+        fs::rename(&event.path, dir.join(format!("{}.json", event.id))).await
+    }
+}
+
+fn queued_event(path: PathBuf) -> Option<QueuedEvent> {
+    let name = path.file_name()?.to_str()?.strip_suffix(".json")?;
+    let (id, attempts) = match name.split_once('.') {
+        Some((id, attempts)) => (id, attempts.parse().ok()?),
+        None => (name, 0),
+    };
+    Some(QueuedEvent {
+        id: id.to_string(),
+        attempts,
+        path,
+    })
+}
+
+// Persist the rename of a queued event
+async fn sync_dir(dir: &Path) -> Result<(), std::io::Error> {
+    fs::File::open(dir).await?.sync_all().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_queue() {
+        let root = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let queue = EventQueue::new(&root);
+        assert!(queue.pending("indexer", 10).await.unwrap().is_empty());
+
+        queue.push("indexer", b"first").await.unwrap();
+        queue.push("indexer", b"second").await.unwrap();
+        let pending = queue.pending("indexer", 10).await.unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(fs::read(&pending[0].path).await.unwrap(), b"first");
+        assert_eq!(pending[0].attempts, 0);
+
+        let retried = queue.retry(&pending[0]).await.unwrap();
+        assert_eq!(retried.attempts, 1);
+        let pending = queue.pending("indexer", 10).await.unwrap();
+        assert_eq!(pending[0], retried);
+        assert_eq!(queue.pending("indexer", 1).await.unwrap().len(), 1);
+
+        queue.dead_letter(&pending[0]).await.unwrap();
+        queue.complete(&pending[1]).await.unwrap();
+        assert!(queue.pending("indexer", 10).await.unwrap().is_empty());
+        let dead = queue
+            .target_dir("indexer")
+            .join(DEAD_LETTER_DIR)
+            .join(format!("{}.json", retried.id));
+        assert_eq!(fs::read(dead).await.unwrap(), b"first");
+
+        fs::remove_dir_all(root).await.unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+use std::{collections::HashMap, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::config::{NotificationConfig, NotificationTarget};
+
+use super::{EventQueue, QueuedEvent};
+
+// Events read from the queue per poll
+const BATCH_SIZE: usize = 100;
+
+/// Delivers the queued events of a webhook target.
+///
+/// Events are POSTed in the order they were queued. A failed delivery is
+/// retried with exponential backoff, and the event is moved to the dead
+/// letters of the target once it has used up its attempts. Delivery is at
+/// least once, an event may be sent again if the gateway stops before it is
+/// removed from the queue.
+pub struct WebhookWorker {
+    name: String,
+    target: NotificationTarget,
+    queue: EventQueue,
+    config: NotificationConfig,
+    client: reqwest::Client,
+    // When failed events are due again, attempts restart after a restart
+    retry_at: HashMap<String, Instant>,
+}
+
+impl WebhookWorker {
+    pub fn new(
+        name: String,
+        target: NotificationTarget,
+        queue: EventQueue,
+        config: NotificationConfig,
+    ) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .unwrap_or_default();
+        Self {
+            name,
+            target,
+            queue,
+            config,
+            client,
+            retry_at: HashMap::new(),
+        }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(self.run());
+    }
+
+    async fn run(mut self) {
+        loop {
+            match self.run_once().await {
+                Ok(0) => {}
+                Ok(events) => tracing::debug!(name = %self.name, "Delivered {} events", events),
+                Err(e) => tracing::warn!(
+                    name = %self.name,
+                    "Failed to read notification queue: {:?}",
+                    e
+                ),
+            }
+            tokio::time::sleep(Duration::from_millis(self.config.poll_ms)).await;
+        }
+    }
+
+    async fn run_once(&mut self) -> Result<usize, std::io::Error> {
+        let now = Instant::now();
+        let mut delivered = 0;
+        for event in self.queue.pending(&self.name, BATCH_SIZE).await? {
+            if self.retry_at.get(&event.id).is_some_and(|at| *at > now) {
+                continue;
+            }
+            if event.attempts >= self.config.max_attempts {
+                self.give_up(&event).await?;
+                continue;
+            }
+
+            let body = tokio::fs::read(&event.path).await?;
+            match self.deliver(body).await {
+                Ok(()) => {
+                    self.queue.complete(&event).await?;
+                    self.retry_at.remove(&event.id);
+                    delivered += 1;
+                }
+                Err(e) => {
+                    let event = self.queue.retry(&event).await?;
+                    tracing::debug!(
+                        name = %self.name,
+                        id = %event.id,
+                        attempts = event.attempts,
+                        "Failed to deliver event: {}",
+                        e
+                    );
+                    if event.attempts >= self.config.max_attempts {
+                        self.give_up(&event).await?;
+                    } else {
+                        let delay = backoff(&self.config, event.attempts);
+                        self.retry_at.insert(event.id, Instant::now() + delay);
+                    }
+                }
+            }
+        }
+        Ok(delivered)
+    }
+
+    async fn deliver(&self, body: Vec<u8>) -> Result<(), String> {
+        let mut request = self
+            .client
+            .post(&self.target.endpoint)
+            .header("Content-Type", "application/json")
+            .body(body);
+        if !self.target.auth_token.is_empty() {
+            request = request.bearer_auth(&self.target.auth_token);
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("webhook answered {}", response.status()));
+        }
+        Ok(())
+    }
+
+    async fn give_up(&mut self, event: &QueuedEvent) -> Result<(), std::io::Error> {
+        tracing::warn!(
+            name = %self.name,
+            id = %event.id,
+            "Event could not be delivered after {} attempts, moving it to the dead letters",
+            event.attempts
+        );
+        self.retry_at.remove(&event.id);
+        self.queue.dead_letter(event).await
+    }
+}
+
+// Delay before retrying an event that failed `attempts` times
+fn backoff(config: &NotificationConfig, attempts: u32) -> Duration {
+    let factor = 2u64.saturating_pow(attempts.saturating_sub(1));
+    Duration::from_secs(
+        config
+            .retry_base_secs
+            .saturating_mul(factor)
+            .min(config.retry_max_secs),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let config = NotificationConfig::default();
+        assert_eq!(backoff(&config, 1), Duration::from_secs(5));
+        assert_eq!(backoff(&config, 2), Duration::from_secs(10));
+        assert_eq!(backoff(&config, 4), Duration::from_secs(40));
+        assert_eq!(backoff(&config, 64), Duration::from_secs(3600));
+    }
+}
@@ -0,0 +1,75 @@
+use std::{sync::Arc, time::Duration};
+
+use s3_core::S3Error;
+
+use crate::config::PackingConfig;
+
+/// Reclaims the space of deleted objects in packed volumes.
+///
+/// Volumes whose live bytes fell below `compaction_max_live_ratio` of their
+/// size are leased one at a time, so every gateway may run a compactor.
+pub struct CompactionWorker {
+    fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+    config: PackingConfig,
+}
+
+impl CompactionWorker {
+    pub fn new(
+        fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+        config: PackingConfig,
+    ) -> Self {
+        Self { fullstack, config }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(self.run());
+    }
+
+    async fn run(self) {
+        loop {
+            match self.run_once().await {
+                // Look for the next volume right away
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Failed to claim volume: {:?}", e),
+            }
+            tokio::time::sleep(Duration::from_secs(self.config.compaction_poll_secs)).await;
+        }
+    }
+
+    async fn run_once(&self) -> Result<bool, S3Error> {
+        let now = chrono::Utc::now();
+        let min_age = chrono::Duration::seconds(self.config.compaction_min_age_secs as i64);
+        let lease = chrono::Duration::seconds(self.config.compaction_lease_secs as i64);
+        let volume = match self
+            .fullstack
+            .claim_volume(
+                now - min_age,
+                self.config.compaction_max_live_ratio,
+                now + lease,
+            )
+            .await?
+        {
+            Some(volume) => volume,
+            None => return Ok(false),
+        };
+
+        match self.fullstack.compact_volume(&volume).await {
+            Ok(true) => tracing::info!(
+                bucket = volume.bucket_name,
+                volume = %volume.id,
+                "Compacted volume, reclaimed {} bytes",
+                volume.size - volume.live_bytes
+            ),
+            Ok(false) => {}
+            // The volume is retried by any worker once its lease expires
+            Err(e) => tracing::warn!(
+                bucket = volume.bucket_name,
+                volume = %volume.id,
+                "Failed to compact volume: {:?}",
+                e
+            ),
+        }
+        Ok(true)
+    }
+}
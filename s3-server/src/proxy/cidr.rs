@@ -0,0 +1,95 @@
+use std::{net::IpAddr, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IpCidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpCidr {
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = mask_u32(self.prefix_len);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = mask_u128(self.prefix_len);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = String;
+
+    // Accepts "10.0.0.0/8", "fd00::/8" or a bare address
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len)),
+            None => (s, None),
+        };
+        let addr = IpAddr::from_str(addr.trim())
+            .map_err(|_| format!("Invalid address in CIDR: {}", s))?
+            .to_canonical();
+        let max_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_len)
+                .ok_or_else(|| format!("Invalid prefix length in CIDR: {}", s))?,
+            None => max_len,
+        };
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+fn mask_u32(prefix_len: u8) -> u32 {
+    u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0)
+}
+
+fn mask_u128(prefix_len: u8) -> u128 {
+    u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        IpAddr::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_contains() {
+        let cidr = IpCidr::from_str("10.1.0.0/16").unwrap();
+        assert!(cidr.contains(&ip("10.1.200.3")));
+        assert!(!cidr.contains(&ip("10.2.0.1")));
+        assert!(cidr.contains(&ip("::ffff:10.1.0.1")));
+
+        let cidr = IpCidr::from_str("fd00::/8").unwrap();
+        assert!(cidr.contains(&ip("fd12::1")));
+        assert!(!cidr.contains(&ip("fe80::1")));
+
+        let cidr = IpCidr::from_str("0.0.0.0/0").unwrap();
+        assert!(cidr.contains(&ip("203.0.113.9")));
+
+        let cidr = IpCidr::from_str("192.0.2.1").unwrap();
+        assert!(cidr.contains(&ip("192.0.2.1")));
+        assert!(!cidr.contains(&ip("192.0.2.2")));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(IpCidr::from_str("10.0.0.0/33").is_err());
+        assert!(IpCidr::from_str("example.com/8").is_err());
+        assert!(IpCidr::from_str("10.0.0.0/x").is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use std::{net::IpAddr, str::FromStr};
+
+use axum::http::HeaderMap;
+
+use super::IpCidr;
+
+/// Proxies whose forwarding headers are believed when resolving the client.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    cidrs: Vec<IpCidr>,
+}
+
+impl TrustedProxies {
+    pub fn new(cidrs: &[String]) -> Result<Self, String> {
+        let cidrs = cidrs
+            .iter()
+            .map(|cidr| IpCidr::from_str(cidr))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { cidrs })
+    }
+
+    pub fn is_trusted(&self, ip: &IpAddr) -> bool {
+        self.cidrs.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    /// Resolve the address of the client that originated the request.
+    ///
+    /// Forwarding headers are only considered when the peer is a trusted
+    /// proxy. X-Forwarded-For is walked right to left, skipping trusted hops,
+    /// so that entries prepended by the client itself are never used.
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let peer = peer.to_canonical();
+        if !self.is_trusted(&peer) {
+            return peer;
+        }
+
+        let forwarded_for = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect::<Vec<_>>();
+        if !forwarded_for.is_empty() {
+            let mut client = peer;
+            for hop in forwarded_for.iter().rev() {
+                match parse_ip(hop) {
+                    Some(ip) => {
+                        client = ip;
+                        if !self.is_trusted(&ip) {
+                            break;
+                        }
+                    }
+                    // Anything left of a malformed entry cannot be trusted
+                    None => break,
+                }
+            }
+            return client;
+        }
+
+        for header in ["cf-connecting-ip", "x-real-ip"] {
+            if let Some(ip) = headers
+                .get(header)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_ip)
+            {
+                return ip;
+            }
+        }
+
+        peer
+    }
+
+    /// Whether the client reached a trusted proxy over TLS. The gateway
+    /// itself only speaks plain HTTP, so a request only counts as secure
+    /// when the proxy in front of it says so in X-Forwarded-Proto.
+    pub fn is_secure(&self, peer: IpAddr, headers: &HeaderMap) -> bool {
+        if !self.is_trusted(&peer.to_canonical()) {
+            return false;
+        }
+        // The last hop's value is the one the trusted proxy set
+        headers
+            .get_all("x-forwarded-proto")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .last()
+            .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("https"))
+    }
+}
+
+fn parse_ip(value: &str) -> Option<IpAddr> {
+    let value = value.trim();
+    // Strip brackets and ports, e.g. "[2001:db8::1]:443" or "192.0.2.1:8080"
+    if let Ok(ip) = IpAddr::from_str(value) {
+        return Some(ip.to_canonical());
+    }
+    std::net::SocketAddr::from_str(value)
+        .ok()
+        .map(|addr| addr.ip().to_canonical())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        IpAddr::from_str(s).unwrap()
+    }
+
+    fn headers(values: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (key, value) in values {
+            headers.append(*key, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_untrusted_peer() {
+        let proxies = TrustedProxies::new(&["10.0.0.0/8".to_string()]).unwrap();
+        let headers = headers(&[("x-forwarded-for", "1.2.3.4"), ("x-real-ip", "1.2.3.4")]);
+        assert_eq!(
+            proxies.resolve(ip("203.0.113.7"), &headers),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn test_forwarded_for() {
+        let proxies = TrustedProxies::new(&["10.0.0.0/8".to_string()]).unwrap();
+
+        // Client spoofed the first entry, the trusted hop appended the real one
+        let headers = headers(&[
+            ("x-forwarded-for", "1.2.3.4, 198.51.100.2"),
+            ("x-forwarded-for", "10.0.0.5"),
+        ]);
+        assert_eq!(
+            proxies.resolve(ip("10.0.0.1"), &headers),
+            ip("198.51.100.2")
+        );
+
+        let headers = self::headers(&[("x-forwarded-for", "garbage, 10.0.0.9")]);
+        assert_eq!(proxies.resolve(ip("10.0.0.1"), &headers), ip("10.0.0.9"));
+
+        let headers = self::headers(&[("x-forwarded-for", "[2001:db8::1]:443")]);
+        assert_eq!(proxies.resolve(ip("10.0.0.1"), &headers), ip("2001:db8::1"));
+    }
+
+    #[test]
+    fn test_real_ip() {
+        let proxies = TrustedProxies::new(&["127.0.0.1".to_string()]).unwrap();
+        let headers = headers(&[("x-real-ip", "198.51.100.2")]);
+        assert_eq!(
+            proxies.resolve(ip("127.0.0.1"), &headers),
+            ip("198.51.100.2")
+        );
+        assert_eq!(
+            proxies.resolve(ip("127.0.0.1"), &HeaderMap::new()),
+            ip("127.0.0.1")
+        );
+    }
+
+    #[test]
+    fn test_is_secure() {
+        let proxies = TrustedProxies::new(&["10.0.0.0/8".to_string()]).unwrap();
+        let https = headers(&[("x-forwarded-proto", "https")]);
+        assert!(proxies.is_secure(ip("10.0.0.1"), &https));
+        assert!(!proxies.is_secure(ip("203.0.113.7"), &https));
+        assert!(!proxies.is_secure(ip("10.0.0.1"), &HeaderMap::new()));
+
+        // A client claiming https in front of a proxy that saw http
+        let headers = headers(&[("x-forwarded-proto", "https, http")]);
+        assert!(!proxies.is_secure(ip("10.0.0.1"), &headers));
+    }
+}
@@ -0,0 +1,6 @@
+mod cidr;
+mod client_ip;
+pub mod protocol;
+
+use cidr::IpCidr;
+pub use client_ip::TrustedProxies;
@@ -0,0 +1,270 @@
+// PROXY protocol v1 and v2 as sent by load balancers in front of the gateway
+// https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt
+
+use std::{
+    io::{Error, ErrorKind},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+static V1_PREFIX: &[u8] = b"PROXY ";
+static V2_SIGNATURE: [u8; 12] = [
+    0x0D, 0x0A, 0x0D, 0x0A, 0x00, 0x0D, 0x0A, 0x51, 0x55, 0x49, 0x54, 0x0A,
+];
+// Longest possible v1 header including CRLF
+static V1_MAX_LENGTH: usize = 107;
+// Sizes of the v2 address blocks, TLVs follow them
+static V2_IPV4_LENGTH: usize = 12;
+static V2_IPV6_LENGTH: usize = 36;
+static V2_UNIX_LENGTH: usize = 216;
+// PP2_TYPE_SSL TLV, whose first byte has PP2_CLIENT_SSL set when the client
+// connected to the proxy over TLS
+static PP2_TYPE_SSL: u8 = 0x20;
+static PP2_CLIENT_SSL: u8 = 0x01;
+
+/// Connection details reported by the proxy.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProxyHeader {
+    /// Source address of the client, `None` for LOCAL (health check)
+    /// connections and unknown address families, in which case the socket
+    /// peer should be used.
+    pub source: Option<SocketAddr>,
+    /// Whether the client connected to the proxy over TLS. Only v2 headers
+    /// carry it, in the PP2_TYPE_SSL TLV.
+    pub ssl: bool,
+}
+
+/// Request extension of connections whose proxy terminated TLS, see
+/// ProxyHeader::ssl.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientTls;
+
+/// Read a PROXY protocol header from the start of a connection.
+pub async fn read_header<R>(reader: &mut R) -> Result<ProxyHeader, Error>
+where
+    R: AsyncBufRead + Unpin,
+{
+    // Both versions are at least 12 bytes long, so this never over-reads
+    let mut signature = [0u8; 12];
+    reader.read_exact(&mut signature).await?;
+
+    if signature == V2_SIGNATURE {
+        let mut header = [0u8; 4];
+        reader.read_exact(&mut header).await?;
+        let len = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let mut payload = vec![0u8; len];
+        reader.read_exact(&mut payload).await?;
+        return parse_v2(header, &payload);
+    }
+
+    if signature.starts_with(V1_PREFIX) {
+        let mut line = signature.to_vec();
+        let mut limited = reader.take((V1_MAX_LENGTH - signature.len()) as u64);
+        limited.read_until(b'\n', &mut line).await?;
+        return parse_v1(&line);
+    }
+
+    Err(invalid("missing PROXY protocol header"))
+}
+
+fn parse_v1(line: &[u8]) -> Result<ProxyHeader, Error> {
+    let line = std::str::from_utf8(line).map_err(|_| invalid("invalid v1 header"))?;
+    let line = line
+        .strip_suffix("\r\n")
+        .ok_or_else(|| invalid("v1 header is not terminated"))?;
+
+    let parts = line.split(' ').collect::<Vec<_>>();
+    let source = match parts.as_slice() {
+        ["PROXY", "UNKNOWN", ..] => None,
+        ["PROXY", family @ ("TCP4" | "TCP6"), src, _dst, src_port, _dst_port] => {
+            let ip = src
+                .parse::<IpAddr>()
+                .map_err(|_| invalid("invalid v1 source address"))?;
+            if (*family == "TCP4") != ip.is_ipv4() {
+                return Err(invalid("v1 address does not match family"));
+            }
+            let port = src_port
+                .parse::<u16>()
+                .map_err(|_| invalid("invalid v1 source port"))?;
+            Some(SocketAddr::new(ip, port))
+        }
+        _ => return Err(invalid("malformed v1 header")),
+    };
+    Ok(ProxyHeader { source, ssl: false })
+}
+
+fn parse_v2(header: [u8; 4], payload: &[u8]) -> Result<ProxyHeader, Error> {
+    let version = header[0] >> 4;
+    let command = header[0] & 0x0F;
+    if version != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+
+    match command {
+        // LOCAL
+        0x0 => return Ok(ProxyHeader::default()),
+        // PROXY
+        0x1 => {}
+        _ => return Err(invalid("unsupported v2 command")),
+    }
+
+    // Upper nibble is the address family, lower nibble the transport
+    let (source, tlvs) = match header[1] >> 4 {
+        // AF_INET
+        0x1 => {
+            if payload.len() < V2_IPV4_LENGTH {
+                return Err(invalid("short v2 IPv4 address block"));
+            }
+            let ip = Ipv4Addr::new(payload[0], payload[1], payload[2], payload[3]);
+            let port = u16::from_be_bytes([payload[8], payload[9]]);
+            (
+                Some(SocketAddr::new(IpAddr::V4(ip), port)),
+                &payload[V2_IPV4_LENGTH..],
+            )
+        }
+        // AF_INET6
+        0x2 => {
+            if payload.len() < V2_IPV6_LENGTH {
+                return Err(invalid("short v2 IPv6 address block"));
+            }
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&payload[0..16]);
+            let port = u16::from_be_bytes([payload[32], payload[33]]);
+            (
+                Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(octets)), port)),
+                &payload[V2_IPV6_LENGTH..],
+            )
+        }
+        // AF_UNIX carries no usable client address
+        0x3 => (None, payload.get(V2_UNIX_LENGTH..).unwrap_or_default()),
+        // AF_UNSPEC has no address block
+        _ => (None, payload),
+    };
+    Ok(ProxyHeader {
+        source,
+        ssl: client_ssl(tlvs)?,
+    })
+}
+
+// Whether the TLVs hold a PP2_TYPE_SSL with PP2_CLIENT_SSL set
+fn client_ssl(mut tlvs: &[u8]) -> Result<bool, Error> {
+    while !tlvs.is_empty() {
+        if tlvs.len() < 3 {
+            return Err(invalid("truncated v2 TLV"));
+        }
+        let len = u16::from_be_bytes([tlvs[1], tlvs[2]]) as usize;
+        let value = tlvs
+            .get(3..3 + len)
+            .ok_or_else(|| invalid("truncated v2 TLV"))?;
+        if tlvs[0] == PP2_TYPE_SSL {
+            return Ok(value
+                .first()
+                .is_some_and(|client| client & PP2_CLIENT_SSL != 0));
+        }
+        tlvs = &tlvs[3 + len..];
+    }
+    Ok(false)
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(addr: &str) -> ProxyHeader {
+        ProxyHeader {
+            source: Some(addr.parse().unwrap()),
+            ssl: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_v1() {
+        assert_eq!(
+            parse_v1(b"PROXY TCP4 192.0.2.10 10.0.0.1 56324 443\r\n").unwrap(),
+            source("192.0.2.10:56324")
+        );
+        assert_eq!(
+            parse_v1(b"PROXY TCP6 2001:db8::1 ::1 56324 443\r\n").unwrap(),
+            source("[2001:db8::1]:56324")
+        );
+        assert_eq!(
+            parse_v1(b"PROXY UNKNOWN\r\n").unwrap(),
+            ProxyHeader::default()
+        );
+        assert!(parse_v1(b"PROXY TCP4 2001:db8::1 ::1 1 2\r\n").is_err());
+        assert!(parse_v1(b"PROXY TCP4 192.0.2.10 10.0.0.1 56324 443").is_err());
+    }
+
+    #[test]
+    fn test_parse_v2() {
+        let payload = [192, 0, 2, 10, 10, 0, 0, 1, 0xDC, 0x04, 0x01, 0xBB];
+        assert_eq!(
+            parse_v2([0x21, 0x11, 0x00, 0x0C], &payload).unwrap(),
+            source("192.0.2.10:56324")
+        );
+        assert_eq!(
+            parse_v2([0x20, 0x00, 0x00, 0x00], &[]).unwrap(),
+            ProxyHeader::default()
+        );
+        assert!(parse_v2([0x21, 0x11, 0x00, 0x04], &payload[..4]).is_err());
+        assert!(parse_v2([0x11, 0x11, 0x00, 0x0C], &payload).is_err());
+    }
+
+    #[test]
+    fn test_parse_v2_ssl() {
+        let address = [192, 0, 2, 10, 10, 0, 0, 1, 0xDC, 0x04, 0x01, 0xBB];
+        // PP2_TYPE_ALPN "h2", then PP2_TYPE_SSL with PP2_CLIENT_SSL, verified,
+        // and a PP2_SUBTYPE_SSL_VERSION of TLSv1.3
+        let tlvs = [
+            &[0x01, 0x00, 0x02][..],
+            b"h2",
+            &[0x20, 0x00, 0x0F, 0x01, 0x00, 0x00, 0x00, 0x00],
+            &[0x21, 0x00, 0x07],
+            b"TLSv1.3",
+        ]
+        .concat();
+        let payload = [&address[..], &tlvs].concat();
+        let header = parse_v2([0x21, 0x11, 0x00, payload.len() as u8], &payload).unwrap();
+        assert_eq!(header.source, Some("192.0.2.10:56324".parse().unwrap()));
+        assert!(header.ssl);
+
+        // The TLV without PP2_CLIENT_SSL is a plain connection
+        let plain = [
+            &address[..],
+            &[0x20, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00],
+        ]
+        .concat();
+        assert!(
+            !parse_v2([0x21, 0x11, 0x00, plain.len() as u8], &plain)
+                .unwrap()
+                .ssl
+        );
+        let truncated = [&address[..], &[0x20, 0x00, 0x05, 0x01]].concat();
+        assert!(parse_v2([0x21, 0x11, 0x00, truncated.len() as u8], &truncated).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_header() {
+        let mut input: &[u8] = b"PROXY TCP4 192.0.2.10 10.0.0.1 56324 443\r\nGET / HTTP/1.1\r\n";
+        let header = read_header(&mut input).await.unwrap();
+        assert_eq!(header, source("192.0.2.10:56324"));
+        assert_eq!(input, b"GET / HTTP/1.1\r\n");
+
+        let mut v2 = V2_SIGNATURE.to_vec();
+        v2.extend_from_slice(&[0x21, 0x11, 0x00, 0x0C]);
+        v2.extend_from_slice(&[192, 0, 2, 10, 10, 0, 0, 1, 0xDC, 0x04, 0x01, 0xBB]);
+        v2.extend_from_slice(b"GET");
+        let mut input = v2.as_slice();
+        let header = read_header(&mut input).await.unwrap();
+        assert_eq!(header, source("192.0.2.10:56324"));
+        assert_eq!(input, b"GET");
+
+        let mut input: &[u8] = b"GET / HTTP/1.1\r\nHost: localhost\r\n";
+        assert!(read_header(&mut input).await.is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use std::{num::NonZero, sync::Arc};
+
+use axum::body::{Body, Bytes};
+use futures_util::StreamExt;
+
+use crate::config::RateLimitConfig;
+
+// Bodies are paced in slices of at most this size so that a single large
+// chunk does not leave the link idle and then saturate it all at once.
+static PACING_CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Ingress,
+    Egress,
+}
+
+type ByteRateLimiter = governor::DefaultKeyedRateLimiter<String>;
+
+/// Per node byte-rate limits for request (ingress) and response (egress)
+/// bodies, keyed by user and by bucket.
+pub struct BandwidthLimiter {
+    user_ingress: Option<ByteRateLimiter>,
+    user_egress: Option<ByteRateLimiter>,
+    bucket_ingress: Option<ByteRateLimiter>,
+    bucket_egress: Option<ByteRateLimiter>,
+}
+
+impl BandwidthLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            user_ingress: byte_rate_limiter(config.user_ingress_bytes_per_sec),
+            user_egress: byte_rate_limiter(config.user_egress_bytes_per_sec),
+            bucket_ingress: byte_rate_limiter(config.bucket_ingress_bytes_per_sec),
+            bucket_egress: byte_rate_limiter(config.bucket_egress_bytes_per_sec),
+        }
+    }
+
+    fn limiters(&self, direction: Direction) -> [Option<&ByteRateLimiter>; 2] {
+        match direction {
+            Direction::Ingress => [self.user_ingress.as_ref(), self.bucket_ingress.as_ref()],
+            Direction::Egress => [self.user_egress.as_ref(), self.bucket_egress.as_ref()],
+        }
+    }
+
+    pub fn is_enabled(&self, direction: Direction) -> bool {
+        self.limiters(direction).iter().any(Option::is_some)
+    }
+
+    /// Wait until `len` bytes may be transferred for the user and bucket.
+    pub async fn pace(&self, direction: Direction, user: &str, bucket: &str, len: usize) {
+        let [user_limiter, bucket_limiter] = self.limiters(direction);
+        let Some(len) = NonZero::new(len as u32) else {
+            return;
+        };
+
+        if let Some(limiter) = user_limiter {
+            // Burst is never below PACING_CHUNK_SIZE so capacity is always sufficient
+            let _ = limiter.until_key_n_ready(&user.to_string(), len).await;
+        }
+        if let (Some(limiter), false) = (bucket_limiter, bucket.is_empty()) {
+            let _ = limiter.until_key_n_ready(&bucket.to_string(), len).await;
+        }
+    }
+
+    /// Wrap a body so that it is only polled as fast as the limits allow.
+    pub fn throttle_body(
+        self: &Arc<Self>,
+        direction: Direction,
+        user: String,
+        bucket: String,
+        body: Body,
+    ) -> Body {
+        if !self.is_enabled(direction) {
+            return body;
+        }
+
+        let limiter = self.clone();
+        let stream = body
+            .into_data_stream()
+            .flat_map(|chunk| {
+                let chunks = match chunk {
+                    Ok(bytes) => split(bytes).into_iter().map(Ok).collect(),
+                    Err(e) => vec![Err(e)],
+                };
+                futures_util::stream::iter(chunks)
+            })
+            .then(move |chunk| {
+                let limiter = limiter.clone();
+                let user = user.clone();
+                let bucket = bucket.clone();
+                async move {
+                    if let Ok(bytes) = &chunk {
+                        limiter.pace(direction, &user, &bucket, bytes.len()).await;
+                    }
+                    chunk
+                }
+            });
+        Body::from_stream(stream)
+    }
+}
+
+fn byte_rate_limiter(bytes_per_sec: u32) -> Option<ByteRateLimiter> {
+    let rate = NonZero::new(bytes_per_sec)?;
+    let burst = NonZero::new(bytes_per_sec.max(PACING_CHUNK_SIZE as u32))?;
+    Some(governor::RateLimiter::keyed(
+        governor::Quota::per_second(rate).allow_burst(burst),
+    ))
+}
+
+fn split(mut bytes: Bytes) -> Vec<Bytes> {
+    let mut chunks = Vec::with_capacity(bytes.len() / PACING_CHUNK_SIZE + 1);
+    while bytes.len() > PACING_CHUNK_SIZE {
+        chunks.push(bytes.split_to(PACING_CHUNK_SIZE));
+    }
+    chunks.push(bytes);
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let chunks = split(Bytes::from(vec![0u8; PACING_CHUNK_SIZE * 2 + 1]));
+        assert_eq!(
+            chunks.iter().map(Bytes::len).collect::<Vec<_>>(),
+            vec![PACING_CHUNK_SIZE, PACING_CHUNK_SIZE, 1]
+        );
+        assert_eq!(split(Bytes::new()).len(), 1);
+    }
+}
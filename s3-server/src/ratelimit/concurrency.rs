@@ -0,0 +1,63 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+// Idle semaphores are only swept once the map grows past this size
+static SWEEP_THRESHOLD: usize = 1024;
+
+/// Caps the number of requests in flight per key.
+pub struct ConcurrencyLimiter {
+    max_in_flight: usize,
+    semaphores: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            max_in_flight,
+            semaphores: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_in_flight > 0
+    }
+
+    /// Returns a permit that must be held for as long as the request is in
+    /// flight, or `None` if the key already has the maximum in flight.
+    pub fn try_acquire(&self, key: &str) -> Option<OwnedSemaphorePermit> {
+        let semaphore = {
+            let mut semaphores = self.semaphores.lock().unwrap();
+            if semaphores.len() > SWEEP_THRESHOLD {
+                // Only the map holds a reference when no permit is outstanding
+                semaphores.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+            }
+            semaphores
+                .entry(key.to_string())
+                .or_insert_with(|| Arc::new(Semaphore::new(self.max_in_flight)))
+                .clone()
+        };
+        semaphore.try_acquire_owned().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_acquire() {
+        let limiter = ConcurrencyLimiter::new(2);
+        let first = limiter.try_acquire("RGTEST");
+        let second = limiter.try_acquire("RGTEST");
+        assert!(first.is_some() && second.is_some());
+        assert!(limiter.try_acquire("RGTEST").is_none());
+        assert!(limiter.try_acquire("OTHER").is_some());
+
+        drop(first);
+        assert!(limiter.try_acquire("RGTEST").is_some());
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use redis::cluster_async::ClusterConnection;
+use sha2::Digest;
+
+// GCRA evaluated atomically on the Redis node owning the key. Time is taken from
+// the Redis server so that gateways with skewed clocks share the same view.
+//
+// KEYS[1] - limiter key
+// ARGV[1] - emission interval in microseconds
+// ARGV[2] - burst tolerance in microseconds
+// ARGV[3] - cost of the request (number of emission intervals)
+//
+// Returns {allowed, retry_after_us}
+static GCRA_SCRIPT: &str = r#"
+local now = redis.call('TIME')
+local now_us = tonumber(now[1]) * 1000000 + tonumber(now[2])
+local interval = tonumber(ARGV[1])
+local tolerance = tonumber(ARGV[2])
+local cost = tonumber(ARGV[3])
+
+local tat = tonumber(redis.call('GET', KEYS[1]))
+if not tat or tat < now_us then
+    tat = now_us
+end
+
+local new_tat = tat + interval * cost
+local allow_at = new_tat - tolerance
+if allow_at > now_us then
+    return {0, allow_at - now_us}
+end
+
+redis.call('SET', KEYS[1], new_tat, 'PX', math.ceil((new_tat - now_us) / 1000) + 1)
+return {1, 0}
+"#;
+
+static KEY_PREFIX: &str = "s3gw:rl:";
+
+// After a failed connection attempt requests fail fast for this long instead
+// of each waiting out the connect timeout
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    Allowed,
+    Limited(Duration),
+}
+
+/// Rate limiter shared by every gateway through a Redis Cluster.
+pub struct RedisRateLimiter {
+    client: redis::cluster::ClusterClient,
+    connection: Mutex<ConnectionState>,
+    script: redis::Script,
+    timeout: Duration,
+}
+
+impl RedisRateLimiter {
+    pub fn new(client: redis::cluster::ClusterClient, timeout: Duration) -> Self {
+        Self {
+            client,
+            connection: Mutex::new(ConnectionState::default()),
+            script: redis::Script::new(GCRA_SCRIPT),
+            timeout,
+        }
+    }
+
+    /// Take `cost` tokens for `key` from a bucket refilled at `rps` per second
+    /// holding at most `burst` tokens.
+    pub async fn check(
+        &self,
+        key: &str,
+        rps: u32,
+        burst: u32,
+        cost: u32,
+    ) -> Result<Decision, redis::RedisError> {
+        let rps = rps.max(1) as u64;
+        let interval = 1_000_000 / rps;
+        let tolerance = interval * burst.max(1) as u64;
+
+        let mut conn = self.connection().await?;
+        let mut invocation = self.script.key(hashed_key(key));
+        invocation.arg(interval).arg(tolerance).arg(cost.max(1));
+
+        let result: Result<(i64, i64), redis::RedisError> =
+            match tokio::time::timeout(self.timeout, invocation.invoke_async(&mut conn)).await {
+                Ok(result) => result,
+                Err(_) => Err(redis::RedisError::from((
+                    redis::ErrorKind::IoError,
+                    "rate limit request timed out",
+                ))),
+            };
+
+        match result {
+            Ok((1, _)) => Ok(Decision::Allowed),
+            Ok((_, retry_after)) => Ok(Decision::Limited(Duration::from_micros(
+                retry_after.max(0) as u64,
+            ))),
+            Err(e) => {
+                // Drop the connection so the next request reconnects
+                self.state().connection.take();
+                Err(e)
+            }
+        }
+    }
+
+    // The lock is never held across an await, so a slow connection attempt
+    // does not queue up every other request behind it. Requests racing to
+    // connect may each open a connection, the last one is kept.
+    async fn connection(&self) -> Result<ClusterConnection, redis::RedisError> {
+        {
+            let state = self.state();
+            if let Some(conn) = state.connection.as_ref() {
+                return Ok(conn.clone());
+            }
+            if state.retry_at.is_some_and(|at| Instant::now() < at) {
+                return Err(redis::RedisError::from((
+                    redis::ErrorKind::IoError,
+                    "rate limit connection backing off",
+                )));
+            }
+        }
+
+        let result =
+            match tokio::time::timeout(self.timeout, self.client.get_async_connection()).await {
+                Ok(result) => result,
+                Err(_) => Err(redis::RedisError::from((
+                    redis::ErrorKind::IoError,
+                    "rate limit connection timed out",
+                ))),
+            };
+        let mut state = self.state();
+        match result {
+            Ok(conn) => {
+                state.connection = Some(conn.clone());
+                state.retry_at = None;
+                Ok(conn)
+            }
+            Err(e) => {
+                state.retry_at = Some(Instant::now() + RECONNECT_BACKOFF);
+                Err(e)
+            }
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, ConnectionState> {
+        self.connection.lock().unwrap()
+    }
+}
+
+#[derive(Default)]
+struct ConnectionState {
+    connection: Option<ClusterConnection>,
+    // No connection is attempted before this instant
+    retry_at: Option<Instant>,
+}
+
+// Keys are hashed so that limiter state is spread evenly across cluster slots
+// regardless of how skewed the raw identifiers are.
+fn hashed_key(key: &str) -> String {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(key.as_bytes());
+    let digest = hasher.finalize();
+    format!("{}{}", KEY_PREFIX, const_hex::encode(&digest[..16]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashed_key() {
+        let key = hashed_key("127.0.0.1");
+        assert!(key.starts_with(KEY_PREFIX));
+        assert_eq!(key.len(), KEY_PREFIX.len() + 32);
+        assert_eq!(key, hashed_key("127.0.0.1"));
+        assert_ne!(key, hashed_key("127.0.0.2"));
+    }
+}
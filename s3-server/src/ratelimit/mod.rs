@@ -0,0 +1,9 @@
+mod bandwidth;
+mod concurrency;
+mod gcra;
+mod rules;
+
+pub use bandwidth::{BandwidthLimiter, Direction};
+pub use concurrency::ConcurrencyLimiter;
+pub use gcra::{Decision, RedisRateLimiter};
+pub use rules::{RuleSet, Subjects};
@@ -0,0 +1,259 @@
+use std::{num::NonZero, str::FromStr};
+
+use s3_core::S3ActionClass;
+
+use crate::backend::types;
+
+// Matches every distinct value of a dimension, each with its own budget
+pub static WILDCARD: &str = "*";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    UserId,
+    AccessKey,
+    Bucket,
+    Ip,
+    Action,
+}
+
+impl FromStr for Dimension {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user_id" => Ok(Dimension::UserId),
+            "access_key" => Ok(Dimension::AccessKey),
+            "bucket" | "bucket_id" => Ok(Dimension::Bucket),
+            "ip" => Ok(Dimension::Ip),
+            "action" => Ok(Dimension::Action),
+            _ => Err(format!("Unknown rate limit dimension: {}", s)),
+        }
+    }
+}
+
+impl Dimension {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Dimension::UserId => "user_id",
+            Dimension::AccessKey => "access_key",
+            Dimension::Bucket => "bucket",
+            Dimension::Ip => "ip",
+            Dimension::Action => "action",
+        }
+    }
+}
+
+/// Identity of a request along every dimension a rule can limit on.
+#[derive(Debug, Clone)]
+pub struct Subjects {
+    pub user_id: i64,
+    pub access_key: String,
+    pub bucket: String,
+    pub ip: String,
+    pub action: S3ActionClass,
+}
+
+impl Subjects {
+    fn value(&self, dimension: Dimension) -> String {
+        match dimension {
+            Dimension::UserId => self.user_id.to_string(),
+            Dimension::AccessKey => self.access_key.clone(),
+            Dimension::Bucket => self.bucket.clone(),
+            Dimension::Ip => self.ip.clone(),
+            Dimension::Action => self.action.as_str().to_string(),
+        }
+    }
+}
+
+pub struct Rule {
+    pub limit: types::RateLimit,
+    pub dimension: Dimension,
+    pub rps: u32,
+    pub weight: u32,
+    // Bucket size, large enough for one request of the rule's weight
+    pub burst: u32,
+    // Fallback used while Redis is unreachable
+    pub local: governor::DefaultKeyedRateLimiter<String>,
+}
+
+impl Rule {
+    pub fn new(limit: types::RateLimit) -> Result<Self, String> {
+        let dimension = Dimension::from_str(&limit.dimension)?;
+        if dimension == Dimension::Action && limit.value != WILDCARD {
+            S3ActionClass::from_str(&limit.value)?;
+        }
+        let rps = u32::try_from(limit.rps)
+            .ok()
+            .and_then(NonZero::new)
+            .ok_or_else(|| format!("Invalid rps for rate limit {}: {}", limit.id, limit.rps))?;
+        let weight = u32::try_from(limit.weight).unwrap_or(1).max(1);
+        // A bucket of rps tokens could never admit a heavier request
+        let burst = rps.max(NonZero::new(weight).unwrap_or(NonZero::<u32>::MIN));
+
+        Ok(Self {
+            dimension,
+            rps: rps.get(),
+            weight,
+            burst: burst.get(),
+            local: governor::RateLimiter::keyed(
+                governor::Quota::per_second(rps).allow_burst(burst),
+            ),
+            limit,
+        })
+    }
+
+    fn matches(&self, subjects: &Subjects) -> Option<String> {
+        if let Some(user_id) = self.limit.user_id {
+            if user_id != subjects.user_id {
+                return None;
+            }
+        }
+
+        let value = subjects.value(self.dimension);
+        if value.is_empty() {
+            return None;
+        }
+        if self.limit.value != WILDCARD && !self.limit.value.eq_ignore_ascii_case(&value) {
+            return None;
+        }
+
+        // Action limits are budgets per user, otherwise a single tenant could
+        // consume the whole class for everyone.
+        let subject = match self.dimension {
+            Dimension::Action => format!("{}:{}", value, subjects.user_id),
+            _ => value,
+        };
+        Some(format!(
+            "{}:{}:{}",
+            self.limit.id,
+            self.dimension.as_str(),
+            subject
+        ))
+    }
+}
+
+#[derive(Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    pub fn new(limits: Vec<types::RateLimit>) -> Self {
+        let rules = limits
+            .into_iter()
+            .filter_map(|limit| match Rule::new(limit) {
+                Ok(rule) => Some(rule),
+                Err(e) => {
+                    tracing::warn!("Ignoring rate limit rule: {}", e);
+                    None
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Returns the rules that apply to the request along with the limiter key
+    /// for each. When an exclusive rule matches, only exclusive rules apply.
+    pub fn matching(&self, subjects: &Subjects) -> Vec<(&Rule, String)> {
+        let matched = self
+            .rules
+            .iter()
+            .filter_map(|rule| rule.matches(subjects).map(|key| (rule, key)))
+            .collect::<Vec<_>>();
+
+        if matched.iter().any(|(rule, _)| rule.limit.exclusive) {
+            return matched
+                .into_iter()
+                .filter(|(rule, _)| rule.limit.exclusive)
+                .collect();
+        }
+        matched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(dimension: &str, value: &str, exclusive: bool) -> types::RateLimit {
+        types::RateLimit {
+            id: uuid::Uuid::new_v4(),
+            user_id: None,
+            rps: 10,
+            dimension: dimension.to_string(),
+            value: value.to_string(),
+            weight: 1,
+            exclusive,
+        }
+    }
+
+    fn subjects() -> Subjects {
+        Subjects {
+            user_id: 7,
+            access_key: "RGTEST".to_string(),
+            bucket: "photos".to_string(),
+            ip: "10.0.0.1".to_string(),
+            action: S3ActionClass::Write,
+        }
+    }
+
+    #[test]
+    fn test_matching() {
+        let rules = RuleSet::new(vec![
+            limit("ip", "*", false),
+            limit("bucket", "photos", false),
+            limit("bucket", "videos", false),
+            limit("action", "write", false),
+            limit("action", "read", false),
+        ]);
+        let matched = rules.matching(&subjects());
+        assert_eq!(matched.len(), 3);
+        assert!(matched[0].1.ends_with(":ip:10.0.0.1"));
+        assert!(matched[1].1.ends_with(":bucket:photos"));
+        assert!(matched[2].1.ends_with(":action:write:7"));
+    }
+
+    #[test]
+    fn test_exclusive() {
+        let rules = RuleSet::new(vec![
+            limit("ip", "*", false),
+            limit("access_key", "RGTEST", true),
+        ]);
+        let matched = rules.matching(&subjects());
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].0.dimension, Dimension::AccessKey);
+    }
+
+    #[test]
+    fn test_heavy_rule() {
+        let mut heavy = limit("ip", "*", false);
+        heavy.weight = 25;
+        let rule = Rule::new(heavy).unwrap();
+        assert_eq!(rule.burst, 25);
+        // One request of the full weight fits in a rested bucket
+        let weight = NonZero::new(rule.weight).unwrap();
+        assert!(matches!(
+            rule.local.check_key_n(&"10.0.0.1".to_string(), weight),
+            Ok(Ok(()))
+        ));
+
+        let light = Rule::new(limit("ip", "*", false)).unwrap();
+        assert_eq!(light.burst, 10);
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        let mut zero = limit("ip", "*", false);
+        zero.rps = 0;
+        let rules = RuleSet::new(vec![
+            zero,
+            limit("region", "*", false),
+            limit("action", "everything", false),
+        ]);
+        assert_eq!(rules.len(), 0);
+    }
+}
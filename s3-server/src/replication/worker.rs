@@ -0,0 +1,68 @@
+use std::{sync::Arc, time::Duration};
+
+use s3_core::S3Error;
+
+use crate::config::ReplicationConfig;
+
+/// Copies new versions of replicated buckets to their destination buckets in
+/// the background.
+///
+/// Every gateway runs a worker. Versions are leased from the
+/// replication_queue table, so each is copied by a single worker at a time,
+/// and a copy that failed is retried from the queue after a backoff.
+pub struct ReplicationWorker {
+    fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+    config: ReplicationConfig,
+}
+
+impl ReplicationWorker {
+    pub fn new(
+        fullstack: Arc<Box<crate::backend::FullstackBackend>>,
+        config: ReplicationConfig,
+    ) -> Self {
+        Self { fullstack, config }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(self.run());
+    }
+
+    async fn run(self) {
+        loop {
+            match self.run_once().await {
+                // A full batch means more versions are waiting
+                Ok(items) if items as i64 >= self.config.batch_size => continue,
+                Ok(0) => {}
+                Ok(items) => tracing::debug!("Replicated {} object versions", items),
+                Err(e) => tracing::warn!("Failed to claim replication items: {:?}", e),
+            }
+            tokio::time::sleep(Duration::from_secs(self.config.poll_secs)).await;
+        }
+    }
+
+    async fn run_once(&self) -> Result<usize, S3Error> {
+        let lease = chrono::Duration::seconds(self.config.lease_secs as i64);
+        let items = self
+            .fullstack
+            .claim_replication_items(chrono::Utc::now() + lease, self.config.batch_size)
+            .await?;
+
+        for item in &items {
+            if let Err(e) = self
+                .fullstack
+                .run_replication_item(item, &self.config)
+                .await
+            {
+                tracing::warn!(
+                    bucket = %item.bucket_name,
+                    key = %item.key,
+                    version = %item.version_id,
+                    attempts = item.attempts + 1,
+                    "Failed to replicate object version: {:?}",
+                    e
+                );
+            }
+        }
+        Ok(items.len())
+    }
+}
@@ -12,18 +12,25 @@ pub struct Result {
 
 pub struct Router {
     hosts: Vec<String>,
+    // Host suffixes of the static website endpoint
+    website_hosts: Vec<String>,
     route_matcher: RouteMatcher,
 }
 
 impl Router {
-    pub fn new(hosts: Vec<String>) -> Self {
+    pub fn new(hosts: Vec<String>, website_hosts: Vec<String>) -> Self {
         Router {
             hosts,
+            website_hosts,
             route_matcher: RouteMatcher::new(),
         }
     }
 
     pub fn match_result(&self, req: &axum::http::Request<Bytes>) -> Result {
+        if let Some(result) = self.match_website(req) {
+            return result;
+        }
+
         let req_host = req.uri().host().unwrap_or("").to_string();
 
         for host in &self.hosts {
@@ -56,6 +63,28 @@ impl Router {
         }
     }
 
+    // The website endpoint is host style only, every request names the
+    // bucket in its Host header and is served by the website handler. Hosts
+    // are compared without their port, as clients may leave a default one out.
+    fn match_website(&self, req: &axum::http::Request<Bytes>) -> Option<Result> {
+        let req_host = req
+            .headers()
+            .get(axum::http::header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .or(req.uri().authority().map(|authority| authority.as_str()))?;
+        let req_host = host_name(req_host);
+
+        self.website_hosts.iter().find_map(|host| {
+            let bucket = req_host.strip_suffix(host_name(host))?.strip_suffix('.')?;
+            Some(Result {
+                action: S3Action::WebsiteRequest,
+                bucket: bucket.to_string(),
+                key: req.uri().path().trim_start_matches('/').to_string(),
+                host: host.to_string(),
+            })
+        })
+    }
+
     fn match_route(
         &self,
         host: &str,
@@ -153,6 +182,13 @@ impl RouteMatcher {
                 arguments: vec![has_query("encryption")],
             },
         );
+        matcher.add_bucket_route(
+            "GET",
+            Route {
+                operation: S3Action::GetObjectLockConfiguration,
+                arguments: vec![has_query("object-lock")],
+            },
+        );
         matcher.add_bucket_route(
             "GET",
             Route {
@@ -457,6 +493,13 @@ impl RouteMatcher {
                 arguments: vec![has_query("encryption")],
             },
         );
+        matcher.add_bucket_route(
+            "PUT",
+            Route {
+                operation: S3Action::PutObjectLockConfiguration,
+                arguments: vec![has_query("object-lock")],
+            },
+        );
         matcher.add_bucket_route(
             "PUT",
             Route {
@@ -591,13 +634,6 @@ impl RouteMatcher {
                 arguments: vec![has_query("legal-hold")],
             },
         );
-        matcher.add_key_route(
-            "GET",
-            Route {
-                operation: S3Action::GetObjectLockConfiguration,
-                arguments: vec![has_query("lock")],
-            },
-        );
         matcher.add_key_route(
             "GET",
             Route {
@@ -717,13 +753,6 @@ impl RouteMatcher {
                 arguments: vec![has_query("legal-hold")],
             },
         );
-        matcher.add_key_route(
-            "PUT",
-            Route {
-                operation: S3Action::PutObjectLockConfiguration,
-                arguments: vec![has_query("lock")],
-            },
-        );
         matcher.add_key_route(
             "PUT",
             Route {
@@ -866,3 +895,74 @@ fn has_header_value(name: &str, value: &str) -> HasArguments {
             .unwrap_or(false)
     })
 }
+
+// Host of a Host header or authority, without its port
+fn host_name(authority: &str) -> &str {
+    match authority.rsplit_once(':') {
+        // A colon of a bracketed IPv6 address is not a port separator
+        Some((host, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => host,
+        _ => authority,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, host: &str, uri: &str) -> Request<Bytes> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Host", host)
+            .body(Bytes::new())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_match_website() {
+        let router = Router::new(
+            vec!["localhost:3000".to_string()],
+            vec!["s3-website.localhost:3000".to_string()],
+        );
+
+        let result = router.match_result(&request(
+            "GET",
+            "site.s3-website.localhost:3000",
+            "/docs/index.html",
+        ));
+        assert_eq!(result.action, S3Action::WebsiteRequest);
+        assert_eq!(result.bucket, "site");
+        assert_eq!(result.key, "docs/index.html");
+
+        // Methods are checked by the website handler
+        let result = router.match_result(&request("PUT", "site.s3-website.localhost:3000", "/"));
+        assert_eq!(result.action, S3Action::WebsiteRequest);
+        assert_eq!(result.key, "");
+
+        // The API endpoint is unaffected
+        let result = router.match_result(&request("GET", "localhost:3000", "/site/index.html"));
+        assert_eq!(result.action, S3Action::GetObject);
+        assert_eq!(result.bucket, "site");
+        let result = router.match_result(&request("GET", "s3-website.localhost:3000", "/site/a"));
+        assert_eq!(result.action, S3Action::GetObject);
+
+        // Ports are ignored on both sides
+        for host in ["site.s3-website.localhost", "site.s3-website.localhost:80"] {
+            let result = router.match_result(&request("GET", host, "/"));
+            assert_eq!(result.action, S3Action::WebsiteRequest);
+            assert_eq!(result.bucket, "site");
+        }
+        let router = Router::new(vec![], vec!["s3-website.example.com".to_string()]);
+        let result = router.match_result(&request("GET", "site.s3-website.example.com:8080", "/"));
+        assert_eq!(result.action, S3Action::WebsiteRequest);
+        assert_eq!(result.bucket, "site");
+    }
+
+    #[test]
+    fn test_host_name() {
+        assert_eq!(host_name("example.com:8080"), "example.com");
+        assert_eq!(host_name("example.com"), "example.com");
+        assert_eq!(host_name("[::1]:3000"), "[::1]");
+        assert_eq!(host_name("[::1]"), "[::1]");
+    }
+}
@@ -2,49 +2,91 @@ use std::collections::HashMap;
 use std::error::Error;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use axum::body::to_bytes;
+use axum::body::{to_bytes, Body, Bytes};
 use axum::extract::{ConnectInfo, Request};
 use axum::response::{IntoResponse, Response};
 use axum::routing::any;
 use axum::{extract::State, Router};
+use futures_util::StreamExt;
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::service::TowerToHyperService;
 use s3_core::S3Error;
 use s3_iam::iam::StreamKeysRequest;
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
 use tokio::signal::unix::{signal, SignalKind};
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
+use tokio::task::JoinSet;
+use tower::ServiceExt;
 
+use crate::access_log::AccessLogger;
+use crate::config::{ProxyConfig, RateLimitConfig};
 use crate::filter::{
-    AuthenticationFilter, BucketFilter, Filter, FilterChain, ParserFilter, RateLimitFilter,
-    RequestIdFilter, S3Data, SecretKeyFilter,
+    AuthenticationFilter, BucketFilter, ClientIpFilter, Filter, FilterChain, ParserFilter,
+    RateLimitFilter, RequestIdFilter, S3Data, SecretKeyFilter,
 };
+use crate::proxy::{protocol, TrustedProxies};
+use crate::ratelimit::{BandwidthLimiter, Direction};
 use crate::signature::{Key, SignatureValidator};
 
+// Listener and request handling settings of the S3 API server
+pub struct ServerSettings {
+    pub addr: String,
+    // Domains of path and virtual host style requests
+    pub hosts: Vec<String>,
+    // Domains of website endpoint requests
+    pub website_hosts: Vec<String>,
+    pub rate_limit: RateLimitConfig,
+    pub trusted_proxies: TrustedProxies,
+    pub proxy: ProxyConfig,
+}
+
 pub struct Server {
     pub addr: String,
     pub router: Router,
+    trusted_proxies: TrustedProxies,
+    proxy_config: ProxyConfig,
 }
 
 impl Server {
     pub async fn new(
-        addr: String,
-        hosts: Vec<String>,
+        settings: ServerSettings,
         client: s3_iam::iam::iam_client::IamClient<tonic::transport::Channel>,
         fullstack: Arc<Box<crate::backend::FullstackBackend>>,
         redis_client: redis::cluster::ClusterClient,
         local_rate_limiter: governor::DefaultKeyedRateLimiter<String>,
+        access_logger: AccessLogger,
     ) -> Self {
+        let ServerSettings {
+            addr,
+            hosts,
+            website_hosts,
+            rate_limit: rate_limit_config,
+            trusted_proxies,
+            proxy: proxy_config,
+        } = settings;
         let keys = Arc::new(RwLock::new(HashMap::new()));
 
         // Refresh keys
         tokio::spawn(Self::refresh_keys(client.clone(), keys.clone()));
 
+        let bandwidth_limiter = Arc::new(BandwidthLimiter::new(&rate_limit_config));
         let filters: Vec<Box<dyn Filter>> = vec![
             Box::new(RequestIdFilter::new()),
+            Box::new(ClientIpFilter::new(trusted_proxies.clone())),
+            Box::new(ParserFilter::new(hosts, website_hosts)),
             Box::new(AuthenticationFilter::new(SignatureValidator::new(
                 keys.clone(),
             ))),
-            Box::new(ParserFilter::new(hosts)),
-            Box::new(RateLimitFilter::new(redis_client, local_rate_limiter)),
+            Box::new(RateLimitFilter::new(
+                redis_client,
+                local_rate_limiter,
+                rate_limit_config,
+                fullstack.clone(),
+            )),
             Box::new(SecretKeyFilter::new(keys.clone())),
             Box::new(BucketFilter::new(fullstack.clone())),
         ];
@@ -52,11 +94,15 @@ impl Server {
         let app_state = Arc::new(AppState {
             fullstack,
             filter_chain,
+            bandwidth_limiter,
+            access_logger,
         });
 
         let mut server = Server {
             addr,
             router: Router::new(),
+            trusted_proxies,
+            proxy_config,
         };
 
         let app = Router::new()
@@ -70,6 +116,10 @@ impl Server {
 
     pub async fn start(self) -> Result<(), Box<dyn Error>> {
         let listener = tokio::net::TcpListener::bind(&self.addr).await.unwrap();
+        if self.proxy_config.proxy_protocol {
+            return self.serve_proxy_protocol(listener).await;
+        }
+
         axum::serve(
             listener,
             self.router
@@ -80,6 +130,57 @@ impl Server {
         .map_err(Into::into)
     }
 
+    // axum::serve has no hook to read the PROXY protocol header before HTTP,
+    // so connections are accepted and handed to hyper here instead. On
+    // shutdown the listener is closed and open connections are drained like
+    // axum::serve does, finishing their in-flight requests.
+    async fn serve_proxy_protocol(self, listener: TcpListener) -> Result<(), Box<dyn Error>> {
+        let header_timeout = Duration::from_millis(self.proxy_config.header_timeout_ms);
+        let shutdown = shutdown_signal();
+        tokio::pin!(shutdown);
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let mut connections = JoinSet::new();
+
+        loop {
+            let (stream, peer) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        tracing::warn!("Error accepting connection: {:?}", e);
+                        continue;
+                    }
+                },
+                // Reap finished connections so the set does not grow
+                Some(_) = connections.join_next(), if !connections.is_empty() => continue,
+                _ = &mut shutdown => break,
+            };
+
+            let router = self.router.clone();
+            let trusted_proxies = self.trusted_proxies.clone();
+            let shutdown_rx = shutdown_rx.clone();
+            connections.spawn(async move {
+                if let Err(e) = serve_connection(
+                    stream,
+                    peer,
+                    router,
+                    trusted_proxies,
+                    header_timeout,
+                    shutdown_rx,
+                )
+                .await
+                {
+                    tracing::debug!(peer = %peer, "Error serving connection: {:?}", e);
+                }
+            });
+        }
+
+        drop(listener);
+        let _ = shutdown_tx.send(true);
+        tracing::info!("Draining {} connections", connections.len());
+        while connections.join_next().await.is_some() {}
+        Ok(())
+    }
+
     async fn refresh_keys(
         mut client: s3_iam::iam::iam_client::IamClient<tonic::transport::Channel>,
         keys: Arc<RwLock<HashMap<String, Key>>>,
@@ -118,61 +219,219 @@ impl Server {
         ConnectInfo(addr): ConnectInfo<SocketAddr>,
         req: Request,
     ) -> Response {
+        let received = chrono::Utc::now();
+        let started = Instant::now();
         let mut data = S3Data::new();
+        let response = Self::respond(&state, &mut data, addr, req).await;
+        state
+            .access_logger
+            .log_response(&data, response, received, started)
+    }
 
+    async fn respond(
+        state: &Arc<AppState>,
+        data: &mut S3Data,
+        addr: SocketAddr,
+        req: Request,
+    ) -> Response {
+        // Filters only need the head of the request. The body is read once the
+        // tenant is known so that uploads can be paced.
         let (parts, body) = req.into_parts();
+        data.req = axum::http::Request::from_parts(parts, Bytes::new());
+        data.peer_addr = addr;
+        match state.filter_chain.run_filters(data).await {
+            Ok(_) => {}
+            Err(e) if data.action == s3_core::S3Action::WebsiteRequest => {
+                return e.into_html_response();
+            }
+            Err(e) => {
+                return e.into_response();
+            }
+        }
+
+        let user_id = data.auth_key.user_id.to_string();
+        let body = state.bandwidth_limiter.throttle_body(
+            Direction::Ingress,
+            user_id.clone(),
+            data.bucket_name.clone(),
+            body,
+        );
         let body = to_bytes(body, s3_core::MAX_OBJECT_PART_SIZE)
             .await
             .map_err(|e| {
                 tracing::error!("Error reading body: {:?}", e);
                 S3Error::MaxMessageLengthExceeded
             });
-        let body = match body {
-            Ok(body) => body,
+        match body {
+            Ok(body) => *data.req.body_mut() = body,
             Err(e) => {
                 return e.into_response();
             }
         };
-        let req = axum::http::Request::from_parts(parts, body);
-        data.req = req;
-        data.req.headers_mut().insert(
-            "x-real-ip",
-            reqwest::header::HeaderValue::from_str(&addr.ip().to_string()).unwrap(),
-        );
-        match state.filter_chain.run_filters(&mut data).await {
-            Ok(_) => {}
-            Err(e) => {
-                return e.into_response();
-            }
-        }
 
         // TODO: Route to the correct handler
-        match data.action {
-            s3_core::S3Action::ListBuckets => {
-                return Self::list_buckets(&state, &mut data).await;
+        let mut response = match data.action {
+            s3_core::S3Action::ListBuckets => Self::list_buckets(state, data).await,
+            s3_core::S3Action::CreateBucket => Self::create_bucket(state, data).await,
+            s3_core::S3Action::DeleteBucket => Self::delete_bucket(state, data).await,
+            s3_core::S3Action::PutObject => Self::put_object(state, data).await,
+            s3_core::S3Action::GetObject => Self::get_object(state, data).await,
+            s3_core::S3Action::HeadObject => Self::head_object(state, data).await,
+            s3_core::S3Action::PutBucketCors => Self::put_bucket_cors(state, data).await,
+            s3_core::S3Action::GetBucketCors => Self::get_bucket_cors(state, data).await,
+            s3_core::S3Action::DeleteBucketCors => Self::delete_bucket_cors(state, data).await,
+            s3_core::S3Action::OptionsPreflight => Self::options_preflight(data),
+            s3_core::S3Action::PutBucketLifecycleConfiguration => {
+                Self::put_bucket_lifecycle(state, data).await
             }
-            s3_core::S3Action::CreateBucket => {
-                return Self::create_bucket(&state, &mut data).await;
+            s3_core::S3Action::GetBucketLifecycleConfiguration => {
+                Self::get_bucket_lifecycle(state, data).await
             }
-            s3_core::S3Action::DeleteBucket => {
-                return Self::delete_bucket(&state, &mut data).await;
+            s3_core::S3Action::DeleteBucketLifecycle => {
+                Self::delete_bucket_lifecycle(state, data).await
             }
-            s3_core::S3Action::PutObject => {
-                return Self::put_object(&state, &mut data).await;
+            s3_core::S3Action::PutBucketEncryption => {
+                Self::put_bucket_encryption(state, data).await
             }
-            s3_core::S3Action::GetObject => {
-                return Self::get_object(&state, &mut data).await;
+            s3_core::S3Action::GetBucketEncryption => {
+                Self::get_bucket_encryption(state, data).await
             }
-            _ => {
-                return axum::response::IntoResponse::into_response(S3Error::NotImplemented);
+            s3_core::S3Action::DeleteBucketEncryption => {
+                Self::delete_bucket_encryption(state, data).await
             }
-        }
+            s3_core::S3Action::DeleteObject => Self::delete_object(state, data).await,
+            s3_core::S3Action::DeleteObjects => Self::delete_objects(state, data).await,
+            s3_core::S3Action::CopyObject => Self::copy_object(state, data).await,
+            s3_core::S3Action::CreateMultipartUpload => {
+                Self::create_multipart_upload(state, data).await
+            }
+            s3_core::S3Action::UploadPart => Self::upload_part(state, data).await,
+            s3_core::S3Action::CompleteMultipartUpload => {
+                Self::complete_multipart_upload(state, data).await
+            }
+            s3_core::S3Action::AbortMultipartUpload => {
+                Self::abort_multipart_upload(state, data).await
+            }
+            s3_core::S3Action::PutObjectTagging => Self::put_object_tagging(state, data).await,
+            s3_core::S3Action::GetObjectTagging => Self::get_object_tagging(state, data).await,
+            s3_core::S3Action::DeleteObjectTagging => {
+                Self::delete_object_tagging(state, data).await
+            }
+            s3_core::S3Action::PutBucketTagging => Self::put_bucket_tagging(state, data).await,
+            s3_core::S3Action::GetBucketTagging => Self::get_bucket_tagging(state, data).await,
+            s3_core::S3Action::DeleteBucketTagging => {
+                Self::delete_bucket_tagging(state, data).await
+            }
+            s3_core::S3Action::PutObjectLockConfiguration => {
+                Self::put_object_lock_configuration(state, data).await
+            }
+            s3_core::S3Action::GetObjectLockConfiguration => {
+                Self::get_object_lock_configuration(state, data).await
+            }
+            s3_core::S3Action::PutBucketVersioning => {
+                Self::put_bucket_versioning(state, data).await
+            }
+            s3_core::S3Action::GetBucketVersioning => {
+                Self::get_bucket_versioning(state, data).await
+            }
+            s3_core::S3Action::PutObjectRetention => Self::put_object_retention(state, data).await,
+            s3_core::S3Action::GetObjectRetention => Self::get_object_retention(state, data).await,
+            s3_core::S3Action::PutObjectLegalHold => Self::put_object_legal_hold(state, data).await,
+            s3_core::S3Action::GetObjectLegalHold => Self::get_object_legal_hold(state, data).await,
+            s3_core::S3Action::PutBucketWebsite => Self::put_bucket_website(state, data).await,
+            s3_core::S3Action::GetBucketWebsite => Self::get_bucket_website(state, data).await,
+            s3_core::S3Action::DeleteBucketWebsite => {
+                Self::delete_bucket_website(state, data).await
+            }
+            s3_core::S3Action::PutBucketLogging => Self::put_bucket_logging(state, data).await,
+            s3_core::S3Action::GetBucketLogging => Self::get_bucket_logging(state, data).await,
+            s3_core::S3Action::PutBucketNotificationConfiguration => {
+                Self::put_bucket_notification_configuration(state, data).await
+            }
+            s3_core::S3Action::GetBucketNotificationConfiguration => {
+                Self::get_bucket_notification_configuration(state, data).await
+            }
+            s3_core::S3Action::PutBucketReplication => {
+                Self::put_bucket_replication(state, data).await
+            }
+            s3_core::S3Action::GetBucketReplication => {
+                Self::get_bucket_replication(state, data).await
+            }
+            s3_core::S3Action::DeleteBucketReplication => {
+                Self::delete_bucket_replication(state, data).await
+            }
+            s3_core::S3Action::WebsiteRequest => Self::website_request(state, data).await,
+            _ => axum::response::IntoResponse::into_response(S3Error::NotImplemented),
+        };
+
+        Self::apply_cors(data, &mut response);
+
+        // The request stays in flight until its response body has been sent
+        let permit = data.concurrency_permit.take();
+        let (parts, body) = response.into_parts();
+        let body = state.bandwidth_limiter.throttle_body(
+            Direction::Egress,
+            user_id,
+            data.bucket_name.clone(),
+            body,
+        );
+        let body = match permit {
+            Some(permit) => Body::from_stream(body.into_data_stream().map(move |chunk| {
+                let _ = &permit;
+                chunk
+            })),
+            None => body,
+        };
+        Response::from_parts(parts, body)
     }
 }
 
 pub struct AppState {
     pub fullstack: Arc<Box<crate::backend::FullstackBackend>>,
     pub filter_chain: Arc<FilterChain>,
+    pub bandwidth_limiter: Arc<BandwidthLimiter>,
+    pub access_logger: AccessLogger,
+}
+
+async fn serve_connection(
+    stream: TcpStream,
+    peer: SocketAddr,
+    router: Router,
+    trusted_proxies: TrustedProxies,
+    header_timeout: Duration,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    // Only trusted proxies may assert a different source address
+    if !trusted_proxies.is_trusted(&peer.ip()) {
+        return Err("PROXY protocol header from untrusted peer".into());
+    }
+
+    let mut stream = BufReader::new(stream);
+    let header = tokio::select! {
+        header = tokio::time::timeout(header_timeout, protocol::read_header(&mut stream)) => header??,
+        _ = shutdown.changed() => return Ok(()),
+    };
+    let addr = header.source.unwrap_or(peer);
+
+    let service = router.map_request(move |mut req: axum::http::Request<Incoming>| {
+        req.extensions_mut().insert(ConnectInfo(addr));
+        // A load balancer that terminates TLS reports it in the header
+        if header.ssl {
+            req.extensions_mut().insert(protocol::ClientTls);
+        }
+        req
+    });
+    let builder = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new());
+    let conn = builder
+        .serve_connection_with_upgrades(TokioIo::new(stream), TowerToHyperService::new(service));
+    tokio::pin!(conn);
+    tokio::select! {
+        result = conn.as_mut() => return result,
+        _ = shutdown.changed() => {}
+    }
+    // Stop taking new requests and let the current one finish
+    conn.as_mut().graceful_shutdown();
+    conn.await
 }
 
 async fn shutdown_signal() {